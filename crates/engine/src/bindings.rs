@@ -1,17 +1,79 @@
 //! Bindings installed into the JS runtime.
 //! - `global`: alias to globalThis
-//! - `include(path)`: include and execute a file inline. Supports `.jhp` and `.js`.
+//! - `include(path, args?)`: include and execute a file inline. Supports `.jhp` and `.js`.
+//!   When `args` is passed it is bound to `$args` for the included file only.
+//!   Top-level declarations of a page are globals an included file can see, except
+//!   in a page using `await`, which runs inside an async function; pass values through
+//!   `args` or `$ctx` instead.
 //!   If `path` has no extension, it is treated as a module name and we attempt to
 //!   resolve `<name>.js` from the document root or the extensions directory.
+//! - `readFile(path)` / `writeFile(path, data)`: file access confined to the document root.
+//! - `$store`: in-memory key/value store shared by all executors in the process.
+//! - `base64Encode`/`base64Decode`, `hexEncode`/`hexDecode`: binary-to-text encoding.
+//! - `sha256(data)` / `hmacSha256(key, data)`: hex digests.
+//! - `urlEncode`/`urlDecode`, `formEncode`/`formDecode`, `buildQuery(object)`: URI
+//!   component and form encoding.
+//! - `$log.debug/info/warn/error(message, fields?)`: structured server log entries.
+//! - `toJson(value, space?)`: JSON safe to embed in a `<script>` element.
+//! - `now()` / `hrtime()`: monotonic milliseconds and `BigInt` nanoseconds.
+//! - `htmlspecialchars(str, flags?)` / `nl2br(str)`: PHP-compatible HTML text helpers.
+//! - `abort(status, message?)`: stop rendering and answer with an HTTP error status.
+//! - `redirect(url, status?)` / `redirectPermanent(url)`: stop rendering and redirect.
+//! - `render(template, data?)`: render a JHP template string and return its output.
+//! - `assert(condition, message?)`: fail the render when `condition` is falsy.
+//! - `$random.int/float/bytes/uuid`: pseudo-random values, reproducible with a seed.
+//! - `pathJoin(...)`, `dirname`/`basename`/`extname(path)`: string-only POSIX path helpers.
+//! - the hidden stringifier `<?= expr ?>` values go through, when one is configured.
 
 use crate::config::EngineConfig;
-use crate::extensions::ModuleRegistry;
+use crate::extensions::{ModuleError, ModuleRegistry};
+use crate::fs::{DocumentRoot, EmbeddedFiles};
 use jhp_executor::BindingInstaller;
 use jhp_parser as parser;
+use std::cell::RefCell;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
+mod abort;
+mod assert;
+mod encoding;
+mod files;
+mod hash;
+mod html;
+mod json;
+mod log;
+mod path;
+mod random;
+mod redirect;
+mod render;
+mod store;
+mod stringifier;
+mod time;
+mod url;
+
+pub use abort::AbortBinding;
+pub use assert::AssertBinding;
+pub use encoding::EncodingBinding;
+pub use files::FileBinding;
+pub use hash::HashBinding;
+pub use html::HtmlBinding;
+pub use json::JsonBinding;
+pub use log::LogBinding;
+pub use path::PathBinding;
+pub use random::RandomBinding;
+pub use redirect::RedirectBinding;
+pub use render::RenderBinding;
+pub use store::StoreBinding;
+pub use stringifier::StringifierBinding;
+pub use time::TimeBinding;
+pub use url::UrlBinding;
+
+/// Something that attaches globals to a context.
+///
+/// A binding may hand its functions a pointer to its own state rather than allocating a
+/// copy on every install, so it must outlive every context it was installed into. The
+/// engine builds each binding once and keeps it in its installer for the life of the pool.
 pub trait InstallBindings {
     fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>);
 }
@@ -28,20 +90,28 @@ impl InstallBindings for GlobalBinding {
     }
 }
 
-/// Installs an `include(path)` function to inline-execute files.
+/// Installs an `include(path, args?)` function to inline-execute files.
+/// - A `path` without extension is first tried as a native module name; otherwise the
+///   file is found by [`resolve_include`].
 /// - If `path` ends with `.jhp`, the file is parsed with the JHP parser and transformed to JS.
+///   A file using `await` is settled before `include()` returns, so its output stays in order.
 /// - If `path` ends with `.js`, the file contents are executed directly.
+/// - If `args` is given, it is visible to the included file as `$args` while it runs.
+/// - Re-entering a file that is already being included throws a "circular include" error.
 pub struct IncludeBinding {
-    /// Fallback directory to resolve includes relative to when a provided path
-    /// isn't directly readable.
-    pub document_root: PathBuf,
-    /// Directory containing extensions (native `.so` and JS shims). When `include()`
-    /// is called with a bare module name (no extension), we'll look for `<name>.js`
-    /// here as a convenience, e.g. `include('sqlite3')` resolves to `ext/sqlite3.js`
-    /// or `ext/sqlite/sqlite3.js`.
-    pub extensions_dir: PathBuf,
+    /// Where included files are looked for: the document root, then the include paths,
+    /// then, for a bare module name, the extensions directory (native `.so` and JS shims),
+    /// e.g. `include('sqlite3')` resolves to `ext/sqlite3.js` or `ext/sqlite/sqlite3.js`.
+    pub dirs: IncludeDirs,
     /// Shared registry for lazy-loading native modules.
     pub modules: Arc<ModuleRegistry>,
+    /// How `<?= expr ?>` values in included `.jhp` files are printed.
+    pub expression_output: parser::ExpressionOutput,
+}
+
+thread_local! {
+    /// Files being included on this executor thread: (canonical path, name as given).
+    static INCLUDE_STACK: RefCell<Vec<(PathBuf, String)>> = const { RefCell::new(Vec::new()) };
 }
 
 impl IncludeBinding {
@@ -51,36 +121,55 @@ impl IncludeBinding {
         modules: Arc<ModuleRegistry>,
     ) -> Self {
         Self {
-            document_root: document_root.into(),
-            extensions_dir: extensions_dir.into(),
+            dirs: IncludeDirs {
+                document_root: document_root.into(),
+                embedded_root: None,
+                include_paths: Vec::new(),
+                extensions_dir: extensions_dir.into(),
+            },
             modules,
+            expression_output: parser::ExpressionOutput::default(),
         }
     }
+
+    pub fn with_include_paths(mut self, include_paths: Vec<PathBuf>) -> Self {
+        self.dirs.include_paths = include_paths;
+        self
+    }
+
+    /// Find the files of the document root in `files` rather than on disk.
+    pub fn with_embedded_root(mut self, files: Option<Arc<EmbeddedFiles>>) -> Self {
+        self.dirs.embedded_root = files;
+        self
+    }
+
+    pub fn with_expression_output(mut self, output: parser::ExpressionOutput) -> Self {
+        self.expression_output = output;
+        self
+    }
 }
 
 impl InstallBindings for IncludeBinding {
     fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
         let global = scope.get_current_context().global(scope);
 
-        // Pass state via External pointer into the callback to satisfy V8's callback requirements
-        #[repr(C)]
-        struct IncludeState {
-            doc_root: PathBuf,
-            ext_dir: PathBuf,
-            modules: Arc<ModuleRegistry>,
-        }
-        let state = IncludeState {
-            doc_root: self.document_root.clone(),
-            ext_dir: self.extensions_dir.clone(),
-            modules: self.modules.clone(),
-        };
-        let state_ptr = Box::into_raw(Box::new(state)) as *mut std::ffi::c_void;
+        // a context left mid-include (e.g. by a terminated render) must not leave its files
+        // behind for the next one
+        INCLUDE_STACK.with_borrow_mut(Vec::clear);
+
+        // SAFETY: see `InstallBindings`; the binding outlives the context
+        let state_ptr = self as *const Self as *mut std::ffi::c_void;
         let external = v8::External::new(scope, state_ptr);
 
         let include_fn = v8::Function::builder(
             move |scope: &mut v8::HandleScope,
                   args: v8::FunctionCallbackArguments,
                   mut rv: v8::ReturnValue| {
+                let st_ptr = v8::Local::<v8::External>::try_from(args.data())
+                    .map(|e| e.value() as *const IncludeBinding)
+                    .unwrap();
+                let st: &IncludeBinding = unsafe { &*st_ptr };
+
                 // path argument
                 let path_val = args.get(0);
                 let Some(path_str) = path_val.to_string(scope) else {
@@ -96,10 +185,6 @@ impl InstallBindings for IncludeBinding {
                 let has_ext = Path::new(&path).extension().is_some();
                 if !has_ext {
                     // Try to lazy-load module by name
-                    let st_ptr = v8::Local::<v8::External>::try_from(args.data())
-                        .map(|e| e.value() as *const IncludeState)
-                        .unwrap();
-                    let st: &IncludeState = unsafe { &*st_ptr };
                     match st.modules.ensure_loaded(&path) {
                         Ok(Some(_)) => {
                             // Newly loaded: install just this module into current context
@@ -113,9 +198,17 @@ impl InstallBindings for IncludeBinding {
                             let mut cs = v8::ContextScope::new(scope, context);
                             st.modules.install_one(&path, &mut cs);
                         }
-                        Err(_e) => {
+                        Err(ModuleError::NotFound(_)) => {
                             // Not a native module; fall through to file resolution below
                         }
+                        Err(ModuleError::Load(e)) => {
+                            // A library exists but is broken; looking further would hide why
+                            throw_error(
+                                scope,
+                                &format!("include('{}'): failed to load module: {}", path, e),
+                            );
+                            return;
+                        }
                     }
                     // If module object now exists, return it.
                     if let Some(obj_name) = st.modules.object_name(&path) {
@@ -130,137 +223,86 @@ impl InstallBindings for IncludeBinding {
                     // else: proceed to try JS shim resolution
                 }
 
-                // resolve and load file content as before
-                let path_ref = Path::new(&path);
-                let mut content: Option<String> = None;
-                if content.is_none() {
-                    if let Ok(s) = fs::read_to_string(path_ref) {
-                        content = Some(s);
-                    }
-                }
-                if content.is_none() {
-                    let st_ptr = v8::Local::<v8::External>::try_from(args.data())
-                        .map(|e| e.value() as *const IncludeState)
-                        .unwrap();
-                    let st: &IncludeState = unsafe { &*st_ptr };
-                    if let Ok(s) = fs::read_to_string(st.doc_root.join(&path)) {
-                        content = Some(s);
+                let Some((resolved_path, kind)) = resolve_include(&path, &st.dirs) else {
+                    // no file, but a directory where one was looked for
+                    let candidates = include_candidates(&path, &st.dirs);
+                    if candidates.iter().any(|candidate| st.dirs.is_dir(candidate)) {
+                        throw_error(
+                            scope,
+                            &format!("include('{}') is a directory, not a file", path),
+                        );
+                        return;
                     }
-                }
-                if !has_ext {
-                    let name = &path;
-                    let st_ptr = v8::Local::<v8::External>::try_from(args.data())
-                        .map(|e| e.value() as *const IncludeState)
-                        .unwrap();
-                    let st: &IncludeState = unsafe { &*st_ptr };
-                    let candidates = [
-                        st.doc_root.join(format!("{}.js", name)),
-                        st.ext_dir.join(name).join(format!("{}.js", name)),
-                        st.ext_dir.join(format!("{}.js", name)),
-                    ];
-                    for p in candidates.iter() {
-                        if content.is_none() {
-                            if let Ok(s) = fs::read_to_string(p) {
-                                content = Some(s);
-                                break;
-                            }
-                        }
-                    }
-                }
-                let Some(content) = content else {
-                    let msg = v8::String::new(
+                    throw_error(
                         scope,
                         &format!(
                             "include('{}') read error: not found as module or file",
                             path
                         ),
-                    )
-                    .unwrap();
-                    let exc = v8::Exception::error(scope, msg);
-                    scope.throw_exception(exc);
+                    );
                     return;
                 };
-
-                // execute..
-                let result_val: Option<v8::Local<v8::Value>> = if path.ends_with(".jhp") {
-                    let mut p = parser::Parser::new(&content);
-                    let res = p.parse();
-                    let js = parser::blocks_to_js(res.blocks);
-                    // compile+run and capture result
-                    let context = scope.get_current_context();
-                    let mut cs = v8::ContextScope::new(scope, context);
-                    let src = v8::String::new(&mut cs, &js).unwrap();
-                    let name = v8::String::new(&mut cs, &path).unwrap();
-                    let origin = v8::ScriptOrigin::new(
-                        &mut cs,
-                        name.into(),
-                        0,
-                        0,
-                        false,
-                        0,
-                        None,
-                        false,
-                        false,
-                        false,
-                        None,
-                    );
-                    match v8::Script::compile(&mut cs, src, Some(&origin))
-                        .and_then(|s| s.run(&mut cs))
-                    {
-                        Some(v) => Some(v),
-                        None => None,
+                let content = match st.dirs.read_to_string(&resolved_path) {
+                    Ok(content) => content,
+                    Err(e) => {
+                        throw_error(scope, &format!("include('{}') read error: {}", path, e));
+                        return;
                     }
-                } else if path.ends_with(".js") {
-                    let context = scope.get_current_context();
-                    let mut cs = v8::ContextScope::new(scope, context);
-                    let src = v8::String::new(&mut cs, &content).unwrap();
-                    let name = v8::String::new(&mut cs, &path).unwrap();
-                    let origin = v8::ScriptOrigin::new(
-                        &mut cs,
-                        name.into(),
-                        0,
-                        0,
-                        false,
-                        0,
-                        None,
-                        false,
-                        false,
-                        false,
-                        None,
+                };
+
+                // Refuse to re-enter a file that is still being included further up the stack.
+                let key = fs::canonicalize(&resolved_path).unwrap_or(resolved_path);
+                let cycle = INCLUDE_STACK.with_borrow(|stack| {
+                    let pos = stack.iter().position(|(k, _)| *k == key)?;
+                    let cycle: Vec<String> = stack[pos..]
+                        .iter()
+                        .map(|(_, name)| name.clone())
+                        .chain(std::iter::once(path.clone()))
+                        .collect();
+                    Some(cycle)
+                });
+                if let Some(cycle) = cycle {
+                    throw_error(
+                        scope,
+                        &format!(
+                            "include('{}'): circular include: {}",
+                            path,
+                            cycle.join(" -> ")
+                        ),
                     );
-                    match v8::Script::compile(&mut cs, src, Some(&origin))
-                        .and_then(|s| s.run(&mut cs))
-                    {
-                        Some(v) => Some(v),
-                        None => None,
-                    }
+                    return;
+                }
+                INCLUDE_STACK.with_borrow_mut(|stack| stack.push((key, path.clone())));
+
+                // An optional second argument is exposed to the included file as `$args`
+                // for the duration of its execution; any previous binding is restored after.
+                let locals = args.get(1);
+                let saved_args = if locals.is_undefined() {
+                    None
                 } else {
-                    // Treated as module shim (no extension), run as JS and return value
-                    let context = scope.get_current_context();
-                    let mut cs = v8::ContextScope::new(scope, context);
-                    let src = v8::String::new(&mut cs, &content).unwrap();
-                    let name = v8::String::new(&mut cs, &format!("{}.js", path)).unwrap();
-                    let origin = v8::ScriptOrigin::new(
-                        &mut cs,
-                        name.into(),
-                        0,
-                        0,
-                        false,
-                        0,
-                        None,
-                        false,
-                        false,
-                        false,
-                        None,
-                    );
-                    match v8::Script::compile(&mut cs, src, Some(&origin))
-                        .and_then(|s| s.run(&mut cs))
-                    {
-                        Some(v) => Some(v),
-                        None => None,
+                    Some(swap_global(scope, ARGS_GLOBAL, locals))
+                };
+
+                // execute..
+                let result_val: Option<v8::Local<v8::Value>> = match kind {
+                    IncludeKind::Template => {
+                        let mut p = parser::Parser::new(&content);
+                        let res = p.parse();
+                        let js = parser::template_to_js(res.blocks, st.expression_output);
+                        // errors in it are reported at lines of the file, not of the script
+                        jhp_executor::v8utils::set_line_map(scope, &path, js.lines);
+                        run_source(scope, &js.code, &path).and_then(|v| settle_promise(scope, v))
                     }
+                    IncludeKind::Script => run_source(scope, &content, &path),
+                    // a module shim runs as JS and its completion value is returned
+                    IncludeKind::Shim => run_source(scope, &content, &format!("{}.js", path)),
                 };
 
+                if let Some(previous) = saved_args {
+                    restore_global(scope, ARGS_GLOBAL, previous);
+                }
+                INCLUDE_STACK.with_borrow_mut(Vec::pop);
+
                 if let Some(v) = result_val {
                     rv.set(v);
                 } else {
@@ -279,6 +321,232 @@ impl InstallBindings for IncludeBinding {
     }
 }
 
+/// How the file found for `include(path)` is run, decided by the extension of `path`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncludeKind {
+    /// `.jhp`: parsed and run as a template.
+    Template,
+    /// `.js`: run as a script.
+    Script,
+    /// Anything else, e.g. a module name resolved to its `<name>.js` shim: run as JS
+    /// named `<path>.js`, its completion value returned.
+    Shim,
+}
+
+/// The directories [`resolve_include`] searches.
+#[derive(Debug, Clone, Default)]
+pub struct IncludeDirs {
+    pub document_root: PathBuf,
+    /// Files standing in for the document root, as served by the HTTP server; paths
+    /// under `document_root` then name these rather than files on disk.
+    pub embedded_root: Option<Arc<EmbeddedFiles>>,
+    pub include_paths: Vec<PathBuf>,
+    pub extensions_dir: PathBuf,
+}
+
+impl IncludeDirs {
+    /// The embedded files and the path in them `path` names, when it is under an
+    /// embedded document root.
+    fn embedded<'p>(&self, path: &'p Path) -> Option<(&EmbeddedFiles, &'p Path)> {
+        let files = self.embedded_root.as_deref()?;
+        Some((files, path.strip_prefix(&self.document_root).ok()?))
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        match self.embedded(path) {
+            Some((files, rel)) => files.get(rel).is_ok(),
+            None => path.is_file(),
+        }
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        match self.embedded(path) {
+            Some((files, rel)) => files.get(rel).is_err() && files.is_dir(rel),
+            None => path.is_dir(),
+        }
+    }
+
+    /// Read a file found by [`resolve_include`].
+    pub fn read_to_string(&self, path: &Path) -> std::io::Result<String> {
+        match self.embedded(path) {
+            Some((files, rel)) => String::from_utf8(files.get(rel)?.to_vec())
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            None => fs::read_to_string(path),
+        }
+    }
+}
+
+/// Find the file `include(path)` runs. The first candidate that is a file wins:
+/// 1. `path` as given (absolute, or relative to the working directory);
+/// 2. `path` under the document root;
+/// 3. `path` under each of the include paths, in order;
+/// 4. for a name without extension (a module with no native library): `<name>.js` in
+///    the document root, then `<name>/<name>.js` and `<name>.js` in the extensions
+///    directory.
+///
+/// With an embedded root, the document root's files are looked up in it instead; such a
+/// file is returned under `document_root`, to be read with [`IncludeDirs::read_to_string`].
+pub fn resolve_include(path: &str, dirs: &IncludeDirs) -> Option<(PathBuf, IncludeKind)> {
+    let kind = match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("jhp") => IncludeKind::Template,
+        Some("js") => IncludeKind::Script,
+        _ => IncludeKind::Shim,
+    };
+    include_candidates(path, dirs)
+        .into_iter()
+        .find(|candidate| dirs.is_file(candidate))
+        .map(|found| (found, kind))
+}
+
+/// The paths [`resolve_include`] tries for `path`, in order.
+fn include_candidates(path: &str, dirs: &IncludeDirs) -> Vec<PathBuf> {
+    let given = Path::new(path);
+    let mut candidates = vec![given.to_path_buf(), dirs.document_root.join(path)];
+    candidates.extend(dirs.include_paths.iter().map(|dir| dir.join(path)));
+    if given.extension().is_none() {
+        candidates.extend([
+            dirs.document_root.join(format!("{}.js", path)),
+            dirs.extensions_dir.join(path).join(format!("{}.js", path)),
+            dirs.extensions_dir.join(format!("{}.js", path)),
+        ]);
+    }
+    candidates
+}
+
+/// Name of the global holding the arguments passed to `include(path, args)`.
+const ARGS_GLOBAL: &str = "$args";
+
+/// Compile and run `code` in the current context, returning the completion value.
+/// Exceptions are left pending on the isolate so they propagate to the caller.
+fn run_source<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    code: &str,
+    resource_name: &str,
+) -> Option<v8::Local<'s, v8::Value>> {
+    let context = scope.get_current_context();
+    let mut cs = v8::ContextScope::new(scope, context);
+    let src = v8::String::new(&mut cs, code)?;
+    let name = v8::String::new(&mut cs, resource_name)?;
+    let origin = v8::ScriptOrigin::new(
+        &mut cs,
+        name.into(),
+        0,
+        0,
+        false,
+        0,
+        None,
+        false,
+        false,
+        false,
+        None,
+    );
+    v8::Script::compile(&mut cs, src, Some(&origin)).and_then(|s| s.run(&mut cs))
+}
+
+/// A `.jhp` file using `await` runs as an async IIFE whose completion value is a promise.
+/// Pump microtasks so it finishes (and echoes) before `include()` returns: a rejection is
+/// rethrown and a fulfilled promise yields its value. If it can't settle yet (e.g. the
+/// include itself runs inside a microtask), the promise is returned for the caller to await.
+fn settle_promise<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    value: v8::Local<'s, v8::Value>,
+) -> Option<v8::Local<'s, v8::Value>> {
+    let Ok(promise) = v8::Local::<v8::Promise>::try_from(value) else {
+        return Some(value);
+    };
+    scope.perform_microtask_checkpoint();
+    match promise.state() {
+        v8::PromiseState::Fulfilled => Some(promise.result(scope)),
+        v8::PromiseState::Rejected => {
+            let reason = promise.result(scope);
+            scope.throw_exception(reason);
+            None
+        }
+        v8::PromiseState::Pending => Some(value),
+    }
+}
+
+/// Set `global[name] = value`, returning the previous own value (if any) for `restore_global`.
+fn swap_global<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    name: &str,
+    value: v8::Local<'s, v8::Value>,
+) -> Option<v8::Local<'s, v8::Value>> {
+    let global = scope.get_current_context().global(scope);
+    let key = v8::String::new(scope, name)?;
+    let previous = if global.has_own_property(scope, key.into()) == Some(true) {
+        global.get(scope, key.into())
+    } else {
+        None
+    };
+    let _ = global.set(scope, key.into(), value);
+    previous
+}
+
+/// Undo a `swap_global`: put back the previous value, or remove the binding if there was none.
+fn restore_global<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    name: &str,
+    previous: Option<v8::Local<'s, v8::Value>>,
+) {
+    let global = scope.get_current_context().global(scope);
+    let Some(key) = v8::String::new(scope, name) else {
+        return;
+    };
+    match previous {
+        Some(value) => {
+            let _ = global.set(scope, key.into(), value);
+        }
+        None => {
+            let _ = global.delete(scope, key.into());
+        }
+    }
+}
+
+/// Throw a JS `Error` with `message` in the current context.
+pub(crate) fn throw_error(scope: &mut v8::HandleScope, message: &str) {
+    let msg = v8::String::new(scope, message).unwrap();
+    let exc = v8::Exception::error(scope, msg);
+    scope.throw_exception(exc);
+}
+
+pub(crate) fn throw_type_error(scope: &mut v8::HandleScope, message: &str) {
+    let msg = v8::String::new(scope, message).unwrap();
+    let exc = v8::Exception::type_error(scope, msg);
+    scope.throw_exception(exc);
+}
+
+/// Copy the bytes of a typed array, `DataView` or `ArrayBuffer`; anything else is converted
+/// with `String()` and encoded as UTF-8. Returns `None` if that conversion threw.
+pub(crate) fn value_to_bytes(
+    scope: &mut v8::HandleScope,
+    value: v8::Local<v8::Value>,
+) -> Option<Vec<u8>> {
+    let view = if let Ok(buffer) = v8::Local::<v8::ArrayBuffer>::try_from(value) {
+        v8::Uint8Array::new(scope, buffer, 0, buffer.byte_length()).map(Into::into)
+    } else {
+        v8::Local::<v8::ArrayBufferView>::try_from(value).ok()
+    };
+    if let Some(view) = view {
+        let mut bytes = vec![0u8; view.byte_length()];
+        view.copy_contents(&mut bytes);
+        return Some(bytes);
+    }
+    let s = value.to_string(scope)?;
+    Some(s.to_rust_string_lossy(scope).into_bytes())
+}
+
+/// Wrap `bytes` in a new `Uint8Array` without copying them again.
+pub(crate) fn bytes_to_uint8array<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    bytes: Vec<u8>,
+) -> Option<v8::Local<'s, v8::Uint8Array>> {
+    let len = bytes.len();
+    let store = v8::ArrayBuffer::new_backing_store_from_vec(bytes).make_shared();
+    let buffer = v8::ArrayBuffer::with_backing_store(scope, &store);
+    v8::Uint8Array::new(scope, buffer, 0, len)
+}
+
 /// Build the default set of binding installers used by the engine, configured with a document root.
 pub fn default_installers(
     cfg: &EngineConfig,
@@ -286,7 +554,8 @@ pub fn default_installers(
 ) -> Vec<BindingInstaller> {
     let document_root = cfg.document_root.clone();
     let extensions_dir = cfg.extensions_dir.clone();
-    vec![
+    let expression_output = cfg.effective_expression_output();
+    let mut installers: Vec<BindingInstaller> = vec![
         Arc::new(|scope: &mut v8::ContextScope<v8::HandleScope>| {
             GlobalBinding.install(scope);
         }),
@@ -298,12 +567,87 @@ pub fn default_installers(
             })
         },
         {
-            let tr_doc = document_root.clone();
-            let tr_ext = extensions_dir.clone();
-            let modules = modules.clone();
+            let binding = IncludeBinding::new(&document_root, &extensions_dir, modules.clone())
+                .with_embedded_root(cfg.embedded_root.clone())
+                .with_include_paths(cfg.include_paths.clone())
+                .with_expression_output(expression_output);
+            Arc::new(move |scope: &mut v8::ContextScope<v8::HandleScope>| {
+                binding.install(scope);
+            })
+        },
+        {
+            let binding = FileBinding::new(DocumentRoot::new(
+                document_root,
+                cfg.effective_index_files(),
+            ));
+            Arc::new(move |scope: &mut v8::ContextScope<v8::HandleScope>| {
+                binding.install(scope);
+            })
+        },
+        Arc::new(|scope: &mut v8::ContextScope<v8::HandleScope>| {
+            StoreBinding.install(scope);
+        }),
+        Arc::new(|scope: &mut v8::ContextScope<v8::HandleScope>| {
+            EncodingBinding.install(scope);
+        }),
+        Arc::new(|scope: &mut v8::ContextScope<v8::HandleScope>| {
+            HashBinding.install(scope);
+        }),
+        Arc::new(|scope: &mut v8::ContextScope<v8::HandleScope>| {
+            UrlBinding.install(scope);
+        }),
+        Arc::new(|scope: &mut v8::ContextScope<v8::HandleScope>| {
+            JsonBinding.install(scope);
+        }),
+        Arc::new(|scope: &mut v8::ContextScope<v8::HandleScope>| {
+            TimeBinding.install(scope);
+        }),
+        Arc::new(|scope: &mut v8::ContextScope<v8::HandleScope>| {
+            HtmlBinding.install(scope);
+        }),
+        Arc::new(|scope: &mut v8::ContextScope<v8::HandleScope>| {
+            PathBinding.install(scope);
+        }),
+        Arc::new(|scope: &mut v8::ContextScope<v8::HandleScope>| {
+            AbortBinding.install(scope);
+        }),
+        Arc::new(|scope: &mut v8::ContextScope<v8::HandleScope>| {
+            RedirectBinding.install(scope);
+        }),
+        {
+            let binding = RenderBinding::new(expression_output);
             Arc::new(move |scope: &mut v8::ContextScope<v8::HandleScope>| {
-                IncludeBinding::new(tr_doc.clone(), tr_ext.clone(), modules.clone()).install(scope);
+                binding.install(scope);
             })
         },
-    ]
+        {
+            let binding = LogBinding::new(cfg.logger());
+            Arc::new(move |scope: &mut v8::ContextScope<v8::HandleScope>| {
+                binding.install(scope);
+            })
+        },
+        {
+            let binding = AssertBinding::new(cfg.asserts, cfg.logger());
+            Arc::new(move |scope: &mut v8::ContextScope<v8::HandleScope>| {
+                binding.install(scope);
+            })
+        },
+        {
+            let rng = Arc::new(Mutex::new(random::Rng::from_seed(cfg.rng_seed)));
+            let binding = RandomBinding::new(rng);
+            Arc::new(move |scope: &mut v8::ContextScope<v8::HandleScope>| {
+                binding.install(scope);
+            })
+        },
+    ];
+    if let Some(source) = cfg.expression_stringifier.source() {
+        let omit_nullish = cfg.expression_output == parser::ExpressionOutput::OmitNullish;
+        let binding = StringifierBinding::new(source, omit_nullish);
+        installers.push(Arc::new(
+            move |scope: &mut v8::ContextScope<v8::HandleScope>| {
+                binding.install(scope);
+            },
+        ));
+    }
+    installers
 }
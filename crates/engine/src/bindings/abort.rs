@@ -0,0 +1,53 @@
+//! `abort(status, message?)`: end a render with an HTTP error status.
+
+use super::{InstallBindings, throw_type_error};
+use jhp_executor::abort::{self, Abort};
+
+/// Installs `abort(status, message?)`, which stops the render like a thrown error but
+/// answers the request with `status` (400-599) and `message` as the body (the status'
+/// reason phrase without one) instead of the output. Being an exception, it unwinds
+/// through `include()` and `await`, and a `try`/`catch` around it can intercept it.
+pub struct AbortBinding;
+
+impl InstallBindings for AbortBinding {
+    fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
+        let global = scope.get_current_context().global(scope);
+        let function = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             _rv: v8::ReturnValue| {
+                let status = args.get(0);
+                let status = status
+                    .is_number()
+                    .then(|| status.integer_value(scope))
+                    .flatten()
+                    .and_then(|s| u16::try_from(s).ok())
+                    .filter(|s| (400..=599).contains(s));
+                let Some(status) = status else {
+                    throw_type_error(
+                        scope,
+                        "abort: status must be an HTTP error status (400-599)",
+                    );
+                    return;
+                };
+                let message = args.get(1);
+                let message =
+                    (!message.is_null_or_undefined()).then(|| message.to_rust_string_lossy(scope));
+                abort::throw(
+                    scope,
+                    &Abort {
+                        status,
+                        message,
+                        location: None,
+                    },
+                );
+            },
+        )
+        .build(scope)
+        .expect("Failed to create abort function");
+
+        if let Some(key) = v8::String::new(scope, "abort") {
+            let _ = global.set(scope, key.into(), function.into());
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! `assert(condition, message?)`: cheap sanity checks in templates.
+
+use super::{InstallBindings, throw_error};
+use crate::config::AssertMode;
+use crate::log::{LogEntry, LogLevel, Logger};
+use jhp_executor::abort::{self, Abort};
+use jhp_executor::v8utils::current_resource;
+
+/// Installs `assert(condition, message?)`, which does nothing when `condition` is truthy.
+/// A falsy one is handled according to [`AssertMode`]:
+/// - `Throw`: throws `Error("assertion failed: <message>")`, which stops the render and
+///   is reported at the call like any other error.
+/// - `Generic`: logs the message as an error entry and aborts with a bare 500.
+/// - `Off`: ignored.
+pub struct AssertBinding {
+    pub mode: AssertMode,
+    /// Where `Generic` mode writes the failed assertion.
+    pub logger: Logger,
+}
+
+impl AssertBinding {
+    pub fn new(mode: AssertMode, logger: Logger) -> Self {
+        Self { mode, logger }
+    }
+}
+
+impl InstallBindings for AssertBinding {
+    fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
+        let global = scope.get_current_context().global(scope);
+
+        // SAFETY: see `InstallBindings`; the binding outlives the context
+        let state_ptr = self as *const Self as *mut std::ffi::c_void;
+        let external = v8::External::new(scope, state_ptr);
+
+        let function = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             _rv: v8::ReturnValue| {
+                let Ok(external) = v8::Local::<v8::External>::try_from(args.data()) else {
+                    return;
+                };
+                // SAFETY: the binding outlives the context.
+                let AssertBinding { mode, logger } =
+                    unsafe { &*(external.value() as *const AssertBinding) };
+                if *mode == AssertMode::Off || args.get(0).boolean_value(scope) {
+                    return;
+                }
+
+                let message = args.get(1);
+                let message = if message.is_null_or_undefined() {
+                    "assertion failed".to_string()
+                } else {
+                    format!("assertion failed: {}", message.to_rust_string_lossy(scope))
+                };
+                if *mode == AssertMode::Throw {
+                    throw_error(scope, &message);
+                    return;
+                }
+                logger.log(&LogEntry {
+                    level: LogLevel::Error,
+                    resource: current_resource(scope),
+                    message,
+                    fields: serde_json::Map::new(),
+                });
+                abort::throw(
+                    scope,
+                    &Abort {
+                        status: 500,
+                        message: None,
+                        location: None,
+                    },
+                );
+            },
+        )
+        .data(external.into())
+        .build(scope)
+        .expect("Failed to create assert function");
+
+        if let Some(key) = v8::String::new(scope, "assert") {
+            let _ = global.set(scope, key.into(), function.into());
+        }
+    }
+}
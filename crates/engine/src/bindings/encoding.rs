@@ -0,0 +1,160 @@
+//! `base64Encode`/`base64Decode` and `hexEncode`/`hexDecode`.
+
+use super::{InstallBindings, bytes_to_uint8array, throw_error, value_to_bytes};
+use base64::Engine as _;
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+
+/// Standard alphabet; padding is written on encode and optional on decode.
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Installs binary-to-text helpers:
+/// - `base64Encode(data)` / `hexEncode(data)`: encode a string (as UTF-8) or bytes
+///   (`Uint8Array`/`ArrayBuffer`) and return the encoded string.
+/// - `base64Decode(text, 'utf8'?)` / `hexDecode(text, 'utf8'?)`: decode into a `Uint8Array`,
+///   or into a string when `'utf8'` is passed.
+///
+/// Malformed input (or non-UTF-8 bytes when decoding to a string) throws an `Error`.
+pub struct EncodingBinding;
+
+impl InstallBindings for EncodingBinding {
+    fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
+        let global = scope.get_current_context().global(scope);
+
+        let functions = [
+            (
+                "base64Encode",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     rv: v8::ReturnValue| {
+                        encode(scope, &args, rv, |bytes| BASE64.encode(bytes));
+                    },
+                )
+                .build(scope),
+            ),
+            (
+                "base64Decode",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     rv: v8::ReturnValue| {
+                        decode(scope, &args, rv, "base64Decode", |text| {
+                            BASE64.decode(text.trim()).map_err(|e| e.to_string())
+                        });
+                    },
+                )
+                .build(scope),
+            ),
+            (
+                "hexEncode",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     rv: v8::ReturnValue| {
+                        encode(scope, &args, rv, hex_encode);
+                    },
+                )
+                .build(scope),
+            ),
+            (
+                "hexDecode",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     rv: v8::ReturnValue| {
+                        decode(scope, &args, rv, "hexDecode", |text| {
+                            hex_decode(text.trim())
+                        });
+                    },
+                )
+                .build(scope),
+            ),
+        ];
+
+        for (name, function) in functions {
+            let function = function.unwrap_or_else(|| panic!("Failed to create {} function", name));
+            if let Some(key) = v8::String::new(scope, name) {
+                let _ = global.set(scope, key.into(), function.into());
+            }
+        }
+    }
+}
+
+fn encode(
+    scope: &mut v8::HandleScope,
+    args: &v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+    encoder: impl Fn(&[u8]) -> String,
+) {
+    let Some(bytes) = value_to_bytes(scope, args.get(0)) else {
+        return;
+    };
+    if let Some(s) = v8::String::new(scope, &encoder(&bytes)) {
+        rv.set(s.into());
+    }
+}
+
+fn decode(
+    scope: &mut v8::HandleScope,
+    args: &v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+    name: &str,
+    decoder: impl Fn(&str) -> Result<Vec<u8>, String>,
+) {
+    let text = args.get(0).to_rust_string_lossy(scope);
+    let as_string = args.get(1).to_rust_string_lossy(scope) == "utf8";
+    let bytes = match decoder(&text) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            throw_error(scope, &format!("{}: invalid input: {}", name, e));
+            return;
+        }
+    };
+    if as_string {
+        match String::from_utf8(bytes) {
+            Ok(s) => {
+                if let Some(s) = v8::String::new(scope, &s) {
+                    rv.set(s.into());
+                }
+            }
+            Err(_) => throw_error(
+                scope,
+                &format!("{}: decoded bytes are not valid UTF-8", name),
+            ),
+        }
+    } else if let Some(array) = bytes_to_uint8array(scope, bytes) {
+        rv.set(array.into());
+    }
+}
+
+pub(super) fn hex_encode(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        out.push(DIGITS[(b >> 4) as usize] as char);
+        out.push(DIGITS[(b & 0x0f) as usize] as char);
+    }
+    out
+}
+
+fn hex_decode(text: &str) -> Result<Vec<u8>, String> {
+    if !text.len().is_multiple_of(2) {
+        return Err("odd number of digits".to_string());
+    }
+    text.as_bytes()
+        .chunks(2)
+        .enumerate()
+        .map(|(i, pair)| match (hex_digit(pair[0]), hex_digit(pair[1])) {
+            (Some(hi), Some(lo)) => Ok(hi << 4 | lo),
+            _ => Err(format!("invalid digit at offset {}", i * 2)),
+        })
+        .collect()
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|d| d as u8)
+}
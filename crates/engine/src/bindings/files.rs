@@ -0,0 +1,112 @@
+//! `readFile(path)` and `writeFile(path, data)`: file access confined to the document root.
+
+use super::{InstallBindings, throw_error, value_to_bytes};
+use crate::fs::DocumentRoot;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Installs `readFile(path)` returning the file contents as a string, and
+/// `writeFile(path, data)` accepting a string or bytes (`Uint8Array`/`ArrayBuffer`).
+/// Paths are resolved with [`DocumentRoot::resolve`], so templates cannot reach
+/// files outside the document root. Both throw an `Error` on failure.
+pub struct FileBinding {
+    pub doc_root: DocumentRoot,
+}
+
+impl FileBinding {
+    pub fn new(doc_root: DocumentRoot) -> Self {
+        Self { doc_root }
+    }
+}
+
+impl InstallBindings for FileBinding {
+    fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
+        let global = scope.get_current_context().global(scope);
+
+        // SAFETY: see `InstallBindings`; the binding outlives the context
+        let state_ptr = &self.doc_root as *const DocumentRoot as *mut std::ffi::c_void;
+        let external = v8::External::new(scope, state_ptr);
+
+        let read_fn = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             mut rv: v8::ReturnValue| {
+                let root = doc_root_from(&args);
+                let path = args.get(0).to_rust_string_lossy(scope);
+                match root.resolve(&path).and_then(fs::read_to_string) {
+                    Ok(contents) => {
+                        if let Some(s) = v8::String::new(scope, &contents) {
+                            rv.set(s.into());
+                        }
+                    }
+                    Err(e) => throw_error(scope, &format!("readFile('{}'): {}", path, e)),
+                }
+            },
+        )
+        .data(external.into())
+        .build(scope)
+        .expect("Failed to create readFile function");
+
+        let write_fn = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             _rv: v8::ReturnValue| {
+                let root = doc_root_from(&args);
+                let path = args.get(0).to_rust_string_lossy(scope);
+                let Some(data) = value_to_bytes(scope, args.get(1)) else {
+                    return;
+                };
+                if let Err(e) = root.resolve(&path).and_then(|p| write_atomic(&p, &data)) {
+                    throw_error(scope, &format!("writeFile('{}'): {}", path, e));
+                }
+            },
+        )
+        .data(external.into())
+        .build(scope)
+        .expect("Failed to create writeFile function");
+
+        if let Some(key) = v8::String::new(scope, "readFile") {
+            let _ = global.set(scope, key.into(), read_fn.into());
+        }
+        if let Some(key) = v8::String::new(scope, "writeFile") {
+            let _ = global.set(scope, key.into(), write_fn.into());
+        }
+    }
+}
+
+fn doc_root_from<'a>(args: &v8::FunctionCallbackArguments) -> &'a DocumentRoot {
+    let ptr = v8::Local::<v8::External>::try_from(args.data())
+        .map(|e| e.value() as *const DocumentRoot)
+        .unwrap();
+    // SAFETY: the DocumentRoot belongs to the binding, which outlives the context.
+    unsafe { &*ptr }
+}
+
+/// Write `data` to a temporary sibling of `path` and rename it into place, so readers
+/// never observe a partially written file.
+fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "missing file name"))?;
+    let tmp = path.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        name.to_string_lossy(),
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let written = fs::File::create(&tmp).and_then(|mut f| {
+        f.write_all(data)?;
+        f.sync_all()
+    });
+    match written.and_then(|_| fs::rename(&tmp, path)) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = fs::remove_file(&tmp);
+            Err(e)
+        }
+    }
+}
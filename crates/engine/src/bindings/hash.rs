@@ -0,0 +1,69 @@
+//! `sha256(data)` and `hmacSha256(key, data)`.
+
+use super::encoding::hex_encode;
+use super::{InstallBindings, value_to_bytes};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+/// Installs `sha256(data)` and `hmacSha256(key, data)`, both returning a lowercase hex
+/// digest. `key` and `data` may be strings (hashed as UTF-8) or bytes
+/// (`Uint8Array`/`ArrayBuffer`).
+pub struct HashBinding;
+
+impl InstallBindings for HashBinding {
+    fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
+        let global = scope.get_current_context().global(scope);
+
+        let sha256_fn = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             mut rv: v8::ReturnValue| {
+                let Some(data) = value_to_bytes(scope, args.get(0)) else {
+                    return;
+                };
+                let digest = hex_encode(&sha256(&data));
+                if let Some(s) = v8::String::new(scope, &digest) {
+                    rv.set(s.into());
+                }
+            },
+        )
+        .build(scope)
+        .expect("Failed to create sha256 function");
+
+        let hmac_fn = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             mut rv: v8::ReturnValue| {
+                let Some(key) = value_to_bytes(scope, args.get(0)) else {
+                    return;
+                };
+                let Some(data) = value_to_bytes(scope, args.get(1)) else {
+                    return;
+                };
+                let digest = hex_encode(&hmac_sha256(&key, &data));
+                if let Some(s) = v8::String::new(scope, &digest) {
+                    rv.set(s.into());
+                }
+            },
+        )
+        .build(scope)
+        .expect("Failed to create hmacSha256 function");
+
+        if let Some(key) = v8::String::new(scope, "sha256") {
+            let _ = global.set(scope, key.into(), sha256_fn.into());
+        }
+        if let Some(key) = v8::String::new(scope, "hmacSha256") {
+            let _ = global.set(scope, key.into(), hmac_fn.into());
+        }
+    }
+}
+
+pub(crate) fn sha256(data: &[u8]) -> Vec<u8> {
+    Sha256::digest(data).to_vec()
+}
+
+pub(crate) fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
@@ -0,0 +1,112 @@
+//! `htmlspecialchars` and `nl2br`, named and behaving as in PHP.
+
+use super::InstallBindings;
+
+/// Escape `'` (PHP's `ENT_HTML_QUOTE_SINGLE`).
+const QUOTE_SINGLE: i64 = 1;
+/// Escape `"` (PHP's `ENT_HTML_QUOTE_DOUBLE`).
+const QUOTE_DOUBLE: i64 = 2;
+
+/// `htmlspecialchars` flag constants, with PHP's values.
+const FLAGS: [(&str, i64); 3] = [
+    ("ENT_NOQUOTES", 0),
+    ("ENT_COMPAT", QUOTE_DOUBLE),
+    ("ENT_QUOTES", QUOTE_SINGLE | QUOTE_DOUBLE),
+];
+
+/// Installs HTML text helpers for template authors used to PHP:
+/// - `htmlspecialchars(str, flags?)`: escape `&`, `<`, `>` and, depending on `flags`,
+///   `"` and `'`. `flags` is one of `ENT_QUOTES` (the default, as in PHP 8.1+),
+///   `ENT_COMPAT` (double quotes only) or `ENT_NOQUOTES`, all installed as globals.
+/// - `nl2br(str)`: insert `<br>` before every line break (`\r\n`, `\n\r`, `\n` or `\r`).
+///
+/// Non-string arguments are converted with `String()` first.
+pub struct HtmlBinding;
+
+impl InstallBindings for HtmlBinding {
+    fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
+        let global = scope.get_current_context().global(scope);
+        let functions = [
+            (
+                "htmlspecialchars",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     mut rv: v8::ReturnValue| {
+                        let text = args.get(0).to_rust_string_lossy(scope);
+                        let flags = args.get(1);
+                        let flags = if flags.is_undefined() {
+                            QUOTE_SINGLE | QUOTE_DOUBLE
+                        } else {
+                            flags.integer_value(scope).unwrap_or_default()
+                        };
+                        if let Some(s) = v8::String::new(scope, &html_special_chars(&text, flags)) {
+                            rv.set(s.into());
+                        }
+                    },
+                )
+                .build(scope),
+            ),
+            (
+                "nl2br",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     mut rv: v8::ReturnValue| {
+                        let text = args.get(0).to_rust_string_lossy(scope);
+                        if let Some(s) = v8::String::new(scope, &nl2br(&text)) {
+                            rv.set(s.into());
+                        }
+                    },
+                )
+                .build(scope),
+            ),
+        ];
+
+        for (name, function) in functions {
+            let function = function.unwrap_or_else(|| panic!("Failed to create {} function", name));
+            if let Some(key) = v8::String::new(scope, name) {
+                let _ = global.set(scope, key.into(), function.into());
+            }
+        }
+        for (name, value) in FLAGS {
+            if let Some(key) = v8::String::new(scope, name) {
+                let value = v8::Integer::new(scope, value as i32);
+                let _ = global.set(scope, key.into(), value.into());
+            }
+        }
+    }
+}
+
+fn html_special_chars(text: &str, flags: i64) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if flags & QUOTE_DOUBLE != 0 => out.push_str("&quot;"),
+            '\'' if flags & QUOTE_SINGLE != 0 => out.push_str("&#039;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn nl2br(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\n' && c != '\r' {
+            out.push(c);
+            continue;
+        }
+        out.push_str("<br>");
+        out.push(c);
+        // a two-character break (`\r\n` or `\n\r`) gets a single `<br>`
+        if let Some(next) = chars.next_if(|&next| (next == '\n' || next == '\r') && next != c) {
+            out.push(next);
+        }
+    }
+    out
+}
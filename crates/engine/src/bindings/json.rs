@@ -0,0 +1,82 @@
+//! `toJson`: JSON that is safe to embed in a `<script>` element.
+
+use super::InstallBindings;
+
+/// Installs `toJson(value, space?)`: `JSON.stringify` with `<`, `>`, `&`, U+2028 and
+/// U+2029 written as `\uXXXX` escapes, so `</script>` or `<!--` inside strings can't end
+/// the script element and the result is valid both as JSON and as a JavaScript literal.
+/// Values `JSON.stringify` can't encode at the top level (`undefined`, functions,
+/// symbols) give `null`; errors such as cycles are thrown as usual.
+pub struct JsonBinding;
+
+impl InstallBindings for JsonBinding {
+    fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
+        let global = scope.get_current_context().global(scope);
+        let function = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             mut rv: v8::ReturnValue| {
+                let value = args.get(0);
+                if value.is_undefined() || value.is_function() || value.is_symbol() {
+                    if let Some(s) = v8::String::new(scope, "null") {
+                        rv.set(s.into());
+                    }
+                    return;
+                }
+                let space = args.get(1);
+                let json = if space.is_undefined() {
+                    v8::json::stringify(scope, value)
+                } else {
+                    stringify_with_space(scope, value, space)
+                };
+                // None means JSON.stringify threw; the exception is already pending
+                let Some(json) = json else {
+                    return;
+                };
+                let escaped = escape_for_script(&json.to_rust_string_lossy(scope));
+                if let Some(s) = v8::String::new(scope, &escaped) {
+                    rv.set(s.into());
+                }
+            },
+        )
+        .build(scope)
+        .expect("Failed to create toJson function");
+
+        if let Some(key) = v8::String::new(scope, "toJson") {
+            let _ = global.set(scope, key.into(), function.into());
+        }
+    }
+}
+
+/// `JSON.stringify(value, null, space)`, for indented output.
+fn stringify_with_space<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    value: v8::Local<v8::Value>,
+    space: v8::Local<v8::Value>,
+) -> Option<v8::Local<'s, v8::String>> {
+    let global = scope.get_current_context().global(scope);
+    let json_key = v8::String::new(scope, "JSON")?;
+    let json = global.get(scope, json_key.into())?;
+    let json = v8::Local::<v8::Object>::try_from(json).ok()?;
+    let stringify_key = v8::String::new(scope, "stringify")?;
+    let stringify = json.get(scope, stringify_key.into())?;
+    let stringify = v8::Local::<v8::Function>::try_from(stringify).ok()?;
+    let null = v8::null(scope).into();
+    let result = stringify.call(scope, json.into(), &[value, null, space])?;
+    result.to_string(scope)
+}
+
+/// Escape the characters of `json` that could end a `<script>` element or a JavaScript
+/// string literal. They only occur inside JSON strings, where `\uXXXX` means the same.
+pub(crate) fn escape_for_script(json: &str) -> String {
+    let mut out = String::with_capacity(json.len());
+    for c in json.chars() {
+        match c {
+            '<' | '>' | '&' | '\u{2028}' | '\u{2029}' => {
+                out.push_str(&format!("\\u{:04x}", c as u32));
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,128 @@
+//! `$log`: structured server-side log entries written from templates.
+
+use super::{InstallBindings, throw_type_error};
+use crate::log::{LogEntry, LogLevel, Logger};
+use jhp_executor::v8utils::current_resource;
+
+/// Installs `$log` with `debug`, `info`, `warn` and `error(message, fields?)`. Each call
+/// writes one entry to the configured [`Logger`] instead of the page, tagged with the
+/// template or script that made it. `fields` must be a JSON-serializable object.
+pub struct LogBinding {
+    pub logger: Logger,
+}
+
+impl LogBinding {
+    pub fn new(logger: Logger) -> Self {
+        Self { logger }
+    }
+}
+
+impl InstallBindings for LogBinding {
+    fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
+        let global = scope.get_current_context().global(scope);
+        let obj = v8::Object::new(scope);
+
+        // SAFETY: see `InstallBindings`; the binding outlives the context
+        let state_ptr = &self.logger as *const Logger as *mut std::ffi::c_void;
+        let external = v8::External::new(scope, state_ptr);
+
+        let functions = [
+            (
+                "debug",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     _rv: v8::ReturnValue| log(scope, &args, LogLevel::Debug),
+                ),
+            ),
+            (
+                "info",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     _rv: v8::ReturnValue| log(scope, &args, LogLevel::Info),
+                ),
+            ),
+            (
+                "warn",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     _rv: v8::ReturnValue| log(scope, &args, LogLevel::Warn),
+                ),
+            ),
+            (
+                "error",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     _rv: v8::ReturnValue| log(scope, &args, LogLevel::Error),
+                ),
+            ),
+        ];
+
+        for (name, builder) in functions {
+            let function = builder
+                .data(external.into())
+                .build(scope)
+                .unwrap_or_else(|| panic!("Failed to create $log.{} function", name));
+            if let Some(key) = v8::String::new(scope, name) {
+                let _ = obj.set(scope, key.into(), function.into());
+            }
+        }
+        if let Some(key) = v8::String::new(scope, "$log") {
+            let _ = global.set(scope, key.into(), obj.into());
+        }
+    }
+}
+
+fn log(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments, level: LogLevel) {
+    let ptr = v8::Local::<v8::External>::try_from(args.data())
+        .map(|e| e.value() as *const Logger)
+        .unwrap();
+    // SAFETY: the Logger belongs to the binding, which outlives the context.
+    let logger: &Logger = unsafe { &*ptr };
+    if !logger.enabled(level) {
+        return;
+    }
+
+    let message = args.get(0).to_rust_string_lossy(scope);
+    let fields = args.get(1);
+    let fields = if fields.is_null_or_undefined() {
+        serde_json::Map::new()
+    } else {
+        match fields_to_json(scope, fields) {
+            Some(fields) => fields,
+            None => {
+                let msg = format!(
+                    "$log.{}(message, fields): fields must be a JSON-serializable object",
+                    level.as_str()
+                );
+                throw_type_error(scope, &msg);
+                return;
+            }
+        }
+    };
+
+    logger.log(&LogEntry {
+        level,
+        resource: current_resource(scope),
+        message,
+        fields,
+    });
+}
+
+fn fields_to_json(
+    scope: &mut v8::HandleScope,
+    value: v8::Local<v8::Value>,
+) -> Option<serde_json::Map<String, serde_json::Value>> {
+    if !value.is_object() || value.is_array() {
+        return None;
+    }
+    let tc = &mut v8::TryCatch::new(scope);
+    let json = v8::json::stringify(tc, value)?;
+    match serde_json::from_str(&json.to_rust_string_lossy(tc)).ok()? {
+        serde_json::Value::Object(map) => Some(map),
+        _ => None,
+    }
+}
@@ -0,0 +1,172 @@
+//! `pathJoin`, `dirname`, `basename` and `extname`: POSIX path strings, as in Node's `path`.
+
+use super::InstallBindings;
+
+/// Installs string-only path helpers; they never look at the filesystem, so `..` is
+/// resolved lexically and nothing is checked for existence:
+/// - `pathJoin(...parts)`: join the non-empty `parts` with `/` and normalize the result,
+///   collapsing repeated slashes and resolving `.` and `..`. A trailing slash is kept;
+///   joining nothing gives `"."`.
+/// - `dirname(path)`: everything before the last segment, e.g. `"/a/b"` for `"/a/b/c/"`.
+/// - `basename(path, suffix?)`: the last segment, without `suffix` when it ends with it.
+/// - `extname(path)`: the last segment from its last `.`, e.g. `".gz"` for `"a.tar.gz"`;
+///   empty without one, or when the only `.` starts the name (`".hidden"`).
+///
+/// Non-string arguments are converted with `String()` first.
+pub struct PathBinding;
+
+impl InstallBindings for PathBinding {
+    fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
+        let global = scope.get_current_context().global(scope);
+        let functions = [
+            (
+                "pathJoin",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     mut rv: v8::ReturnValue| {
+                        let parts: Vec<String> = (0..args.length())
+                            .map(|i| args.get(i).to_rust_string_lossy(scope))
+                            .collect();
+                        if let Some(s) = v8::String::new(scope, &join(&parts)) {
+                            rv.set(s.into());
+                        }
+                    },
+                )
+                .build(scope),
+            ),
+            (
+                "dirname",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     mut rv: v8::ReturnValue| {
+                        let path = args.get(0).to_rust_string_lossy(scope);
+                        if let Some(s) = v8::String::new(scope, dirname(&path)) {
+                            rv.set(s.into());
+                        }
+                    },
+                )
+                .build(scope),
+            ),
+            (
+                "basename",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     mut rv: v8::ReturnValue| {
+                        let path = args.get(0).to_rust_string_lossy(scope);
+                        let suffix = args.get(1);
+                        let suffix =
+                            (!suffix.is_undefined()).then(|| suffix.to_rust_string_lossy(scope));
+                        if let Some(s) = v8::String::new(scope, basename(&path, suffix.as_deref()))
+                        {
+                            rv.set(s.into());
+                        }
+                    },
+                )
+                .build(scope),
+            ),
+            (
+                "extname",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     mut rv: v8::ReturnValue| {
+                        let path = args.get(0).to_rust_string_lossy(scope);
+                        if let Some(s) = v8::String::new(scope, extname(&path)) {
+                            rv.set(s.into());
+                        }
+                    },
+                )
+                .build(scope),
+            ),
+        ];
+
+        for (name, function) in functions {
+            let function = function.unwrap_or_else(|| panic!("Failed to create {} function", name));
+            if let Some(key) = v8::String::new(scope, name) {
+                let _ = global.set(scope, key.into(), function.into());
+            }
+        }
+    }
+}
+
+fn join(parts: &[String]) -> String {
+    let joined = parts
+        .iter()
+        .filter(|part| !part.is_empty())
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("/");
+    normalize(&joined)
+}
+
+/// `path` with repeated slashes collapsed and `.`/`..` segments resolved. `..` above the
+/// root is dropped; in a relative path it is kept, since there is nothing to resolve it to.
+fn normalize(path: &str) -> String {
+    if path.is_empty() {
+        return ".".to_string();
+    }
+    let absolute = path.starts_with('/');
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => match segments.last() {
+                Some(&last) if last != ".." => {
+                    segments.pop();
+                }
+                _ if absolute => {}
+                _ => segments.push(".."),
+            },
+            segment => segments.push(segment),
+        }
+    }
+    let mut out = segments.join("/");
+    if absolute {
+        out.insert(0, '/');
+    }
+    if out.is_empty() {
+        out.push('.');
+    }
+    if path.ends_with('/') && !out.ends_with('/') {
+        out.push('/');
+    }
+    out
+}
+
+fn dirname(path: &str) -> &str {
+    if path.is_empty() {
+        return ".";
+    }
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        return "/";
+    }
+    match trimmed.rfind('/') {
+        None => ".",
+        Some(i) => match trimmed[..i].trim_end_matches('/') {
+            "" => "/",
+            dir => dir,
+        },
+    }
+}
+
+fn basename<'a>(path: &'a str, suffix: Option<&str>) -> &'a str {
+    let trimmed = path.trim_end_matches('/');
+    let name = trimmed.rsplit('/').next().unwrap_or_default();
+    match suffix.and_then(|suffix| name.strip_suffix(suffix)) {
+        // the suffix is never the whole name
+        Some(stem) if !stem.is_empty() => stem,
+        _ => name,
+    }
+}
+
+fn extname(path: &str) -> &str {
+    let name = basename(path, None);
+    match name.rfind('.') {
+        Some(i) if i > 0 && name != ".." => &name[i..],
+        _ => "",
+    }
+}
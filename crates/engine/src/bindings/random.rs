@@ -0,0 +1,242 @@
+//! `$random`: pseudo-random numbers that can be made reproducible with a seed.
+
+use super::encoding::hex_encode;
+use super::{InstallBindings, bytes_to_uint8array, throw_type_error};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Largest integer a JavaScript number holds exactly (`Number.MAX_SAFE_INTEGER`).
+const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+/// Most bytes one `$random.bytes(n)` call returns.
+const MAX_BYTES: usize = 65536;
+
+/// xoshiro256**, seeded through splitmix64. Fast and well distributed, but predictable
+/// from its output: not for secrets.
+pub struct Rng {
+    s: [u64; 4],
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut x = seed;
+        let mut splitmix = || {
+            x = x.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = x;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        };
+        Self {
+            s: [splitmix(), splitmix(), splitmix(), splitmix()],
+        }
+    }
+
+    /// Seeded with `seed`, or from the process' random hashing keys without one.
+    pub fn from_seed(seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(|| RandomState::new().hash_one(SystemTime::now()));
+        Self::new(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    /// Uniform in `0..n`, without the bias of a plain modulo; `n` must not be 0.
+    fn below(&mut self, n: u64) -> u64 {
+        // values under `threshold` would make the low results more likely
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let x = self.next_u64();
+            if x >= threshold {
+                return x % n;
+            }
+        }
+    }
+
+    /// Uniform in `[0, 1)`, with 53 random bits.
+    fn float(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    /// A version 4 UUID, e.g. `"3b241101-e2bb-4255-8caf-4136c566a962"`.
+    fn uuid(&mut self) -> String {
+        let mut bytes = [0u8; 16];
+        self.fill(&mut bytes);
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        let hex = hex_encode(&bytes);
+        format!(
+            "{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        )
+    }
+}
+
+/// An integer argument within JavaScript's safe range, if `value` is one.
+fn safe_integer(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> Option<i64> {
+    let n = value
+        .is_number()
+        .then(|| value.number_value(scope))
+        .flatten()?;
+    (n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER).then_some(n as i64)
+}
+
+/// The generator a `$random` function was installed with.
+fn rng_of<'a>(args: &v8::FunctionCallbackArguments) -> Option<&'a Arc<Mutex<Rng>>> {
+    let external = v8::Local::<v8::External>::try_from(args.data()).ok()?;
+    // SAFETY: the generator belongs to the binding, which outlives the context.
+    Some(unsafe { &*(external.value() as *const Arc<Mutex<Rng>>) })
+}
+
+/// Installs `$random`, drawing from one generator shared by every executor of the pool:
+/// - `int(min, max)`: an integer between `min` and `max`, both included.
+/// - `float()`: a number in `[0, 1)`.
+/// - `bytes(n)`: a `Uint8Array` of `n` random bytes (at most 65536).
+/// - `uuid()`: a random (version 4) UUID string.
+///
+/// With [`EngineConfig::rng_seed`](crate::config::EngineConfig::rng_seed) set, the values
+/// come in the same order on every run, as long as renders do (e.g. with one executor).
+/// None of them is fit for secrets such as tokens or passwords.
+pub struct RandomBinding {
+    pub rng: Arc<Mutex<Rng>>,
+}
+
+impl RandomBinding {
+    pub fn new(rng: Arc<Mutex<Rng>>) -> Self {
+        Self { rng }
+    }
+}
+
+impl InstallBindings for RandomBinding {
+    fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
+        let global = scope.get_current_context().global(scope);
+        let random = v8::Object::new(scope);
+
+        // SAFETY: see `InstallBindings`; the binding outlives the context
+        let state_ptr = &self.rng as *const Arc<Mutex<Rng>> as *mut std::ffi::c_void;
+        let external = v8::External::new(scope, state_ptr);
+
+        let int_fn = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             mut rv: v8::ReturnValue| {
+                let Some(rng) = rng_of(&args) else {
+                    return;
+                };
+                let min = safe_integer(scope, args.get(0));
+                let max = safe_integer(scope, args.get(1));
+                let (Some(min), Some(max)) = (min, max) else {
+                    throw_type_error(scope, "$random.int(min, max): min and max must be integers");
+                    return;
+                };
+                if min > max {
+                    throw_type_error(scope, "$random.int(min, max): min must not exceed max");
+                    return;
+                }
+                let span = (max - min) as u64 + 1;
+                let n = min + rng.lock().unwrap().below(span) as i64;
+                rv.set(v8::Number::new(scope, n as f64).into());
+            },
+        )
+        .data(external.into())
+        .build(scope)
+        .expect("Failed to create $random.int function");
+
+        let float_fn = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             mut rv: v8::ReturnValue| {
+                let Some(rng) = rng_of(&args) else {
+                    return;
+                };
+                let n = rng.lock().unwrap().float();
+                rv.set(v8::Number::new(scope, n).into());
+            },
+        )
+        .data(external.into())
+        .build(scope)
+        .expect("Failed to create $random.float function");
+
+        let bytes_fn = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             mut rv: v8::ReturnValue| {
+                let Some(rng) = rng_of(&args) else {
+                    return;
+                };
+                let len = safe_integer(scope, args.get(0))
+                    .and_then(|n| usize::try_from(n).ok())
+                    .filter(|&n| n <= MAX_BYTES);
+                let Some(len) = len else {
+                    throw_type_error(
+                        scope,
+                        "$random.bytes(n): n must be an integer from 0 to 65536",
+                    );
+                    return;
+                };
+                let mut bytes = vec![0u8; len];
+                rng.lock().unwrap().fill(&mut bytes);
+                if let Some(array) = bytes_to_uint8array(scope, bytes) {
+                    rv.set(array.into());
+                }
+            },
+        )
+        .data(external.into())
+        .build(scope)
+        .expect("Failed to create $random.bytes function");
+
+        let uuid_fn = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             mut rv: v8::ReturnValue| {
+                let Some(rng) = rng_of(&args) else {
+                    return;
+                };
+                let uuid = rng.lock().unwrap().uuid();
+                if let Some(s) = v8::String::new(scope, &uuid) {
+                    rv.set(s.into());
+                }
+            },
+        )
+        .data(external.into())
+        .build(scope)
+        .expect("Failed to create $random.uuid function");
+
+        for (name, function) in [
+            ("int", int_fn),
+            ("float", float_fn),
+            ("bytes", bytes_fn),
+            ("uuid", uuid_fn),
+        ] {
+            if let Some(key) = v8::String::new(scope, name) {
+                let _ = random.set(scope, key.into(), function.into());
+            }
+        }
+        if let Some(key) = v8::String::new(scope, "$random") {
+            let _ = global.set(scope, key.into(), random.into());
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! `redirect(url, status?)` / `redirectPermanent(url)`: end a render with a redirect.
+
+use super::{InstallBindings, throw_type_error};
+use jhp_executor::abort::{self, Abort};
+
+/// Statuses a redirect may use: 301 and 308 are permanent, 302, 303 and 307 temporary;
+/// 307 and 308 keep the request method, 303 always switches to `GET`.
+const REDIRECT_STATUSES: [u16; 5] = [301, 302, 303, 307, 308];
+
+/// Installs `redirect(url, status?)`, answering with `status` (302 by default; 301, 303,
+/// 307 or 308 otherwise) and `Location: url`, and `redirectPermanent(url)` for a 301.
+/// Like `abort()`, they stop the render: output echoed before is discarded and the body is
+/// empty. `url` must be a well-formed absolute or relative URL reference, with anything
+/// outside printable ASCII percent-encoded (as `encodeURI` does).
+pub struct RedirectBinding;
+
+impl InstallBindings for RedirectBinding {
+    fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
+        let global = scope.get_current_context().global(scope);
+
+        let redirect = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             _rv: v8::ReturnValue| {
+                let status = args.get(1);
+                let status = if status.is_null_or_undefined() {
+                    Some(302)
+                } else {
+                    status
+                        .is_number()
+                        .then(|| status.integer_value(scope))
+                        .flatten()
+                        .and_then(|s| u16::try_from(s).ok())
+                        .filter(|s| REDIRECT_STATUSES.contains(s))
+                };
+                let Some(status) = status else {
+                    throw_type_error(
+                        scope,
+                        "redirect: status must be a redirect status (301, 302, 303, 307 or 308)",
+                    );
+                    return;
+                };
+                throw_redirect(scope, "redirect", args.get(0), status);
+            },
+        )
+        .build(scope)
+        .expect("Failed to create redirect function");
+
+        let permanent = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             _rv: v8::ReturnValue| {
+                throw_redirect(scope, "redirectPermanent", args.get(0), 301);
+            },
+        )
+        .build(scope)
+        .expect("Failed to create redirectPermanent function");
+
+        for (name, function) in [("redirect", redirect), ("redirectPermanent", permanent)] {
+            if let Some(key) = v8::String::new(scope, name) {
+                let _ = global.set(scope, key.into(), function.into());
+            }
+        }
+    }
+}
+
+/// Stop the render with a redirect to `url`, or throw a `TypeError` naming `function`
+/// when it isn't a valid URL reference.
+fn throw_redirect(
+    scope: &mut v8::HandleScope,
+    function: &str,
+    url: v8::Local<v8::Value>,
+    status: u16,
+) {
+    let url = url
+        .is_string()
+        .then(|| url.to_rust_string_lossy(scope))
+        .filter(|url| is_url_reference(url));
+    let Some(url) = url else {
+        throw_type_error(
+            scope,
+            &format!(
+                "{}: url must be a well-formed absolute or relative URL",
+                function
+            ),
+        );
+        return;
+    };
+    abort::throw(
+        scope,
+        &Abort {
+            status,
+            message: None,
+            location: Some(url),
+        },
+    );
+}
+
+/// Whether `url` is a URI reference as RFC 3986 defines it: only unreserved, reserved and
+/// percent-encoded characters, and a valid scheme when its first segment has a `:` (which
+/// a relative reference's can't). Whitespace and control characters are out, so a
+/// `Location` can't smuggle in other headers, and so is `\`, which browsers read as `/`.
+fn is_url_reference(url: &str) -> bool {
+    const ALLOWED: &str = "-._~:/?#[]@!$&'()*+,;=%";
+    let bytes = url.as_bytes();
+    if url.is_empty()
+        || !bytes
+            .iter()
+            .all(|b| b.is_ascii_alphanumeric() || ALLOWED.as_bytes().contains(b))
+    {
+        return false;
+    }
+    let escapes_ok = bytes.iter().enumerate().all(|(i, &b)| {
+        b != b'%'
+            || bytes
+                .get(i + 1..i + 3)
+                .is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+    });
+    if !escapes_ok {
+        return false;
+    }
+
+    let first_segment = url.split(['/', '?', '#']).next().unwrap_or("");
+    match first_segment.split_once(':') {
+        Some((scheme, _)) => {
+            let mut chars = scheme.chars();
+            chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+                && chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        }
+        None => true,
+    }
+}
@@ -0,0 +1,241 @@
+//! `render(template, data?)`: render a JHP template string to a string.
+
+use super::{
+    InstallBindings, restore_global, run_source, settle_promise, swap_global, throw_error,
+    throw_type_error,
+};
+use jhp_parser as parser;
+use std::cell::Cell;
+
+/// How deeply `render()` calls may nest before the next one throws, so a template that
+/// renders itself fails instead of overflowing the stack.
+const MAX_DEPTH: usize = 32;
+
+/// Resource name reported for errors in a rendered template.
+const RESOURCE: &str = "render()";
+
+/// Words that can't name a local; `data` properties named like this are not bound.
+const RESERVED: &[&str] = &[
+    "arguments",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "null",
+    "return",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+thread_local! {
+    /// `render()` calls in progress on this executor thread.
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Installs `render(template, data?)`, which renders a JHP template string (e.g. one
+/// loaded from a database) and returns its output instead of echoing it:
+/// - `template` is parsed like a `.jhp` file and runs in its own function scope, so its
+///   declarations stay out of the caller's globals.
+/// - Each own property of `data` named like a JS identifier is a local of the template.
+/// - Everything echoed while it runs, including by files it includes, is captured.
+/// - A template using `await` is settled before `render()` returns.
+///
+/// Calls nest at most 32 deep; the next one throws.
+pub struct RenderBinding {
+    /// How `<?= expr ?>` values in the rendered template are printed.
+    pub expression_output: parser::ExpressionOutput,
+}
+
+impl RenderBinding {
+    pub fn new(expression_output: parser::ExpressionOutput) -> Self {
+        Self { expression_output }
+    }
+}
+
+impl InstallBindings for RenderBinding {
+    fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
+        let global = scope.get_current_context().global(scope);
+
+        // SAFETY: see `InstallBindings`; the binding outlives the context
+        let state_ptr =
+            &self.expression_output as *const parser::ExpressionOutput as *mut std::ffi::c_void;
+        let external = v8::External::new(scope, state_ptr);
+
+        let render_fn = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             mut rv: v8::ReturnValue| {
+                let Ok(external) = v8::Local::<v8::External>::try_from(args.data()) else {
+                    return;
+                };
+                let output = unsafe { *(external.value() as *const parser::ExpressionOutput) };
+
+                let template = args.get(0);
+                if !template.is_string() {
+                    throw_type_error(scope, "render(template, data): template must be a string");
+                    return;
+                }
+                let template = template.to_rust_string_lossy(scope);
+                let data = args.get(1);
+                let data = if data.is_null_or_undefined() {
+                    v8::Object::new(scope)
+                } else if let Ok(data) = v8::Local::<v8::Object>::try_from(data) {
+                    data
+                } else {
+                    throw_type_error(scope, "render(template, data): data must be an object");
+                    return;
+                };
+
+                if DEPTH.get() >= MAX_DEPTH {
+                    throw_error(
+                        scope,
+                        &format!("render(): templates nested more than {} deep", MAX_DEPTH),
+                    );
+                    return;
+                }
+
+                let Some(code) = template_function(scope, &template, data, output) else {
+                    return;
+                };
+                let chunks = v8::Array::new(scope, 0);
+                let Some(capture) = v8::Function::builder(capture_echo)
+                    .data(chunks.into())
+                    .build(scope)
+                else {
+                    return;
+                };
+
+                DEPTH.set(DEPTH.get() + 1);
+                let previous = swap_global(scope, "echo", capture.into());
+                let result = run_source(scope, &code, RESOURCE)
+                    .and_then(|f| v8::Local::<v8::Function>::try_from(f).ok())
+                    .and_then(|f| {
+                        let recv = v8::undefined(scope).into();
+                        f.call(scope, recv, &[data.into()])
+                    })
+                    .and_then(|value| settle_promise(scope, value));
+                restore_global(scope, "echo", previous);
+                DEPTH.set(DEPTH.get() - 1);
+
+                let Some(result) = result else {
+                    // the template threw; leave the exception to propagate
+                    return;
+                };
+                if result.is_promise() {
+                    throw_error(
+                        scope,
+                        "render(): template is still awaiting when it must return",
+                    );
+                    return;
+                }
+                let mut out = String::new();
+                for i in 0..chunks.length() {
+                    if let Some(chunk) = chunks.get_index(scope, i) {
+                        out.push_str(&chunk.to_rust_string_lossy(scope));
+                    }
+                }
+                if let Some(s) = v8::String::new(scope, &out) {
+                    rv.set(s.into());
+                }
+            },
+        )
+        .data(external.into())
+        .build(scope)
+        .expect("Failed to create render function");
+
+        if let Some(key) = v8::String::new(scope, "render") {
+            let _ = global.set(scope, key.into(), render_fn.into());
+        }
+    }
+}
+
+/// JS source of a function taking `data` and running `template` with its fields as locals.
+/// The template's first line stays on line 1, so error positions match the template.
+fn template_function(
+    scope: &mut v8::HandleScope,
+    template: &str,
+    data: v8::Local<v8::Object>,
+    output: parser::ExpressionOutput,
+) -> Option<String> {
+    let names = data.get_own_property_names(scope, Default::default())?;
+    let mut locals = Vec::new();
+    for i in 0..names.length() {
+        let Some(name) = names.get_index(scope, i) else {
+            continue;
+        };
+        let name = name.to_rust_string_lossy(scope);
+        if is_identifier(&name) {
+            locals.push(name);
+        }
+    }
+
+    let mut p = parser::Parser::new(template);
+    let js = parser::template_to_js(p.parse().blocks, output);
+    // an async template's promise is what the function returns, to be settled by the caller
+    let body = if js.is_async {
+        format!("return {}", js.code)
+    } else {
+        js.code
+    };
+    Some(format!(
+        "(function ({{ {} }}) {{ {}\n}})",
+        locals.join(", "),
+        body
+    ))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    (first.is_alphabetic() || first == '_' || first == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        && !RESERVED.contains(&name)
+}
+
+/// `echo` while a template renders: appends to the array in the function's data.
+fn capture_echo(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    let Ok(chunks) = v8::Local::<v8::Array>::try_from(args.data()) else {
+        return;
+    };
+    if let Some(text) = args.get(0).to_string(scope) {
+        let _ = chunks.set_index(scope, chunks.length(), text.into());
+    }
+}
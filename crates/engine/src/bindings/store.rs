@@ -0,0 +1,140 @@
+//! `$store`: a key/value store shared by every executor in the process.
+
+use super::{InstallBindings, throw_error, throw_type_error};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+static STORE: LazyLock<Mutex<HashMap<String, Value>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Installs `$store`, an in-memory key/value store shared across requests and executor
+/// threads, e.g. for counters or small caches. Values are copied in and out as JSON, so
+/// only JSON-serializable data survives. The store lives only as long as the process
+/// and is not shared between processes.
+/// - `get(key)`: the stored value, or `undefined` when missing.
+/// - `set(key, value)`: store a copy of `value`.
+/// - `incr(key, by = 1)`: atomically add `by` to a numeric value (missing counts as 0)
+///   and return the result.
+/// - `delete(key)`: remove `key`, returning whether it was present.
+pub struct StoreBinding;
+
+impl InstallBindings for StoreBinding {
+    fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
+        let global = scope.get_current_context().global(scope);
+        let store = v8::Object::new(scope);
+
+        let get_fn = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             mut rv: v8::ReturnValue| {
+                let key = args.get(0).to_rust_string_lossy(scope);
+                let value = STORE.lock().unwrap().get(&key).cloned();
+                if let Some(value) = value.and_then(|v| json_to_v8(scope, &v)) {
+                    rv.set(value);
+                }
+            },
+        )
+        .build(scope)
+        .expect("Failed to create $store.get function");
+
+        let set_fn = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             _rv: v8::ReturnValue| {
+                let key = args.get(0).to_rust_string_lossy(scope);
+                let Some(value) = v8_to_json(scope, args.get(1)) else {
+                    let msg = format!("$store.set('{}'): value is not JSON-serializable", key);
+                    throw_type_error(scope, &msg);
+                    return;
+                };
+                STORE.lock().unwrap().insert(key, value);
+            },
+        )
+        .build(scope)
+        .expect("Failed to create $store.set function");
+
+        let incr_fn = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             mut rv: v8::ReturnValue| {
+                let key = args.get(0).to_rust_string_lossy(scope);
+                let by = args.get(1);
+                let by = if by.is_undefined() {
+                    1.0
+                } else if by.is_number() {
+                    by.number_value(scope).unwrap_or(0.0)
+                } else {
+                    throw_type_error(
+                        scope,
+                        &format!("$store.incr('{}'): by must be a number", key),
+                    );
+                    return;
+                };
+
+                let result = {
+                    let mut store = STORE.lock().unwrap();
+                    let current = match store.get(&key) {
+                        None => Ok(0.0),
+                        Some(Value::Number(n)) => Ok(n.as_f64().unwrap_or(0.0)),
+                        Some(_) => Err("stored value is not a number"),
+                    };
+                    current.and_then(|n| {
+                        let next = n + by;
+                        let number = serde_json::Number::from_f64(next)
+                            .ok_or("result is not a finite number")?;
+                        store.insert(key.clone(), Value::Number(number));
+                        Ok(next)
+                    })
+                };
+                match result {
+                    Ok(next) => rv.set(v8::Number::new(scope, next).into()),
+                    Err(e) => throw_error(scope, &format!("$store.incr('{}'): {}", key, e)),
+                }
+            },
+        )
+        .build(scope)
+        .expect("Failed to create $store.incr function");
+
+        let delete_fn = v8::Function::builder(
+            |scope: &mut v8::HandleScope,
+             args: v8::FunctionCallbackArguments,
+             mut rv: v8::ReturnValue| {
+                let key = args.get(0).to_rust_string_lossy(scope);
+                let removed = STORE.lock().unwrap().remove(&key).is_some();
+                rv.set(v8::Boolean::new(scope, removed).into());
+            },
+        )
+        .build(scope)
+        .expect("Failed to create $store.delete function");
+
+        for (name, f) in [
+            ("get", get_fn),
+            ("set", set_fn),
+            ("incr", incr_fn),
+            ("delete", delete_fn),
+        ] {
+            if let Some(key) = v8::String::new(scope, name) {
+                let _ = store.set(scope, key.into(), f.into());
+            }
+        }
+        if let Some(key) = v8::String::new(scope, "$store") {
+            let _ = global.set(scope, key.into(), store.into());
+        }
+    }
+}
+
+/// Serialize a JS value with `JSON.stringify`. `None` if it throws or yields `undefined`.
+fn v8_to_json(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> Option<Value> {
+    let tc = &mut v8::TryCatch::new(scope);
+    let json = v8::json::stringify(tc, value)?;
+    serde_json::from_str(&json.to_rust_string_lossy(tc)).ok()
+}
+
+fn json_to_v8<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    value: &Value,
+) -> Option<v8::Local<'s, v8::Value>> {
+    let text = v8::String::new(scope, &value.to_string())?;
+    v8::json::parse(scope, text)
+}
@@ -0,0 +1,46 @@
+//! The function `<?= expr ?>` values are printed with under a configured stringifier.
+
+use super::InstallBindings;
+use jhp_executor::v8utils::compile_and_run_current;
+use jhp_parser::STRINGIFIER_GLOBAL;
+
+/// Installs the hidden global [`STRINGIFIER_GLOBAL`] that expressions call under
+/// [`ExpressionOutput::Stringifier`](jhp_parser::ExpressionOutput::Stringifier): the
+/// configured function, with its result passed through `String()`. With `omit_nullish`,
+/// `null` and `undefined` print nothing without reaching it.
+///
+/// The function's source is evaluated in each context. When it doesn't evaluate to a
+/// function, e.g. for a syntax error, every expression throws that error instead.
+pub struct StringifierBinding {
+    /// Script defining the global, built once from the function's source.
+    script: String,
+}
+
+impl StringifierBinding {
+    pub fn new(source: &str, omit_nullish: bool) -> Self {
+        // evaluated from a string literal, so a syntax error in it can be caught
+        let source = serde_json::to_string(&format!("({}\n)", source)).unwrap_or_default();
+        let script = format!(
+            "Object.defineProperty(globalThis, {global:?}, {{ value: (() => {{
+    let stringify;
+    try {{
+        stringify = (0, eval)({source});
+    }} catch (e) {{
+        return () => {{ throw e; }};
+    }}
+    if (typeof stringify !== 'function') {{
+        return () => {{ throw new TypeError('expression stringifier is not a function'); }};
+    }}
+    return (value) => ({omit_nullish} && value == null) ? '' : String(stringify(value));
+}})() }});",
+            global = STRINGIFIER_GLOBAL,
+        );
+        Self { script }
+    }
+}
+
+impl InstallBindings for StringifierBinding {
+    fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
+        let _ = compile_and_run_current(scope, &self.script, "<expression stringifier>");
+    }
+}
@@ -0,0 +1,55 @@
+//! `now()` and `hrtime()`: monotonic timers for measuring template sections.
+
+use super::InstallBindings;
+use std::sync::LazyLock;
+use std::time::Instant;
+
+/// The shared origin of both timers, fixed the first time either is read.
+static ORIGIN: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+/// Installs monotonic timers that, unlike `Date.now()`, never go backwards:
+/// - `now()`: milliseconds as a fractional number, like `performance.now()`.
+/// - `hrtime()`: nanoseconds as a `BigInt`.
+///
+/// Both count from the same arbitrary origin shared by every executor, so only
+/// differences between readings are meaningful.
+pub struct TimeBinding;
+
+impl InstallBindings for TimeBinding {
+    fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
+        let global = scope.get_current_context().global(scope);
+        let functions = [
+            (
+                "now",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     _args: v8::FunctionCallbackArguments,
+                     mut rv: v8::ReturnValue| {
+                        let elapsed = ORIGIN.elapsed().as_secs_f64() * 1000.0;
+                        rv.set(v8::Number::new(scope, elapsed).into());
+                    },
+                )
+                .build(scope),
+            ),
+            (
+                "hrtime",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     _args: v8::FunctionCallbackArguments,
+                     mut rv: v8::ReturnValue| {
+                        let nanos = u64::try_from(ORIGIN.elapsed().as_nanos()).unwrap_or(u64::MAX);
+                        rv.set(v8::BigInt::new_from_u64(scope, nanos).into());
+                    },
+                )
+                .build(scope),
+            ),
+        ];
+
+        for (name, function) in functions {
+            let function = function.unwrap_or_else(|| panic!("Failed to create {} function", name));
+            if let Some(key) = v8::String::new(scope, name) {
+                let _ = global.set(scope, key.into(), function.into());
+            }
+        }
+    }
+}
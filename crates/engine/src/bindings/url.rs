@@ -0,0 +1,184 @@
+//! `urlEncode`/`urlDecode`, `formEncode`/`formDecode` and `buildQuery`.
+
+use super::{InstallBindings, throw_error, throw_type_error};
+use jhp_executor::query::{
+    decode_component, decode_uri_component, encode_component, encode_form_component,
+};
+
+/// Values nested deeper than this (usually a cycle) make `buildQuery` throw.
+const MAX_DEPTH: usize = 32;
+
+/// Installs URL helpers, in two flavours that only differ in how they treat spaces:
+/// - `urlEncode(str)`: encode a URI component (a path segment, a cookie value), like
+///   `encodeURIComponent` but stricter: everything but `A-Z a-z 0-9 - _ . ~` is
+///   percent-encoded, so a space becomes `%20`.
+/// - `urlDecode(str)`: decode `%XX` escapes; `+` stays a `+`.
+/// - `formEncode(str)`: encode a form field (`application/x-www-form-urlencoded`), as
+///   `urlEncode` but with a space as `+`.
+/// - `formDecode(str)`: decode `%XX` escapes and `+` as a space, as `$query` does.
+/// - `buildQuery(object)`: form-encode an object as `a=1&b=hello+world`. Array values
+///   become repeated keys, nested objects use brackets (`user[name]=x`), and
+///   `null`/`undefined` values are left out.
+///
+/// The decoders keep malformed escapes as-is rather than throwing.
+pub struct UrlBinding;
+
+impl InstallBindings for UrlBinding {
+    fn install(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
+        let global = scope.get_current_context().global(scope);
+
+        let functions = [
+            (
+                "urlEncode",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     rv: v8::ReturnValue| {
+                        convert(scope, &args, rv, encode_component);
+                    },
+                )
+                .build(scope),
+            ),
+            (
+                "urlDecode",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     rv: v8::ReturnValue| {
+                        convert(scope, &args, rv, decode_uri_component);
+                    },
+                )
+                .build(scope),
+            ),
+            (
+                "formEncode",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     rv: v8::ReturnValue| {
+                        convert(scope, &args, rv, encode_form_component);
+                    },
+                )
+                .build(scope),
+            ),
+            (
+                "formDecode",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     rv: v8::ReturnValue| {
+                        convert(scope, &args, rv, decode_component);
+                    },
+                )
+                .build(scope),
+            ),
+            (
+                "buildQuery",
+                v8::Function::builder(
+                    |scope: &mut v8::HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     mut rv: v8::ReturnValue| {
+                        let Ok(object) = v8::Local::<v8::Object>::try_from(args.get(0)) else {
+                            throw_type_error(scope, "buildQuery: expected an object");
+                            return;
+                        };
+                        let mut pairs = Vec::new();
+                        if let Err(e) = append_fields(scope, None, object, 0, &mut pairs) {
+                            throw_error(scope, &format!("buildQuery: {}", e));
+                            return;
+                        }
+                        if let Some(s) = v8::String::new(scope, &pairs.join("&")) {
+                            rv.set(s.into());
+                        }
+                    },
+                )
+                .build(scope),
+            ),
+        ];
+
+        for (name, function) in functions {
+            let function = function.unwrap_or_else(|| panic!("Failed to create {} function", name));
+            if let Some(key) = v8::String::new(scope, name) {
+                let _ = global.set(scope, key.into(), function.into());
+            }
+        }
+    }
+}
+
+fn convert(
+    scope: &mut v8::HandleScope,
+    args: &v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+    f: impl Fn(&str) -> String,
+) {
+    let text = args.get(0).to_rust_string_lossy(scope);
+    if let Some(s) = v8::String::new(scope, &f(&text)) {
+        rv.set(s.into());
+    }
+}
+
+/// Append `key=value` pairs for each own enumerable field of `object`, in property order.
+/// Field names are nested under `prefix` as `prefix[name]`.
+fn append_fields(
+    scope: &mut v8::HandleScope,
+    prefix: Option<&str>,
+    object: v8::Local<v8::Object>,
+    depth: usize,
+    pairs: &mut Vec<String>,
+) -> Result<(), String> {
+    let Some(names) = object.get_own_property_names(scope, Default::default()) else {
+        return Ok(());
+    };
+    for i in 0..names.length() {
+        let Some(name) = names.get_index(scope, i) else {
+            continue;
+        };
+        let Some(value) = object.get(scope, name) else {
+            continue;
+        };
+        let name = name.to_rust_string_lossy(scope);
+        let key = match prefix {
+            Some(prefix) => format!("{}[{}]", prefix, name),
+            None => name,
+        };
+        append_value(scope, &key, value, depth, pairs)?;
+    }
+    Ok(())
+}
+
+fn append_value(
+    scope: &mut v8::HandleScope,
+    key: &str,
+    value: v8::Local<v8::Value>,
+    depth: usize,
+    pairs: &mut Vec<String>,
+) -> Result<(), String> {
+    if depth > MAX_DEPTH {
+        return Err("value nested too deeply (is it cyclic?)".to_string());
+    }
+    if value.is_null_or_undefined() || value.is_function() || value.is_symbol() {
+        return Ok(());
+    }
+    if let Ok(array) = v8::Local::<v8::Array>::try_from(value) {
+        for i in 0..array.length() {
+            if let Some(item) = array.get_index(scope, i) {
+                append_value(scope, key, item, depth + 1, pairs)?;
+            }
+        }
+        return Ok(());
+    }
+    // dates are printed rather than walked as objects
+    let object = v8::Local::<v8::Object>::try_from(value)
+        .ok()
+        .filter(|_| !value.is_date());
+    if let Some(object) = object {
+        return append_fields(scope, Some(key), object, depth + 1, pairs);
+    }
+    let value = value.to_rust_string_lossy(scope);
+    pairs.push(format!(
+        "{}={}",
+        encode_form_component(key),
+        encode_form_component(&value)
+    ));
+    Ok(())
+}
@@ -1,22 +1,279 @@
+use crate::fs::EmbeddedFiles;
+use crate::log::{LogEntry, LogLevel, LogSink, Logger};
+use jhp_executor::{ErrorHook, ErrorOutput, ExecutorConfig};
+use jhp_parser::ExpressionOutput;
+use std::collections::HashMap;
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct EngineConfig {
     pub host: String,
     pub port: u16,
     pub document_root: PathBuf,
+    /// Files the HTTP server serves in place of `document_root`, from memory, for a
+    /// single-binary deployment. `include()`, and so layouts and WebSocket handlers, find
+    /// the document root's files here too; `readFile`/`writeFile` still use the disk.
+    pub embedded_root: Option<Arc<EmbeddedFiles>>,
+    /// Index documents tried in order for the root and other directories.
+    pub index_files: Vec<String>,
+    /// The single index document of configs from before `index_files`. Unless empty (the
+    /// default) or already listed, it is tried before `index_files`.
+    #[deprecated(note = "use `index_files`, which can list several candidates")]
     pub index_file: String,
     pub extensions_dir: PathBuf,
+    /// Serve runtime statistics as JSON at `/__jhp/stats`.
+    pub stats_endpoint: bool,
+    /// List the loaded native modules and their functions as JSON at `/__jhp/extensions`.
+    pub extensions_endpoint: bool,
+    /// Report the engine and V8 versions and the server's capabilities as JSON at
+    /// `/__jhp/version`.
+    pub version_endpoint: bool,
+    /// PEM certificate chain; together with `tls_key` this switches the listener to HTTPS.
+    pub tls_cert: Option<PathBuf>,
+    /// PEM private key matching `tls_cert`.
+    pub tls_key: Option<PathBuf>,
+    /// How `<?= expr ?>` values are printed; by default `null`/`undefined` print nothing.
+    /// Use `ExpressionOutput::String` to print them literally as `String()` does.
+    pub expression_output: ExpressionOutput,
+    /// What turns `<?= expr ?>` values into text; `String()` by default.
+    pub expression_stringifier: ExpressionStringifier,
+    /// Whether a thrown error is appended to the partial output (the default) or the
+    /// output is simply cut off where the error happened.
+    pub error_output: ErrorOutput,
+    /// What a failing `assert(condition, message?)` does; by default it throws, so the
+    /// message shows up like any other error.
+    pub asserts: AssertMode,
+    /// Seed of the generator behind `$random`, also handed to native extensions as the
+    /// `rng_seed` setting, so their output is the same on every run, e.g. in tests.
+    /// `None` (the default) seeds from the system.
+    pub rng_seed: Option<u64>,
+    /// Send a strong `ETag` with successful responses and answer matching
+    /// `If-None-Match`/`If-Modified-Since` requests with `304 Not Modified`.
+    pub etag: bool,
+    /// `Cache-Control` for static (non-`.jhp`) files, e.g. `"public, max-age=3600"`; a
+    /// `max-age` also yields an `Expires` header. Rendered templates never get one.
+    pub static_cache_control: Option<String>,
+    /// Per-extension overrides of `static_cache_control`, keyed by lowercase extension
+    /// without the dot (`"css"`).
+    pub static_cache_control_by_ext: HashMap<String, String>,
+    /// Native modules (by `include()` name, e.g. `"sqlite"`) loaded when the executor pool
+    /// starts rather than on first use, so no request pays the loading cost. They are
+    /// installed into every context as if already included.
+    pub preload_modules: Vec<String>,
+    /// How long the results of native extension functions are cached, by function name
+    /// (`"get_quote"`), over what the extensions declare themselves. Calls with the same
+    /// arguments within the TTL return the cached result instead of reaching the
+    /// extension; a zero TTL turns caching off for a function that declares one.
+    pub extension_cache_ttls: HashMap<String, Duration>,
+    /// How many renders may wait in each executor's mailbox before senders (e.g. HTTP
+    /// handlers) have to wait for room. A deep queue absorbs bursts and keeps workers
+    /// busy, but requests can sit in it for a long time; a shallow one pushes back on
+    /// callers sooner, keeping queueing latency low at some cost in throughput. Zero is
+    /// taken as 1; see [`effective_worker_queue_depth`](Self::effective_worker_queue_depth).
+    pub worker_queue_depth: usize,
+    /// Lowest level of server log entries (such as `$log` calls) that gets written.
+    pub log_level: LogLevel,
+    /// Where server log entries go; JSON lines on stderr by default.
+    pub log_sink: LogSink,
+    /// `Content-Type` of rendered templates that don't declare one with a `contentType`
+    /// directive; `text/html; charset=utf-8` by default.
+    pub default_content_type: String,
+    /// How long a client may take to send a request's headers before the connection is
+    /// closed, which guards against slow-loris clients; 30 seconds by default. `None`
+    /// waits forever.
+    pub header_read_timeout: Option<Duration>,
+    /// Longest time a request may take to be answered once its headers are in; slower
+    /// ones get `408 Request Timeout` and their render is abandoned. A template that calls
+    /// `flush()` has answered once it first does. Unlimited by default.
+    pub request_timeout: Option<Duration>,
+    /// Extra directories served under URL prefixes, tried in order before falling back
+    /// to `document_root`. Templates served from a mount still resolve `include()` and
+    /// file access against `document_root`.
+    pub mounts: Vec<Mount>,
+    /// WebSocket endpoints, each handled by a JS module. None by default.
+    pub websockets: Vec<WebSocketRoute>,
+    /// Directories `include()` also looks in, in order, when a file isn't found in
+    /// `document_root`. See [`resolve_include`](crate::bindings::resolve_include).
+    pub include_paths: Vec<PathBuf>,
+    /// Glob patterns of paths answered with `404 Not Found` instead of being served or
+    /// rendered, as if they didn't exist; `include()` and file access still reach them.
+    /// A pattern without a `/` applies to each path segment, one with a `/` to the whole
+    /// root-relative path; `*` doesn't cross a `/` but `**` does. By default, hidden files
+    /// and directories (`.*`) and include-only templates (`*.inc.jhp`).
+    pub denied_paths: Vec<String>,
+    /// Reverse proxies whose `X-Forwarded-For`, `X-Forwarded-Proto` and `X-Forwarded-Host`
+    /// headers are believed for `$request.remoteAddr`, `scheme` and `host`. The headers
+    /// of any other peer are ignored. Empty by default.
+    pub trusted_proxies: Vec<Cidr>,
 }
 
+/// What turns the value of a `<?= expr ?>` block into text. `null` and `undefined` are
+/// still printed as [`EngineConfig::expression_output`] says, whichever is used.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ExpressionStringifier {
+    /// JS `String()`: an array prints as `a,b`, a plain object as `[object Object]`.
+    #[default]
+    String,
+    /// Arrays and plain objects print as JSON (`["a","b"]`); anything else goes
+    /// through `String()`.
+    Json,
+    /// A JS function expression, e.g. `v => v instanceof Date ? v.toISOString() : String(v)`,
+    /// evaluated once per render; what it returns is printed with `String()`.
+    Custom(String),
+}
+
+impl ExpressionStringifier {
+    /// Source of the JS function this stringifier stands for, or `None` for plain `String()`.
+    pub fn source(&self) -> Option<&str> {
+        match self {
+            ExpressionStringifier::String => None,
+            ExpressionStringifier::Json => Some(JSON_STRINGIFIER),
+            ExpressionStringifier::Custom(source) => Some(source),
+        }
+    }
+}
+
+const JSON_STRINGIFIER: &str = "(value) => {
+    const proto = value !== null && typeof value === 'object' ? Object.getPrototypeOf(value) : undefined;
+    const json = Array.isArray(value) || proto === Object.prototype || proto === null;
+    return json ? JSON.stringify(value) : String(value);
+}";
+
+/// How `assert(condition, message?)` treats a falsy condition.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AssertMode {
+    /// Throw an error carrying the message, reported at the `assert()` call.
+    #[default]
+    Throw,
+    /// Log the message as a server error and answer `500 Internal Server Error` with
+    /// nothing about the failure in the response, for production.
+    Generic,
+    /// Ignore asserts. Their arguments are still evaluated, as for any call.
+    Off,
+}
+
+/// A directory served under a URL prefix, e.g. `/static` -> `assets/`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    /// Path prefix the mount answers for; `/static` covers `/static` and `/static/...`.
+    pub url_prefix: String,
+    pub directory: PathBuf,
+}
+
+/// A WebSocket endpoint. Each event of a connection runs a hook of the `handler` module
+/// on an executor, as a render would:
+/// - `onOpen(socket)` once connected, `onMessage(socket, message)` for each text message,
+///   and `onClose(socket)` once it is gone. The module declares them as functions; a
+///   missing one is skipped, and they may be `async`.
+/// - `socket.id` tells connections apart; `socket.send(text)` answers this connection and
+///   `socket.broadcast(text)` sends to every connection of the endpoint, this one included.
+///   What a hook returns (unless `null` or `undefined`) and what it echoes are sent too.
+///
+/// Hooks run in a fresh context each time, so state that must outlive one goes in `$store`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebSocketRoute {
+    /// Path the endpoint answers at, e.g. `/chat`.
+    pub url_path: String,
+    /// The module, found as `include()` finds files. One in the document root can be
+    /// downloaded like any static file, source and all; keep handlers in an include path
+    /// (see [`EngineConfig::add_include_path`]), or list them in `denied_paths`.
+    pub handler: PathBuf,
+}
+
+/// An IP network in CIDR notation, e.g. `10.0.0.0/8` or `fd00::/8`. A bare address
+/// stands for just that address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Whether `ip` is in this network. IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`)
+    /// count as their IPv4 address.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let (net, ip, bits) = match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => (u32::from(net).into(), u32::from(ip).into(), 32),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => (u128::from(net), u128::from(ip), 128),
+            _ => return false,
+        };
+        let host_bits = bits - u32::from(self.prefix_len);
+        host_bits == bits || net >> host_bits == ip >> host_bits
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, prefix_len)) => (addr, Some(prefix_len)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| format!("invalid network '{}': bad IP address", s))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            None => max,
+            Some(p) => p
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max)
+                .ok_or_else(|| format!("invalid network '{}': bad prefix length", s))?,
+        };
+        Ok(Self { addr, prefix_len })
+    }
+}
+
+/// Paths never served directly unless configured otherwise: dotfiles and include-only
+/// templates.
+pub const DEFAULT_DENIED_PATHS: &[&str] = &[".*", "*.inc.jhp"];
+
+/// The `Content-Type` rendered templates get unless configured otherwise.
+pub const DEFAULT_CONTENT_TYPE: &str = "text/html; charset=utf-8";
+
 impl Default for EngineConfig {
+    #[allow(deprecated)]
     fn default() -> Self {
         Self {
             host: "127.0.0.1".to_string(),
             port: 3000,
             document_root: PathBuf::from("jhp-tests"),
-            index_file: "index.jhp".to_string(),
+            embedded_root: None,
+            index_files: vec!["index.jhp".to_string()],
+            index_file: String::new(),
             extensions_dir: PathBuf::from("ext"),
+            stats_endpoint: false,
+            extensions_endpoint: false,
+            version_endpoint: false,
+            tls_cert: None,
+            tls_key: None,
+            expression_output: ExpressionOutput::default(),
+            expression_stringifier: ExpressionStringifier::default(),
+            error_output: ErrorOutput::default(),
+            asserts: AssertMode::default(),
+            rng_seed: None,
+            etag: true,
+            static_cache_control: None,
+            static_cache_control_by_ext: HashMap::new(),
+            preload_modules: Vec::new(),
+            extension_cache_ttls: HashMap::new(),
+            worker_queue_depth: 1024,
+            log_level: LogLevel::default(),
+            log_sink: LogSink::default(),
+            default_content_type: DEFAULT_CONTENT_TYPE.to_string(),
+            header_read_timeout: Some(Duration::from_secs(30)),
+            request_timeout: None,
+            mounts: Vec::new(),
+            websockets: Vec::new(),
+            include_paths: Vec::new(),
+            denied_paths: DEFAULT_DENIED_PATHS.iter().map(|p| p.to_string()).collect(),
+            trusted_proxies: Vec::new(),
         }
     }
 }
@@ -26,8 +283,20 @@ impl EngineConfig {
         format!("{}:{}", self.host, self.port)
     }
 
+    /// Path of the first index candidate, whether or not it exists.
     pub fn index_path(&self) -> PathBuf {
-        self.document_root.join(&self.index_file)
+        index_path(&self.document_root, &self.effective_index_files())
+    }
+
+    /// The index documents tried in order: [`index_files`](Self::index_files), after the
+    /// deprecated `index_file` when that is set.
+    #[allow(deprecated)]
+    pub fn effective_index_files(&self) -> Vec<String> {
+        let mut names = self.index_files.clone();
+        if !self.index_file.is_empty() && !names.contains(&self.index_file) {
+            names.insert(0, self.index_file.clone());
+        }
+        names
     }
 
     pub fn set_document_root<P: AsRef<Path>>(mut self, root: P) -> Self {
@@ -35,14 +304,163 @@ impl EngineConfig {
         self
     }
 
+    pub fn set_embedded_root(mut self, files: EmbeddedFiles) -> Self {
+        self.embedded_root = Some(Arc::new(files));
+        self
+    }
+
+    /// Use a single index document, as before multiple candidates were supported.
+    pub fn set_index_file<S: Into<String>>(self, name: S) -> Self {
+        self.set_index_files([name])
+    }
+
+    pub fn set_index_files<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.index_files = names.into_iter().map(Into::into).collect();
+        self
+    }
+
     pub fn set_extensions_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
         self.extensions_dir = dir.as_ref().to_path_buf();
         self
     }
 
+    pub fn set_tls<P: AsRef<Path>, Q: AsRef<Path>>(mut self, cert: P, key: Q) -> Self {
+        self.tls_cert = Some(cert.as_ref().to_path_buf());
+        self.tls_key = Some(key.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn set_static_cache_control<S: Into<String>>(mut self, value: S) -> Self {
+        self.static_cache_control = Some(value.into());
+        self
+    }
+
+    pub fn set_cache_control_for<E: AsRef<str>, S: Into<String>>(
+        mut self,
+        ext: E,
+        value: S,
+    ) -> Self {
+        let ext = ext.as_ref().trim_start_matches('.').to_ascii_lowercase();
+        self.static_cache_control_by_ext.insert(ext, value.into());
+        self
+    }
+
+    pub fn set_preload_modules<I, S>(mut self, modules: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.preload_modules = modules.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn set_extension_cache_ttl<S: Into<String>>(mut self, function: S, ttl: Duration) -> Self {
+        self.extension_cache_ttls.insert(function.into(), ttl);
+        self
+    }
+
+    /// Let `depth` renders wait in each executor's mailbox, at least 1.
+    pub fn set_worker_queue_depth(mut self, depth: usize) -> Self {
+        self.worker_queue_depth = depth.max(1);
+        self
+    }
+
+    /// The mailbox size of each executor: [`worker_queue_depth`](Self::worker_queue_depth),
+    /// with zero taken as 1 since a mailbox needs room for one render.
+    pub fn effective_worker_queue_depth(&self) -> usize {
+        self.worker_queue_depth.max(1)
+    }
+
+    pub fn set_default_content_type<S: Into<String>>(mut self, content_type: S) -> Self {
+        self.default_content_type = content_type.into();
+        self
+    }
+
+    /// Serve `directory` under `url_prefix`, after any mounts added before.
+    pub fn add_mount<S: Into<String>, P: AsRef<Path>>(
+        mut self,
+        url_prefix: S,
+        directory: P,
+    ) -> Self {
+        self.mounts.push(Mount {
+            url_prefix: url_prefix.into(),
+            directory: directory.as_ref().to_path_buf(),
+        });
+        self
+    }
+
+    /// Accept WebSocket connections at `url_path`, handled by the JS module `handler`
+    /// (see [`WebSocketRoute`]).
+    pub fn add_websocket<S: Into<String>, P: AsRef<Path>>(
+        mut self,
+        url_path: S,
+        handler: P,
+    ) -> Self {
+        self.websockets.push(WebSocketRoute {
+            url_path: url_path.into(),
+            handler: handler.as_ref().to_path_buf(),
+        });
+        self
+    }
+
+    /// Let `include()` find files in `directory`, after the include paths added before.
+    pub fn add_include_path<P: AsRef<Path>>(mut self, directory: P) -> Self {
+        self.include_paths.push(directory.as_ref().to_path_buf());
+        self
+    }
+
+    /// Believe the forwarded headers of peers in `network`.
+    pub fn add_trusted_proxy(mut self, network: Cidr) -> Self {
+        self.trusted_proxies.push(network);
+        self
+    }
+
+    pub fn set_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
     pub fn http(&self) -> HttpServerConfig {
         self.into()
     }
+
+    pub fn logger(&self) -> Logger {
+        Logger::new(self.log_level, self.log_sink.clone())
+    }
+
+    /// How templates print expression values: [`expression_output`](Self::expression_output),
+    /// or with a stringifier other than `String()`, the function installed for it.
+    pub fn effective_expression_output(&self) -> ExpressionOutput {
+        match self.expression_stringifier {
+            ExpressionStringifier::String => self.expression_output,
+            _ => ExpressionOutput::Stringifier,
+        }
+    }
+
+    /// The executors' settings; errors left out of the output are logged at `error` level
+    /// with their position as fields.
+    pub fn executor(&self) -> ExecutorConfig {
+        let logger = self.logger();
+        ExecutorConfig {
+            expression_output: self.effective_expression_output(),
+            error_output: self.error_output,
+            error_hook: ErrorHook::new(move |err| {
+                let mut fields = serde_json::Map::new();
+                fields.insert("line".into(), err.line.into());
+                fields.insert("column".into(), err.column.into());
+                logger.log(&LogEntry {
+                    level: LogLevel::Error,
+                    resource: err.resource.clone(),
+                    message: err.message.clone(),
+                    fields,
+                });
+            }),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -50,7 +468,26 @@ pub struct HttpServerConfig {
     pub host: String,
     pub port: u16,
     pub document_root: PathBuf,
-    pub index_file: String,
+    pub embedded_root: Option<Arc<EmbeddedFiles>>,
+    pub index_files: Vec<String>,
+    pub stats_endpoint: bool,
+    pub extensions_endpoint: bool,
+    pub version_endpoint: bool,
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    pub etag: bool,
+    pub static_cache_control: Option<String>,
+    pub static_cache_control_by_ext: HashMap<String, String>,
+    pub default_content_type: String,
+    pub header_read_timeout: Option<Duration>,
+    pub request_timeout: Option<Duration>,
+    pub mounts: Vec<Mount>,
+    pub websockets: Vec<WebSocketRoute>,
+    pub denied_paths: Vec<String>,
+    pub trusted_proxies: Vec<Cidr>,
+    /// Where the server reports failures that have no response to go in, e.g. those of
+    /// WebSocket hooks.
+    pub logger: Logger,
 }
 
 impl HttpServerConfig {
@@ -59,17 +496,38 @@ impl HttpServerConfig {
     }
 
     pub fn index_path(&self) -> PathBuf {
-        self.document_root.join(&self.index_file)
+        index_path(&self.document_root, &self.index_files)
     }
 }
 
+fn index_path(root: &Path, index_files: &[String]) -> PathBuf {
+    root.join(index_files.first().map(String::as_str).unwrap_or_default())
+}
+
 impl From<&EngineConfig> for HttpServerConfig {
     fn from(cfg: &EngineConfig) -> Self {
         Self {
             host: cfg.host.clone(),
             port: cfg.port,
             document_root: cfg.document_root.clone(),
-            index_file: cfg.index_file.clone(),
+            embedded_root: cfg.embedded_root.clone(),
+            index_files: cfg.effective_index_files(),
+            stats_endpoint: cfg.stats_endpoint,
+            extensions_endpoint: cfg.extensions_endpoint,
+            version_endpoint: cfg.version_endpoint,
+            tls_cert: cfg.tls_cert.clone(),
+            tls_key: cfg.tls_key.clone(),
+            etag: cfg.etag,
+            static_cache_control: cfg.static_cache_control.clone(),
+            static_cache_control_by_ext: cfg.static_cache_control_by_ext.clone(),
+            default_content_type: cfg.default_content_type.clone(),
+            header_read_timeout: cfg.header_read_timeout,
+            request_timeout: cfg.request_timeout,
+            mounts: cfg.mounts.clone(),
+            websockets: cfg.websockets.clone(),
+            denied_paths: cfg.denied_paths.clone(),
+            trusted_proxies: cfg.trusted_proxies.clone(),
+            logger: cfg.logger(),
         }
     }
 }
@@ -1,7 +1,12 @@
 use crate::config::EngineConfig;
 use crate::http::HttpServer;
+use crate::log::{LogEntry, LogLevel};
 use crate::{bindings, extensions};
-use jhp_executor::{BindingInstaller, Executor, Op};
+use jhp_executor::{
+    BindingInstaller, Chunk, Executor, Op, RenderOutput, RequestInfo, WorkerSnapshot, WorkerStats,
+};
+use jhp_parser::{CodeBlock, Parser};
+use std::path::Path;
 use std::sync::Arc;
 use std::thread::JoinHandle;
 use std::{
@@ -15,6 +20,7 @@ use tokio::sync::mpsc;
 
 pub struct ExecutorPool {
     senders: Vec<mpsc::Sender<Op>>,
+    stats: Vec<Arc<WorkerStats>>,
     threads: Mutex<Vec<JoinHandle<()>>>,
     next_idx: AtomicUsize,
     pub modules: Arc<extensions::ModuleRegistry>,
@@ -24,10 +30,27 @@ impl ExecutorPool {
     pub fn new(nb: usize, config: &EngineConfig) -> Self {
         let mut threads = Vec::with_capacity(nb);
         let mut senders = Vec::with_capacity(nb);
+        let mut stats = Vec::with_capacity(nb);
 
         // Shared module registry for lazy loading
-        let modules: Arc<extensions::ModuleRegistry> =
-            Arc::new(extensions::ModuleRegistry::new(&config.extensions_dir));
+        let modules: Arc<extensions::ModuleRegistry> = Arc::new(
+            extensions::ModuleRegistry::new(&config.extensions_dir)
+                .with_rng_seed(config.rng_seed)
+                .with_cache_ttls(config.extension_cache_ttls.clone()),
+        );
+        // Load configured modules up front; the installers below then set them up in every
+        // executor's bootstrap context and in each render context, like included modules.
+        let logger = config.logger();
+        for name in &config.preload_modules {
+            if let Err(e) = modules.ensure_loaded(name) {
+                logger.log(&LogEntry {
+                    level: LogLevel::Error,
+                    resource: config.extensions_dir.display().to_string(),
+                    message: format!("failed to preload module {}: {}", name, e),
+                    fields: serde_json::Map::new(),
+                });
+            }
+        }
 
         // Prepare installers: built-ins + include (uses modules). Do NOT eagerly load .so or .js.
         let all_installers: Vec<BindingInstaller> =
@@ -35,15 +58,18 @@ impl ExecutorPool {
         let installers: Arc<Vec<BindingInstaller>> = Arc::new(all_installers);
 
         for id in 0..nb {
-            // each executor gets its own channel
-            // Use a deeper mailbox to absorb bursts from the HTTP server under load.
-            // This reduces contention and awaits in the forwarder at high concurrency.
-            let (tx, rx) = mpsc::channel::<Op>(1024);
+            // each executor gets its own bounded channel; see `EngineConfig::worker_queue_depth`
+            let (tx, rx) = mpsc::channel::<Op>(config.effective_worker_queue_depth());
             senders.push(tx);
 
+            let worker_stats = Arc::new(WorkerStats::default());
+            stats.push(worker_stats.clone());
+
             let installers_cloned = installers.clone();
+            let executor_config = config.executor();
             let handle = thread::spawn(move || {
-                let mut executor = Executor::new(id, rx, installers_cloned);
+                let mut executor =
+                    Executor::new(id, rx, installers_cloned, worker_stats, executor_config);
                 // create a single-threaded tokio runtime for this thread
                 let rt = tokio::runtime::Builder::new_current_thread()
                     .enable_all()
@@ -59,6 +85,7 @@ impl ExecutorPool {
 
         ExecutorPool {
             senders,
+            stats,
             threads: Mutex::new(threads),
             next_idx: AtomicUsize::new(0),
             modules,
@@ -71,7 +98,53 @@ impl ExecutorPool {
         self.senders[idx].send(op).await
     }
 
-    /// Consume ops from a central channel and dispatch to executors in round-robin
+    /// Render parsed blocks on the next executor and wait for the output, which carries
+    /// the script error that stopped the render, if any.
+    pub async fn render(
+        &self,
+        blocks: Vec<Box<CodeBlock>>,
+        resource_name: &str,
+        request: RequestInfo,
+    ) -> Result<RenderOutput, RenderError> {
+        self.render_with(blocks, resource_name, request, None).await
+    }
+
+    /// Like [`ExecutorPool::render`], but output the template passes to `flush()` is sent
+    /// to `stream` as soon as it is flushed, and left out of the returned output. The
+    /// stream closes before the output is returned.
+    pub async fn render_streaming(
+        &self,
+        blocks: Vec<Box<CodeBlock>>,
+        resource_name: &str,
+        request: RequestInfo,
+        stream: mpsc::UnboundedSender<Chunk>,
+    ) -> Result<RenderOutput, RenderError> {
+        self.render_with(blocks, resource_name, request, Some(stream))
+            .await
+    }
+
+    async fn render_with(
+        &self,
+        blocks: Vec<Box<CodeBlock>>,
+        resource_name: &str,
+        request: RequestInfo,
+        stream: Option<mpsc::UnboundedSender<Chunk>>,
+    ) -> Result<RenderOutput, RenderError> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.send(Op::Render {
+            blocks,
+            resource_name: resource_name.to_string(),
+            request,
+            stream,
+            respond_to: tx,
+        })
+        .await
+        .map_err(|_| RenderError::ExecutorUnavailable)?;
+        rx.await.map_err(|_| RenderError::ExecutorUnavailable)
+    }
+
+    /// Consume ops from a central channel and dispatch to executors in round-robin.
+    /// Optional: callers that can await [`ExecutorPool::send`] directly don't need it.
     pub async fn forward(&self, mut rx: mpsc::UnboundedReceiver<Op>) {
         while let Some(op) = rx.recv().await {
             let _ = self.send(op).await;
@@ -82,6 +155,36 @@ impl ExecutorPool {
         self.senders.len()
     }
 
+    /// Snapshot each worker's counters together with the number of ops waiting in its mailbox.
+    pub fn stats(&self) -> Vec<WorkerReport> {
+        self.stats
+            .iter()
+            .zip(&self.senders)
+            .enumerate()
+            .map(|(id, (stats, sender))| WorkerReport {
+                id,
+                stats: stats.snapshot(),
+                queued: sender.max_capacity() - sender.capacity(),
+            })
+            .collect()
+    }
+
+    /// Whether at least one executor has set up its bootstrap context and can take renders.
+    pub fn is_ready(&self) -> bool {
+        self.stats.iter().any(|stats| stats.snapshot().ready)
+    }
+
+    /// Ask every executor to stop once it has finished the renders already queued, then
+    /// wait for their threads to exit. Renders sent afterwards fail with
+    /// [`RenderError::ExecutorUnavailable`].
+    pub async fn shutdown(self: &Arc<Self>) {
+        for sender in &self.senders {
+            let _ = sender.send(Op::Shutdown).await;
+        }
+        let pool = self.clone();
+        let _ = tokio::task::spawn_blocking(move || pool.join()).await;
+    }
+
     /// Take ownership of handles, then join outside the lock
     pub fn join(&self) {
         let handles: Vec<JoinHandle<()>> = {
@@ -94,10 +197,50 @@ impl ExecutorPool {
     }
 }
 
+/// Runtime figures for a single executor, as returned by [`ExecutorPool::stats`].
+#[derive(Debug, Clone, Copy)]
+pub struct WorkerReport {
+    pub id: usize,
+    pub stats: WorkerSnapshot,
+    pub queued: usize,
+}
+
+/// Why a render produced no output. Script errors are not render errors: they come back
+/// in [`RenderOutput::error`] and, depending on the config, inline in the output.
+#[derive(Debug)]
+pub enum RenderError {
+    /// The template file could not be read.
+    Io(std::io::Error),
+    /// The executor shut down before the render completed.
+    ExecutorUnavailable,
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::Io(e) => write!(f, "failed to read template: {}", e),
+            RenderError::ExecutorUnavailable => write!(f, "executor unavailable"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RenderError::Io(e) => Some(e),
+            RenderError::ExecutorUnavailable => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for RenderError {
+    fn from(e: std::io::Error) -> Self {
+        RenderError::Io(e)
+    }
+}
+
 pub struct Engine {
     pub executor_pool: std::sync::Arc<ExecutorPool>,
-    sender: mpsc::UnboundedSender<Op>,
-    receiver: Option<mpsc::UnboundedReceiver<Op>>,
     pub config: EngineConfig,
 }
 
@@ -109,30 +252,50 @@ impl Engine {
     pub fn new_with_config(nb_executors: usize, config: EngineConfig) -> Self {
         assert!(nb_executors > 0);
         let pool = std::sync::Arc::new(ExecutorPool::new(nb_executors, &config));
-        let (sender, receiver) = mpsc::unbounded_channel::<Op>();
 
         Self {
             executor_pool: pool,
-            sender,
-            receiver: Some(receiver),
             config,
         }
     }
 
-    pub async fn run(&mut self) -> Result<(), String> {
-        // spawn two tokio tasks: pool forwarder and HTTP server
-        if let Some(rx) = self.receiver.take() {
-            let pool = std::sync::Arc::clone(&self.executor_pool);
-            tokio::spawn(async move {
-                pool.forward(rx).await;
-            });
-        }
+    /// Render a template string without going through HTTP. `resource_name` names the
+    /// template in error positions; `$request` is empty.
+    pub async fn render_str(
+        &self,
+        template: &str,
+        resource_name: &str,
+    ) -> Result<String, RenderError> {
+        let blocks = Parser::new(template).parse().blocks;
+        self.executor_pool
+            .render(blocks, resource_name, RequestInfo::default())
+            .await
+            .map(|output| output.body)
+    }
 
+    /// Read and render the template at `path` (relative to the working directory, not the
+    /// document root) without going through HTTP.
+    pub async fn render_file<P: AsRef<Path>>(&self, path: P) -> Result<String, RenderError> {
+        let path = path.as_ref();
+        let template = tokio::fs::read_to_string(path).await?;
+        self.render_str(&template, &path.to_string_lossy()).await
+    }
+
+    /// Serve HTTP until the server stops. When it fails, e.g. because its address can't
+    /// be bound, the executors are shut down and the error returned.
+    pub async fn run(&mut self) -> Result<(), String> {
+        // the HTTP server dispatches straight to the pool's bounded per-worker mailboxes
         let task = tokio::spawn({
-            let server = HttpServer::new(self.sender.clone(), self.config.http());
+            let server = HttpServer::new(self.executor_pool.clone(), self.config.http());
             async move { server.start().await }
         });
-        task.await.unwrap();
-        Ok(())
+        let result = match task.await {
+            Ok(served) => served,
+            Err(e) => Err(e.to_string()),
+        };
+        if result.is_err() {
+            self.executor_pool.shutdown().await;
+        }
+        result
     }
 }
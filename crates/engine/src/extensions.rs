@@ -1,11 +1,13 @@
 use jhp_executor::BindingInstaller;
 use libloading::Library;
-use std::collections::{HashMap, HashSet};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::{CStr, OsStr};
 use std::fs;
 use std::os::raw::{c_char, c_uchar};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
 // NOTE: legacy C-ABI removed.
 
@@ -42,29 +44,540 @@ pub struct JhpRegisterV1 {
 
 pub type ExtRegisterV1Fn = unsafe extern "C" fn() -> JhpRegisterV1;
 
+/// A named constant exported by an extension; `json` is a NUL-terminated JSON document.
+#[repr(C)]
+pub struct JhpValueDescV1 {
+    pub name: *const c_char,
+    pub json: *const c_char,
+}
+
+#[repr(C)]
+pub struct JhpValuesV1 {
+    pub abi_version: u32, // must be 1
+    pub values: *const JhpValueDescV1,
+    pub len: usize,
+}
+
+/// Optional symbol exporting constants next to the function table.
+pub type ExtRegisterValuesV1Fn = unsafe extern "C" fn() -> JhpValuesV1;
+
+/// A function's parameters, checked by the engine before each call. `params` is a
+/// NUL-terminated JSON array of `{"name": ..., "type": ..., "optional": ...}` objects; see
+/// [`Signature`].
+#[repr(C)]
+pub struct JhpSignatureDescV1 {
+    pub name: *const c_char,
+    pub params: *const c_char,
+}
+
+#[repr(C)]
+pub struct JhpSignaturesV1 {
+    pub abi_version: u32, // must be 1
+    pub signatures: *const JhpSignatureDescV1,
+    pub len: usize,
+}
+
+/// Optional symbol declaring the parameters of some of the exported functions.
+pub type ExtRegisterSignaturesV1Fn = unsafe extern "C" fn() -> JhpSignaturesV1;
+
+/// How long results of a function may be cached: calls with the same arguments within
+/// `ttl_ms` milliseconds get the result of the first one, without reaching the function.
+#[repr(C)]
+pub struct JhpCacheDescV1 {
+    pub name: *const c_char,
+    pub ttl_ms: u64,
+}
+
+#[repr(C)]
+pub struct JhpCachesV1 {
+    pub abi_version: u32, // must be 1
+    pub caches: *const JhpCacheDescV1,
+    pub len: usize,
+}
+
+/// Optional symbol declaring which of the exported functions have cacheable results.
+pub type ExtRegisterCachesV1Fn = unsafe extern "C" fn() -> JhpCachesV1;
+
+/// Optional init hook, called once after loading with the extension's settings as a JSON
+/// object. A failed result (`ok == false`) stops the library from being used.
+pub type ExtInitV1Fn = unsafe extern "C" fn(JhpBuf) -> JhpCallResult;
+
+/// File in the extensions directory listing the native extensions to load.
+pub const MANIFEST_FILE: &str = "manifest.toml";
+
+/// `ext/manifest.toml`: one table per extension to load, holding its settings.
+/// ```toml
+/// [extensions.sqlite]
+///
+/// [extensions.get_quote]
+/// language = "en"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    extensions: BTreeMap<String, serde_json::Map<String, serde_json::Value>>,
+}
+
+/// Read `ext_dir/manifest.toml`; `None` when there is no manifest.
+fn read_manifest(ext_dir: &Path) -> Result<Option<Manifest>, String> {
+    let path = ext_dir.join(MANIFEST_FILE);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    toml::from_str(&text)
+        .map(Some)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// A native library for [`load_installers`] to load.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtensionLibrary {
+    pub path: PathBuf,
+    /// Passed to the library's init hook; empty unless the manifest sets some.
+    pub settings: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The native libraries to load from `ext_dir`: exactly those listed in its
+/// [`MANIFEST_FILE`] when there is one, otherwise every `.so` found recursively.
+/// A manifest that can't be read or lists an extension without a library is an error.
+pub fn extension_libraries(ext_dir: &Path) -> Result<Vec<ExtensionLibrary>, String> {
+    if let Some(manifest) = read_manifest(ext_dir)? {
+        return manifest
+            .extensions
+            .into_iter()
+            .map(|(name, settings)| {
+                let path = library_path(&name, ext_dir).ok_or_else(|| {
+                    format!(
+                        "{} lists '{}', but there is no native library for it in {}",
+                        MANIFEST_FILE,
+                        name,
+                        ext_dir.display()
+                    )
+                })?;
+                Ok(ExtensionLibrary { path, settings })
+            })
+            .collect();
+    }
+    if !ext_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    fn collect_sos(dir: &Path, out: &mut Vec<PathBuf>) {
+        if let Ok(entries) = fs::read_dir(dir) {
+            for e in entries.flatten() {
+                let p = e.path();
+                if p.is_dir() {
+                    collect_sos(&p, out);
+                } else if p.extension() == Some(OsStr::new("so")) {
+                    out.push(p);
+                }
+            }
+        }
+    }
+    let mut libs: Vec<PathBuf> = Vec::new();
+    collect_sos(ext_dir, &mut libs);
+    libs.sort();
+    Ok(libs
+        .into_iter()
+        .map(|path| ExtensionLibrary {
+            path,
+            settings: serde_json::Map::new(),
+        })
+        .collect())
+}
+
+/// `settings` plus `rng_seed` under that name, unless absent or already set.
+fn with_rng_seed(
+    mut settings: serde_json::Map<String, serde_json::Value>,
+    rng_seed: Option<u64>,
+) -> serde_json::Map<String, serde_json::Value> {
+    if let Some(seed) = rng_seed {
+        settings
+            .entry("rng_seed")
+            .or_insert_with(|| serde_json::Value::from(seed));
+    }
+    settings
+}
+
+/// Call the library's `jhp_init_v1` hook, if it exports one, with `settings`.
+///
+/// # Safety
+/// `lib` must be a loaded JHP extension whose init hook follows the v1 ABI.
+unsafe fn run_init(
+    lib: &Library,
+    lib_path: &Path,
+    settings: &serde_json::Map<String, serde_json::Value>,
+    free_fn: ExtFreeV1,
+) -> Result<(), String> {
+    let Ok(init) = (unsafe { lib.get::<ExtInitV1Fn>(b"jhp_init_v1") }) else {
+        return Ok(());
+    };
+    let json = serde_json::to_string(settings).unwrap_or_else(|_| "{}".to_string());
+    let res = unsafe {
+        init(JhpBuf {
+            ptr: json.as_ptr(),
+            len: json.len(),
+        })
+    };
+    if res.ok {
+        if !res.data.ptr.is_null() && res.data.len > 0 {
+            free_fn(res.data.ptr, res.data.len);
+        }
+        return Ok(());
+    }
+    let mut message = String::new();
+    if !res.data.ptr.is_null() && res.data.len > 0 {
+        let data = unsafe { std::slice::from_raw_parts(res.data.ptr, res.data.len) };
+        // `err_message` payloads are `{"error": message}`; show anything else as is
+        message = serde_json::from_slice::<serde_json::Value>(data)
+            .ok()
+            .and_then(|v| v.get("error")?.as_str().map(str::to_string))
+            .unwrap_or_else(|| String::from_utf8_lossy(data).into_owned());
+        free_fn(res.data.ptr, res.data.len);
+    }
+    Err(format!(
+        "init of {} failed (code {}): {}",
+        lib_path.display(),
+        res.code,
+        message
+    ))
+}
+
 // NOTE: legacy C-ABI support removed.
 
+/// Types a parameter may declare; `any` accepts every value.
+const PARAM_TYPES: &[&str] = &[
+    "string", "number", "integer", "boolean", "object", "array", "null", "any",
+];
+
+/// One declared parameter of an extension function.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Param {
+    pub name: String,
+    /// Accepted types, from `"type": "string"` or `"type": ["string", "null"]`.
+    #[serde(rename = "type", deserialize_with = "one_or_many")]
+    pub types: Vec<String>,
+    /// The argument may be left out (or be `undefined`).
+    #[serde(default)]
+    pub optional: bool,
+}
+
+fn one_or_many<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(d)? {
+        OneOrMany::One(t) => vec![t],
+        OneOrMany::Many(ts) => ts,
+    })
+}
+
+/// The parameters an extension declares for one of its functions. Calls are checked
+/// against them before the extension sees the arguments: too few or too many arguments,
+/// or one of the wrong type, throw a `TypeError` naming the function and parameter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub name: String,
+    pub params: Vec<Param>,
+}
+
+impl Signature {
+    /// Parse the JSON `params` of `name`, rejecting unknown types.
+    pub fn parse(name: &str, params: &str) -> Result<Self, String> {
+        let params: Vec<Param> = serde_json::from_str(params)
+            .map_err(|e| format!("invalid signature for '{}': {}", name, e))?;
+        for param in &params {
+            if let Some(t) = param
+                .types
+                .iter()
+                .find(|t| !PARAM_TYPES.contains(&t.as_str()))
+            {
+                return Err(format!(
+                    "invalid signature for '{}': unknown type '{}' of parameter '{}'",
+                    name, t, param.name
+                ));
+            }
+        }
+        Ok(Self {
+            name: name.to_string(),
+            params,
+        })
+    }
+
+    /// Check a call's arguments; the error is the message of the `TypeError` to throw.
+    fn check(
+        &self,
+        scope: &mut v8::HandleScope,
+        args: &v8::FunctionCallbackArguments,
+    ) -> Result<(), String> {
+        let given = args.length() as usize;
+        let required = self
+            .params
+            .iter()
+            .rposition(|p| !p.optional)
+            .map_or(0, |i| i + 1);
+        if given < required || given > self.params.len() {
+            let expected = if required == self.params.len() {
+                format!("{}", required)
+            } else if given < required {
+                format!("at least {}", required)
+            } else {
+                format!("at most {}", self.params.len())
+            };
+            return Err(format!(
+                "{}: expected {} argument{}, got {}",
+                self,
+                expected,
+                if expected == "1" { "" } else { "s" },
+                given
+            ));
+        }
+        for (i, param) in self.params.iter().enumerate().take(given) {
+            let value = args.get(i as i32);
+            if param.optional && value.is_undefined() {
+                continue;
+            }
+            if !param.types.iter().any(|t| type_matches(scope, value, t)) {
+                return Err(format!(
+                    "{}: argument {} ('{}') must be {}, got {}",
+                    self,
+                    i + 1,
+                    param.name,
+                    param.types.join(" or "),
+                    type_name(value)
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `name(a: string, b?: number | null)`
+impl std::fmt::Display for Signature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .map(|p| {
+                let optional = if p.optional { "?" } else { "" };
+                format!("{}{}: {}", p.name, optional, p.types.join(" | "))
+            })
+            .collect();
+        write!(f, "{}({})", self.name, params.join(", "))
+    }
+}
+
+fn type_matches(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>, ty: &str) -> bool {
+    match ty {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_number()
+                && value
+                    .number_value(scope)
+                    .is_some_and(|n| n.is_finite() && n.fract() == 0.0)
+        }
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object() && !value.is_array() && !value.is_function(),
+        "array" => value.is_array(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+/// The type of `value` as a signature would name it, for error messages.
+fn type_name(value: v8::Local<v8::Value>) -> &'static str {
+    if value.is_string() {
+        "string"
+    } else if value.is_number() {
+        "number"
+    } else if value.is_boolean() {
+        "boolean"
+    } else if value.is_null() {
+        "null"
+    } else if value.is_undefined() {
+        "undefined"
+    } else if value.is_array() {
+        "array"
+    } else if value.is_function() {
+        "function"
+    } else if value.is_big_int() {
+        "bigint"
+    } else if value.is_symbol() {
+        "symbol"
+    } else {
+        "object"
+    }
+}
+
+/// Read the parameter declarations a library exports through
+/// `jhp_register_signatures_v1`, by function name. Libraries without the symbol declare
+/// none, and their functions receive any arguments.
+///
+/// # Safety
+/// `lib` must be a loaded JHP extension whose signature table follows the v1 ABI.
+unsafe fn load_signatures(
+    lib: &Library,
+    lib_path: &Path,
+) -> Result<HashMap<String, Arc<Signature>>, String> {
+    let Ok(sym) = (unsafe { lib.get::<ExtRegisterSignaturesV1Fn>(b"jhp_register_signatures_v1") })
+    else {
+        return Ok(HashMap::new());
+    };
+    let table = unsafe { sym() };
+    if table.abi_version != 1 {
+        return Err(format!(
+            "Unsupported signature table ABI in {}",
+            lib_path.display()
+        ));
+    }
+    if table.signatures.is_null() || table.len == 0 {
+        return Ok(HashMap::new());
+    }
+    let slice = unsafe { std::slice::from_raw_parts(table.signatures, table.len) };
+    let mut signatures = HashMap::with_capacity(slice.len());
+    for desc in slice {
+        if desc.name.is_null() || desc.params.is_null() {
+            continue;
+        }
+        let name = unsafe { CStr::from_ptr(desc.name) }.to_string_lossy();
+        let params = unsafe { CStr::from_ptr(desc.params) }.to_string_lossy();
+        let signature = Signature::parse(&name, &params)
+            .map_err(|e| format!("{} in {}", e, lib_path.display()))?;
+        signatures.insert(name.into_owned(), Arc::new(signature));
+    }
+    Ok(signatures)
+}
+
+/// Read the cache TTLs a library declares through `jhp_register_cache_v1`, by function
+/// name, with `overrides` (see
+/// [`EngineConfig::extension_cache_ttls`](crate::config::EngineConfig::extension_cache_ttls))
+/// applied on top. Zero TTLs are left out.
+///
+/// # Safety
+/// `lib` must be a loaded JHP extension whose cache table follows the v1 ABI.
+unsafe fn load_cache_ttls(
+    lib: &Library,
+    lib_path: &Path,
+    overrides: &HashMap<String, Duration>,
+) -> Result<HashMap<String, Duration>, String> {
+    let mut ttls = HashMap::new();
+    if let Ok(sym) = unsafe { lib.get::<ExtRegisterCachesV1Fn>(b"jhp_register_cache_v1") } {
+        let table = unsafe { sym() };
+        if table.abi_version != 1 {
+            return Err(format!(
+                "Unsupported cache table ABI in {}",
+                lib_path.display()
+            ));
+        }
+        if !table.caches.is_null() && table.len > 0 {
+            let slice = unsafe { std::slice::from_raw_parts(table.caches, table.len) };
+            for desc in slice {
+                if desc.name.is_null() {
+                    continue;
+                }
+                let name = unsafe { CStr::from_ptr(desc.name) }.to_string_lossy();
+                ttls.insert(name.into_owned(), Duration::from_millis(desc.ttl_ms));
+            }
+        }
+    }
+    ttls.extend(overrides.iter().map(|(name, ttl)| (name.clone(), *ttl)));
+    ttls.retain(|_, ttl| !ttl.is_zero());
+    Ok(ttls)
+}
+
+/// How many results a [`ResultCache`] holds; once full, expired ones are dropped, and
+/// new results aren't cached while none have expired.
+const MAX_CACHED_RESULTS: usize = 1024;
+
+/// Results of one extension function by the JSON of their arguments, kept for `ttl`.
+/// Shared by every executor, like the function itself.
+pub struct ResultCache {
+    ttl: Duration,
+    results: Mutex<HashMap<String, (Instant, Option<String>)>>,
+}
+
+impl ResultCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            results: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The result cached for `args` unless it expired: `Some(None)` for a call that
+    /// returned no data.
+    fn get(&self, args: &str) -> Option<Option<String>> {
+        let results = self.results.lock().unwrap();
+        let (stored, data) = results.get(args)?;
+        (stored.elapsed() < self.ttl).then(|| data.clone())
+    }
+
+    fn put(&self, args: String, data: Option<String>) {
+        let mut results = self.results.lock().unwrap();
+        if results.len() >= MAX_CACHED_RESULTS && !results.contains_key(&args) {
+            let ttl = self.ttl;
+            results.retain(|_, (stored, _)| stored.elapsed() < ttl);
+            if results.len() >= MAX_CACHED_RESULTS {
+                return;
+            }
+        }
+        results.insert(args, (Instant::now(), data));
+    }
+}
+
+/// An extension function as registered by its library: what [`make_v8_func_from_c_v1`]
+/// wraps. Built once when the library is loaded and kept by its installer, so installing
+/// it into a context allocates nothing.
+pub struct ExtFunction {
+    pub name: String,
+    pub call: ExtCallV1,
+    pub free_fn: ExtFreeV1,
+    pub signature: Option<Arc<Signature>>,
+    /// Where its successful results are kept, when they may be cached.
+    pub cache: Option<ResultCache>,
+}
+
+/// Wrap an extension function as a JS function. A call that fails (`ok == false`) throws
+/// an `Error` named after the function, see [`throw_call_error`]. With a signature,
+/// arguments that don't match it throw a `TypeError` instead of reaching the extension.
+/// With a cache, a call with the same arguments as an earlier successful one returns its
+/// result while it hasn't expired.
+///
+/// The call runs synchronously on the worker's thread: the worker does nothing else until
+/// the extension returns, and a render timeout can't interrupt it.
+///
+/// The JS function refers to `function` rather than a copy of it, so `function` must
+/// outlive the context.
 pub fn make_v8_func_from_c_v1<'s>(
     scope: &mut v8::ContextScope<'s, v8::HandleScope>,
-    func_ptr: ExtCallV1,
-    free_fn: ExtFreeV1,
+    function: &ExtFunction,
 ) -> v8::Local<'s, v8::Function> {
-    // Pack two pointers (call, free) into a pair stored via External array-like layout.
-    #[repr(C)]
-    struct Pair {
-        call: ExtCallV1,
-        free_fn: ExtFreeV1,
-    }
-    let pair = Pair {
-        call: func_ptr,
-        free_fn,
-    };
-    let raw = Box::into_raw(Box::new(pair)) as *mut std::ffi::c_void;
+    let raw = function as *const ExtFunction as *mut std::ffi::c_void;
     let ext = v8::External::new(scope, raw);
 
     let cb = |scope: &mut v8::HandleScope,
               args: v8::FunctionCallbackArguments,
               mut rv: v8::ReturnValue| {
+        let function_ptr = v8::Local::<v8::External>::try_from(args.data())
+            .map(|e| e.value() as *const ExtFunction)
+            .unwrap();
+        // SAFETY: the function outlives the context, see above
+        let function = unsafe { &*function_ptr };
+        let checked = function.signature.as_ref().map(|s| s.check(scope, &args));
+        if let Some(Err(message)) = checked {
+            if let Some(message) = v8::String::new(scope, &message) {
+                let exc = v8::Exception::type_error(scope, message);
+                scope.throw_exception(exc);
+            }
+            return;
+        }
+
         // Marshal args to JSON string
         let arr = v8::Array::new(scope, args.length());
         for i in 0..args.length() {
@@ -83,45 +596,45 @@ pub fn make_v8_func_from_c_v1<'s>(
         let json_val = stringify.call(scope, undef, &js_args).unwrap();
         let json_str = json_val.to_rust_string_lossy(scope);
 
-        // Retrieve pair
-        let pair_ptr = v8::Local::<v8::External>::try_from(args.data())
-            .map(|e| e.value() as *mut Pair)
-            .unwrap();
-        let pair_ref = unsafe { &*pair_ptr };
+        let cached = function
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.get(&json_str));
+        if let Some(data) = cached {
+            if let Some(result) = data.and_then(|data| parse_result(scope, &data)) {
+                rv.set(result);
+            }
+            return;
+        }
 
         let buf = JhpBuf {
             ptr: json_str.as_ptr(),
             len: json_str.len(),
         };
         // Call extension
-        let res = (pair_ref.call)(buf);
-        if res.ok && !res.data.ptr.is_null() && res.data.len > 0 {
+        let res = (function.call)(buf);
+        let data = (!res.data.ptr.is_null() && res.data.len > 0).then(|| {
             // SAFETY: extension promises UTF-8 JSON
-            let s = unsafe {
+            unsafe {
                 std::str::from_utf8_unchecked(std::slice::from_raw_parts(
                     res.data.ptr,
                     res.data.len,
                 ))
-            };
-            if let Some(json_str) = v8::String::new(scope, s) {
-                // JSON.parse to return structured value
-                let global = scope.get_current_context().global(scope);
-                let json_key = v8::String::new(scope, "JSON").unwrap();
-                let json_val = global.get(scope, json_key.into()).unwrap();
-                let json_obj: v8::Local<v8::Object> = json_val.try_into().unwrap();
-                let parse_key = v8::String::new(scope, "parse").unwrap();
-                let parse_val = json_obj.get(scope, parse_key.into()).unwrap();
-                let parse_fn: v8::Local<v8::Function> = parse_val.try_into().unwrap();
-                let undef = v8::undefined(scope).into();
-                let args = [json_str.into()];
-                if let Some(parsed) = parse_fn.call(scope, undef, &args) {
-                    rv.set(parsed);
-                }
+            }
+        });
+        if !res.ok {
+            throw_call_error(scope, &function.name, data, res.code);
+        } else {
+            if let Some(cache) = &function.cache {
+                cache.put(json_str, data.map(str::to_string));
+            }
+            if let Some(result) = data.and_then(|data| parse_result(scope, data)) {
+                rv.set(result);
             }
         }
         // Free returned buffer if any
         if !res.data.ptr.is_null() && res.data.len > 0 {
-            (pair_ref.free_fn)(res.data.ptr, res.data.len);
+            (function.free_fn)(res.data.ptr, res.data.len);
         }
     };
 
@@ -131,76 +644,233 @@ pub fn make_v8_func_from_c_v1<'s>(
         .expect("build ext v1 function")
 }
 
-/// Load all native extensions from `ext_dir`, Returns the combined list
-/// of installers to install into each V8 context.
-pub fn load_installers(ext_dir: &Path) -> Vec<BindingInstaller> {
-    let mut installers: Vec<BindingInstaller> = Vec::new();
-    if !ext_dir.exists() {
-        return installers;
+/// A function's result parsed from its JSON, to return it as a structured value.
+fn parse_result<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    data: &str,
+) -> Option<v8::Local<'s, v8::Value>> {
+    let text = v8::String::new(scope, data)?;
+    v8::json::parse(scope, text)
+}
+
+/// Throw a JS `Error` for a failed extension call. The message is the `error` field of
+/// the returned JSON (or the raw payload), prefixed with the function name; the error
+/// also carries the extension's `code` and the `resource` (template or script) that
+/// made the call.
+fn throw_call_error(scope: &mut v8::HandleScope, name: &str, data: Option<&str>, code: i32) {
+    let detail = match data {
+        Some(text) => serde_json::from_str::<serde_json::Value>(text)
+            .ok()
+            .and_then(|v| v.get("error").and_then(|e| e.as_str()).map(str::to_string))
+            .unwrap_or_else(|| text.to_string()),
+        None => "call failed".to_string(),
+    };
+    let resource = jhp_executor::v8utils::current_resource(scope);
+    let Some(msg) = v8::String::new(scope, &format!("{}: {}", name, detail)) else {
+        return;
+    };
+    let exc = v8::Exception::error(scope, msg);
+    if let Ok(obj) = v8::Local::<v8::Object>::try_from(exc) {
+        let code = v8::Integer::new(scope, code);
+        if let Some(key) = v8::String::new(scope, "code") {
+            let _ = obj.set(scope, key.into(), code.into());
+        }
+        if let (Some(key), Some(value)) = (
+            v8::String::new(scope, "resource"),
+            v8::String::new(scope, &resource),
+        ) {
+            let _ = obj.set(scope, key.into(), value.into());
+        }
     }
+    scope.throw_exception(exc);
+}
 
-    // 1) Native extensions (*.so) discovered recursively
-    fn collect_sos(dir: &Path, out: &mut Vec<PathBuf>) {
-        if let Ok(entries) = fs::read_dir(dir) {
-            for e in entries.flatten() {
-                let p = e.path();
-                if p.is_dir() {
-                    collect_sos(&p, out);
-                } else if p.extension() == Some(OsStr::new("so")) {
-                    out.push(p);
-                }
-            }
+/// Read the constants a library exports through `jhp_register_values_v1`, as
+/// (name, JSON text) pairs. Libraries without the symbol export none.
+///
+/// # Safety
+/// `lib` must be a loaded JHP extension whose value table follows the v1 ABI.
+unsafe fn load_values(lib: &Library, lib_path: &Path) -> Result<Vec<(String, String)>, String> {
+    let Ok(sym) = (unsafe { lib.get::<ExtRegisterValuesV1Fn>(b"jhp_register_values_v1") }) else {
+        return Ok(Vec::new());
+    };
+    let table = unsafe { sym() };
+    if table.abi_version != 1 {
+        return Err(format!(
+            "Unsupported value table ABI in {}",
+            lib_path.display()
+        ));
+    }
+    if table.values.is_null() || table.len == 0 {
+        return Ok(Vec::new());
+    }
+    let slice = unsafe { std::slice::from_raw_parts(table.values, table.len) };
+    let mut values = Vec::with_capacity(slice.len());
+    for desc in slice {
+        if desc.name.is_null() || desc.json.is_null() {
+            continue;
         }
+        let name = unsafe { CStr::from_ptr(desc.name) }
+            .to_string_lossy()
+            .into_owned();
+        let json = unsafe { CStr::from_ptr(desc.json) }
+            .to_string_lossy()
+            .into_owned();
+        if serde_json::from_str::<serde_json::Value>(&json).is_err() {
+            return Err(format!(
+                "Invalid JSON for value '{}' in {}",
+                name,
+                lib_path.display()
+            ));
+        }
+        values.push((name, json));
     }
-    let mut libs: Vec<PathBuf> = Vec::new();
-    collect_sos(ext_dir, &mut libs);
+    Ok(values)
+}
 
-    for lib_path in libs {
+/// Set each exported constant on `target`, parsed from its JSON.
+fn set_values(
+    scope: &mut v8::ContextScope<v8::HandleScope>,
+    target: v8::Local<v8::Object>,
+    values: &[(String, String)],
+) {
+    for (name, json) in values {
+        let (Some(key), Some(text)) = (v8::String::new(scope, name), v8::String::new(scope, json))
+        else {
+            continue;
+        };
+        if let Some(value) = v8::json::parse(scope, text) {
+            let _ = target.set(scope, key.into(), value);
+        }
+    }
+}
+
+/// Load the native extensions of `ext_dir` (see [`extension_libraries`]), caching their
+/// results for the TTLs they declare with `overrides` (see
+/// [`EngineConfig::extension_cache_ttls`](crate::config::EngineConfig::extension_cache_ttls))
+/// applied on top. With `rng_seed`, each init hook gets it as its `rng_seed` setting
+/// unless the manifest sets one. Returns, for each library, what it provides and an
+/// installer that sets its functions and values on the global object of a V8 context.
+pub fn load_installers(
+    ext_dir: &Path,
+    rng_seed: Option<u64>,
+    overrides: &HashMap<String, Duration>,
+) -> Vec<(ModuleInfo, BindingInstaller)> {
+    let mut loaded = Vec::new();
+    let libs = match extension_libraries(ext_dir) {
+        Ok(libs) => libs,
+        Err(e) => {
+            eprintln!("extension load: {}", e);
+            return loaded;
+        }
+    };
+
+    for ExtensionLibrary {
+        path: lib_path,
+        settings,
+    } in libs
+    {
+        let settings = with_rng_seed(settings, rng_seed);
         unsafe {
-            match Library::new(&lib_path) {
-                Ok(lib) => {
-                    // Safety: leak the lib to keep it alive for the process lifetime
-                    let lib = Box::leak(Box::new(lib));
-                    // v1 ABI
-                    if let Ok(sym_v1) = lib.get::<ExtRegisterV1Fn>(b"jhp_register_v1") {
-                        let reg = sym_v1();
-                        if reg.abi_version == 1 && !reg.funcs.is_null() && reg.len > 0 {
-                            let slice = std::slice::from_raw_parts(reg.funcs, reg.len);
-                            for fdesc in slice.iter() {
-                                if fdesc.name.is_null() {
-                                    continue;
-                                }
-                                let name = match CStr::from_ptr(fdesc.name).to_str() {
-                                    Ok(s) => s.to_owned(),
-                                    Err(_) => continue,
-                                };
-                                let call = fdesc.call;
-                                let free_fn = reg.free_fn;
-                                let installer: BindingInstaller =
-                                    std::sync::Arc::new(move |scope| {
-                                        let name_v8 = v8::String::new(scope, &name).unwrap();
-                                        let func = make_v8_func_from_c_v1(scope, call, free_fn);
-                                        let global = scope.get_current_context().global(scope);
-                                        let _ = global.set(scope, name_v8.into(), func.into());
-                                    });
-                                installers.push(installer);
-                            }
+            let lib = match Library::new(&lib_path) {
+                // Safety: leak the lib to keep it alive for the process lifetime
+                Ok(lib) => Box::leak(Box::new(lib)),
+                Err(e) => {
+                    eprintln!("failed to load extension {}: {}", lib_path.display(), e);
+                    continue;
+                }
+            };
+            let mut functions: Vec<ExtFunction> = Vec::new();
+            // v1 ABI
+            if let Ok(sym_v1) = lib.get::<ExtRegisterV1Fn>(b"jhp_register_v1") {
+                let reg = sym_v1();
+                if let Err(e) = run_init(lib, &lib_path, &settings, reg.free_fn) {
+                    eprintln!("extension load: {}", e);
+                    continue;
+                }
+                let signatures = match load_signatures(lib, &lib_path) {
+                    Ok(signatures) => signatures,
+                    Err(e) => {
+                        eprintln!("extension load: {}", e);
+                        continue;
+                    }
+                };
+                let cache_ttls = match load_cache_ttls(lib, &lib_path, overrides) {
+                    Ok(ttls) => ttls,
+                    Err(e) => {
+                        eprintln!("extension load: {}", e);
+                        continue;
+                    }
+                };
+                if reg.abi_version == 1 && !reg.funcs.is_null() && reg.len > 0 {
+                    let slice = std::slice::from_raw_parts(reg.funcs, reg.len);
+                    for fdesc in slice.iter() {
+                        if fdesc.name.is_null() {
+                            continue;
                         }
-                    } else {
-                        eprintln!(
-                            "extension load: no supported register symbol in {}",
-                            lib_path.display()
-                        );
+                        let name = match CStr::from_ptr(fdesc.name).to_str() {
+                            Ok(s) => s.to_owned(),
+                            Err(_) => continue,
+                        };
+                        functions.push(ExtFunction {
+                            signature: signatures.get(&name).cloned(),
+                            cache: cache_ttls.get(&name).copied().map(ResultCache::new),
+                            name,
+                            call: fdesc.call,
+                            free_fn: reg.free_fn,
+                        });
                     }
                 }
+            } else {
+                eprintln!(
+                    "extension load: no supported register symbol in {}",
+                    lib_path.display()
+                );
+            }
+            let values = match load_values(lib, &lib_path) {
+                Ok(values) => values,
                 Err(e) => {
-                    eprintln!("failed to load extension {}: {}", lib_path.display(), e);
+                    eprintln!("extension load: {}", e);
+                    continue;
                 }
-            }
+            };
+
+            let info = ModuleInfo {
+                name: library_module_name(&lib_path),
+                object: GLOBAL_OBJECT.to_string(),
+                functions: functions.iter().map(|f| f.name.clone()).collect(),
+                values: values.iter().map(|(vname, _)| vname.clone()).collect(),
+            };
+            let installer: BindingInstaller = Arc::new(move |scope| {
+                let global = scope.get_current_context().global(scope);
+                for function in &functions {
+                    let name_v8 = v8::String::new(scope, &function.name).unwrap();
+                    let func = make_v8_func_from_c_v1(scope, function);
+                    let _ = global.set(scope, name_v8.into(), func.into());
+                }
+                set_values(scope, global, &values);
+            });
+            loaded.push((info, installer));
         }
     }
 
-    installers
+    loaded
+}
+
+/// The [`ModuleInfo::object`] of libraries loaded by [`load_installers`], whose members
+/// are set on the global object.
+const GLOBAL_OBJECT: &str = "globalThis";
+
+/// The module name of a library: its file name without the `libjhp_ext_` prefix and the
+/// extension, e.g. `get_quote` for `libjhp_ext_get_quote.so`.
+fn library_module_name(lib_path: &Path) -> String {
+    let stem = lib_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    stem.strip_prefix("libjhp_ext_")
+        .unwrap_or(&stem)
+        .to_string()
 }
 
 /// discover js extensions under `ext_dir` recursively and produce installers that run them.
@@ -262,48 +932,112 @@ fn module_name_candidates(name: &str) -> Vec<String> {
     cands
 }
 
-/// Find and load a native module by logical name; returns the module object name and an installer
-/// that will, when run in a context, create `global[ObjectName]` and attach native functions and
-/// execute any JS bootstrap scripts found under the module folder.
+/// The library for a module name: `libjhp_ext_<cand>.so` in `ext_dir`, for the first of
+/// [`module_name_candidates`] that exists.
+fn library_path(name: &str, ext_dir: &Path) -> Option<PathBuf> {
+    module_name_candidates(name)
+        .into_iter()
+        .map(|cand| ext_dir.join(format!("libjhp_ext_{}.so", cand)))
+        .find(|p| p.exists())
+}
+
+/// Why a module could not be loaded by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModuleError {
+    /// There is no native library for the name, so it may still be a JS module or file.
+    NotFound(String),
+    /// A library was found but could not be loaded (bad file, missing symbol, wrong ABI).
+    Load(String),
+}
+
+impl std::fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModuleError::NotFound(msg) | ModuleError::Load(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for ModuleError {}
+
+/// What a loaded native module provides, as listed by [`ModuleRegistry::modules`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleInfo {
+    /// The name it was loaded by, e.g. `sqlite` for `include('sqlite')`.
+    pub name: String,
+    /// The global object its members are attached to, e.g. `Sqlite`, or `globalThis` for
+    /// libraries loaded by [`load_installers`].
+    pub object: String,
+    /// Native functions, in the order the library exports them.
+    pub functions: Vec<String>,
+    /// Constant values exported by the library.
+    pub values: Vec<String>,
+}
+
+/// Find and load a native module by logical name; returns what the module provides and an
+/// installer that will, when run in a context, create `global[ObjectName]` and attach native
+/// functions and execute any JS bootstrap scripts found under the module folder.
+/// With `rng_seed`, the module's init hook gets it as its `rng_seed` setting unless the
+/// manifest sets one. `cache_ttls` override the cache TTLs the module declares for its
+/// functions.
 pub fn load_module_installer(
     name: &str,
     ext_dir: &Path,
-) -> Result<(String, BindingInstaller), String> {
+    rng_seed: Option<u64>,
+    cache_ttls: &HashMap<String, Duration>,
+) -> Result<(ModuleInfo, BindingInstaller), ModuleError> {
     let obj_name = object_name_for(name);
-    let obj_name_for_return = obj_name.clone();
     let candidates = module_name_candidates(name);
 
-    // Locate a .so library: try libjhp_ext_<cand>.so in ext_dir
-    let mut lib_path: Option<PathBuf> = None;
-    for cand in &candidates {
-        let p = ext_dir.join(format!("libjhp_ext_{}.so", cand));
-        if p.exists() {
-            lib_path = Some(p);
-            break;
-        }
-    }
-    let lib_path = lib_path.ok_or_else(|| {
-        format!(
+    // With a manifest, only the modules it lists may load
+    let settings = match read_manifest(ext_dir).map_err(ModuleError::Load)? {
+        Some(manifest) => candidates
+            .iter()
+            .find_map(|cand| manifest.extensions.get(cand))
+            .cloned()
+            .ok_or_else(|| {
+                ModuleError::NotFound(format!(
+                    "Module '{}' is not listed in {}",
+                    name,
+                    ext_dir.join(MANIFEST_FILE).display()
+                ))
+            })?,
+        None => serde_json::Map::new(),
+    };
+    let settings = with_rng_seed(settings, rng_seed);
+
+    let lib_path = library_path(name, ext_dir).ok_or_else(|| {
+        ModuleError::NotFound(format!(
             "No native library found for module '{}' in {}",
             name,
             ext_dir.display()
-        )
+        ))
     })?;
 
     // Load the library and collect function descriptors
     unsafe {
         let lib = match Library::new(&lib_path) {
             Ok(l) => Box::leak(Box::new(l)),
-            Err(e) => return Err(format!("Failed to load {}: {}", lib_path.display(), e)),
+            Err(e) => {
+                let msg = format!("Failed to load {}: {}", lib_path.display(), e);
+                return Err(ModuleError::Load(msg));
+            }
         };
         let sym_v1 = lib.get::<ExtRegisterV1Fn>(b"jhp_register_v1");
         let reg = match sym_v1 {
             Ok(s) => s(),
-            Err(_) => return Err(format!("Missing jhp_register_v1 in {}", lib_path.display())),
+            Err(_) => {
+                let msg = format!("Missing jhp_register_v1 in {}", lib_path.display());
+                return Err(ModuleError::Load(msg));
+            }
         };
         if reg.abi_version != 1 || reg.funcs.is_null() || reg.len == 0 {
-            return Err("Unsupported extension ABI or empty function table".to_string());
+            return Err(ModuleError::Load(format!(
+                "Unsupported extension ABI or empty function table in {}",
+                lib_path.display()
+            )));
         }
+        run_init(lib, &lib_path, &settings, reg.free_fn).map_err(ModuleError::Load)?;
         let slice = std::slice::from_raw_parts(reg.funcs, reg.len);
         // Capture function entries for later installer use
         let mut funcs: Vec<(String, ExtCallV1)> = Vec::new();
@@ -317,6 +1051,15 @@ pub fn load_module_installer(
             funcs.push((name_c.to_string(), fdesc.call));
         }
         let free_fn = reg.free_fn;
+        let values = load_values(lib, &lib_path).map_err(ModuleError::Load)?;
+        let signatures = load_signatures(lib, &lib_path).map_err(ModuleError::Load)?;
+        let cache_ttls = load_cache_ttls(lib, &lib_path, cache_ttls).map_err(ModuleError::Load)?;
+        let info = ModuleInfo {
+            name: name.to_string(),
+            object: obj_name.clone(),
+            functions: funcs.iter().map(|(fname, _)| fname.clone()).collect(),
+            values: values.iter().map(|(vname, _)| vname.clone()).collect(),
+        };
 
         // Collect JS bootstraps under ext_dir/<cand>/*.js sorted
         let mut js_files: Vec<(String, String)> = Vec::new(); // (resource, code)
@@ -342,6 +1085,16 @@ pub fn load_module_installer(
         }
 
         // Build installer
+        let functions: Vec<ExtFunction> = funcs
+            .into_iter()
+            .map(|(fname, call)| ExtFunction {
+                signature: signatures.get(&fname).cloned(),
+                cache: cache_ttls.get(&fname).copied().map(ResultCache::new),
+                name: fname,
+                call,
+                free_fn,
+            })
+            .collect();
         let obj_name_cloned = obj_name.clone();
         let installer: BindingInstaller = Arc::new(move |scope| {
             let global = scope.get_current_context().global(scope);
@@ -353,11 +1106,12 @@ pub fn load_module_installer(
                 v8::Object::new(scope)
             };
             // Attach functions under module object
-            for (fname, fptr) in &funcs {
-                let f = make_v8_func_from_c_v1(scope, *fptr, free_fn);
-                let fkey = v8::String::new(scope, fname).unwrap();
+            for function in &functions {
+                let f = make_v8_func_from_c_v1(scope, function);
+                let fkey = v8::String::new(scope, &function.name).unwrap();
                 let _ = module_obj.set(scope, fkey.into(), f.into());
             }
+            set_values(scope, module_obj, &values);
             // Set module object on global in case it wasn't there
             let key = v8::String::new(scope, &obj_name_cloned).unwrap();
             let _ = global.set(scope, key.into(), module_obj.into());
@@ -367,7 +1121,7 @@ pub fn load_module_installer(
                 let _ = jhp_executor::v8utils::compile_and_run_current(scope, code, resource);
             }
         });
-        Ok((obj_name_for_return, installer))
+        Ok((info, installer))
     }
 }
 
@@ -375,9 +1129,13 @@ pub fn load_module_installer(
 #[derive(Default)]
 pub struct ModuleRegistry {
     ext_dir: PathBuf,
+    /// Handed to the init hook of every module loaded; see [`load_module_installer`].
+    rng_seed: Option<u64>,
+    /// Cache TTLs by function name, over those the modules declare.
+    cache_ttls: HashMap<String, Duration>,
     loaded: RwLock<HashSet<String>>, // module keys requested (e.g., "sqlite3")
     installers: RwLock<HashMap<String, BindingInstaller>>, // key -> installer
-    obj_names: RwLock<HashMap<String, String>>, // key -> object name (e.g., Sqlite3)
+    infos: RwLock<HashMap<String, ModuleInfo>>, // key -> what the module provides
 }
 
 impl ModuleRegistry {
@@ -388,8 +1146,21 @@ impl ModuleRegistry {
         }
     }
 
+    /// Seed the modules' random output with `seed` (see [`load_module_installer`]).
+    pub fn with_rng_seed(mut self, seed: Option<u64>) -> Self {
+        self.rng_seed = seed;
+        self
+    }
+
+    /// Cache the results of the named functions for their TTL, whatever their modules
+    /// declare (see [`load_module_installer`]).
+    pub fn with_cache_ttls(mut self, ttls: HashMap<String, Duration>) -> Self {
+        self.cache_ttls = ttls;
+        self
+    }
+
     /// Ensure a module is loaded; if newly loaded, returns its installer for immediate use.
-    pub fn ensure_loaded(&self, key: &str) -> Result<Option<BindingInstaller>, String> {
+    pub fn ensure_loaded(&self, key: &str) -> Result<Option<BindingInstaller>, ModuleError> {
         {
             let loaded = self.loaded.read().unwrap();
             if loaded.contains(key) {
@@ -401,11 +1172,9 @@ impl ModuleRegistry {
         if loaded_w.contains(key) {
             return Ok(None);
         }
-        let (obj_name, installer) = load_module_installer(key, &self.ext_dir)?;
-        self.obj_names
-            .write()
-            .unwrap()
-            .insert(key.to_string(), obj_name);
+        let (info, installer) =
+            load_module_installer(key, &self.ext_dir, self.rng_seed, &self.cache_ttls)?;
+        self.infos.write().unwrap().insert(key.to_string(), info);
         self.installers
             .write()
             .unwrap()
@@ -414,6 +1183,23 @@ impl ModuleRegistry {
         Ok(Some(installer))
     }
 
+    /// Load every native library of the extensions dir up front (see [`load_installers`]),
+    /// to be installed into each context by [`install_all`](Self::install_all) and listed
+    /// by [`modules`](Self::modules). One already loaded by name keeps its installer.
+    pub fn load_all(&self) {
+        let mut loaded = self.loaded.write().unwrap();
+        for (info, installer) in load_installers(&self.ext_dir, self.rng_seed, &self.cache_ttls) {
+            if !loaded.insert(info.name.clone()) {
+                continue;
+            }
+            self.installers
+                .write()
+                .unwrap()
+                .insert(info.name.clone(), installer);
+            self.infos.write().unwrap().insert(info.name.clone(), info);
+        }
+    }
+
     pub fn install_all(&self, scope: &mut v8::ContextScope<v8::HandleScope>) {
         let installers = self.installers.read().unwrap();
         for installer in installers.values() {
@@ -428,6 +1214,17 @@ impl ModuleRegistry {
     }
 
     pub fn object_name(&self, key: &str) -> Option<String> {
-        self.obj_names.read().unwrap().get(key).cloned()
+        self.infos
+            .read()
+            .unwrap()
+            .get(key)
+            .map(|info| info.object.clone())
+    }
+
+    /// The native modules loaded so far (preloaded or through `include()`), by name.
+    pub fn modules(&self) -> Vec<ModuleInfo> {
+        let mut modules: Vec<ModuleInfo> = self.infos.read().unwrap().values().cloned().collect();
+        modules.sort_by(|a, b| a.name.cmp(&b.name));
+        modules
     }
 }
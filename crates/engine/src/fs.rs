@@ -1,41 +1,348 @@
-use std::path::{Path, PathBuf};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
 use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// The kind of a path under the document root, as returned by [`DocumentRoot::stat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    /// A regular file, or anything else that isn't a directory.
+    File,
+    Dir,
+}
+
+/// The files of an in-memory document root, by root-relative path (`css/site.css`),
+/// for a self-contained binary that embeds its documents with `include_bytes!` or
+/// `include_dir!`. Directories exist through the files under them.
+#[derive(Clone, Default)]
+pub struct EmbeddedFiles {
+    files: BTreeMap<String, Cow<'static, [u8]>>,
+}
+
+impl EmbeddedFiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add (or replace) the file at `path`; a leading `/` is ignored.
+    pub fn add_file<S: AsRef<str>, B: Into<Cow<'static, [u8]>>>(
+        mut self,
+        path: S,
+        contents: B,
+    ) -> Self {
+        self.files
+            .insert(normalize_key(Path::new(path.as_ref())), contents.into());
+        self
+    }
+
+    pub(crate) fn get(&self, rel: &Path) -> io::Result<&[u8]> {
+        self.files
+            .get(&normalize_key(rel))
+            .map(|contents| contents.as_ref())
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    }
+
+    pub(crate) fn is_dir(&self, rel: &Path) -> bool {
+        let dir = normalize_key(rel);
+        if dir.is_empty() {
+            return true;
+        }
+        let prefix = format!("{}/", dir);
+        self.files
+            .range(prefix.clone()..)
+            .next()
+            .is_some_and(|(path, _)| path.starts_with(&prefix))
+    }
+}
+
+/// Lists the paths only; the contents can be large.
+impl std::fmt::Debug for EmbeddedFiles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.files.keys()).finish()
+    }
+}
+
+/// `rel` as an [`EmbeddedFiles`] key: its plain segments joined with `/`.
+fn normalize_key(rel: &Path) -> String {
+    let segments: Vec<_> = rel
+        .components()
+        .filter_map(|comp| match comp {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect();
+    segments.join("/")
+}
+
+/// Where a [`DocumentRoot`] reads its files from.
+#[derive(Clone, Debug)]
+enum Source {
+    Disk(PathBuf),
+    Memory(Arc<EmbeddedFiles>),
+}
 
 #[derive(Clone, Debug)]
 pub struct DocumentRoot {
-    root: PathBuf,
-    index_file: String,
+    source: Source,
+    /// The disk root with symlinks resolved, once when the root is created; `None` when
+    /// it didn't exist then. See [`DocumentRoot::resolve`].
+    real_root: Option<PathBuf>,
+    index_files: Vec<String>,
 }
 
 impl DocumentRoot {
     /// Create a new DocumentRoot for the web server.
     /// `root` is the directory that serves as the document root, and
-    /// `index_file` is the default index document (e.g., "index.jhp").
-    pub fn new(root: PathBuf, index_file: String) -> Self {
-        Self { root, index_file }
+    /// `index_files` are the index documents tried in order for a directory
+    /// (e.g., `["index.jhp", "index.html"]`).
+    pub fn new(root: PathBuf, index_files: Vec<String>) -> Self {
+        Self {
+            real_root: std::fs::canonicalize(&root).ok(),
+            source: Source::Disk(root),
+            index_files,
+        }
+    }
+
+    /// A document root serving `files` without touching the filesystem. Embedded files
+    /// have no modification time.
+    pub fn embedded(files: Arc<EmbeddedFiles>, index_files: Vec<String>) -> Self {
+        Self {
+            source: Source::Memory(files),
+            real_root: None,
+            index_files,
+        }
     }
 
     pub async fn root_file_exists(&self, name: &str) -> bool {
-        fs::metadata(self.root.join(name)).await.is_ok()
+        self.stat(name).await.is_some()
+    }
+
+    /// Returns the index file candidates, in the order they are tried.
+    pub fn index_names(&self) -> &[String] {
+        &self.index_files
+    }
+
+    fn first_index(&self) -> &str {
+        self.index_files.first().map_or("", String::as_str)
     }
 
-    /// Returns the full path to the index document under the document root.
+    /// Returns the full path to the first index candidate under the document root.
+    #[deprecated(note = "a directory may have several index candidates; use `find_index`")]
     pub fn index_path(&self) -> PathBuf {
-        self.root.join(&self.index_file)
+        match &self.source {
+            Source::Disk(root) => root.join(self.first_index()),
+            Source::Memory(_) => PathBuf::from(self.first_index()),
+        }
     }
 
-    /// Returns the name of the index file (e.g., "index.jhp").
+    /// Returns the name of the first index candidate (e.g., "index.jhp").
+    #[deprecated(note = "use `index_names`")]
     pub fn index_name(&self) -> &str {
-        &self.index_file
+        self.first_index()
     }
 
-    /// Read the index document contents.
+    /// Read the first index candidate of the document root.
+    #[deprecated(note = "use `find_index` and `read_file`")]
     pub async fn read_index(&self) -> std::io::Result<String> {
-        fs::read_to_string(self.index_path()).await
+        self.read_file(self.first_index()).await
+    }
+
+    /// What `rel` names under the document root, from a single `metadata` call; `None`
+    /// if it doesn't exist (or can't be read). Symlinks are followed.
+    pub async fn stat<P: AsRef<Path>>(&self, rel: P) -> Option<FileKind> {
+        let root = match &self.source {
+            Source::Disk(root) => root,
+            Source::Memory(files) => {
+                let rel = rel.as_ref();
+                return if files.get(rel).is_ok() {
+                    Some(FileKind::File)
+                } else {
+                    files.is_dir(rel).then_some(FileKind::Dir)
+                };
+            }
+        };
+        let meta = fs::metadata(root.join(rel)).await.ok()?;
+        if meta.is_dir() {
+            Some(FileKind::Dir)
+        } else {
+            Some(FileKind::File)
+        }
+    }
+
+    /// The first index candidate that exists as a file in the directory `dir` (relative
+    /// to the document root; empty for the root itself), as a root-relative path.
+    pub async fn find_index(&self, dir: &str) -> Option<String> {
+        let dir = dir.trim_matches('/');
+        for name in &self.index_files {
+            let rel = if dir.is_empty() {
+                name.clone()
+            } else {
+                format!("{}/{}", dir, name)
+            };
+            if self.stat(&rel).await == Some(FileKind::File) {
+                return Some(rel);
+            }
+        }
+        None
     }
 
     /// Read an arbitrary file under the document root.
     pub async fn read_file<P: AsRef<Path>>(&self, rel: P) -> std::io::Result<String> {
-        fs::read_to_string(self.root.join(rel)).await
+        match &self.source {
+            Source::Disk(root) => fs::read_to_string(root.join(rel)).await,
+            Source::Memory(files) => String::from_utf8(files.get(rel.as_ref())?.to_vec())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        }
+    }
+
+    /// Read an arbitrary file under the document root as raw bytes.
+    pub async fn read_bytes<P: AsRef<Path>>(&self, rel: P) -> std::io::Result<Vec<u8>> {
+        match &self.source {
+            Source::Disk(root) => fs::read(root.join(rel)).await,
+            Source::Memory(files) => Ok(files.get(rel.as_ref())?.to_vec()),
+        }
+    }
+
+    /// Read up to `len` bytes of a file under the document root, starting at `offset`,
+    /// without loading the rest of it. Fewer bytes come back if the file ends first.
+    pub async fn read_range<P: AsRef<Path>>(
+        &self,
+        rel: P,
+        offset: u64,
+        len: u64,
+    ) -> std::io::Result<Vec<u8>> {
+        let root = match &self.source {
+            Source::Disk(root) => root,
+            Source::Memory(files) => {
+                let contents = files.get(rel.as_ref())?;
+                let start = usize::try_from(offset)
+                    .unwrap_or(usize::MAX)
+                    .min(contents.len());
+                let len = usize::try_from(len).unwrap_or(usize::MAX);
+                let end = start.saturating_add(len).min(contents.len());
+                return Ok(contents[start..end].to_vec());
+            }
+        };
+        let mut file = fs::File::open(root.join(rel)).await?;
+        file.seek(io::SeekFrom::Start(offset)).await?;
+        let mut buf = Vec::new();
+        file.take(len).read_to_end(&mut buf).await?;
+        Ok(buf)
+    }
+
+    /// Size in bytes of a file relative to the document root.
+    pub async fn file_size<P: AsRef<Path>>(&self, rel: P) -> std::io::Result<u64> {
+        match &self.source {
+            Source::Disk(root) => Ok(fs::metadata(root.join(rel)).await?.len()),
+            Source::Memory(files) => Ok(files.get(rel.as_ref())?.len() as u64),
+        }
+    }
+
+    /// Last modification time of a file relative to the document root; `Unsupported`
+    /// for embedded files.
+    pub async fn modified<P: AsRef<Path>>(&self, rel: P) -> std::io::Result<SystemTime> {
+        match &self.source {
+            Source::Disk(root) => fs::metadata(root.join(rel)).await?.modified(),
+            Source::Memory(files) => {
+                files.get(rel.as_ref())?;
+                Err(io::ErrorKind::Unsupported.into())
+            }
+        }
+    }
+
+    /// Resolve `rel` to a path under the document root without touching its contents.
+    /// A leading `/` is treated as root-relative. Paths that would leave the root, either
+    /// lexically (`..` past the root) or through a symlink, are rejected with
+    /// `PermissionDenied`. The target itself does not need to exist, but the root must
+    /// have existed when this `DocumentRoot` was created; otherwise the error is
+    /// `NotFound`. An embedded root has no symlinks, and resolves to the cleaned relative
+    /// path.
+    ///
+    /// This follows symlinks on the filesystem; async callers use
+    /// [`resolve_async`](Self::resolve_async).
+    pub fn resolve<P: AsRef<Path>>(&self, rel: P) -> io::Result<PathBuf> {
+        let rel = rel.as_ref();
+        let (full, real_root) = match self.lexical(rel)? {
+            Lexical::Embedded(clean) => return Ok(clean),
+            Lexical::Disk(full, real_root) => (full, real_root),
+        };
+        if stays_inside(&full, real_root) {
+            Ok(full)
+        } else {
+            Err(escapes(rel))
+        }
+    }
+
+    /// [`resolve`](Self::resolve), following symlinks on a blocking thread.
+    pub async fn resolve_async<P: AsRef<Path>>(&self, rel: P) -> io::Result<PathBuf> {
+        let rel = rel.as_ref();
+        let (full, real_root) = match self.lexical(rel)? {
+            Lexical::Embedded(clean) => return Ok(clean),
+            Lexical::Disk(full, real_root) => (full, real_root.to_path_buf()),
+        };
+        let checked = full.clone();
+        let inside = tokio::task::spawn_blocking(move || stays_inside(&checked, &real_root))
+            .await
+            .map_err(io::Error::other)?;
+        if inside { Ok(full) } else { Err(escapes(rel)) }
+    }
+
+    /// The part of [`resolve`](Self::resolve) that doesn't touch the filesystem.
+    fn lexical(&self, rel: &Path) -> io::Result<Lexical<'_>> {
+        let mut clean = PathBuf::new();
+        for comp in rel.components() {
+            match comp {
+                Component::Normal(part) => clean.push(part),
+                Component::CurDir | Component::RootDir => {}
+                Component::ParentDir => {
+                    if !clean.pop() {
+                        return Err(escapes(rel));
+                    }
+                }
+                Component::Prefix(_) => return Err(escapes(rel)),
+            }
+        }
+        let root = match &self.source {
+            Source::Disk(root) => root,
+            Source::Memory(_) => return Ok(Lexical::Embedded(clean)),
+        };
+        let Some(real_root) = &self.real_root else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("document root '{}' does not exist", root.display()),
+            ));
+        };
+        Ok(Lexical::Disk(root.join(clean), real_root))
+    }
+}
+
+/// A path cleaned by [`DocumentRoot::lexical`]: relative to an embedded root, or under a
+/// disk root along with that root's real path.
+enum Lexical<'a> {
+    Embedded(PathBuf),
+    Disk(PathBuf, &'a Path),
+}
+
+fn escapes(rel: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!("path '{}' escapes the document root", rel.display()),
+    )
+}
+
+/// Whether the longest existing prefix of `full`, with symlinks followed, is inside
+/// `real_root`.
+fn stays_inside(full: &Path, real_root: &Path) -> bool {
+    let mut existing = Some(full);
+    while let Some(p) = existing {
+        if let Ok(real) = std::fs::canonicalize(p) {
+            return real.starts_with(real_root);
+        }
+        existing = p.parent();
     }
+    true
 }
@@ -1,56 +1,220 @@
 use crate::config::HttpServerConfig;
-use crate::fs::DocumentRoot;
+use crate::engine::{ExecutorPool, RenderError};
+use crate::fs::{DocumentRoot, FileKind};
 use axum::{
-    Router,
-    http::StatusCode,
+    Json, Router,
+    extract::{ConnectInfo, Request, ws::WebSocketUpgrade},
+    handler::Handler,
+    http::{HeaderValue, StatusCode, header},
+    middleware::{self, Next},
     response::{Html, IntoResponse, Response},
-    routing::get,
+    routing::{MethodRouter, get},
 };
-use jhp_executor::Op;
+use axum_server::tls_rustls::RustlsConfig;
+use hyper_util::rt::{TokioExecutor, TokioTimer};
+use hyper_util::server::conn::auto;
+use jhp_executor::{Abort, RenderOutput, RequestInfo};
 use jhp_parser as parser;
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use tokio::sync::mpsc;
 
+mod cache;
+mod conditional;
+mod deny;
+mod mime;
+mod precompressed;
+mod proxy;
+mod range;
+mod stream;
+mod websocket;
+
 #[derive(Clone)]
 pub struct HttpServer {
     router: Arc<Router>,
     config: HttpServerConfig,
 }
 
+/// Shared by every request handler.
+struct ServerState {
+    pool: Arc<ExecutorPool>,
+    doc_root: DocumentRoot,
+    /// Extra document roots by URL prefix (without slashes), in lookup order.
+    mounts: Vec<(String, DocumentRoot)>,
+    config: HttpServerConfig,
+}
+
+impl ServerState {
+    /// The URL prefix and document root serving `rel`, and the path within that root:
+    /// the first mount whose prefix covers `rel`, otherwise the primary document root.
+    fn root_for<'s, 'r>(&'s self, rel: &'r str) -> (&'s str, &'s DocumentRoot, &'r str) {
+        for (prefix, root) in &self.mounts {
+            let Some(rest) = rel.strip_prefix(prefix.as_str()) else {
+                continue;
+            };
+            if rest.is_empty() || rest.starts_with('/') {
+                return (prefix, root, rest.trim_start_matches('/'));
+            }
+        }
+        ("", &self.doc_root, rel)
+    }
+}
+
 pub struct HttpRequest;
 pub struct HttpRespnse;
 
 impl HttpServer {
     /// Construct an HttpServer with routes defined here.
     /// By default exposes:
-    /// - GET "/": renders `jhp-tests/index.jhp` via the executor.
-    pub fn new(sender: mpsc::UnboundedSender<Op>, config: HttpServerConfig) -> Self {
-        let doc_root = DocumentRoot::new(config.document_root.clone(), config.index_file.clone());
-        let router = Router::new()
+    /// - GET "/" (and any directory): renders or serves the first existing index file,
+    ///   e.g. `jhp-tests/index.jhp`.
+    /// - GET "/__jhp/health": `{"status":"ok"}` whenever the server is up, without touching
+    ///   the document root or the executors.
+    /// - GET "/__jhp/ready": the same once an executor has finished bootstrapping, and
+    ///   `503 Service Unavailable` before that.
+    /// - GET "/__jhp/version": the engine and V8 versions, and the capabilities enabled
+    ///   for this server, as JSON, when enabled in the config.
+    /// - GET "/__jhp/stats": executor statistics as JSON, when enabled in the config.
+    /// - GET "/__jhp/extensions": loaded native modules with their functions and values as
+    ///   JSON, when enabled in the config.
+    /// - the WebSocket endpoints of the config, see
+    ///   [`WebSocketRoute`](crate::config::WebSocketRoute).
+    ///
+    /// Documents are served from the config's `embedded_root` when it has one, and from
+    /// its `document_root` directory otherwise.
+    ///
+    /// Being explicit routes, the `/__jhp/` endpoints take precedence over document-root files.
+    /// Requests are dispatched straight to `pool`, so a full worker mailbox makes the
+    /// handler wait rather than queueing work without bound. With a `request_timeout`,
+    /// any route taking longer answers `408 Request Timeout`.
+    pub fn new(pool: Arc<ExecutorPool>, config: HttpServerConfig) -> Self {
+        let state = Arc::new(ServerState {
+            doc_root: match &config.embedded_root {
+                Some(files) => DocumentRoot::embedded(files.clone(), config.index_files.clone()),
+                None => DocumentRoot::new(config.document_root.clone(), config.index_files.clone()),
+            },
+            mounts: config
+                .mounts
+                .iter()
+                .map(|mount| {
+                    let root =
+                        DocumentRoot::new(mount.directory.clone(), config.index_files.clone());
+                    (mount.url_prefix.trim_matches('/').to_string(), root)
+                })
+                .collect(),
+            pool,
+            config: config.clone(),
+        });
+        let mut router = Router::new()
+            .route(
+                "/__jhp/health",
+                read_only(|| async { Self::status_response(StatusCode::OK, "ok") }),
+            )
             .route(
-                "/",
-                get({
-                    let sender = sender.clone();
-                    let doc_root = doc_root.clone();
+                "/__jhp/ready",
+                read_only({
+                    let state = state.clone();
                     move || {
-                        let sender = sender.clone();
-                        let doc_root = doc_root.clone();
-                        async move { Self::handle_request(sender, doc_root, String::new()).await }
+                        let state = state.clone();
+                        async move { Self::handle_ready(&state.pool) }
                     }
                 }),
-            )
-            .route(
-                "/{*path}",
-                get({
-                    let sender = sender.clone();
-                    let doc_root = doc_root.clone();
-                    move |axum::extract::Path(path): axum::extract::Path<String>| {
-                        let sender = sender.clone();
-                        let doc_root = doc_root.clone();
-                        async move { Self::handle_request(sender, doc_root, path).await }
+            );
+        if config.version_endpoint {
+            router = router.route(
+                "/__jhp/version",
+                read_only({
+                    let config = config.clone();
+                    move || {
+                        let config = config.clone();
+                        async move { Self::handle_version(&config) }
+                    }
+                }),
+            );
+        }
+        if config.stats_endpoint {
+            router = router.route(
+                "/__jhp/stats",
+                read_only({
+                    let state = state.clone();
+                    move || {
+                        let state = state.clone();
+                        async move { Self::handle_stats(&state.pool) }
+                    }
+                }),
+            );
+        }
+        if config.extensions_endpoint {
+            router = router.route(
+                "/__jhp/extensions",
+                read_only({
+                    let state = state.clone();
+                    move || {
+                        let state = state.clone();
+                        async move { Self::handle_extensions(&state.pool) }
+                    }
+                }),
+            );
+        }
+        for route in &config.websockets {
+            let endpoint = Arc::new(websocket::Endpoint::new(
+                route.clone(),
+                config.logger.clone(),
+            ));
+            let state = state.clone();
+            router = router.route(
+                &route.url_path,
+                get(move |ws: WebSocketUpgrade, request: Request| {
+                    let request = request_info(&request, &state.config);
+                    let pool = state.pool.clone();
+                    let endpoint = endpoint.clone();
+                    async move {
+                        ws.on_upgrade(move |socket| endpoint.serve(socket, pool, request))
                     }
                 }),
             );
+        }
+        router = router.route(
+            "/",
+            read_only({
+                let state = state.clone();
+                move |request: Request| {
+                    let state = state.clone();
+                    let request = request_info(&request, &state.config);
+                    async move { Self::handle_request(state, String::new(), request).await }
+                }
+            }),
+        );
+        router = router.route(
+            "/{*path}",
+            read_only({
+                let state = state.clone();
+                move |request: Request| {
+                    let state = state.clone();
+                    // decoded strictly here rather than by axum's `Path` extractor
+                    let path = decode_path(request.uri().path());
+                    let request = request_info(&request, &state.config);
+                    async move {
+                        match path {
+                            Some(path) => Self::handle_request(state, path, request).await,
+                            None => {
+                                (StatusCode::BAD_REQUEST, "Malformed request path").into_response()
+                            }
+                        }
+                    }
+                }
+            }),
+        );
+        if let Some(limit) = config.request_timeout {
+            router = router.layer(middleware::from_fn(
+                move |request: Request, next: Next| async move {
+                    Self::with_timeout(limit, next.run(request)).await
+                },
+            ));
+        }
 
         Self {
             router: Arc::new(router),
@@ -58,62 +222,198 @@ impl HttpServer {
         }
     }
 
+    /// Returns the router serving this server's routes, e.g. to drive it without a listener.
+    pub fn router(&self) -> Router {
+        (*self.router).clone()
+    }
+
+    /// Await `response`, or answer `408 Request Timeout` once `limit` has passed. Dropping
+    /// the handler also drops its pending render, which the executor then abandons.
+    async fn with_timeout(limit: Duration, response: impl Future<Output = Response>) -> Response {
+        match tokio::time::timeout(limit, response).await {
+            Ok(response) => response,
+            Err(_) => (StatusCode::REQUEST_TIMEOUT, "Request Timeout").into_response(),
+        }
+    }
+
+    fn handle_ready(pool: &ExecutorPool) -> Response {
+        if pool.is_ready() {
+            Self::status_response(StatusCode::OK, "ok")
+        } else {
+            Self::status_response(StatusCode::SERVICE_UNAVAILABLE, "starting")
+        }
+    }
+
+    fn status_response(code: StatusCode, status: &str) -> Response {
+        (code, Json(serde_json::json!({ "status": status }))).into_response()
+    }
+
+    /// `{"version", "v8", "capabilities"}`. The capabilities are those of this server:
+    /// `tls` when it was given a certificate, `etag` when it sends ETags, and `compression`
+    /// (precompressed gzip siblings) and `streaming` (`flush()`), which are always on.
+    /// The engine has no sessions, so there is no `sessions` capability to report.
+    fn handle_version(config: &HttpServerConfig) -> Response {
+        let tls = config.tls_cert.is_some() && config.tls_key.is_some();
+        let capabilities: Vec<&str> = [
+            ("tls", tls),
+            ("compression", true),
+            ("streaming", true),
+            ("etag", config.etag),
+        ]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect();
+        Json(serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "v8": v8::V8::get_version(),
+            "capabilities": capabilities,
+        }))
+        .into_response()
+    }
+
+    fn handle_stats(pool: &ExecutorPool) -> Response {
+        let workers = pool.stats();
+        let served: u64 = workers.iter().map(|w| w.stats.requests_served).sum();
+        let in_flight: usize = workers.iter().map(|w| w.stats.in_flight).sum();
+        let workers: Vec<_> = workers
+            .iter()
+            .map(|w| {
+                serde_json::json!({
+                    "id": w.id,
+                    "requests_served": w.stats.requests_served,
+                    "in_flight": w.stats.in_flight,
+                    "queued": w.queued,
+                    "heap": {
+                        "used": w.stats.heap_used,
+                        "total": w.stats.heap_total,
+                        "limit": w.stats.heap_limit,
+                    },
+                    "scripts": {
+                        "compiled": w.stats.scripts_compiled,
+                        "cache_hits": w.stats.code_cache_hits,
+                    },
+                    "renders": {
+                        "blocks": w.stats.blocks_executed,
+                        "compile_us": w.stats.compile_micros,
+                        "run_us": w.stats.run_micros,
+                        "output_bytes": w.stats.output_bytes,
+                    },
+                })
+            })
+            .collect();
+        Json(serde_json::json!({
+            "requests_served": served,
+            "in_flight": in_flight,
+            "workers": workers,
+        }))
+        .into_response()
+    }
+
+    fn handle_extensions(pool: &ExecutorPool) -> Response {
+        let modules: Vec<_> = pool
+            .modules
+            .modules()
+            .into_iter()
+            .map(|module| {
+                serde_json::json!({
+                    "name": module.name,
+                    "object": module.object,
+                    "functions": module.functions,
+                    "values": module.values,
+                })
+            })
+            .collect();
+        Json(serde_json::json!({ "modules": modules })).into_response()
+    }
+
     async fn handle_request(
-        sender: mpsc::UnboundedSender<Op>,
-        doc_root: DocumentRoot,
+        state: Arc<ServerState>,
         path: String,
+        request: RequestInfo,
     ) -> Response {
-        // Root path: empty or only slashes -> render index or 404
-        if path.trim_matches('/').is_empty() {
-            match doc_root.read_index().await {
-                Ok(content) => {
-                    let mut p = parser::Parser::new(&content);
-                    let blocks = p.parse().blocks;
-                    let (tx, rx) = tokio::sync::oneshot::channel();
-                    let _ = sender.send(Op::Render {
-                        blocks,
-                        resource_name: doc_root.index_name().to_string(),
-                        respond_to: tx,
-                    });
-                    return match rx.await {
-                        Ok(body) => Html(body).into_response(),
-                        Err(_) => (StatusCode::SERVICE_UNAVAILABLE, "Executor unavailable")
-                            .into_response(),
-                    };
-                }
-                Err(_) => {
-                    return (StatusCode::NOT_FOUND, "Cannot get '/': File Not Found")
-                        .into_response();
-                }
-            }
+        let (response, last_modified) = Self::respond(&state, path, &request).await;
+        let streamed = response.extensions().get::<stream::Streamed>().is_some();
+        if state.config.etag && response.status() == StatusCode::OK && !streamed {
+            conditional::apply(&request, response, last_modified).await
+        } else {
+            response
         }
+    }
 
-        let rel = path.trim_start_matches('/');
-        if rel.contains("..") {
-            return (StatusCode::FORBIDDEN, "Invalid path").into_response();
-        }
-
-        if rel == doc_root.index_name() {
-            match doc_root.read_index().await {
-                Ok(content) => {
-                    let mut p = parser::Parser::new(&content);
-                    let blocks = p.parse().blocks;
-                    let (tx, rx) = tokio::sync::oneshot::channel();
-                    let _ = sender.send(Op::Render {
-                        blocks,
-                        resource_name: doc_root.index_name().to_string(),
-                        respond_to: tx,
-                    });
-                    return match rx.await {
-                        Ok(body) => Html(body).into_response(),
-                        Err(_) => (StatusCode::SERVICE_UNAVAILABLE, "Executor unavailable")
-                            .into_response(),
-                    };
-                }
-                Err(_) => {
-                    return (StatusCode::NOT_FOUND, "Cannot get '/': File Not Found")
-                        .into_response();
+    /// Produce the response for `path`, along with the modification time of the static
+    /// file it was served from (rendered responses have none). Paths under a mount's
+    /// prefix are served from that mount's directory.
+    async fn respond(
+        state: &ServerState,
+        path: String,
+        request: &RequestInfo,
+    ) -> (Response, Option<SystemTime>) {
+        let url_rel = path.trim_start_matches('/');
+        let (prefix, doc_root, rel) = state.root_for(url_rel);
+        let not_found = || {
+            let msg = format!("Cannot get '/{}': File Not Found", url_rel);
+            ((StatusCode::NOT_FOUND, msg).into_response(), None)
+        };
+        let forbidden = || {
+            (
+                (StatusCode::FORBIDDEN, "Invalid path").into_response(),
+                None,
+            )
+        };
+        // every root guards itself, including against symlinks leading out of it
+        if url_rel.contains("..") {
+            return forbidden();
+        }
+        if let Err(e) = doc_root.resolve_async(rel).await {
+            // a root that doesn't exist has nothing to serve
+            return if e.kind() == io::ErrorKind::NotFound {
+                not_found()
+            } else {
+                forbidden()
+            };
+        }
+
+        // denied paths look like missing ones, so their existence isn't revealed
+        if deny::is_denied(&state.config.denied_paths, url_rel) {
+            return not_found();
+        }
+
+        // One stat decides the route: files are served as they are, and the root and other
+        // directories through their first existing index file
+        let target = match doc_root.stat(rel).await {
+            Some(FileKind::File) => Some(rel.to_string()),
+            // `/docs` becomes `/docs/`, so relative links in its index resolve inside it
+            Some(FileKind::Dir) if !request.path.ends_with('/') => {
+                let mut location = format!("{}/", request.path);
+                if !request.query.is_empty() {
+                    location = format!("{}?{}", location, request.query);
                 }
+                let response = (
+                    StatusCode::MOVED_PERMANENTLY,
+                    [(header::LOCATION, location)],
+                );
+                return (response.into_response(), None);
+            }
+            Some(FileKind::Dir) => doc_root.find_index(rel).await,
+            None => None,
+        };
+        let Some(target) = target else {
+            return not_found();
+        };
+        let rel = target.as_str();
+        // templates are named by their URL path in error positions
+        let resource_name = if prefix.is_empty() {
+            target.clone()
+        } else {
+            format!("{}/{}", prefix, target)
+        };
+
+        // Range requests only read the bytes they ask for
+        if !rel.ends_with(".jhp") && request.header("range").is_some() {
+            let modified = doc_root.modified(rel).await.ok();
+            if let Some(mut response) = range::respond(doc_root, rel, request, modified).await {
+                cache::apply_static(response.headers_mut(), &state.config, rel, modified);
+                return (response, modified);
             }
         }
 
@@ -121,36 +421,287 @@ impl HttpServer {
         match doc_root.read_file(rel).await {
             Ok(content) => {
                 if rel.ends_with(".jhp") {
-                    let mut p = parser::Parser::new(&content);
-                    let blocks = p.parse().blocks;
-                    let resource_name = rel.to_string();
-                    let (tx, rx) = tokio::sync::oneshot::channel();
-                    let _ = sender.send(Op::Render {
-                        blocks,
-                        resource_name,
-                        respond_to: tx,
-                    });
-                    match rx.await {
-                        Ok(body) => Html(body).into_response(),
-                        Err(_) => (StatusCode::SERVICE_UNAVAILABLE, "Executor unavailable")
-                            .into_response(),
-                    }
+                    let response =
+                        Self::render_template(state, &content, &resource_name, request.clone());
+                    (response.await, None)
                 } else {
-                    Html(content).into_response()
+                    let modified = doc_root.modified(rel).await.ok();
+                    // a `.gz` sibling is sent with the type of the file it compresses, and
+                    // only read for a client that accepts it
+                    let content_type = (header::CONTENT_TYPE, mime::content_type(rel));
+                    let vary = precompressed::has_gzip_sibling(doc_root, rel).await;
+                    let gzipped = if vary && precompressed::accepts_gzip(request) {
+                        precompressed::read_gzip_sibling(doc_root, rel).await
+                    } else {
+                        None
+                    };
+                    let mut response = match gzipped {
+                        Some(gzipped) => (
+                            [
+                                content_type,
+                                (header::CONTENT_ENCODING, "gzip"),
+                                (header::VARY, "Accept-Encoding"),
+                            ],
+                            gzipped,
+                        )
+                            .into_response(),
+                        None if vary => {
+                            ([content_type, (header::VARY, "Accept-Encoding")], content)
+                                .into_response()
+                        }
+                        None => ([content_type], content).into_response(),
+                    };
+                    response
+                        .headers_mut()
+                        .insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+                    cache::apply_static(response.headers_mut(), &state.config, rel, modified);
+                    (response, modified)
                 }
             }
             Err(_) => {
-                let msg = format!("Cannot get '/{}': File Not Found", rel);
-                (StatusCode::NOT_FOUND, msg).into_response()
+                let msg = format!("Cannot get '/{}': File Not Found", resource_name);
+                ((StatusCode::NOT_FOUND, msg).into_response(), None)
             }
         }
     }
 
-    pub async fn start(&self) {
-        let listener = tokio::net::TcpListener::bind(&self.config.addr())
+    /// Parse and render a template on an executor. A leading `contentType(...)` directive
+    /// overrides the configured default response type (`text/html` unless changed). A
+    /// template calling `abort(status, message?)` gets that status with the message (or
+    /// the reason phrase) as a plain-text body instead, and a redirect its status and
+    /// `Location` with an empty body. A status or content type the
+    /// template set itself (e.g. with `$response.json`) wins over both defaults, and an
+    /// uncaught error answers `500`. Once the template calls `flush()`, the response is
+    /// streamed instead, see [`stream::response`].
+    async fn render_template(
+        state: &ServerState,
+        content: &str,
+        resource_name: &str,
+        request: RequestInfo,
+    ) -> Response {
+        let parsed = parser::Parser::new(content).parse();
+        let content_type = parsed
+            .directive("contentType")
+            .and_then(|d| d.args.first())
+            .or(Some(&state.config.default_content_type))
+            .and_then(|v| HeaderValue::from_str(v).ok());
+
+        let (chunk_tx, mut chunks) = mpsc::unbounded_channel();
+        // owned, so a streamed body can keep awaiting it after this returns
+        let rendered = {
+            let pool = state.pool.clone();
+            let resource_name = resource_name.to_string();
+            async move {
+                pool.render_streaming(parsed.blocks, &resource_name, request, chunk_tx)
+                    .await
+            }
+        };
+        let mut rendered = Box::pin(rendered);
+        let first = tokio::select! {
+            biased;
+            chunk = chunks.recv() => chunk,
+            rendered = &mut rendered => return Self::buffered_response(rendered, content_type),
+        };
+        match first {
+            Some(first) => stream::response(first, chunks, rendered, content_type),
+            // closed without a chunk: the render is done and never flushed
+            None => Self::buffered_response(rendered.await, content_type),
+        }
+    }
+
+    /// The whole response of a render that never flushed.
+    fn buffered_response(
+        rendered: Result<RenderOutput, RenderError>,
+        content_type: Option<HeaderValue>,
+    ) -> Response {
+        match rendered {
+            Ok(RenderOutput {
+                abort: Some(abort), ..
+            }) => Self::abort_response(abort),
+            Ok(RenderOutput {
+                body,
+                error,
+                status,
+                content_type: set_type,
+                ..
+            }) => {
+                let status = match error {
+                    Some(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                    None => status
+                        .and_then(|s| StatusCode::from_u16(s).ok())
+                        .unwrap_or(StatusCode::OK),
+                };
+                let content_type = set_type
+                    .and_then(|v| HeaderValue::from_str(&v).ok())
+                    .or(content_type);
+                match content_type {
+                    Some(content_type) => {
+                        (status, [(header::CONTENT_TYPE, content_type)], body).into_response()
+                    }
+                    None => (status, Html(body)).into_response(),
+                }
+            }
+            Err(_) => (StatusCode::SERVICE_UNAVAILABLE, "Executor unavailable").into_response(),
+        }
+    }
+
+    fn abort_response(abort: Abort) -> Response {
+        let status =
+            StatusCode::from_u16(abort.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        if let Some(location) = abort.location {
+            return match HeaderValue::from_str(&location) {
+                Ok(location) => (status, [(header::LOCATION, location)]).into_response(),
+                Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            };
+        }
+        let body = abort
+            .message
+            .unwrap_or_else(|| status.canonical_reason().unwrap_or_default().to_string());
+        (status, body).into_response()
+    }
+
+    /// Serve until the listener stops. Plain HTTP by default; HTTPS when both a TLS
+    /// certificate and key are configured, in which case they are loaded up front so a
+    /// bad certificate or key is reported before any connection is accepted. An address
+    /// that can't be bound (e.g. a port in use) is an error rather than a panic.
+    pub async fn start(&self) -> Result<(), String> {
+        match (&self.config.tls_cert, &self.config.tls_key) {
+            (Some(cert), Some(key)) => self.start_tls(cert, key).await,
+            (None, None) => {
+                let mut server = axum_server::from_tcp(self.bind().await?);
+                self.configure_connections(server.http_builder());
+                server
+                    .serve(
+                        (*self.router)
+                            .clone()
+                            .into_make_service_with_connect_info::<SocketAddr>(),
+                    )
+                    .await
+                    .map_err(|e| e.to_string())
+            }
+            _ => Err("TLS needs both a certificate and a key".to_string()),
+        }
+    }
+
+    async fn start_tls(&self, cert: &Path, key: &Path) -> Result<(), String> {
+        let tls = RustlsConfig::from_pem_file(cert, key).await.map_err(|e| {
+            format!(
+                "failed to load TLS certificate '{}' and key '{}': {}",
+                cert.display(),
+                key.display(),
+                e
+            )
+        })?;
+
+        let mut server = axum_server::from_tcp_rustls(self.bind().await?, tls);
+        self.configure_connections(server.http_builder());
+        server
+            .serve(
+                (*self.router)
+                    .clone()
+                    .into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Bind the listen address, so a failure can be reported as such before serving.
+    async fn bind(&self) -> Result<std::net::TcpListener, String> {
+        let addr = self.listen_addr().await?;
+        std::net::TcpListener::bind(addr)
+            .and_then(|listener| {
+                listener.set_nonblocking(true)?;
+                Ok(listener)
+            })
+            .map_err(|e| format!("failed to bind {}: {}", self.config.addr(), e))
+    }
+
+    async fn listen_addr(&self) -> Result<SocketAddr, String> {
+        tokio::net::lookup_host(self.config.addr())
             .await
-            .unwrap();
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .ok_or_else(|| format!("invalid listen address {}", self.config.addr()))
+    }
+
+    /// Apply the connection settings of the config: a client that hasn't sent complete
+    /// request headers within `header_read_timeout` is disconnected.
+    fn configure_connections(&self, builder: &mut auto::Builder<TokioExecutor>) {
+        if let Some(timeout) = self.config.header_read_timeout {
+            builder
+                .http1()
+                .timer(TokioTimer::new())
+                .header_read_timeout(timeout);
+        }
+    }
+}
+
+/// Methods accepted by routes that only read.
+const READ_METHODS: &str = "GET, HEAD";
+
+/// A GET (and HEAD) route that answers any other method with `405 Method Not Allowed`
+/// and an `Allow` header listing [`READ_METHODS`].
+fn read_only<H, T>(handler: H) -> MethodRouter
+where
+    H: Handler<T, ()>,
+    T: 'static,
+{
+    get(handler).fallback(|| async {
+        (
+            StatusCode::METHOD_NOT_ALLOWED,
+            [(header::ALLOW, READ_METHODS)],
+        )
+            .into_response()
+    })
+}
 
-        axum::serve(listener, (*self.router).clone()).await.unwrap();
+/// Percent-decode a request path strictly: `None` for a `%` not followed by two hex
+/// digits, an encoded NUL byte, or a result that isn't UTF-8.
+fn decode_path(raw: &str) -> Option<String> {
+    let bytes = raw.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = if bytes[i] == b'%' {
+            let hex = raw
+                .get(i + 1..i + 3)
+                .filter(|h| h.bytes().all(|c| c.is_ascii_hexdigit()))?;
+            i += 2;
+            u8::from_str_radix(hex, 16).ok()?
+        } else {
+            bytes[i]
+        };
+        if byte == 0 {
+            return None;
+        }
+        out.push(byte);
+        i += 1;
+    }
+    String::from_utf8(out).ok()
+}
+
+/// Capture the request details templates see as `$request`. The client address, scheme
+/// and host come from forwarded headers only when the peer is a trusted proxy.
+fn request_info(request: &Request, config: &HttpServerConfig) -> RequestInfo {
+    let (uri, headers) = (request.uri(), request.headers());
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let tls = config.tls_cert.is_some() && config.tls_key.is_some();
+    let origin = proxy::origin(peer, headers, tls, &config.trusted_proxies);
+
+    let mut info = RequestInfo::new(request.method().as_str(), uri.path())
+        .set_query(uri.query().unwrap_or(""))
+        .set_start_time(SystemTime::now())
+        .set_scheme(origin.scheme);
+    if let Some(remote_addr) = origin.remote_addr {
+        info = info.set_remote_addr(remote_addr);
+    }
+    if let Some(host) = origin.host {
+        info = info.set_host(host);
     }
+    headers.iter().fold(info, |info, (name, value)| {
+        info.add_header(name, String::from_utf8_lossy(value.as_bytes()))
+    })
 }
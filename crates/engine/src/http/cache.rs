@@ -0,0 +1,56 @@
+//! Caching headers for static files.
+
+use crate::config::HttpServerConfig;
+use axum::http::{HeaderMap, HeaderValue, header};
+use httpdate::HttpDate;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// The `Cache-Control` value configured for the static file `rel`: the entry for its
+/// extension if there is one, otherwise the global setting.
+pub(crate) fn cache_control_for<'c>(config: &'c HttpServerConfig, rel: &str) -> Option<&'c str> {
+    let ext = Path::new(rel)
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase());
+    ext.and_then(|ext| config.static_cache_control_by_ext.get(&ext))
+        .or(config.static_cache_control.as_ref())
+        .map(String::as_str)
+}
+
+/// The `max-age` directive of a `Cache-Control` value, if present.
+fn max_age(cache_control: &str) -> Option<u64> {
+    cache_control.split(',').find_map(|directive| {
+        let (name, value) = directive.trim().split_once('=')?;
+        if name.trim().eq_ignore_ascii_case("max-age") {
+            value.trim().trim_matches('"').parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+/// Add `Last-Modified` and, when configured, `Cache-Control` plus a matching `Expires`
+/// (derived from `max-age`, for HTTP/1.0 caches) to a static file response.
+pub(crate) fn apply_static(
+    headers: &mut HeaderMap,
+    config: &HttpServerConfig,
+    rel: &str,
+    modified: Option<SystemTime>,
+) {
+    let modified = modified.map(|m| HttpDate::from(m).to_string());
+    if let Some(value) = modified.and_then(|m| HeaderValue::from_str(&m).ok()) {
+        headers.insert(header::LAST_MODIFIED, value);
+    }
+
+    let Some(cache_control) = cache_control_for(config, rel) else {
+        return;
+    };
+    if let Ok(value) = HeaderValue::from_str(cache_control) {
+        headers.insert(header::CACHE_CONTROL, value);
+    }
+    let expires = max_age(cache_control)
+        .map(|secs| HttpDate::from(SystemTime::now() + Duration::from_secs(secs)).to_string());
+    if let Some(value) = expires.and_then(|e| HeaderValue::from_str(&e).ok()) {
+        headers.insert(header::EXPIRES, value);
+    }
+}
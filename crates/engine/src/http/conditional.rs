@@ -0,0 +1,77 @@
+//! Conditional GET: strong ETags with `If-None-Match`, and `Last-Modified` with
+//! `If-Modified-Since` for static files.
+
+use axum::body::{Body, HttpBody, to_bytes};
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use httpdate::HttpDate;
+use jhp_executor::RequestInfo;
+use sha2::{Digest, Sha256};
+use std::time::SystemTime;
+
+/// Largest body given an ETag; hashing one means holding all of it in memory.
+const MAX_ETAG_BODY: u64 = 8 * 1024 * 1024;
+
+/// A strong ETag for `body`: the first 128 bits of its SHA-256, quoted.
+pub(crate) fn etag(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+    let hex: String = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("\"{}\"", hex)
+}
+
+/// Whether the client's cached copy is still current. `If-None-Match` takes precedence;
+/// `If-Modified-Since` is only consulted without it, and only when `last_modified` is known.
+pub(crate) fn is_not_modified(
+    request: &RequestInfo,
+    etag: &str,
+    last_modified: Option<SystemTime>,
+) -> bool {
+    if let Some(if_none_match) = request.header("if-none-match") {
+        return if_none_match.split(',').map(str::trim).any(|tag| {
+            // If-None-Match uses the weak comparison, so `W/"x"` matches `"x"`
+            tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag
+        });
+    }
+    match (request.header("if-modified-since"), last_modified) {
+        (Some(since), Some(modified)) => since
+            .parse::<HttpDate>()
+            .is_ok_and(|since| HttpDate::from(modified) <= since),
+        _ => false,
+    }
+}
+
+/// Attach an `ETag` to a successful response, answering `304 Not Modified` without a body
+/// instead when the request's validators match. `last_modified` is the static file's
+/// mtime, already sent as `Last-Modified`. A body of unknown length (streamed) or larger
+/// than [`MAX_ETAG_BODY`] is passed through untouched.
+pub(crate) async fn apply(
+    request: &RequestInfo,
+    response: Response,
+    last_modified: Option<SystemTime>,
+) -> Response {
+    let len = match response.body().size_hint().exact() {
+        Some(len) if len <= MAX_ETAG_BODY => len as usize,
+        _ => return response,
+    };
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, len).await else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to read response body",
+        )
+            .into_response();
+    };
+
+    let tag = etag(&bytes);
+    let not_modified = is_not_modified(request, &tag, last_modified);
+    if let Ok(value) = HeaderValue::from_str(&tag) {
+        parts.headers.insert(header::ETAG, value);
+    }
+
+    if not_modified {
+        parts.status = StatusCode::NOT_MODIFIED;
+        parts.headers.remove(header::CONTENT_LENGTH);
+        return Response::from_parts(parts, Body::empty());
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
@@ -0,0 +1,36 @@
+//! Paths that are never served directly, such as dotfiles and include-only templates.
+
+/// Whether the root-relative `path` matches one of `patterns`. A pattern without a `/`
+/// is matched against every segment of the path, so `.*` covers hidden files and files
+/// in hidden directories alike; one with a `/` is matched against the whole path.
+/// `*` stands for any run of characters but `/`, `**` for any run at all, and `?` for
+/// one character other than `/`.
+pub(crate) fn is_denied(patterns: &[String], path: &str) -> bool {
+    let path = path.trim_matches('/');
+    let segments: Vec<Vec<char>> = path.split('/').map(|s| s.chars().collect()).collect();
+    let whole: Vec<char> = path.chars().collect();
+    patterns.iter().any(|pattern| {
+        let pattern: Vec<char> = pattern.trim_start_matches('/').chars().collect();
+        if pattern.contains(&'/') {
+            glob_match(&pattern, &whole)
+        } else {
+            segments.iter().any(|segment| glob_match(&pattern, segment))
+        }
+    })
+}
+
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => match rest.split_first() {
+            Some(('*', rest)) => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+            _ => (0..=text.len())
+                .take_while(|&i| i == 0 || text[i - 1] != '/')
+                .any(|i| glob_match(rest, &text[i..])),
+        },
+        Some(('?', rest)) => {
+            text.first().is_some_and(|&c| c != '/') && glob_match(rest, &text[1..])
+        }
+        Some((c, rest)) => text.first() == Some(c) && glob_match(rest, &text[1..]),
+    }
+}
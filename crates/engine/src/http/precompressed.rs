@@ -0,0 +1,44 @@
+//! Precompressed static files: `app.js.gz` next to `app.js` is sent as-is, with
+//! `Content-Encoding: gzip`, to clients that accept gzip.
+
+use crate::fs::{DocumentRoot, FileKind};
+use jhp_executor::RequestInfo;
+
+/// Whether the request's `Accept-Encoding` allows gzip: an explicit `gzip` (or `x-gzip`)
+/// entry decides, otherwise a `*` entry does. A `q=0` refuses it, and so does a missing
+/// header, since the identity encoding is then preferred.
+pub(crate) fn accepts_gzip(request: &RequestInfo) -> bool {
+    let Some(header) = request.header("accept-encoding") else {
+        return false;
+    };
+    let mut gzip = None;
+    let mut any = None;
+    for entry in header.split(',') {
+        let mut parts = entry.split(';');
+        let coding = parts.next().unwrap_or("").trim();
+        let accepted = parts
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+            .is_none_or(|(_, q)| q.trim().parse::<f32>().is_ok_and(|q| q > 0.0));
+        if coding.eq_ignore_ascii_case("gzip") || coding.eq_ignore_ascii_case("x-gzip") {
+            gzip = Some(accepted);
+        } else if coding == "*" {
+            any = Some(accepted);
+        }
+    }
+    gzip.or(any).unwrap_or(false)
+}
+
+/// Whether `rel` has a `.gz` sibling, found without reading it.
+pub(crate) async fn has_gzip_sibling(doc_root: &DocumentRoot, rel: &str) -> bool {
+    doc_root.stat(gzip_name(rel)).await == Some(FileKind::File)
+}
+
+/// The contents of `rel`'s `.gz` sibling, if it can be read.
+pub(crate) async fn read_gzip_sibling(doc_root: &DocumentRoot, rel: &str) -> Option<Vec<u8>> {
+    doc_root.read_bytes(gzip_name(rel)).await.ok()
+}
+
+fn gzip_name(rel: &str) -> String {
+    format!("{}.gz", rel)
+}
@@ -0,0 +1,91 @@
+//! Where a request really came from when it passed through trusted reverse proxies.
+
+use crate::config::Cidr;
+use axum::http::{HeaderMap, header};
+use std::net::{IpAddr, SocketAddr};
+
+/// Client address, scheme and host of a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Origin {
+    pub remote_addr: Option<IpAddr>,
+    pub scheme: &'static str,
+    pub host: Option<String>,
+}
+
+/// The origin of a request received from `peer` (on a TLS listener if `tls`). Only when
+/// the peer is one of `trusted` are `X-Forwarded-For`, `X-Forwarded-Proto` and
+/// `X-Forwarded-Host` believed over the connection's own details.
+pub(crate) fn origin(
+    peer: Option<IpAddr>,
+    headers: &HeaderMap,
+    tls: bool,
+    trusted: &[Cidr],
+) -> Origin {
+    let mut origin = Origin {
+        remote_addr: peer,
+        scheme: if tls { "https" } else { "http" },
+        host: header_value(headers, header::HOST.as_str()),
+    };
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|network| network.contains(ip));
+    if !peer.is_some_and(is_trusted) {
+        return origin;
+    }
+    if let Some(client) = forwarded_client(headers, is_trusted) {
+        origin.remote_addr = Some(client);
+    }
+    match first_value(headers, "x-forwarded-proto").as_deref() {
+        Some(proto) if proto.eq_ignore_ascii_case("https") => origin.scheme = "https",
+        Some(proto) if proto.eq_ignore_ascii_case("http") => origin.scheme = "http",
+        _ => {}
+    }
+    if let Some(host) = first_value(headers, "x-forwarded-host") {
+        origin.host = Some(host);
+    }
+    origin
+}
+
+/// The client named by `X-Forwarded-For`: each proxy appends the address it received the
+/// request from, so the last entry that isn't a trusted proxy is the client (or the first
+/// entry, if they all are). `None` without the header or when an entry isn't an address.
+fn forwarded_client(headers: &HeaderMap, is_trusted: impl Fn(IpAddr) -> bool) -> Option<IpAddr> {
+    let entries: Vec<String> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|entry| entry.trim().to_string())
+        .collect();
+    let mut client = None;
+    for entry in entries.iter().rev() {
+        let ip = parse_address(entry)?;
+        client = Some(ip);
+        if !is_trusted(ip) {
+            break;
+        }
+    }
+    client
+}
+
+/// An address with or without a port, e.g. `203.0.113.7`, `203.0.113.7:4711` or `[::1]:80`.
+fn parse_address(entry: &str) -> Option<IpAddr> {
+    entry
+        .parse::<IpAddr>()
+        .or_else(|_| entry.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .ok()
+}
+
+/// The first comma-separated value of header `name`: the one the outermost proxy set.
+fn first_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    header_value(headers, name)?
+        .split(',')
+        .map(str::trim)
+        .find(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
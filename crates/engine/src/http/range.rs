@@ -0,0 +1,198 @@
+//! Byte ranges of static files: `Range: bytes=...` is answered with `206 Partial Content`
+//! and only the requested bytes, read from the file without loading the rest of it.
+
+use super::mime;
+use crate::fs::DocumentRoot;
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use httpdate::HttpDate;
+use jhp_executor::RequestInfo;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Most ranges one request may ask for. A header with more is ignored, so a flood of
+/// tiny ranges can't multiply the work of sending a file.
+const MAX_RANGES: usize = 16;
+
+/// What a valid `Range` header asks of a file.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Ranges {
+    /// Inclusive `(first, last)` byte positions, clamped to the file, in file order, with
+    /// overlapping and adjacent ranges merged.
+    Satisfiable(Vec<(u64, u64)>),
+    /// None of the ranges overlaps the file.
+    Unsatisfiable,
+}
+
+/// A byte position: digits only, so `+1` or `1.0` make the whole header invalid.
+fn position(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// Parse a `Range` header against a file of `size` bytes. `None` means the header is to be
+/// ignored and the whole file sent: a unit other than `bytes`, a malformed range, or more
+/// than [`MAX_RANGES`] of them.
+pub(crate) fn parse(header: &str, size: u64) -> Option<Ranges> {
+    let (unit, specs) = header.split_once('=')?;
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        return None;
+    }
+
+    let mut ranges = Vec::new();
+    let mut count = 0;
+    for spec in specs.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        count += 1;
+        if count > MAX_RANGES {
+            return None;
+        }
+        let (first, last) = spec.split_once('-')?;
+        let (first, last) = (first.trim(), last.trim());
+        let range = if first.is_empty() {
+            // `-n`: the last n bytes
+            let suffix = position(last)?;
+            (suffix > 0 && size > 0).then(|| (size - suffix.min(size), size - 1))
+        } else {
+            let first = position(first)?;
+            let last = if last.is_empty() {
+                u64::MAX
+            } else {
+                position(last)?
+            };
+            if last < first {
+                return None;
+            }
+            (first < size).then(|| (first, last.min(size - 1)))
+        };
+        ranges.extend(range);
+    }
+
+    if count == 0 {
+        None
+    } else if ranges.is_empty() {
+        Some(Ranges::Unsatisfiable)
+    } else {
+        Some(Ranges::Satisfiable(merge(ranges)))
+    }
+}
+
+/// Sort `ranges` and merge those that overlap or touch, so no byte is sent twice.
+fn merge(mut ranges: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+    ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+    for (first, last) in ranges {
+        match merged.last_mut() {
+            Some(previous) if first <= previous.1.saturating_add(1) => {
+                previous.1 = previous.1.max(last);
+            }
+            _ => merged.push((first, last)),
+        }
+    }
+    merged
+}
+
+/// Whether an `If-Range` validator still holds. Only a date equal to the file's
+/// `Last-Modified` does: static ETags are computed from the whole body, which a range
+/// request is meant to avoid reading, so an entity tag always sends the full file.
+fn if_range_holds(if_range: &str, modified: Option<SystemTime>) -> bool {
+    match (if_range.parse::<HttpDate>(), modified) {
+        (Ok(date), Some(modified)) => date == HttpDate::from(modified),
+        _ => false,
+    }
+}
+
+/// A multipart boundary that won't turn up in the parts by accident.
+fn boundary() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    format!("jhp-byteranges-{:032x}", nanos)
+}
+
+/// The response to a `Range` request for the static file `rel`, last modified at
+/// `modified`: `206 Partial Content` with one range, or a `multipart/byteranges` body with
+/// several, and `416 Range Not Satisfiable` when none overlaps the file. `None` when the
+/// request has no usable `Range` (or a stale `If-Range`), and the whole file is sent.
+pub(crate) async fn respond(
+    doc_root: &DocumentRoot,
+    rel: &str,
+    request: &RequestInfo,
+    modified: Option<SystemTime>,
+) -> Option<Response> {
+    let value = request.header("range")?;
+    let stale = request
+        .header("if-range")
+        .is_some_and(|if_range| !if_range_holds(&if_range, modified));
+    if stale {
+        return None;
+    }
+    let size = doc_root.file_size(rel).await.ok()?;
+
+    let ranges = match parse(&value, size)? {
+        Ranges::Satisfiable(ranges) => ranges,
+        Ranges::Unsatisfiable => {
+            let response = (
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [
+                    (header::CONTENT_RANGE, format!("bytes */{}", size)),
+                    (header::ACCEPT_RANGES, "bytes".to_string()),
+                ],
+                "Range Not Satisfiable",
+            );
+            return Some(response.into_response());
+        }
+    };
+
+    let content_type = mime::content_type(rel);
+    let content_range = |(first, last): (u64, u64)| format!("bytes {}-{}/{}", first, last, size);
+    if let [range] = ranges[..] {
+        let body = doc_root
+            .read_range(rel, range.0, range.1 - range.0 + 1)
+            .await
+            .ok()?;
+        let response = (
+            StatusCode::PARTIAL_CONTENT,
+            [
+                (header::CONTENT_TYPE, content_type.to_string()),
+                (header::CONTENT_RANGE, content_range(range)),
+                (header::ACCEPT_RANGES, "bytes".to_string()),
+            ],
+            body,
+        );
+        return Some(response.into_response());
+    }
+
+    let boundary = boundary();
+    let mut body = Vec::new();
+    for range in ranges {
+        let part = doc_root
+            .read_range(rel, range.0, range.1 - range.0 + 1)
+            .await
+            .ok()?;
+        body.extend_from_slice(
+            format!(
+                "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+                boundary,
+                content_type,
+                content_range(range)
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(&part);
+    }
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+    let response = (
+        StatusCode::PARTIAL_CONTENT,
+        [
+            (
+                header::CONTENT_TYPE,
+                format!("multipart/byteranges; boundary={}", boundary),
+            ),
+            (header::ACCEPT_RANGES, "bytes".to_string()),
+        ],
+        body,
+    );
+    Some(response.into_response())
+}
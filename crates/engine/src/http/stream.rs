@@ -0,0 +1,75 @@
+//! Chunked responses for templates that call `flush()`: the status and headers go out
+//! with the first flush, then each later flush and the end of the render as they come.
+
+use crate::engine::RenderError;
+use axum::body::Body;
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use futures_util::{StreamExt, stream};
+use jhp_executor::{Chunk, RenderOutput};
+use std::future::Future;
+use std::io;
+use tokio::sync::mpsc;
+
+/// Marks a response whose body is still being rendered, so nothing buffers it whole
+/// (e.g. to compute an ETag).
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Streamed;
+
+/// A response sent while its render goes on: `first` decides the status and content type
+/// (falling back to `content_type`), and the body is its output, every chunk after it, then
+/// what the render echoed last. An error or `abort()` after the first flush can't change
+/// the status anymore: a script error is only in the body as configured, while an abort
+/// or a render that never finished fails the body, so the connection is cut rather than
+/// the response ending as if it were complete.
+pub(crate) fn response(
+    first: Chunk,
+    chunks: mpsc::UnboundedReceiver<Chunk>,
+    rendered: impl Future<Output = Result<RenderOutput, RenderError>> + Send + 'static,
+    content_type: Option<HeaderValue>,
+) -> Response {
+    let status = first
+        .status
+        .and_then(|s| StatusCode::from_u16(s).ok())
+        .unwrap_or(StatusCode::OK);
+    let content_type = first
+        .content_type
+        .and_then(|v| HeaderValue::from_str(&v).ok())
+        .or(content_type)
+        .unwrap_or(HeaderValue::from_static("text/html; charset=utf-8"));
+
+    let rest = stream::unfold(
+        (chunks, Some(Box::pin(rendered))),
+        |(mut chunks, rendered)| async move {
+            // the executor closes the channel before answering, so no chunk comes after
+            if let Some(chunk) = chunks.recv().await {
+                return Some((Ok(chunk.body), (chunks, rendered)));
+            }
+            let last = match rendered?.await {
+                Ok(RenderOutput {
+                    abort: None, body, ..
+                }) => Ok(body),
+                Ok(RenderOutput {
+                    abort: Some(abort), ..
+                }) => Err(io::Error::other(format!(
+                    "aborted with status {} after output was flushed",
+                    abort.status
+                ))),
+                Err(e) => Err(io::Error::other(e)),
+            };
+            Some((last, (chunks, None)))
+        },
+    );
+    let body = stream::iter([Ok(first.body)])
+        .chain(rest)
+        .filter(|part| std::future::ready(!part.as_ref().is_ok_and(String::is_empty)));
+
+    let mut response = (
+        status,
+        [(header::CONTENT_TYPE, content_type)],
+        Body::from_stream(body),
+    )
+        .into_response();
+    response.extensions_mut().insert(Streamed);
+    response
+}
@@ -0,0 +1,213 @@
+//! WebSocket endpoints: each event of a connection runs a hook of the endpoint's JS
+//! module on an executor, see [`WebSocketRoute`].
+
+use crate::config::WebSocketRoute;
+use crate::engine::ExecutorPool;
+use crate::log::{LogEntry, LogLevel, Logger};
+use axum::extract::ws::{CloseFrame, Message, WebSocket, close_code};
+use jhp_executor::RequestInfo;
+use jhp_parser::{CodeBlock, CodeBlockContent};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Separates what a hook echoed from the messages it sent, in the output of its render.
+/// Hook output can't be mistaken for it, since the messages always come last.
+const SEPARATOR: char = '\0';
+
+/// Messages that may wait for one connection. A client that falls this far behind is
+/// dropped rather than let its backlog grow without bound.
+const OUTGOING_QUEUE: usize = 256;
+
+/// The connections of one endpoint, to broadcast to.
+pub(crate) struct Endpoint {
+    route: WebSocketRoute,
+    logger: Logger,
+    next_id: AtomicU64,
+    clients: Mutex<HashMap<u64, mpsc::Sender<String>>>,
+}
+
+/// What a hook sent: messages for its own connection, then ones for all of them.
+#[derive(Debug, serde::Deserialize)]
+struct Sent {
+    send: Vec<String>,
+    broadcast: Vec<String>,
+}
+
+impl Endpoint {
+    pub(crate) fn new(route: WebSocketRoute, logger: Logger) -> Self {
+        Self {
+            route,
+            logger,
+            next_id: AtomicU64::new(1),
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Serve one upgraded connection until either side closes it.
+    pub(crate) async fn serve(
+        self: Arc<Self>,
+        mut socket: WebSocket,
+        pool: Arc<ExecutorPool>,
+        request: RequestInfo,
+    ) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, mut rx) = mpsc::channel(OUTGOING_QUEUE);
+        self.clients.lock().unwrap().insert(id, tx);
+
+        let mut open = self.hook(&pool, &request, id, "onOpen", None).await;
+        let mut lagging = false;
+        while open {
+            tokio::select! {
+                incoming = socket.recv() => match incoming {
+                    Some(Ok(Message::Text(text))) => {
+                        let message = text.as_str().to_string();
+                        open = self.hook(&pool, &request, id, "onMessage", Some(&message)).await;
+                    }
+                    // pings are answered by axum; binary messages have no hook
+                    Some(Ok(Message::Binary(_) | Message::Ping(_) | Message::Pong(_))) => {}
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                },
+                outgoing = rx.recv() => match outgoing {
+                    Some(outgoing) => {
+                        if socket.send(Message::Text(outgoing.into())).await.is_err() {
+                            break;
+                        }
+                    }
+                    // dropped from the clients for not keeping up
+                    None => {
+                        lagging = true;
+                        break;
+                    }
+                },
+            }
+        }
+
+        self.clients.lock().unwrap().remove(&id);
+        let close = if lagging {
+            Some((close_code::AGAIN, "too many messages waiting"))
+        } else if !open {
+            Some((close_code::ERROR, "handler failed"))
+        } else {
+            None
+        };
+        if let Some((code, reason)) = close {
+            let _ = socket
+                .send(Message::Close(Some(CloseFrame {
+                    code,
+                    reason: reason.into(),
+                })))
+                .await;
+        }
+        self.hook(&pool, &request, id, "onClose", None).await;
+    }
+
+    /// Run `hook` for connection `id` and deliver what it sent; `false` when it failed,
+    /// which closes the connection.
+    async fn hook(
+        &self,
+        pool: &ExecutorPool,
+        request: &RequestInfo,
+        id: u64,
+        hook: &str,
+        message: Option<&str>,
+    ) -> bool {
+        let code = hook_script(&self.route, id, hook, message);
+        let block = Box::new(CodeBlock::Javascript(CodeBlockContent {
+            lineno: 1,
+            end_lineno: 1,
+            colno: 1,
+            content: code,
+            level: 0,
+        }));
+        let name = self.route.handler.display().to_string();
+        let output = match pool.render(vec![block], &name, request.clone()).await {
+            Ok(output) if output.error.is_none() && output.abort.is_none() => output,
+            Ok(output) => {
+                if let Some(error) = output.error {
+                    self.log_failure(hook, &error.to_string());
+                }
+                return false;
+            }
+            Err(e) => {
+                self.log_failure(hook, &e.to_string());
+                return false;
+            }
+        };
+        let Some((echoed, sent)) = output.body.rsplit_once(SEPARATOR) else {
+            self.log_failure(hook, "output has no message separator");
+            return false;
+        };
+        let sent: Sent = match serde_json::from_str(sent) {
+            Ok(sent) => sent,
+            Err(e) => {
+                self.log_failure(hook, &format!("malformed sent messages: {e}"));
+                return false;
+            }
+        };
+
+        // a client whose queue is full is dropped, which closes its connection
+        let mut clients = self.clients.lock().unwrap();
+        let mut lagging = Vec::new();
+        if let Some(own) = clients.get(&id) {
+            let echoed = (!echoed.is_empty()).then(|| echoed.to_string());
+            for message in echoed.into_iter().chain(sent.send) {
+                if let Err(mpsc::error::TrySendError::Full(_)) = own.try_send(message) {
+                    lagging.push(id);
+                    break;
+                }
+            }
+        }
+        for message in sent.broadcast {
+            for (client_id, client) in clients.iter() {
+                if let Err(mpsc::error::TrySendError::Full(_)) = client.try_send(message.clone()) {
+                    lagging.push(*client_id);
+                }
+            }
+        }
+        for client_id in lagging {
+            clients.remove(&client_id);
+        }
+        true
+    }
+
+    fn log_failure(&self, hook: &str, error: &str) {
+        self.logger.log(&LogEntry {
+            level: LogLevel::Error,
+            resource: self.route.handler.display().to_string(),
+            message: format!("websocket {}: {}: {}", self.route.url_path, hook, error),
+            fields: serde_json::Map::new(),
+        });
+    }
+}
+
+/// The block running `hook` of the route's module, for connection `id`. It ends by
+/// echoing [`SEPARATOR`] and the messages the hook sent, as JSON.
+fn hook_script(route: &WebSocketRoute, id: u64, hook: &str, message: Option<&str>) -> String {
+    let literal = |s: &str| serde_json::to_string(s).unwrap_or_default();
+    let handler = literal(&route.handler.to_string_lossy());
+    let message = message
+        .map(literal)
+        .unwrap_or_else(|| "undefined".to_string());
+    let args = if hook == "onMessage" {
+        format!("socket, {}", message)
+    } else {
+        "socket".to_string()
+    };
+    format!(
+        "include({handler});
+const __jhp_sent = {{ send: [], broadcast: [] }};
+const socket = Object.freeze({{
+    id: {id},
+    send: (text) => {{ __jhp_sent.send.push(String(text)); }},
+    broadcast: (text) => {{ __jhp_sent.broadcast.push(String(text)); }},
+}});
+if (typeof globalThis.{hook} === 'function') {{
+    const reply = await globalThis.{hook}({args});
+    if (reply != null) socket.send(reply);
+}}
+echo({separator} + JSON.stringify(__jhp_sent));",
+        separator = literal(&SEPARATOR.to_string()),
+    )
+}
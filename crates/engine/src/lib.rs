@@ -4,3 +4,4 @@ pub mod engine;
 pub mod extensions;
 pub mod fs;
 pub mod http;
+pub mod log;
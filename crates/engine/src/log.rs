@@ -0,0 +1,103 @@
+//! Server-side log entries, e.g. those templates write through `$log`.
+
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+/// Severity of a log entry; entries below the configured level are dropped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+        }
+    }
+}
+
+/// One structured log entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub level: LogLevel,
+    /// The template or script that wrote the entry.
+    pub resource: String,
+    pub message: String,
+    pub fields: Map<String, Value>,
+}
+
+impl LogEntry {
+    /// The entry as a single line of JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::json!({
+            "level": self.level.as_str(),
+            "resource": self.resource,
+            "message": self.message,
+            "fields": self.fields,
+        })
+        .to_string()
+    }
+}
+
+/// Where log entries go. Cloning shares the same destination.
+#[derive(Clone)]
+pub struct LogSink(Arc<dyn Fn(&LogEntry) + Send + Sync>);
+
+impl LogSink {
+    pub fn new<F: Fn(&LogEntry) + Send + Sync + 'static>(f: F) -> Self {
+        Self(Arc::new(f))
+    }
+
+    /// Write each entry to stderr as a line of JSON.
+    pub fn stderr() -> Self {
+        Self::new(|entry| eprintln!("{}", entry.to_json()))
+    }
+
+    pub fn write(&self, entry: &LogEntry) {
+        (self.0)(entry)
+    }
+}
+
+impl Default for LogSink {
+    fn default() -> Self {
+        Self::stderr()
+    }
+}
+
+impl std::fmt::Debug for LogSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("LogSink")
+    }
+}
+
+/// A sink together with the lowest level it accepts.
+#[derive(Debug, Clone, Default)]
+pub struct Logger {
+    pub level: LogLevel,
+    pub sink: LogSink,
+}
+
+impl Logger {
+    pub fn new(level: LogLevel, sink: LogSink) -> Self {
+        Self { level, sink }
+    }
+
+    pub fn enabled(&self, level: LogLevel) -> bool {
+        level >= self.level
+    }
+
+    /// Write `entry` unless its level is filtered out.
+    pub fn log(&self, entry: &LogEntry) {
+        if self.enabled(entry.level) {
+            self.sink.write(entry);
+        }
+    }
+}
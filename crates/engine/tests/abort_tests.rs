@@ -0,0 +1,65 @@
+mod common;
+
+use axum::http::{StatusCode, header};
+use common::{config_for, docroot, get, http_server};
+
+#[tokio::test]
+async fn abort_answers_with_the_status_and_reason_phrase() {
+    let root = docroot(&[("page.jhp", "<p>before</p><? abort(404); ?><p>after</p>")]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, _, body) = get(&server, "/page.jhp").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body, "Not Found");
+}
+
+#[tokio::test]
+async fn abort_message_becomes_the_body() {
+    let root = docroot(&[(
+        "page.jhp",
+        "<? if (!$request.header('X-User')) abort(403, 'nope'); ?>secret",
+    )]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, headers, body) = get(&server, "/page.jhp").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body, "nope");
+    assert!(
+        headers[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain")
+    );
+}
+
+#[tokio::test]
+async fn abort_unwinds_through_includes_and_await() {
+    let root = docroot(&[
+        ("part.jhp", "<? abort(410, 'gone'); ?>"),
+        ("include.jhp", "a<? include('part.jhp'); ?>b"),
+        ("await.jhp", "<? await Promise.resolve(); abort(429); ?>"),
+    ]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, _, body) = get(&server, "/include.jhp").await;
+    assert_eq!((status, body.as_str()), (StatusCode::GONE, "gone"));
+    let (status, _, body) = get(&server, "/await.jhp").await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::TOO_MANY_REQUESTS, "Too Many Requests")
+    );
+}
+
+#[tokio::test]
+async fn abort_is_not_reported_as_a_script_error() {
+    let root = docroot(&[(
+        "bad.jhp",
+        "<? try { abort(200); } catch (e) { echo(e.name); } ?>",
+    )]);
+    let server = http_server(&config_for(root.path()));
+
+    // an invalid status is an ordinary TypeError the template can catch
+    let (status, _, body) = get(&server, "/bad.jhp").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "TypeError");
+}
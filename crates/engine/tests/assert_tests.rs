@@ -0,0 +1,79 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{capture_logs, config_for, docroot, get, http_server, render};
+use jhp_engine::config::AssertMode;
+use jhp_engine::engine::ExecutorPool;
+use jhp_engine::log::LogLevel;
+
+const PAGE: &str =
+    "<p>a</p>\n<? const items = [];\n   assert(items.length > 0, 'cart is empty'); ?>b";
+
+#[tokio::test]
+async fn passing_asserts_do_nothing() {
+    let root = docroot(&[]);
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+    let out = render(
+        &pool,
+        "<? assert(1 + 1 === 2, 'math'); assert('x') ?>ok",
+        "index.jhp",
+    )
+    .await;
+    assert_eq!(out, "ok");
+}
+
+#[tokio::test]
+async fn failing_assert_is_reported_at_the_call() {
+    let root = docroot(&[]);
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+
+    let out = render(&pool, PAGE, "index.jhp").await;
+    assert!(
+        out.starts_with("<p>a</p>\n\n<!-- ERROR -->\nindex.jhp:3:4\n"),
+        "unexpected output: {out}"
+    );
+    assert!(
+        out.contains("   assert(items.length > 0, 'cart is empty'); \n   ^"),
+        "unexpected output: {out}"
+    );
+    assert!(
+        out.contains("Error: assertion failed: cart is empty"),
+        "unexpected output: {out}"
+    );
+    assert!(!out.ends_with('b'), "unexpected output: {out}");
+
+    let out = render(&pool, "<? assert(null) ?>", "index.jhp").await;
+    assert!(
+        out.contains("Error: assertion failed\n"),
+        "unexpected output: {out}"
+    );
+}
+
+#[tokio::test]
+async fn generic_mode_answers_500_and_logs_the_message() {
+    let root = docroot(&[("page.jhp", PAGE)]);
+    let (sink, entries) = capture_logs();
+    let mut config = config_for(root.path());
+    config.asserts = AssertMode::Generic;
+    config.log_sink = sink;
+    let server = http_server(&config);
+
+    let (status, _, body) = get(&server, "/page.jhp").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body, "Internal Server Error");
+
+    let entries = entries.lock().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].level, LogLevel::Error);
+    assert_eq!(entries[0].message, "assertion failed: cart is empty");
+}
+
+#[tokio::test]
+async fn off_mode_ignores_asserts() {
+    let root = docroot(&[]);
+    let mut config = config_for(root.path());
+    config.asserts = AssertMode::Off;
+    let pool = ExecutorPool::new(1, &config);
+
+    assert_eq!(render(&pool, PAGE, "index.jhp").await, "<p>a</p>\nb");
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use common::{eval, eval_in};
+
+#[tokio::test]
+async fn expression_can_await() {
+    assert_eq!(eval("<?= await Promise.resolve(7) ?>").await, "7");
+}
+
+#[tokio::test]
+async fn awaiting_blocks_keep_echo_order() {
+    let src = concat!(
+        "a<?= await Promise.resolve('b') ?>c",
+        "<? const v = await new Promise(r => r('d')); echo(v); ?>",
+        "e<? include('part.jhp'); ?>f",
+    );
+    let out = eval_in(&[("part.jhp", "[<?= await Promise.resolve('in') ?>]")], src).await;
+    assert_eq!(out, "abcde[in]f");
+}
+
+#[tokio::test]
+async fn rejected_await_is_reported_at_its_position() {
+    let src = "ok\n<? await null;\n   throw new Error('nope'); ?>after";
+    let out = eval(src).await;
+    assert!(
+        out.starts_with("ok\n\n<!-- ERROR -->\nindex.jhp:3:4\n"),
+        "unexpected output: {out}"
+    );
+    assert!(out.contains("Error: nope"), "unexpected output: {out}");
+    assert!(!out.contains("after"), "unexpected output: {out}");
+}
+
+#[tokio::test]
+async fn strings_mentioning_await_run_synchronously() {
+    // not wrapped, so the declaration stays a global
+    let src = "<? const msg = 'please await'; ?><?= msg ?>";
+    assert_eq!(eval(src).await, "please await");
+}
+
+#[tokio::test]
+async fn awaited_declarations_are_visible_to_later_blocks() {
+    let src = "<? const rows = await Promise.resolve([1, 2]); ?><?= rows.length ?>";
+    assert_eq!(eval(src).await, "2");
+}
+
+#[tokio::test]
+async fn properties_named_await_run_synchronously() {
+    // not wrapped, so an included file still sees the page's declarations
+    let src = "<? const o = { await: 'x' }; o.await += 'y'; ?><? include('part.jhp') ?>";
+    assert_eq!(eval_in(&[("part.jhp", "<?= o.await ?>")], src).await, "xy");
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use common::{config_for, docroot, http_server};
+use jhp_engine::engine::{Engine, RenderError};
+use std::net::TcpListener;
+
+#[tokio::test]
+async fn binding_a_port_in_use_is_an_error() {
+    let root = docroot(&[]);
+    let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = taken.local_addr().unwrap().port();
+    let mut config = config_for(root.path());
+    config.port = port;
+
+    let err = http_server(&config).start().await.unwrap_err();
+    assert!(
+        err.starts_with(&format!("failed to bind 127.0.0.1:{port}: ")),
+        "unexpected error: {err}"
+    );
+}
+
+#[tokio::test]
+async fn a_failed_run_shuts_the_executors_down() {
+    let root = docroot(&[]);
+    let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut config = config_for(root.path());
+    config.port = taken.local_addr().unwrap().port();
+
+    let mut engine = Engine::new_with_config(2, config);
+    let err = engine.run().await.unwrap_err();
+    assert!(err.starts_with("failed to bind"), "unexpected error: {err}");
+    assert!(matches!(
+        engine.render_str("<?= 1 ?>", "index.jhp").await,
+        Err(RenderError::ExecutorUnavailable)
+    ));
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{config_for, docroot, get, http_server};
+use httpdate::HttpDate;
+use std::time::{Duration, SystemTime};
+
+#[tokio::test]
+async fn static_css_gets_configured_cache_control() {
+    let root = docroot(&[
+        ("site.css", "body {}"),
+        ("notes.txt", "hi"),
+        ("page.jhp", "<p>x</p>"),
+    ]);
+    let config = config_for(root.path())
+        .set_static_cache_control("no-cache")
+        .set_cache_control_for(".CSS", "public, max-age=86400");
+    let server = http_server(&config);
+
+    let (status, headers, _) = get(&server, "/site.css").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["cache-control"], "public, max-age=86400");
+    assert!(headers.contains_key("last-modified"));
+    let expires: HttpDate = headers["expires"].to_str().unwrap().parse().unwrap();
+    let expires = SystemTime::from(expires);
+    let expected = SystemTime::now() + Duration::from_secs(86400);
+    let drift = expected
+        .duration_since(expires)
+        .unwrap_or_else(|e| e.duration());
+    assert!(
+        drift < Duration::from_secs(60),
+        "unexpected Expires: {expires:?}"
+    );
+
+    // other static files fall back to the global setting, without max-age no Expires
+    let (_, headers, _) = get(&server, "/notes.txt").await;
+    assert_eq!(headers["cache-control"], "no-cache");
+    assert!(!headers.contains_key("expires"));
+
+    // rendered templates are left alone
+    let (_, headers, _) = get(&server, "/page.jhp").await;
+    assert!(!headers.contains_key("cache-control"));
+    assert!(!headers.contains_key("last-modified"));
+}
+
+#[tokio::test]
+async fn no_cache_headers_by_default() {
+    let root = docroot(&[("site.css", "body {}")]);
+    let mut config = config_for(root.path());
+    config.etag = false;
+    let server = http_server(&config);
+
+    let (_, headers, _) = get(&server, "/site.css").await;
+    assert!(!headers.contains_key("cache-control"));
+    assert!(!headers.contains_key("expires"));
+    assert!(headers.contains_key("last-modified"));
+}
@@ -0,0 +1,31 @@
+mod common;
+
+use common::{pool_in, render};
+use jhp_engine::engine::ExecutorPool;
+
+const PAGE: &str = "<? const items = [1, 2, 3]; ?><?= items.map((i) => i * 2).join(',') ?>";
+
+fn counts(pool: &ExecutorPool) -> (u64, u64) {
+    let stats = pool.stats()[0].stats;
+    (stats.scripts_compiled, stats.code_cache_hits)
+}
+
+#[tokio::test]
+async fn repeated_renders_compile_from_the_code_cache() {
+    let (_root, pool) = pool_in(&[]);
+
+    // (compiled, cache hits): the page's script compiles once, then comes from the cache
+    assert_eq!(render(&pool, PAGE, "index.jhp").await, "2,4,6");
+    assert_eq!(counts(&pool), (1, 0));
+    assert_eq!(render(&pool, PAGE, "index.jhp").await, "2,4,6");
+    assert_eq!(counts(&pool), (1, 1));
+}
+
+#[tokio::test]
+async fn a_changed_template_compiles_again() {
+    let (_root, pool) = pool_in(&[]);
+
+    assert_eq!(render(&pool, "<?= 1 + 1 ?>", "index.jhp").await, "2");
+    assert_eq!(render(&pool, "<?= 1 + 2 ?>", "index.jhp").await, "3");
+    assert_eq!(counts(&pool), (2, 0));
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use common::{config_for, docroot, render};
+use jhp_engine::engine::ExecutorPool;
+use jhp_parser::ExpressionOutput;
+
+/// Templates paired with their output, which must be the same whether the template is
+/// rendered as the page or included by one.
+const CASES: &[(&str, &str)] = &[
+    (
+        r#"it's "quoted" `tick` ${x} \n back\slash"#,
+        r#"it's "quoted" `tick` ${x} \n back\slash"#,
+    ),
+    ("line\r\nbreaks\u{2028}", "line\r\nbreaks\u{2028}"),
+    (
+        "[<?= null ?>|<?= undefined ?>|<?= 0 ?>|<?= '' ?>]",
+        "[||0|]",
+    ),
+    ("<?= 1 + 1; // two ?>", "2"),
+    ("<? for (const i of [1, 2]) { ?><?= i ?>,<? } ?>", "1,2,"),
+    (
+        "a<?= await Promise.resolve('b') ?>c<? echo(await Promise.resolve('d')); ?>e",
+        "abcde",
+    ),
+];
+
+#[tokio::test]
+async fn page_and_include_render_alike() {
+    for &(template, expected) in CASES {
+        let root = docroot(&[("part.jhp", template)]);
+        let pool = ExecutorPool::new(1, &config_for(root.path()));
+
+        let direct = render(&pool, template, "index.jhp").await;
+        let included = render(&pool, "<? include('part.jhp'); ?>", "index.jhp").await;
+        assert_eq!(direct, expected, "rendering {template:?}");
+        assert_eq!(included, direct, "including {template:?}");
+    }
+}
+
+#[tokio::test]
+async fn page_and_include_share_the_expression_output_setting() {
+    let template = "[<?= null ?>|<?= await Promise.resolve(undefined) ?>]";
+    let root = docroot(&[("part.jhp", template)]);
+    let mut config = config_for(root.path());
+    config.expression_output = ExpressionOutput::String;
+    let pool = ExecutorPool::new(1, &config);
+
+    let direct = render(&pool, template, "index.jhp").await;
+    let included = render(&pool, "<? include('part.jhp'); ?>", "index.jhp").await;
+    assert_eq!(direct, "[null|undefined]");
+    assert_eq!(included, direct);
+}
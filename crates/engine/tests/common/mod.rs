@@ -0,0 +1,145 @@
+//! Shared helpers for engine integration tests.
+#![allow(dead_code)]
+
+use axum::body::Body;
+use axum::http::{HeaderMap, Request, StatusCode};
+use jhp_engine::config::EngineConfig;
+use jhp_engine::engine::ExecutorPool;
+use jhp_engine::http::HttpServer;
+use jhp_engine::log::{LogEntry, LogSink};
+use jhp_executor::RequestInfo;
+use jhp_parser::Parser;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+/// A temporary document root populated with `files` (relative path, contents).
+pub fn docroot(files: &[(&str, &str)]) -> TempDir {
+    let dir = tempfile::tempdir().expect("create temp docroot");
+    for (rel, contents) in files {
+        let path = dir.path().join(rel);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(path, contents).unwrap();
+    }
+    dir
+}
+
+/// An engine config serving from `root`, with extensions looked up under `root/ext`.
+pub fn config_for(root: &Path) -> EngineConfig {
+    EngineConfig::default()
+        .set_document_root(root)
+        .set_extensions_dir(root.join("ext"))
+}
+
+/// Parse `source` and render it on `pool` as `resource_name`, returning the output.
+pub async fn render(pool: &ExecutorPool, source: &str, resource_name: &str) -> String {
+    render_request(pool, source, resource_name, RequestInfo::default()).await
+}
+
+/// Render `template` on a fresh single-worker pool over an empty document root.
+pub async fn eval(template: &str) -> String {
+    eval_in(&[], template).await
+}
+
+/// Like [`eval`], with the document root populated with `files` (see [`docroot`]).
+pub async fn eval_in(files: &[(&str, &str)], template: &str) -> String {
+    let (_root, pool) = pool_in(files);
+    render(&pool, template, "index.jhp").await
+}
+
+/// A single-worker pool over a document root populated with `files`, for tests that
+/// render more than once or look at the pool afterwards. Keep the root alive with it.
+pub fn pool_in(files: &[(&str, &str)]) -> (TempDir, ExecutorPool) {
+    let root = docroot(files);
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+    (root, pool)
+}
+
+/// A log sink that keeps every entry, and the list it keeps them in.
+pub fn capture_logs() -> (LogSink, Arc<Mutex<Vec<LogEntry>>>) {
+    let entries = Arc::new(Mutex::new(Vec::new()));
+    let sink = {
+        let entries = entries.clone();
+        LogSink::new(move |entry| entries.lock().unwrap().push(entry.clone()))
+    };
+    (sink, entries)
+}
+
+/// Like [`render`], with `request` exposed to the template as `$request`.
+pub async fn render_request(
+    pool: &ExecutorPool,
+    source: &str,
+    resource_name: &str,
+    request: RequestInfo,
+) -> String {
+    let blocks = Parser::new(source).parse().blocks;
+    pool.render(blocks, resource_name, request)
+        .await
+        .expect("executor unavailable")
+        .body
+}
+
+/// Build an HTTP server backed by a single-worker pool for `config`.
+pub fn http_server(config: &EngineConfig) -> HttpServer {
+    let pool = Arc::new(ExecutorPool::new(1, config));
+    HttpServer::new(pool, config.http())
+}
+
+/// Send `request` through the server's router and collect the full response.
+pub async fn send(server: &HttpServer, request: Request<Body>) -> (StatusCode, HeaderMap, String) {
+    let response = server.router().oneshot(request).await.unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, headers, String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Issue a plain `GET uri` against the server.
+pub async fn get(server: &HttpServer, uri: &str) -> (StatusCode, HeaderMap, String) {
+    send(server, Request::get(uri).body(Body::empty()).unwrap()).await
+}
+
+/// A TCP port on localhost that was free at the time of the call.
+pub fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|l| l.local_addr())
+        .map(|a| a.port())
+        .unwrap()
+}
+
+/// The `get_quote` example extension, built alongside the tests by `cargo test --workspace`.
+pub fn get_quote_library() -> Option<PathBuf> {
+    extension_library("get_quote")
+}
+
+/// The native extension `libjhp_ext_<name>.so` from the workspace's `ext/` crates, built
+/// alongside the tests by `cargo test --workspace`.
+pub fn extension_library(name: &str) -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    // target/<profile>/deps/<test binary>
+    let lib = exe
+        .parent()?
+        .parent()?
+        .join(format!("libjhp_ext_{}.so", name));
+    lib.exists().then_some(lib)
+}
+
+/// Copy the native extension `name` (see [`extension_library`]) into `root/ext`, where
+/// [`config_for`] looks for extensions. `false` when it isn't built, after saying so on
+/// stderr, for the test to skip itself.
+pub fn install_extension(root: &Path, name: &str) -> bool {
+    let Some(lib) = extension_library(name) else {
+        eprintln!("skipping: libjhp_ext_{name}.so not built (run cargo test --workspace)");
+        return false;
+    };
+    let file = format!("libjhp_ext_{}.so", name);
+    fs::create_dir_all(root.join("ext")).unwrap();
+    fs::copy(lib, root.join("ext").join(file)).unwrap();
+    true
+}
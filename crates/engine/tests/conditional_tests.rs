@@ -0,0 +1,99 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use common::{config_for, docroot, get, http_server, send};
+
+async fn get_with(
+    server: &jhp_engine::http::HttpServer,
+    uri: &str,
+    header: &str,
+    value: &str,
+) -> (StatusCode, axum::http::HeaderMap, String) {
+    let request = Request::get(uri)
+        .header(header, value)
+        .body(Body::empty())
+        .unwrap();
+    send(server, request).await
+}
+
+#[tokio::test]
+async fn rendered_response_returns_304_on_matching_etag() {
+    let root = docroot(&[("page.jhp", "<p><?= 40 + 2 ?></p>")]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, headers, body) = get(&server, "/page.jhp").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "<p>42</p>"));
+    let etag = headers["etag"].to_str().unwrap().to_string();
+    assert!(
+        etag.starts_with('"') && etag.ends_with('"'),
+        "not a strong ETag: {etag}"
+    );
+    assert!(!headers.contains_key("last-modified"));
+
+    let (status, headers, body) = get_with(&server, "/page.jhp", "if-none-match", &etag).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert_eq!(headers["etag"], etag.as_str());
+    assert!(body.is_empty());
+
+    let weak = format!("\"other\", W/{etag}");
+    let (status, _, _) = get_with(&server, "/page.jhp", "if-none-match", &weak).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+}
+
+#[tokio::test]
+async fn etag_miss_returns_200_with_body() {
+    let root = docroot(&[("page.jhp", "<p>fresh</p>")]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, headers, body) =
+        get_with(&server, "/page.jhp", "if-none-match", "\"stale\"").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "<p>fresh</p>"));
+    assert!(headers.contains_key("etag"));
+}
+
+#[tokio::test]
+async fn static_files_honor_if_modified_since() {
+    let root = docroot(&[("style.css", "p { color: red }")]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, headers, _) = get(&server, "/style.css").await;
+    assert_eq!(status, StatusCode::OK);
+    let last_modified = headers["last-modified"].to_str().unwrap().to_string();
+
+    let (status, _, body) =
+        get_with(&server, "/style.css", "if-modified-since", &last_modified).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert!(body.is_empty());
+
+    let past = "Thu, 01 Jan 1970 00:00:00 GMT";
+    let (status, _, body) = get_with(&server, "/style.css", "if-modified-since", past).await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::OK, "p { color: red }")
+    );
+}
+
+#[tokio::test]
+async fn etags_can_be_disabled() {
+    let root = docroot(&[("page.jhp", "<p>x</p>")]);
+    let mut config = config_for(root.path());
+    config.etag = false;
+    let server = http_server(&config);
+
+    let (status, headers, _) = get_with(&server, "/page.jhp", "if-none-match", "*").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!headers.contains_key("etag"));
+}
+
+#[tokio::test]
+async fn large_bodies_are_sent_without_an_etag() {
+    let big = "x".repeat(8 * 1024 * 1024 + 1);
+    let root = docroot(&[("big.txt", big.as_str())]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, headers, body) = get_with(&server, "/big.txt", "if-none-match", "*").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!headers.contains_key("etag"));
+    assert_eq!(body.len(), big.len());
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{config_for, docroot, get, http_server};
+
+const FILES: &[(&str, &str)] = &[
+    ("page.jhp", "<? include('parts/header.inc.jhp'); ?>body"),
+    ("parts/header.inc.jhp", "<?= 'header ' ?>"),
+    (".env", "SECRET=1"),
+    (".git/config", "[core]"),
+    ("private/notes.txt", "notes"),
+];
+
+#[tokio::test]
+async fn include_only_templates_are_not_found_but_still_includable() {
+    let root = docroot(FILES);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, _, body) = get(&server, "/parts/header.inc.jhp").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body, "Cannot get '/parts/header.inc.jhp': File Not Found");
+
+    let (status, _, body) = get(&server, "/page.jhp").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "header body"));
+}
+
+#[tokio::test]
+async fn hidden_files_and_directories_are_not_found() {
+    let root = docroot(FILES);
+    let server = http_server(&config_for(root.path()));
+
+    for uri in ["/.env", "/.git/config", "/.git/", "/.missing"] {
+        let (status, _, _) = get(&server, uri).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn denied_paths_are_configurable() {
+    let root = docroot(FILES);
+    let mut config = config_for(root.path());
+    config.denied_paths = vec!["private/**".to_string()];
+    let server = http_server(&config);
+
+    let (status, _, _) = get(&server, "/private/notes.txt").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    // the defaults are replaced, not extended
+    let (status, _, body) = get(&server, "/.env").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "SECRET=1"));
+    let (status, _, body) = get(&server, "/parts/header.inc.jhp").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "header "));
+}
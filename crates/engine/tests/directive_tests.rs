@@ -0,0 +1,50 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{config_for, docroot, get, http_server};
+
+#[tokio::test]
+async fn content_type_directive_sets_response_header() {
+    let root = docroot(&[(
+        "api.jhp",
+        "<?@ contentType(\"application/json\") ?>\n<?= JSON.stringify({ ok: true }) ?>",
+    )]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, headers, body) = get(&server, "/api.jhp").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-type"], "application/json");
+    assert_eq!(body, "{\"ok\":true}");
+}
+
+#[tokio::test]
+async fn templates_without_directives_stay_html() {
+    let root = docroot(&[("index.jhp", "<p>hi</p>")]);
+    let server = http_server(&config_for(root.path()));
+
+    let (_, headers, body) = get(&server, "/").await;
+    assert_eq!(headers["content-type"], "text/html; charset=utf-8");
+    assert_eq!(body, "<p>hi</p>");
+}
+
+#[tokio::test]
+async fn configured_default_content_type_applies_to_rendered_templates() {
+    let root = docroot(&[
+        ("api.jhp", "<?= JSON.stringify({ ok: true }) ?>"),
+        ("page.jhp", "<?@ contentType(\"text/html\") ?>\n<p>hi</p>"),
+        ("style.css", "body {}"),
+    ]);
+    let config = config_for(root.path()).set_default_content_type("application/json");
+    let server = http_server(&config);
+
+    let (status, headers, body) = get(&server, "/api.jhp").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-type"], "application/json");
+    assert_eq!(body, "{\"ok\":true}");
+
+    // a directive still wins, and static files keep the type of their extension
+    let (_, headers, _) = get(&server, "/page.jhp").await;
+    assert_eq!(headers["content-type"], "text/html");
+    let (_, headers, _) = get(&server, "/style.css").await;
+    assert_eq!(headers["content-type"], "text/css; charset=utf-8");
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{config_for, docroot, get};
+use jhp_engine::engine::ExecutorPool;
+use jhp_engine::http::HttpServer;
+use jhp_executor::Op;
+use std::sync::Arc;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn renders_complete_with_direct_dispatch() {
+    let root = docroot(&[(
+        "index.jhp",
+        "<p><?= [1, 2, 3].map(n => n * 2).join(',') ?></p>",
+    )]);
+    let config = config_for(root.path());
+    // no forwarder task: the server sends straight to the pool
+    let pool = Arc::new(ExecutorPool::new(2, &config));
+    let server = HttpServer::new(pool.clone(), config.http());
+
+    let mut requests = tokio::task::JoinSet::new();
+    for _ in 0..8 {
+        let server = server.clone();
+        requests.spawn(async move { get(&server, "/").await });
+    }
+    while let Some(res) = requests.join_next().await {
+        let (status, _, body) = res.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "<p>2,4,6</p>");
+    }
+
+    let served: u64 = pool.stats().iter().map(|w| w.stats.requests_served).sum();
+    assert_eq!(served, 8);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn a_full_mailbox_makes_senders_wait() {
+    let root = docroot(&[]);
+    let config = config_for(root.path()).set_worker_queue_depth(1);
+    let pool = Arc::new(ExecutorPool::new(1, &config));
+
+    // keep the only worker busy, then fill its single mailbox slot
+    let busy = {
+        let pool = pool.clone();
+        tokio::spawn(async move {
+            let blocks = jhp_parser::Parser::new(
+                "<? const t = Date.now(); while (Date.now() - t < 500) {} ?>done",
+            )
+            .parse()
+            .blocks;
+            pool.render(blocks, "busy.jhp", Default::default()).await
+        })
+    };
+    while pool.stats()[0].stats.in_flight == 0 {
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    pool.send(Op::Javascript("1".into())).await.unwrap();
+    assert_eq!(pool.stats()[0].queued, 1);
+
+    let blocked = tokio::time::timeout(
+        std::time::Duration::from_millis(100),
+        pool.send(Op::Javascript("2".into())),
+    )
+    .await;
+    assert!(
+        blocked.is_err(),
+        "send should wait while the mailbox is full"
+    );
+
+    assert_eq!(busy.await.unwrap().unwrap().body, "done");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn a_dropped_render_is_stopped_even_inside_a_block() {
+    let root = docroot(&[]);
+    let pool = Arc::new(ExecutorPool::new(1, &config_for(root.path())));
+
+    let blocks = jhp_parser::Parser::new("<? while (true) {} ?>")
+        .parse()
+        .blocks;
+    let dropped = tokio::time::timeout(
+        std::time::Duration::from_millis(150),
+        pool.render(blocks, "slow.jhp", Default::default()),
+    )
+    .await;
+    assert!(dropped.is_err(), "the slow render should still be running");
+
+    // the watchdog terminates the loop, and the worker takes the next render
+    let started = std::time::Instant::now();
+    let blocks = jhp_parser::Parser::new("next").parse().blocks;
+    let out = pool.render(blocks, "next.jhp", Default::default()).await;
+    assert_eq!(out.unwrap().body, "next");
+    assert!(
+        started.elapsed() < std::time::Duration::from_millis(1000),
+        "took {:?}",
+        started.elapsed()
+    );
+}
+
+#[tokio::test]
+async fn a_zero_queue_depth_is_taken_as_one() {
+    let root = docroot(&[("index.jhp", "<?= 1 + 1 ?>")]);
+    let mut config = config_for(root.path());
+    config.worker_queue_depth = 0;
+    assert_eq!(config.effective_worker_queue_depth(), 1);
+    let server = HttpServer::new(Arc::new(ExecutorPool::new(1, &config)), config.http());
+
+    let (status, _, body) = get(&server, "/").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "2");
+}
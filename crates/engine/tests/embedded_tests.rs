@@ -0,0 +1,70 @@
+mod common;
+
+use axum::http::{StatusCode, header};
+use common::{get, http_server};
+use jhp_engine::config::EngineConfig;
+use jhp_engine::fs::EmbeddedFiles;
+
+/// A config serving `files` from memory, with a document root that doesn't exist, so
+/// nothing can come from the disk.
+fn embedded_config(files: EmbeddedFiles) -> EngineConfig {
+    EngineConfig::default()
+        .set_document_root("/nonexistent/jhp-embedded-tests")
+        .set_embedded_root(files)
+}
+
+#[tokio::test]
+async fn pages_and_assets_are_served_from_memory() {
+    let files = EmbeddedFiles::new()
+        .add_file("index.jhp", &b"<h1><?= 'Hello' + ', embedded' ?></h1>"[..])
+        .add_file("css/site.css", &b"body { margin: 0 }"[..])
+        .add_file("docs/index.jhp", &b"docs"[..]);
+    let server = http_server(&embedded_config(files));
+
+    let (status, _, body) = get(&server, "/").await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::OK, "<h1>Hello, embedded</h1>")
+    );
+
+    let (status, headers, body) = get(&server, "/css/site.css").await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::OK, "body { margin: 0 }")
+    );
+    assert_eq!(headers[header::CONTENT_LENGTH], "18");
+
+    let (status, _, body) = get(&server, "/docs/").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "docs"));
+}
+
+#[tokio::test]
+async fn missing_embedded_files_are_not_found() {
+    let files = EmbeddedFiles::new().add_file("css/site.css", &b""[..]);
+    let server = http_server(&embedded_config(files));
+
+    for path in ["/", "/css/", "/css/other.css", "/index.jhp"] {
+        let (status, _, _) = get(&server, path).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{path}");
+    }
+    let (status, _, _) = get(&server, "/css/../../etc/passwd").await;
+    assert_ne!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn includes_and_layouts_are_found_in_memory() {
+    let files = EmbeddedFiles::new()
+        .add_file(
+            "index.jhp",
+            &b"<? layout('layouts/main.jhp'); include('parts/greet.jhp', { who: 'you' }); ?>"[..],
+        )
+        .add_file("parts/greet.jhp", &b"<p>hi <?= $args.who ?></p>"[..])
+        .add_file("layouts/main.jhp", &b"<main><?= $content ?></main>"[..]);
+    let server = http_server(&embedded_config(files));
+
+    let (status, _, body) = get(&server, "/").await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::OK, "<main><p>hi you</p></main>")
+    );
+}
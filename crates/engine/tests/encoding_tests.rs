@@ -0,0 +1,47 @@
+mod common;
+
+use common::eval;
+
+#[tokio::test]
+async fn bytes_round_trip_through_base64_and_hex() {
+    let out = eval(concat!(
+        "<? const bytes = new Uint8Array([0, 1, 127, 128, 254, 255]); ?>",
+        "<?= base64Encode(bytes) ?> <?= hexEncode(bytes.buffer) ?> ",
+        "<?= Array.from(base64Decode(base64Encode(bytes))).join(',') ?> ",
+        "<?= Array.from(hexDecode(hexEncode(bytes))).join(',') ?>",
+    ))
+    .await;
+    assert_eq!(
+        out,
+        "AAF/gP7/ 00017f80feff 0,1,127,128,254,255 0,1,127,128,254,255"
+    );
+}
+
+#[tokio::test]
+async fn strings_encode_as_utf8_and_decode_back() {
+    let out = eval(concat!(
+        "<?= base64Encode('héllo') ?> <?= hexEncode('é') ?> ",
+        "<?= base64Decode('aMOpbGxv', 'utf8') ?> <?= hexDecode('C3A9', 'utf8') ?>",
+    ))
+    .await;
+    assert_eq!(out, "aMOpbGxv c3a9 héllo é");
+}
+
+#[tokio::test]
+async fn malformed_input_throws() {
+    let out = eval(concat!(
+        "<? for (const [f, arg] of [[base64Decode, 'no*t base64'], [hexDecode, 'abc'], ",
+        "[hexDecode, '0g'], [hexDecode, 'ff', 'utf8']]) {",
+        " try { f(arg, 'utf8'); echo('no error;'); } catch (e) { echo(e.message + ';'); } } ?>",
+    ))
+    .await;
+    assert_eq!(
+        out,
+        concat!(
+            "base64Decode: invalid input: Invalid symbol 42, offset 2.;",
+            "hexDecode: invalid input: odd number of digits;",
+            "hexDecode: invalid input: invalid digit at offset 0;",
+            "hexDecode: decoded bytes are not valid UTF-8;",
+        )
+    );
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use common::{capture_logs, config_for, docroot, render};
+use jhp_engine::engine::ExecutorPool;
+use jhp_engine::log::LogLevel;
+use jhp_executor::ErrorOutput;
+
+const ECHO_THEN_THROW: &str =
+    "<p>a</p><? echo('b'); throw new Error('boom'); echo('never'); ?><p>d</p>";
+
+#[tokio::test]
+async fn error_follows_output_echoed_before_the_throw() {
+    let root = docroot(&[]);
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+    let out = render(&pool, ECHO_THEN_THROW, "index.jhp").await;
+
+    assert!(
+        out.starts_with("<p>a</p>b\n<!-- ERROR -->\nindex.jhp:1:"),
+        "unexpected output: {out}"
+    );
+    assert!(out.contains("Error: boom"), "unexpected output: {out}");
+    assert!(
+        !out.contains("never") && !out.contains("<p>d</p>"),
+        "unexpected output: {out}"
+    );
+}
+
+#[tokio::test]
+async fn truncate_mode_stops_output_at_the_error() {
+    let root = docroot(&[("part.jhp", "[part<? missing(); ?>]")]);
+    let (sink, entries) = capture_logs();
+    let mut config = config_for(root.path());
+    config.error_output = ErrorOutput::Truncate;
+    config.log_sink = sink;
+    let pool = ExecutorPool::new(1, &config);
+
+    assert_eq!(
+        render(&pool, ECHO_THEN_THROW, "index.jhp").await,
+        "<p>a</p>b"
+    );
+    // the error is logged instead
+    {
+        let entries = entries.lock().unwrap();
+        assert_eq!(entries.len(), 1, "{entries:?}");
+        assert_eq!(entries[0].level, LogLevel::Error);
+        assert_eq!(entries[0].resource, "index.jhp");
+        assert_eq!(entries[0].message, "Error: boom");
+        assert_eq!(entries[0].fields["line"], 1);
+    }
+    // output echoed by an included file before it throws is kept too
+    let out = render(
+        &pool,
+        "<p>a</p><? include('part.jhp'); ?><p>d</p>",
+        "index.jhp",
+    )
+    .await;
+    assert_eq!(out, "<p>a</p>[part");
+}
+
+#[tokio::test]
+async fn render_output_carries_the_structured_error() {
+    let root = docroot(&[]);
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+    let blocks = jhp_parser::Parser::new("<p>x</p>\n<? let a = 1;\n  missing(); ?>")
+        .parse()
+        .blocks;
+    let output = pool
+        .render(blocks, "index.jhp", Default::default())
+        .await
+        .unwrap();
+
+    let error = output.error.expect("the render should report its error");
+    assert_eq!(error.resource, "index.jhp");
+    assert_eq!((error.line, error.column, error.end_column), (3, 3, 10));
+    assert_eq!(error.message, "ReferenceError: missing is not defined");
+    assert!(
+        error.stack.starts_with(&error.message) && error.stack.contains("index.jhp:3:3"),
+        "{}",
+        error.stack
+    );
+    assert_eq!(error.source_line.as_deref(), Some("  missing();"));
+    // the same text is what gets appended to the output
+    assert!(output.body.ends_with(&format!("<!-- ERROR -->\n{error}\n")));
+}
+
+#[tokio::test]
+async fn successful_renders_have_no_error() {
+    let root = docroot(&[]);
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+    let blocks = jhp_parser::Parser::new("<?= 1 + 1 ?>").parse().blocks;
+    let output = pool
+        .render(blocks, "index.jhp", Default::default())
+        .await
+        .unwrap();
+    assert_eq!(output.body, "2");
+    assert_eq!(output.error, None);
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::eval;
+
+#[tokio::test]
+async fn expression_error_at_start_of_first_line() {
+    let out = eval("<?= missing ?>").await;
+    assert!(out.contains("index.jhp:1:5\n"), "unexpected output: {out}");
+}
+
+#[tokio::test]
+async fn expression_error_after_unicode() {
+    let out = eval("<p>héllo ü</p><?= 'ü' + missing ?>").await;
+    assert!(out.contains("index.jhp:1:25\n"), "unexpected output: {out}");
+}
+
+#[tokio::test]
+async fn multi_line_expression_error() {
+    let out = eval("line one\n<?= 1 +\n    missing ?>").await;
+    assert!(out.contains("index.jhp:3:5\n"), "unexpected output: {out}");
+}
+
+#[tokio::test]
+async fn javascript_block_error_column() {
+    let out = eval("<p>x</p>\n<? let a = 1;\n  missing(); ?>").await;
+    assert!(out.contains("index.jhp:3:3\n"), "unexpected output: {out}");
+}
+
+#[tokio::test]
+async fn error_shows_the_source_line_with_a_caret() {
+    let out = eval("<p>x</p>\n<? let a = 1;\n  missing(); ?>").await;
+    assert!(
+        out.contains(
+            "index.jhp:3:3\n  missing();\n  ^^^^^^^\nReferenceError: missing is not defined"
+        ),
+        "unexpected output: {out}"
+    );
+}
+
+#[tokio::test]
+async fn caret_lines_up_with_the_first_line_of_a_block() {
+    let out = eval("<p><?= 'a' + missing ?></p>").await;
+    assert!(
+        out.contains("index.jhp:1:14\n       'a' + missing\n             ^^^^^^^\n"),
+        "unexpected output: {out}"
+    );
+}
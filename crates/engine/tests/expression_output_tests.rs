@@ -0,0 +1,99 @@
+mod common;
+
+use common::{config_for, docroot, eval, eval_in, render};
+use jhp_engine::config::ExpressionStringifier;
+use jhp_engine::engine::ExecutorPool;
+use jhp_parser::ExpressionOutput;
+
+#[tokio::test]
+async fn nullish_expressions_render_empty() {
+    let out = eval("[<?= null ?>][<?= undefined ?>][<?= ({}).missing ?>]").await;
+    assert_eq!(out, "[][][]");
+}
+
+#[tokio::test]
+async fn falsy_non_nullish_values_still_render() {
+    let out = eval("[<?= 0 ?>][<?= false ?>][<?= '' ?>][<?= NaN ?>]").await;
+    assert_eq!(out, "[0][false][][NaN]");
+}
+
+#[tokio::test]
+async fn included_templates_follow_the_same_rule() {
+    let out = eval_in(
+        &[("part.jhp", "(<?= null ?>)")],
+        "<? include('part.jhp'); ?>",
+    )
+    .await;
+    assert_eq!(out, "()");
+}
+
+#[tokio::test]
+async fn string_output_prints_nullish_literally() {
+    let root = docroot(&[("part.jhp", "(<?= undefined ?>)")]);
+    let mut config = config_for(root.path());
+    config.expression_output = ExpressionOutput::String;
+    let pool = ExecutorPool::new(1, &config);
+    let out = render(
+        &pool,
+        "[<?= null ?>]<? include('part.jhp'); ?>",
+        "index.jhp",
+    )
+    .await;
+    assert_eq!(out, "[null](undefined)");
+}
+
+#[tokio::test]
+async fn trailing_semicolons_and_comments_are_ignored() {
+    let out =
+        eval("<? const x = 5; ?>[<?= x; ?>][<?= x /* c */ ?>][<?= x; // c ?>][<?= x + 1 ?>]").await;
+    assert_eq!(out, "[5][5][5][6]");
+}
+
+#[tokio::test]
+async fn comma_separated_expressions_echo_each_value() {
+    let out = eval(
+        "<? const a = 1, b = 'two'; const f = (x, y) => x + y; ?>\
+         [<?= a, null, b ?>][<?= f(a, 2) ?>][<?= await Promise.resolve(a), b ?>]",
+    )
+    .await;
+    assert_eq!(out, "[1two][3][1two]");
+}
+
+#[tokio::test]
+async fn arrays_render_through_string_by_default() {
+    let out = eval("<?= ['a', 'b'] ?>").await;
+    assert_eq!(out, "a,b");
+}
+
+#[tokio::test]
+async fn json_stringifier_renders_arrays_and_objects_as_json() {
+    let root = docroot(&[("part.jhp", "<?= {n: 1} ?>")]);
+    let mut config = config_for(root.path());
+    config.expression_stringifier = ExpressionStringifier::Json;
+    let pool = ExecutorPool::new(1, &config);
+    let out = render(
+        &pool,
+        "<?= ['a', 'b'] ?>|<? include('part.jhp') ?>|<?= 'x', 2, null ?>|<?= render('<?= [1] ?>') ?>",
+        "index.jhp",
+    )
+    .await;
+    assert_eq!(out, r#"["a","b"]|{"n":1}|x2|[1]"#);
+}
+
+#[tokio::test]
+async fn custom_stringifier_gets_every_value() {
+    let root = docroot(&[]);
+    let mut config = config_for(root.path());
+    config.expression_output = ExpressionOutput::String;
+    config.expression_stringifier =
+        ExpressionStringifier::Custom("(v) => `<${typeof v}>`".to_string());
+    let pool = ExecutorPool::new(1, &config);
+    let out = render(&pool, "<?= 1 ?><?= undefined ?>", "index.jhp").await;
+    assert_eq!(out, "<number><undefined>");
+
+    config.expression_stringifier = ExpressionStringifier::Custom("not valid (".to_string());
+    let pool = ExecutorPool::new(1, &config);
+    let out = render(&pool, "a<?= 1 ?>", "index.jhp").await;
+    assert!(out.starts_with("a\n<!-- ERROR -->"), "{out}");
+    assert!(out.contains("SyntaxError"), "{out}");
+}
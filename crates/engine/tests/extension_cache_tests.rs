@@ -0,0 +1,85 @@
+mod common;
+
+use common::{config_for, docroot, install_extension, render};
+use jhp_engine::config::EngineConfig;
+use jhp_engine::engine::ExecutorPool;
+use std::time::Duration;
+
+/// A docroot with the get_quote extension in `ext/`, or `None` when it isn't built.
+fn get_quote_root() -> Option<tempfile::TempDir> {
+    let root = docroot(&[]);
+    install_extension(root.path(), "get_quote").then_some(root)
+}
+
+/// `get_quote()` returns the next quote on every call, so two calls only agree when the
+/// second never reached the extension.
+const TWO_QUOTES: &str = "<? const q = include('get_quote'); \
+    echo(q.get_quote().quote === q.get_quote().quote) ?>";
+
+async fn two_quotes_agree(config: &EngineConfig) -> String {
+    let pool = ExecutorPool::new(1, config);
+    render(&pool, TWO_QUOTES, "index.jhp").await
+}
+
+#[tokio::test]
+async fn uncached_functions_are_called_every_time() {
+    let Some(root) = get_quote_root() else {
+        return;
+    };
+    assert_eq!(two_quotes_agree(&config_for(root.path())).await, "false");
+}
+
+#[tokio::test]
+async fn cached_functions_are_not_called_again_within_their_ttl() {
+    let Some(root) = get_quote_root() else {
+        return;
+    };
+    let config =
+        config_for(root.path()).set_extension_cache_ttl("get_quote", Duration::from_secs(60));
+    assert_eq!(two_quotes_agree(&config).await, "true");
+
+    // the cache outlives the render, and is keyed by the arguments
+    let pool = ExecutorPool::new(1, &config);
+    let src = "<?= include('get_quote').get_quote().quote ?>";
+    let first = render(&pool, src, "a.jhp").await;
+    let again = render(&pool, src, "b.jhp").await;
+    let other = render(&pool, &src.replace("()", "(1)"), "c.jhp").await;
+    assert_eq!(first, again);
+    assert_ne!(first, other);
+}
+
+#[tokio::test]
+async fn cached_results_expire_after_their_ttl() {
+    let Some(root) = get_quote_root() else {
+        return;
+    };
+    let config =
+        config_for(root.path()).set_extension_cache_ttl("get_quote", Duration::from_millis(50));
+    let pool = ExecutorPool::new(1, &config);
+    let src = "<?= include('get_quote').get_quote().quote ?>";
+    let first = render(&pool, src, "index.jhp").await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_ne!(render(&pool, src, "index.jhp").await, first);
+}
+
+/// Calls `get_quote_at` twice with the same arguments, then echoes how many calls reached
+/// the extension.
+const TWO_QUOTES_AT: &str = "<? const q = include('get_quote'); \
+    q.get_quote_at('> ', 1); q.get_quote_at('> ', 1); echo(q.get_quote_at_calls()) ?>";
+
+#[tokio::test]
+async fn declared_ttls_apply_unless_configured_otherwise() {
+    // each root loads a library of its own, with its own count of calls
+    let Some(root) = get_quote_root() else {
+        return;
+    };
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+    assert_eq!(render(&pool, TWO_QUOTES_AT, "index.jhp").await, "1");
+
+    let Some(root) = get_quote_root() else {
+        return;
+    };
+    let config = config_for(root.path()).set_extension_cache_ttl("get_quote_at", Duration::ZERO);
+    let pool = ExecutorPool::new(1, &config);
+    assert_eq!(render(&pool, TWO_QUOTES_AT, "index.jhp").await, "2");
+}
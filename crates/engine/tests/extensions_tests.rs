@@ -0,0 +1,89 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{config_for, docroot, get, http_server, install_extension, render};
+use jhp_engine::engine::ExecutorPool;
+use jhp_engine::extensions::ModuleRegistry;
+
+#[tokio::test]
+async fn loaded_sqlite_module_lists_its_functions() {
+    let root = docroot(&[]);
+    if !install_extension(root.path(), "sqlite") {
+        return;
+    }
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+    assert!(pool.modules.modules().is_empty());
+
+    render(&pool, "<? include('sqlite'); ?>", "index.jhp").await;
+
+    let modules = pool.modules.modules();
+    assert_eq!(modules.len(), 1, "{modules:?}");
+    assert_eq!(
+        (modules[0].name.as_str(), modules[0].object.as_str()),
+        ("sqlite", "Sqlite")
+    );
+    for function in [
+        "sqlite_open",
+        "sqlite_query",
+        "sqlite_execute",
+        "sqlite_close",
+    ] {
+        assert!(
+            modules[0].functions.iter().any(|f| f == function),
+            "missing {function}: {modules:?}"
+        );
+    }
+}
+
+#[test]
+fn libraries_loaded_up_front_are_listed() {
+    let root = docroot(&[]);
+    if !install_extension(root.path(), "get_quote") {
+        return;
+    }
+    let registry = ModuleRegistry::new(root.path().join("ext"));
+    registry.load_all();
+
+    let modules = registry.modules();
+    assert_eq!(modules.len(), 1, "{modules:?}");
+    assert_eq!(
+        (modules[0].name.as_str(), modules[0].object.as_str()),
+        ("get_quote", "globalThis")
+    );
+    assert_eq!(modules[0].functions[0], "get_quote");
+    assert_eq!(modules[0].values, ["QUOTE_COUNT"]);
+}
+
+#[tokio::test]
+async fn extensions_endpoint_reports_loaded_modules() {
+    let root = docroot(&[]);
+    if !install_extension(root.path(), "get_quote") {
+        return;
+    }
+    let mut config = config_for(root.path()).set_preload_modules(["get_quote"]);
+    config.extensions_endpoint = true;
+    let server = http_server(&config);
+
+    let (status, headers, body) = get(&server, "/__jhp/extensions").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-type"], "application/json");
+    let listing: serde_json::Value = serde_json::from_str(&body).expect("valid JSON");
+    assert_eq!(
+        listing,
+        serde_json::json!({"modules": [{
+            "name": "get_quote",
+            "object": "Get_quote",
+            "functions": ["get_quote", "get_quote_err", "get_quote_at", "get_quote_at_calls"],
+            "values": ["QUOTE_COUNT"],
+        }]})
+    );
+}
+
+#[tokio::test]
+async fn extensions_endpoint_is_off_by_default() {
+    let root = docroot(&[]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, _, _) = get(&server, "/__jhp/extensions").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{config_for, docroot, eval_in, pool_in, render};
+use jhp_engine::engine::ExecutorPool;
+
+#[tokio::test]
+async fn read_file_returns_contents() {
+    let out = eval_in(
+        &[("data/msg.txt", "hello from disk")],
+        "<?= readFile('data/msg.txt') ?>",
+    )
+    .await;
+    assert_eq!(out, "hello from disk");
+}
+
+#[tokio::test]
+async fn read_file_rejects_traversal() {
+    let outer = docroot(&[("secret.txt", "top secret"), ("www/index.jhp", "")]);
+    let pool = ExecutorPool::new(1, &config_for(&outer.path().join("www")));
+
+    let src =
+        "<? try { echo(readFile('../secret.txt')) } catch (e) { echo('denied: ' + e.message) } ?>";
+    let out = render(&pool, src, "index.jhp").await;
+    assert!(out.starts_with("denied: "), "unexpected output: {out}");
+    assert!(
+        out.contains("escapes the document root"),
+        "unexpected output: {out}"
+    );
+}
+
+#[tokio::test]
+async fn write_then_read_round_trip() {
+    let (root, pool) = pool_in(&[]);
+
+    let src = "<? writeFile('cache.txt', 'cached ' + (6 * 7)) ?><?= readFile('cache.txt') ?>";
+    let out = render(&pool, src, "index.jhp").await;
+    assert_eq!(out, "cached 42");
+    assert_eq!(
+        std::fs::read_to_string(root.path().join("cache.txt")).unwrap(),
+        "cached 42"
+    );
+    // No temporary files are left behind.
+    assert_eq!(std::fs::read_dir(root.path()).unwrap().count(), 1);
+}
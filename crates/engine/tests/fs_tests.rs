@@ -0,0 +1,18 @@
+mod common;
+
+use common::docroot;
+use jhp_engine::fs::{DocumentRoot, FileKind};
+
+#[tokio::test]
+async fn stat_tells_files_directories_and_missing_paths_apart() {
+    let root = docroot(&[("page.html", "<p>"), ("docs/index.jhp", "")]);
+    let doc_root = DocumentRoot::new(root.path().to_path_buf(), vec!["index.jhp".to_string()]);
+
+    assert_eq!(doc_root.stat("page.html").await, Some(FileKind::File));
+    assert_eq!(doc_root.stat("docs/index.jhp").await, Some(FileKind::File));
+    assert_eq!(doc_root.stat("docs").await, Some(FileKind::Dir));
+    assert_eq!(doc_root.stat("docs/").await, Some(FileKind::Dir));
+    assert_eq!(doc_root.stat("").await, Some(FileKind::Dir));
+    assert_eq!(doc_root.stat("missing.html").await, None);
+    assert_eq!(doc_root.stat("docs/missing/deeper").await, None);
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use common::eval;
+
+#[tokio::test]
+async fn sha256_matches_known_vectors() {
+    let out =
+        eval("<?= sha256('abc') ?>|<?= sha256('') ?>|<?= sha256(new Uint8Array([97, 98, 99])) ?>")
+            .await;
+    assert_eq!(
+        out,
+        concat!(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad|",
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855|",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        )
+    );
+}
+
+#[tokio::test]
+async fn hmac_sha256_matches_rfc_4231_vectors() {
+    let out = eval(concat!(
+        // test case 1: 20-byte key of 0x0b
+        "<?= hmacSha256(new Uint8Array(20).fill(0x0b), 'Hi There') ?>|",
+        // test case 2: string key
+        "<?= hmacSha256('Jefe', 'what do ya want for nothing?') ?>|",
+        // test case 6: key longer than the block size
+        "<?= hmacSha256(new Uint8Array(131).fill(0xaa).buffer, ",
+        "'Test Using Larger Than Block-Size Key - Hash Key First') ?>",
+    ))
+    .await;
+    assert_eq!(
+        out,
+        concat!(
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7|",
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843|",
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+        )
+    );
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{config_for, docroot, get, http_server};
+use jhp_engine::engine::ExecutorPool;
+use jhp_engine::http::HttpServer;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn health_is_ok_without_a_document_root() {
+    let config = config_for(std::path::Path::new("/nonexistent/jhp-docroot"));
+    let server = http_server(&config);
+
+    let (status, headers, body) = get(&server, "/__jhp/health").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-type"], "application/json");
+    assert_eq!(body, r#"{"status":"ok"}"#);
+}
+
+#[tokio::test]
+async fn health_takes_precedence_over_document_root_files() {
+    let root = docroot(&[("__jhp/health", "shadow")]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, _, body) = get(&server, "/__jhp/health").await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::OK, r#"{"status":"ok"}"#)
+    );
+}
+
+#[tokio::test]
+async fn ready_once_an_executor_has_bootstrapped() {
+    let root = docroot(&[]);
+    let server = http_server(&config_for(root.path()));
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let (status, _, body) = get(&server, "/__jhp/ready").await;
+        if status == StatusCode::OK {
+            assert_eq!(body, r#"{"status":"ok"}"#);
+            break;
+        }
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(Instant::now() < deadline, "executor never became ready");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[tokio::test]
+async fn not_ready_without_executors() {
+    let root = docroot(&[]);
+    let config = config_for(root.path());
+    let server = HttpServer::new(Arc::new(ExecutorPool::new(0, &config)), config.http());
+
+    let (status, _, body) = get(&server, "/__jhp/ready").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body, r#"{"status":"starting"}"#);
+
+    let (status, _, _) = get(&server, "/__jhp/health").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn version_reports_the_engine_v8_and_capabilities() {
+    let root = docroot(&[]);
+    let mut config = config_for(root.path());
+    config.etag = false;
+    config.version_endpoint = true;
+    let server = http_server(&config);
+
+    let (status, headers, body) = get(&server, "/__jhp/version").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-type"], "application/json");
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+    let v8 = json["v8"].as_str().unwrap();
+    assert!(v8.split('.').count() >= 3, "{v8}");
+    // no certificate and no ETags
+    assert_eq!(
+        json["capabilities"],
+        serde_json::json!(["compression", "streaming"])
+    );
+
+    let config = config.set_tls("cert.pem", "key.pem");
+    let (_, _, body) = get(&http_server(&config), "/__jhp/version").await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["capabilities"][0], "tls");
+}
+
+#[tokio::test]
+async fn version_is_off_by_default() {
+    let root = docroot(&[]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, _, _) = get(&server, "/__jhp/version").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,32 @@
+mod common;
+
+use common::eval;
+
+#[tokio::test]
+async fn htmlspecialchars_matches_php_defaults() {
+    let out = eval(r#"<?= htmlspecialchars(`<a href="x">Tom & 'Jerry' &amp;</a>`) ?>"#).await;
+    assert_eq!(
+        out,
+        "&lt;a href=&quot;x&quot;&gt;Tom &amp; &#039;Jerry&#039; &amp;amp;&lt;/a&gt;"
+    );
+}
+
+#[tokio::test]
+async fn htmlspecialchars_flags_control_quotes() {
+    let out = eval(
+        r#"<? const s = `"it's"`; ?>[<?= htmlspecialchars(s, ENT_QUOTES) ?>][<?= htmlspecialchars(s, ENT_COMPAT) ?>][<?= htmlspecialchars(s, ENT_NOQUOTES) ?>]"#,
+    )
+    .await;
+    assert_eq!(out, r#"[&quot;it&#039;s&quot;][&quot;it's&quot;]["it's"]"#);
+}
+
+#[tokio::test]
+async fn htmlspecialchars_converts_non_strings() {
+    assert_eq!(eval("<?= htmlspecialchars(42) ?>").await, "42");
+}
+
+#[tokio::test]
+async fn nl2br_marks_every_line_break_once() {
+    let out = eval(r#"<?= nl2br("one\ntwo\r\nthree\rfour\n\n") ?>"#).await;
+    assert_eq!(out, "one<br>\ntwo<br>\r\nthree<br>\rfour<br>\n<br>\n");
+}
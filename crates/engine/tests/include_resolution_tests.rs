@@ -0,0 +1,123 @@
+mod common;
+
+use common::{config_for, docroot, render};
+use jhp_engine::bindings::{IncludeDirs, IncludeKind, resolve_include};
+use jhp_engine::engine::ExecutorPool;
+use std::fs;
+use std::path::Path;
+
+/// A scratch tree with a document root, a shared include directory and an extensions
+/// directory, holding `files` (paths relative to the tree).
+fn tree(files: &[&str]) -> (tempfile::TempDir, IncludeDirs) {
+    let dir = tempfile::tempdir().unwrap();
+    for file in files {
+        let path = dir.path().join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, file).unwrap();
+    }
+    let dirs = IncludeDirs {
+        document_root: dir.path().join("www"),
+        include_paths: vec![dir.path().join("shared"), dir.path().join("vendor")],
+        extensions_dir: dir.path().join("ext"),
+        ..IncludeDirs::default()
+    };
+    (dir, dirs)
+}
+
+fn found(root: &Path, path: &str, dirs: &IncludeDirs) -> Option<(String, IncludeKind)> {
+    resolve_include(path, dirs).map(|(found, kind)| {
+        let rel = found.strip_prefix(root).unwrap_or(&found);
+        (rel.to_string_lossy().into_owned(), kind)
+    })
+}
+
+#[test]
+fn absolute_paths_are_used_as_given() {
+    let (dir, dirs) = tree(&["elsewhere/part.jhp", "www/part.jhp"]);
+    let path = dir.path().join("elsewhere/part.jhp");
+
+    let (found, kind) = resolve_include(path.to_str().unwrap(), &dirs).unwrap();
+    assert_eq!((found, kind), (path, IncludeKind::Template));
+}
+
+#[test]
+fn document_root_comes_before_include_paths() {
+    let (dir, dirs) = tree(&["www/lib/util.js", "shared/lib/util.js"]);
+    assert_eq!(
+        found(dir.path(), "lib/util.js", &dirs),
+        Some(("www/lib/util.js".to_string(), IncludeKind::Script))
+    );
+}
+
+#[test]
+fn include_paths_are_searched_in_order() {
+    let (dir, dirs) = tree(&[
+        "shared/header.jhp",
+        "vendor/header.jhp",
+        "vendor/footer.jhp",
+    ]);
+    assert_eq!(
+        found(dir.path(), "header.jhp", &dirs),
+        Some(("shared/header.jhp".to_string(), IncludeKind::Template))
+    );
+    assert_eq!(
+        found(dir.path(), "footer.jhp", &dirs),
+        Some(("vendor/footer.jhp".to_string(), IncludeKind::Template))
+    );
+}
+
+#[test]
+fn bare_names_fall_back_to_module_shims() {
+    let (dir, dirs) = tree(&["www/buffer.js", "ext/buffer.js", "ext/sqlite3/sqlite3.js"]);
+    assert_eq!(
+        found(dir.path(), "buffer", &dirs),
+        Some(("www/buffer.js".to_string(), IncludeKind::Shim))
+    );
+    assert_eq!(
+        found(dir.path(), "sqlite3", &dirs),
+        Some(("ext/sqlite3/sqlite3.js".to_string(), IncludeKind::Shim))
+    );
+
+    let (dir, dirs) = tree(&["ext/buffer.js"]);
+    assert_eq!(
+        found(dir.path(), "buffer", &dirs),
+        Some(("ext/buffer.js".to_string(), IncludeKind::Shim))
+    );
+}
+
+#[test]
+fn a_file_named_like_the_module_wins_over_its_shim() {
+    let (dir, dirs) = tree(&["www/tools", "ext/tools.js"]);
+    assert_eq!(
+        found(dir.path(), "tools", &dirs),
+        Some(("www/tools".to_string(), IncludeKind::Shim))
+    );
+}
+
+#[test]
+fn names_with_an_extension_have_no_shim_fallback() {
+    let (dir, dirs) = tree(&["ext/page.jhp.js"]);
+    assert_eq!(found(dir.path(), "page.jhp", &dirs), None);
+}
+
+#[test]
+fn directories_are_not_included() {
+    let (dir, dirs) = tree(&["www/partials/x.jhp"]);
+    assert_eq!(found(dir.path(), "partials", &dirs), None);
+}
+
+#[tokio::test]
+async fn include_finds_files_in_configured_include_paths() {
+    let shared = docroot(&[("layout/header.jhp", "<header><?= $args.title ?></header>")]);
+    let root = docroot(&[]);
+    let config = config_for(root.path()).add_include_path(shared.path());
+    let pool = ExecutorPool::new(1, &config);
+
+    let out = render(
+        &pool,
+        "<? include('layout/header.jhp', { title: 'Home' }) ?>",
+        "index.jhp",
+    )
+    .await;
+    assert_eq!(out, "<header>Home</header>");
+}
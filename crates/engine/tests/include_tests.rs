@@ -0,0 +1,176 @@
+mod common;
+
+use common::{config_for, docroot, install_extension, render};
+use jhp_engine::engine::ExecutorPool;
+
+#[tokio::test]
+async fn include_exposes_args_to_partial() {
+    let root = docroot(&[("card.jhp", "<h2><?= $args.title ?></h2>")]);
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+
+    let out = render(
+        &pool,
+        "<? include('card.jhp', { title: 'Hello' }) ?>",
+        "index.jhp",
+    )
+    .await;
+    assert_eq!(out, "<h2>Hello</h2>");
+}
+
+#[tokio::test]
+async fn include_restores_previous_args_binding() {
+    let root = docroot(&[("card.jhp", "<?= $args.title ?>")]);
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+
+    let src = concat!(
+        "<? globalThis.$args = 'outer'; ?>",
+        "<? include('card.jhp', { title: 'inner' }) ?>",
+        "|<?= $args ?>|<?= typeof globalThis.$args ?>",
+    );
+    let out = render(&pool, src, "index.jhp").await;
+    assert_eq!(out, "inner|outer|string");
+
+    let out = render(
+        &pool,
+        "<? include('card.jhp', { title: 'x' }) ?>|<?= typeof $args ?>",
+        "index.jhp",
+    )
+    .await;
+    assert_eq!(out, "x|undefined");
+}
+
+#[tokio::test]
+async fn ctx_is_shared_with_includes_for_one_request() {
+    let root = docroot(&[("header.jhp", "<?= $ctx.user ?><? $ctx.title = 'Home' ?>")]);
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+
+    let src = "<? $ctx.user = 'ann'; include('header.jhp') ?>|<?= $ctx.title ?>";
+    assert_eq!(render(&pool, src, "index.jhp").await, "ann|Home");
+
+    // a new request starts over, and the object itself can't be replaced
+    let src = "<? $ctx = 1; delete globalThis.$ctx ?><?= JSON.stringify($ctx) ?>";
+    assert_eq!(render(&pool, src, "index.jhp").await, "{}");
+}
+
+#[tokio::test]
+async fn circular_include_throws_instead_of_overflowing() {
+    let root = docroot(&[
+        ("a.jhp", "A<? include('b.jhp') ?>"),
+        ("b.jhp", "B<? include('a.jhp') ?>"),
+    ]);
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+
+    let out = render(&pool, "<? include('a.jhp') ?>", "index.jhp").await;
+    assert!(out.starts_with("AB"), "unexpected output: {out}");
+    assert!(
+        out.contains("circular include: a.jhp -> b.jhp -> a.jhp"),
+        "missing cycle error: {out}"
+    );
+
+    // The stack unwinds on error, so a later include of the same file still works.
+    let out = render(
+        &pool,
+        "<? try { include('a.jhp') } catch (e) {} ?>|<? include('b.jhp') ?>",
+        "index.jhp",
+    )
+    .await;
+    assert!(out.contains("|B"), "unexpected output: {out}");
+}
+
+#[tokio::test]
+async fn missing_module_falls_through_to_files() {
+    let root = docroot(&[("helper.js", "echo('from helper.js');")]);
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+
+    let out = render(&pool, "<? include('helper') ?>", "index.jhp").await;
+    assert_eq!(out, "from helper.js");
+    let out = render(
+        &pool,
+        "<? try { include('nothing') } catch (e) { echo(e.message) } ?>",
+        "index.jhp",
+    )
+    .await;
+    assert_eq!(
+        out,
+        "include('nothing') read error: not found as module or file"
+    );
+}
+
+#[tokio::test]
+async fn including_a_directory_says_so() {
+    let root = docroot(&[("partials/card.jhp", "card")]);
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+
+    let out = render(
+        &pool,
+        "<? try { include('partials') } catch (e) { echo(e.message) } ?>",
+        "index.jhp",
+    )
+    .await;
+    assert_eq!(out, "include('partials') is a directory, not a file");
+}
+
+#[tokio::test]
+async fn broken_module_library_throws_with_the_reason() {
+    let root = docroot(&[
+        ("ext/libjhp_ext_broken.so", "not a shared library"),
+        ("broken.js", "echo('should not be reached');"),
+    ]);
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+
+    let out = render(
+        &pool,
+        "<? try { include('broken') } catch (e) { echo(e.message) } ?>",
+        "index.jhp",
+    )
+    .await;
+    assert!(
+        out.starts_with("include('broken'): failed to load module: Failed to load "),
+        "unexpected output: {out}"
+    );
+    assert!(
+        out.contains("libjhp_ext_broken.so"),
+        "unexpected output: {out}"
+    );
+}
+
+#[tokio::test]
+async fn module_constants_are_set_on_the_module_object() {
+    let root = docroot(&[]);
+    if !install_extension(root.path(), "get_quote") {
+        return;
+    }
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+
+    let out = render(
+        &pool,
+        "<? const q = include('get_quote') ?><?= q.QUOTE_COUNT ?> <?= typeof q.get_quote ?>",
+        "index.jhp",
+    )
+    .await;
+    assert_eq!(out, "4 function");
+}
+
+#[tokio::test]
+async fn extension_errors_carry_the_calling_template() {
+    let root = docroot(&[(
+        "part.jhp",
+        "<? try { include('get_quote').get_quote_err() } catch (e) { echo(e.resource) } ?>",
+    )]);
+    if !install_extension(root.path(), "get_quote") {
+        return;
+    }
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+
+    let out = render(
+        &pool,
+        concat!(
+            "<? try { include('get_quote').get_quote_err() } ",
+            "catch (e) { echo(`${e.message}|${e.code}|${e.resource}|`) } ",
+            "include('part.jhp') ?>",
+        ),
+        "page.jhp",
+    )
+    .await;
+    assert_eq!(out, "get_quote_err: not implemented|1|page.jhp|part.jhp");
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use axum::http::{StatusCode, header};
+use common::{config_for, docroot, get, http_server};
+
+#[tokio::test]
+async fn directories_serve_the_first_existing_index_candidate() {
+    let root = docroot(&[
+        ("index.jhp", "<p><?= 1 + 1 ?></p>"),
+        ("index.html", "<p>root html</p>"),
+        ("docs/index.html", "<p>docs</p>"),
+        ("empty/readme.txt", "nothing to index"),
+    ]);
+    let config = config_for(root.path()).set_index_files(["index.jhp", "index.html"]);
+    let server = http_server(&config);
+
+    assert_eq!(get(&server, "/").await.2, "<p>2</p>");
+    assert_eq!(get(&server, "/docs/").await.2, "<p>docs</p>");
+    let (status, headers, _) = get(&server, "/docs?page=2").await;
+    assert_eq!(status, StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(headers[header::LOCATION], "/docs/?page=2");
+    let (status, _, body) = get(&server, "/empty/").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body, "Cannot get '/empty/': File Not Found");
+}
+
+#[tokio::test]
+async fn a_single_index_file_still_works() {
+    let root = docroot(&[("home.html", "<p>home</p>"), ("index.jhp", "unused")]);
+    let config = config_for(root.path()).set_index_file("home.html");
+    let server = http_server(&config);
+
+    assert_eq!(get(&server, "/").await.2, "<p>home</p>");
+    let (status, _, _) = get(&server, "/missing/").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[allow(deprecated)]
+async fn the_deprecated_index_file_is_tried_first() {
+    let root = docroot(&[("home.html", "<p>home</p>"), ("index.jhp", "index")]);
+    let mut config = config_for(root.path());
+    config.index_file = "home.html".to_string();
+    assert_eq!(config.index_path(), root.path().join("home.html"));
+    let server = http_server(&config);
+
+    assert_eq!(get(&server, "/").await.2, "<p>home</p>");
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::{config_for, docroot, render};
+use jhp_engine::engine::ExecutorPool;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicIsize, Ordering};
+
+/// Counts the bytes allocated through Rust and not yet freed, process-wide.
+struct Counting;
+
+static LIVE: AtomicIsize = AtomicIsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE.fetch_add(layout.size() as isize, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size() as isize, Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        LIVE.fetch_add(
+            new_size as isize - layout.size() as isize,
+            Ordering::Relaxed,
+        );
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Uses every binding that keeps state, and a script declaring top-level names that a
+/// context reused across renders would see redeclared.
+const PAGE: &str = "<? include('lib.js'); $log.debug('x'); $random.int(1, 6); \
+    assert(true); readFile('lib.js'); ?><?= render('<?= 1 ?>') ?><?= twice(VALUE) ?>";
+
+#[tokio::test]
+async fn repeated_renders_neither_fail_nor_leak() {
+    let root = docroot(&[(
+        "lib.js",
+        "const VALUE = 21; function twice(n) { return n * 2; }",
+    )]);
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+
+    // let buffers and caches reach their size first
+    for _ in 0..50 {
+        assert_eq!(render(&pool, PAGE, "index.jhp").await, "142");
+    }
+    let before = LIVE.load(Ordering::Relaxed);
+    for _ in 0..500 {
+        assert_eq!(render(&pool, PAGE, "index.jhp").await, "142");
+    }
+    let grown = LIVE.load(Ordering::Relaxed) - before;
+    // installing the bindings used to leak a copy of their state on every render
+    assert!(
+        grown < 64 * 1024,
+        "{grown} bytes still allocated after 500 renders"
+    );
+}
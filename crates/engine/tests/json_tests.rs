@@ -0,0 +1,46 @@
+mod common;
+
+use common::eval;
+
+#[tokio::test]
+async fn to_json_escapes_script_breaking_sequences() {
+    let out = eval("<?= toJson({ html: '</script><!-- & -->', n: [1, 2] }) ?>").await;
+    assert!(!out.contains("</script>"), "{out}");
+    assert!(
+        !out.contains('<') && !out.contains('>') && !out.contains('&'),
+        "{out}"
+    );
+    assert_eq!(
+        out,
+        r#"{"html":"\u003c/script\u003e\u003c!-- \u0026 --\u003e","n":[1,2]}"#
+    );
+
+    let parsed: serde_json::Value = serde_json::from_str(&out).expect("valid JSON");
+    assert_eq!(parsed["html"], "</script><!-- & -->");
+}
+
+#[tokio::test]
+async fn to_json_output_round_trips_in_javascript() {
+    let out = eval(concat!(
+        "<? const v = { s: 'a\\u2028b</script>', nested: { ok: true } }; ?>",
+        "<?= JSON.stringify(JSON.parse(toJson(v))) === JSON.stringify(v) ?>|",
+        "<?= eval('(' + toJson(v) + ')').s === v.s ?>|",
+        "<?= toJson(v).includes('\\u2028') ?>",
+    ))
+    .await;
+    assert_eq!(out, "true|true|false");
+}
+
+#[tokio::test]
+async fn to_json_handles_unencodable_values_and_indentation() {
+    let out = eval("<?= toJson(undefined) ?>|<?= toJson(() => 1) ?>|<?= toJson('x') ?>").await;
+    assert_eq!(out, r#"null|null|"x""#);
+
+    let out = eval("<?= toJson({ a: 1 }, 2) ?>").await;
+    assert_eq!(out, "{\n  \"a\": 1\n}");
+
+    let out =
+        eval("<? const o = {}; o.self = o; try { toJson(o); } catch (e) { echo(e.name); } ?>")
+            .await;
+    assert_eq!(out, "TypeError");
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use common::{eval_in, pool_in, render};
+
+const LAYOUT: &str = "<html><title><?= yield('title', 'Untitled') ?></title><?= $content ?></html>";
+
+#[tokio::test]
+async fn layout_wraps_the_page_output() {
+    let (_root, pool) = pool_in(&[("layout.jhp", LAYOUT)]);
+
+    let src = "<? layout('layout.jhp'); section('title', 'Home'); ?><p>body</p>";
+    let out = render(&pool, src, "index.jhp").await;
+    assert_eq!(out, "<html><title>Home</title><p>body</p></html>");
+
+    // an unset section falls back
+    let out = render(&pool, "<? layout('layout.jhp') ?><p>x</p>", "index.jhp").await;
+    assert_eq!(out, "<html><title>Untitled</title><p>x</p></html>");
+}
+
+#[tokio::test]
+async fn sections_can_capture_output() {
+    let src = "<? layout('layout.jhp'); section('title') ?>About <?= 1 + 1 ?><? endSection() ?>\
+        <p>body</p>";
+    let out = eval_in(&[("layout.jhp", LAYOUT)], src).await;
+    assert_eq!(out, "<html><title>About 2</title><p>body</p></html>");
+}
+
+#[tokio::test]
+async fn nested_layouts_are_found_from_the_document_root() {
+    let (_root, pool) = pool_in(&[
+        ("layouts/base.jhp", LAYOUT),
+        (
+            "layouts/article.jhp",
+            "<? layout('layouts/base.jhp') ?><article><?= $content ?></article>",
+        ),
+    ]);
+
+    let src = "<? layout('layouts/article.jhp') ?><p>x</p>";
+    let out = render(&pool, src, "posts/index.jhp").await;
+    assert_eq!(
+        out,
+        "<html><title>Untitled</title><article><p>x</p></article></html>"
+    );
+}
+
+#[tokio::test]
+async fn layouts_can_declare_layouts() {
+    let files = [
+        ("layout.jhp", LAYOUT),
+        (
+            "article.jhp",
+            "<? layout('layout.jhp') ?><article><?= $content ?></article>",
+        ),
+    ];
+    let src = "<? layout('article.jhp'); section('title', 'Post') ?>text";
+    let out = eval_in(&files, src).await;
+    assert_eq!(
+        out,
+        "<html><title>Post</title><article>text</article></html>"
+    );
+
+    // a layout wrapping itself gives up instead of looping
+    let files = [("self.jhp", "<? layout('self.jhp') ?>[<?= $content ?>]")];
+    let out = eval_in(&files, "<? layout('self.jhp') ?>x").await;
+    assert!(out.contains("layouts nested more than 8 deep"), "{out}");
+}
+
+#[tokio::test]
+async fn a_failed_page_is_not_wrapped() {
+    let out = eval_in(
+        &[("layout.jhp", LAYOUT)],
+        "<? layout('layout.jhp'); missing(); ?>",
+    )
+    .await;
+    assert!(!out.contains("<html>"), "{out}");
+    assert!(out.contains("missing"), "{out}");
+}
+
+#[tokio::test]
+async fn layout_state_does_not_leak_into_the_next_render() {
+    let (_root, pool) = pool_in(&[("layout.jhp", LAYOUT)]);
+
+    render(
+        &pool,
+        "<? layout('layout.jhp'); section('title', 'A') ?>a",
+        "a.jhp",
+    )
+    .await;
+    let out = render(&pool, "<?= yield('title', 'none') ?>", "b.jhp").await;
+    assert_eq!(out, "none");
+}
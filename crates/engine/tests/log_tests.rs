@@ -0,0 +1,74 @@
+mod common;
+
+use common::{capture_logs, config_for, docroot, render};
+use jhp_engine::engine::ExecutorPool;
+use jhp_engine::log::LogLevel;
+
+#[tokio::test]
+async fn info_writes_one_structured_entry_and_nothing_to_the_page() {
+    let root = docroot(&[]);
+    let (sink, entries) = capture_logs();
+    let mut config = config_for(root.path());
+    config.log_sink = sink;
+    let pool = ExecutorPool::new(1, &config);
+
+    let out = render(&pool, "<p><? $log.info('hi', { a: 1 }) ?></p>", "index.jhp").await;
+    assert_eq!(out, "<p></p>");
+
+    let entries = entries.lock().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].level, LogLevel::Info);
+    assert_eq!(entries[0].resource, "index.jhp");
+    assert_eq!(
+        entries[0].to_json(),
+        r#"{"fields":{"a":1},"level":"info","message":"hi","resource":"index.jhp"}"#
+    );
+}
+
+#[tokio::test]
+async fn entries_below_the_level_are_dropped_and_includes_are_tagged() {
+    let root = docroot(&[("part.jhp", "<? $log.warn('from part') ?>")]);
+    let (sink, entries) = capture_logs();
+    let mut config = config_for(root.path());
+    config.log_sink = sink;
+    config.log_level = LogLevel::Warn;
+    let pool = ExecutorPool::new(1, &config);
+
+    render(
+        &pool,
+        "<? $log.debug('d'); $log.info('i'); include('part.jhp'); $log.error('e') ?>",
+        "index.jhp",
+    )
+    .await;
+
+    let entries = entries.lock().unwrap();
+    let seen: Vec<_> = entries
+        .iter()
+        .map(|e| (e.level, e.resource.as_str(), e.message.as_str()))
+        .collect();
+    assert_eq!(
+        seen,
+        [
+            (LogLevel::Warn, "part.jhp", "from part"),
+            (LogLevel::Error, "index.jhp", "e"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn non_object_fields_throw() {
+    let root = docroot(&[]);
+    let (sink, entries) = capture_logs();
+    let mut config = config_for(root.path());
+    config.log_sink = sink;
+    let pool = ExecutorPool::new(1, &config);
+
+    let out = render(
+        &pool,
+        "<? try { $log.info('x', 5) } catch (e) { echo(e.name) } ?>",
+        "index.jhp",
+    )
+    .await;
+    assert_eq!(out, "TypeError");
+    assert!(entries.lock().unwrap().is_empty());
+}
@@ -0,0 +1,95 @@
+use jhp_engine::extensions::{ExtensionLibrary, extension_libraries};
+use std::fs;
+use std::path::Path;
+
+/// An extensions directory with (empty, never loaded) libraries for `names`.
+fn ext_dir(names: &[&str]) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    for name in names {
+        fs::write(dir.path().join(format!("libjhp_ext_{}.so", name)), b"").unwrap();
+    }
+    dir
+}
+
+fn paths(libs: &[ExtensionLibrary], dir: &Path) -> Vec<String> {
+    libs.iter()
+        .map(|lib| {
+            lib.path
+                .strip_prefix(dir)
+                .unwrap()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect()
+}
+
+#[test]
+fn manifest_restricts_loading_to_listed_extensions() {
+    let dir = ext_dir(&["get_quote", "sqlite"]);
+    fs::write(
+        dir.path().join("manifest.toml"),
+        "[extensions.sqlite]\nbusy_timeout = 500\nmode = \"wal\"\n",
+    )
+    .unwrap();
+
+    let libs = extension_libraries(dir.path()).unwrap();
+    assert_eq!(paths(&libs, dir.path()), ["libjhp_ext_sqlite.so"]);
+    assert_eq!(
+        serde_json::Value::Object(libs[0].settings.clone()),
+        serde_json::json!({"busy_timeout": 500, "mode": "wal"})
+    );
+}
+
+#[test]
+fn manifest_entries_without_settings_get_empty_settings() {
+    let dir = ext_dir(&["get_quote", "sqlite"]);
+    fs::write(
+        dir.path().join("manifest.toml"),
+        "[extensions.get_quote]\n[extensions.sqlite]\n",
+    )
+    .unwrap();
+
+    let libs = extension_libraries(dir.path()).unwrap();
+    assert_eq!(
+        paths(&libs, dir.path()),
+        ["libjhp_ext_get_quote.so", "libjhp_ext_sqlite.so"]
+    );
+    assert!(libs.iter().all(|lib| lib.settings.is_empty()));
+}
+
+#[test]
+fn manifest_listing_a_missing_library_is_an_error() {
+    let dir = ext_dir(&["sqlite"]);
+    fs::write(dir.path().join("manifest.toml"), "[extensions.redis]\n").unwrap();
+
+    let err = extension_libraries(dir.path()).unwrap_err();
+    assert!(err.contains("'redis'"), "{err}");
+}
+
+#[test]
+fn invalid_manifest_is_an_error() {
+    let dir = ext_dir(&["sqlite"]);
+    fs::write(dir.path().join("manifest.toml"), "[extensions.sqlite\n").unwrap();
+
+    let err = extension_libraries(dir.path()).unwrap_err();
+    assert!(err.contains("manifest.toml"), "{err}");
+}
+
+#[test]
+fn without_manifest_every_library_is_found() {
+    let dir = ext_dir(&["sqlite", "get_quote"]);
+    fs::create_dir(dir.path().join("vendor")).unwrap();
+    fs::write(dir.path().join("vendor/libjhp_ext_extra.so"), b"").unwrap();
+    fs::write(dir.path().join("notes.txt"), b"").unwrap();
+
+    let libs = extension_libraries(dir.path()).unwrap();
+    assert_eq!(
+        paths(&libs, dir.path()),
+        [
+            "libjhp_ext_get_quote.so",
+            "libjhp_ext_sqlite.so",
+            "vendor/libjhp_ext_extra.so"
+        ]
+    );
+    assert!(libs.iter().all(|lib| lib.settings.is_empty()));
+}
@@ -0,0 +1,29 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use common::{config_for, docroot, http_server, send};
+
+#[tokio::test]
+async fn delete_on_a_static_file_is_method_not_allowed() {
+    let root = docroot(&[("style.css", "body {}")]);
+    let server = http_server(&config_for(root.path()));
+
+    for uri in ["/style.css", "/"] {
+        let request = Request::delete(uri).body(Body::empty()).unwrap();
+        let (status, headers, _) = send(&server, request).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED, "{uri}");
+        assert_eq!(headers[header::ALLOW], "GET, HEAD", "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn head_is_still_served() {
+    let root = docroot(&[("style.css", "body {}")]);
+    let server = http_server(&config_for(root.path()));
+
+    let request = Request::head("/style.css").body(Body::empty()).unwrap();
+    let (status, _, body) = send(&server, request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "");
+}
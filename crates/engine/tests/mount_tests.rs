@@ -0,0 +1,66 @@
+mod common;
+
+use axum::http::{StatusCode, header};
+use common::{config_for, docroot, get, http_server};
+
+#[tokio::test]
+async fn mounts_serve_their_prefix_and_the_root_serves_the_rest() {
+    let root = docroot(&[
+        ("page.jhp", "<?= 'page' ?>"),
+        ("static/app.css", "from root"),
+    ]);
+    let assets = docroot(&[
+        ("app.css", "body {}"),
+        ("index.jhp", "<?= 'assets index' ?>"),
+    ]);
+    let config = config_for(root.path()).add_mount("/static", assets.path());
+    let server = http_server(&config);
+
+    assert_eq!(get(&server, "/static/app.css").await.2, "body {}");
+    assert_eq!(get(&server, "/page.jhp").await.2, "page");
+    // the mount's own index serves the prefix, once redirected to its directory form
+    let (status, headers, _) = get(&server, "/static").await;
+    assert_eq!(status, StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(headers[header::LOCATION], "/static/");
+    assert_eq!(get(&server, "/static/").await.2, "assets index");
+    // only whole path segments match a prefix
+    let (status, _, _) = get(&server, "/staticx/app.css").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn mounts_are_tried_in_order() {
+    let root = docroot(&[]);
+    let first = docroot(&[("a.txt", "first")]);
+    let second = docroot(&[("a.txt", "second")]);
+    let config = config_for(root.path())
+        .add_mount("/files/", first.path())
+        .add_mount("files", second.path());
+    let server = http_server(&config);
+
+    assert_eq!(get(&server, "/files/a.txt").await.2, "first");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn a_mount_does_not_follow_symlinks_out_of_its_directory() {
+    let root = docroot(&[("secret.txt", "secret")]);
+    let assets = docroot(&[("ok.txt", "ok")]);
+    std::os::unix::fs::symlink(root.path(), assets.path().join("escape")).unwrap();
+    let config = config_for(root.path()).add_mount("/static", assets.path());
+    let server = http_server(&config);
+
+    assert_eq!(get(&server, "/static/ok.txt").await.2, "ok");
+    let (status, _, _) = get(&server, "/static/escape/secret.txt").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn a_mount_whose_directory_is_missing_serves_nothing() {
+    let root = docroot(&[]);
+    let config = config_for(root.path()).add_mount("/static", root.path().join("missing"));
+    let server = http_server(&config);
+
+    let (status, _, _) = get(&server, "/static/app.css").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::eval;
+
+#[tokio::test]
+async fn path_join_normalizes_the_joined_parts() {
+    let out = eval(
+        "<?= JSON.stringify([\
+            pathJoin('/a/', 'b//', '../c', './d/'), pathJoin('a', '', 'b'), pathJoin(),\
+            pathJoin('', ''), pathJoin('a', '../../b'), pathJoin('/', '..', 'x'),\
+            pathJoin('a', '..'), pathJoin('views', 'user', 1)]) ?>",
+    )
+    .await;
+    assert_eq!(
+        out,
+        r#"["/a/c/d/","a/b",".",".","../b","/x",".","views/user/1"]"#
+    );
+}
+
+#[tokio::test]
+async fn dirname_and_basename_ignore_trailing_slashes() {
+    let out = eval(
+        "<?= JSON.stringify([\
+            dirname('/a/b/c/'), dirname('/a'), dirname('a'), dirname(''), dirname('///'),\
+            dirname('a//b'), basename('/a/b.txt'), basename('/a/b/'), basename('/'),\
+            basename('b.txt', '.txt'), basename('.txt', '.txt')]) ?>",
+    )
+    .await;
+    assert_eq!(
+        out,
+        r#"["/a/b","/",".",".","/","a","b.txt","b","","b",".txt"]"#
+    );
+}
+
+#[tokio::test]
+async fn extname_takes_the_last_extension_of_the_last_segment() {
+    let out = eval(
+        "<?= JSON.stringify([\
+            extname('a.tar.gz'), extname('.hidden'), extname('.hidden.txt'), extname('a.'),\
+            extname('..'), extname('dir.d/file'), extname('a.b/'), extname('')]) ?>",
+    )
+    .await;
+    assert_eq!(out, r#"[".gz","",".txt",".","","",".b",""]"#);
+}
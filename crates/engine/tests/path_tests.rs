@@ -0,0 +1,35 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{config_for, docroot, get, http_server};
+
+#[tokio::test]
+async fn malformed_percent_encoding_is_a_bad_request() {
+    let root = docroot(&[("index.jhp", "home")]);
+    let server = http_server(&config_for(root.path()));
+
+    for uri in ["/%zz", "/a%2", "/%ff.txt", "/%zz/../index.jhp"] {
+        let (status, _, _) = get(&server, uri).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn encoded_nul_is_a_bad_request() {
+    let root = docroot(&[("a.txt", "a")]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, _, _) = get(&server, "/a.txt%00.jhp").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn valid_escapes_are_decoded() {
+    let root = docroot(&[("my file.txt", "spaced"), ("é.txt", "accent")]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, _, body) = get(&server, "/my%20file.txt").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "spaced"));
+    let (status, _, body) = get(&server, "/%C3%A9.txt").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "accent"));
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use common::{config_for, docroot, get, http_server, send};
+
+// The sibling's bytes are sent untouched, so any content stands in for real gzip data.
+const FILES: &[(&str, &str)] = &[("app.js", "plain"), ("app.js.gz", "gzipped")];
+
+fn get_with_encoding(uri: &str, accept_encoding: &str) -> Request<Body> {
+    Request::get(uri)
+        .header(header::ACCEPT_ENCODING, accept_encoding)
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn gz_sibling_is_served_when_gzip_is_accepted() {
+    let root = docroot(FILES);
+    let server = http_server(&config_for(root.path()));
+
+    for accept in ["gzip", "br, gzip;q=0.5", "*", "deflate, X-GZIP"] {
+        let (status, headers, body) = send(&server, get_with_encoding("/app.js", accept)).await;
+        assert_eq!(
+            (status, body.as_str()),
+            (StatusCode::OK, "gzipped"),
+            "{accept}"
+        );
+        assert_eq!(headers[header::CONTENT_ENCODING], "gzip", "{accept}");
+        assert_eq!(headers[header::VARY], "Accept-Encoding", "{accept}");
+        // the type of the file the sibling compresses, not of a `.gz`
+        assert_eq!(
+            headers[header::CONTENT_TYPE],
+            "text/javascript; charset=utf-8",
+            "{accept}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn plain_file_is_served_when_gzip_is_not_accepted() {
+    let root = docroot(FILES);
+    let server = http_server(&config_for(root.path()));
+
+    for accept in ["identity", "br", "gzip;q=0", "*;q=0", "*, gzip;q=0"] {
+        let (status, headers, body) = send(&server, get_with_encoding("/app.js", accept)).await;
+        assert_eq!(
+            (status, body.as_str()),
+            (StatusCode::OK, "plain"),
+            "{accept}"
+        );
+        assert!(headers.get(header::CONTENT_ENCODING).is_none(), "{accept}");
+        assert_eq!(headers[header::VARY], "Accept-Encoding", "{accept}");
+    }
+
+    let (status, headers, body) = get(&server, "/app.js").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "plain"));
+    assert!(headers.get(header::CONTENT_ENCODING).is_none());
+}
+
+#[tokio::test]
+async fn files_without_a_gz_sibling_are_unaffected() {
+    let root = docroot(&[("style.css", "body {}")]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, headers, body) = send(&server, get_with_encoding("/style.css", "gzip")).await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "body {}"));
+    assert!(headers.get(header::CONTENT_ENCODING).is_none());
+    assert!(headers.get(header::VARY).is_none());
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::{capture_logs, config_for, docroot, install_extension, render};
+use jhp_engine::engine::ExecutorPool;
+use jhp_engine::log::LogLevel;
+
+#[tokio::test]
+async fn preloaded_module_is_usable_without_include() {
+    let root = docroot(&[("ext/get_quote/boot.js", "Get_quote.booted = true;")]);
+    if !install_extension(root.path(), "get_quote") {
+        return;
+    }
+    let config = config_for(root.path()).set_preload_modules(["get_quote"]);
+    let pool = ExecutorPool::new(1, &config);
+
+    assert!(pool.modules.object_name("get_quote").is_some());
+    let out = render(
+        &pool,
+        "<?= Get_quote.booted ?> <?= typeof Get_quote.get_quote().quote ?>",
+        "index.jhp",
+    )
+    .await;
+    assert_eq!(out, "true string");
+}
+
+#[tokio::test]
+async fn missing_preload_module_does_not_stop_the_pool() {
+    let root = docroot(&[]);
+    let (sink, entries) = capture_logs();
+    let mut config = config_for(root.path()).set_preload_modules(["nope"]);
+    config.log_sink = sink;
+    let pool = ExecutorPool::new(1, &config);
+
+    assert!(pool.modules.object_name("nope").is_none());
+    assert_eq!(render(&pool, "<?= 1 + 1 ?>", "index.jhp").await, "2");
+    // logged once, when the pool started
+    let entries = entries.lock().unwrap();
+    assert_eq!(entries.len(), 1, "{entries:?}");
+    assert_eq!(entries[0].level, LogLevel::Error);
+    assert!(
+        entries[0]
+            .message
+            .starts_with("failed to preload module nope: "),
+        "{}",
+        entries[0].message
+    );
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::Request;
+use common::{config_for, docroot, http_server, send};
+use jhp_engine::config::{Cidr, EngineConfig};
+use std::net::SocketAddr;
+
+const ORIGIN: &str = "<?= $request.remoteAddr ?>|<?= $request.scheme ?>|<?= $request.host ?>";
+
+/// Render `ORIGIN` for a request from `peer` with the given headers.
+async fn origin(config: EngineConfig, peer: &str, headers: &[(&str, &str)]) -> String {
+    let server = http_server(&config);
+    let mut request = Request::get("/o.jhp");
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let request = request
+        .extension(ConnectInfo(peer.parse::<SocketAddr>().unwrap()))
+        .body(Body::empty())
+        .unwrap();
+    send(&server, request).await.2
+}
+
+const FORWARDED: &[(&str, &str)] = &[
+    ("host", "10.0.0.5:8080"),
+    ("x-forwarded-for", "203.0.113.7, 10.0.0.9"),
+    ("x-forwarded-proto", "https"),
+    ("x-forwarded-host", "example.com"),
+];
+
+#[tokio::test]
+async fn trusted_proxy_forwarded_headers_are_honored() {
+    let root = docroot(&[("o.jhp", ORIGIN)]);
+    let config = config_for(root.path()).add_trusted_proxy("10.0.0.0/8".parse().unwrap());
+
+    let out = origin(config, "10.0.0.1:50000", FORWARDED).await;
+    // 10.0.0.9 is a trusted proxy too, so the client is the entry before it
+    assert_eq!(out, "203.0.113.7|https|example.com");
+}
+
+#[tokio::test]
+async fn untrusted_peer_forwarded_headers_are_ignored() {
+    let root = docroot(&[("o.jhp", ORIGIN)]);
+    let config = config_for(root.path()).add_trusted_proxy("10.0.0.0/8".parse().unwrap());
+
+    let out = origin(config, "198.51.100.4:50000", FORWARDED).await;
+    assert_eq!(out, "198.51.100.4|http|10.0.0.5:8080");
+}
+
+#[tokio::test]
+async fn forwarded_headers_are_ignored_without_trusted_proxies() {
+    let root = docroot(&[("o.jhp", ORIGIN)]);
+
+    let out = origin(config_for(root.path()), "10.0.0.1:50000", FORWARDED).await;
+    assert_eq!(out, "10.0.0.1|http|10.0.0.5:8080");
+}
+
+#[tokio::test]
+async fn spoofed_entries_before_the_client_are_skipped() {
+    let root = docroot(&[("o.jhp", ORIGIN)]);
+    let config = config_for(root.path()).add_trusted_proxy("127.0.0.1".parse().unwrap());
+
+    // the client itself sent `X-Forwarded-For: 1.1.1.1`; the proxy appended its address
+    let headers = [("x-forwarded-for", "1.1.1.1, 198.51.100.4")];
+    let out = origin(config, "127.0.0.1:50000", &headers).await;
+    assert_eq!(out, "198.51.100.4|http|");
+}
+
+#[test]
+fn cidr_parsing_and_matching() {
+    let net: Cidr = "192.168.0.0/16".parse().unwrap();
+    assert!(net.contains("192.168.4.2".parse().unwrap()));
+    assert!(net.contains("::ffff:192.168.4.2".parse().unwrap()));
+    assert!(!net.contains("192.169.0.1".parse().unwrap()));
+    let single: Cidr = "::1".parse().unwrap();
+    assert!(single.contains("::1".parse().unwrap()));
+    assert!(!single.contains("::2".parse().unwrap()));
+    for invalid in ["10.0.0.0/33", "fd00::/129", "example.com", "10.0.0.0/"] {
+        assert!(invalid.parse::<Cidr>().is_err(), "{invalid}");
+    }
+}
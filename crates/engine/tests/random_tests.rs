@@ -0,0 +1,115 @@
+mod common;
+
+use common::{config_for, docroot, install_extension, render};
+use jhp_engine::engine::ExecutorPool;
+use std::path::Path;
+
+const DRAWS: &str = "<?= JSON.stringify([\
+    $random.int(1, 6), $random.int(-1000000, 1000000), $random.float(),\
+    Array.from($random.bytes(5)), $random.uuid()]) ?>";
+
+fn seeded_pool(root: &Path, seed: Option<u64>) -> ExecutorPool {
+    let mut config = config_for(root);
+    config.rng_seed = seed;
+    ExecutorPool::new(1, &config)
+}
+
+/// What three renders of [`DRAWS`] give.
+async fn sequence(pool: &ExecutorPool) -> Vec<String> {
+    let mut out = Vec::new();
+    for _ in 0..3 {
+        out.push(render(pool, DRAWS, "index.jhp").await);
+    }
+    out
+}
+
+#[tokio::test]
+async fn same_seed_gives_the_same_sequence() {
+    let root = docroot(&[]);
+    let first = sequence(&seeded_pool(root.path(), Some(42))).await;
+    let second = sequence(&seeded_pool(root.path(), Some(42))).await;
+    assert_eq!(first, second);
+    // the sequence goes on from render to render
+    assert_ne!(first[0], first[1]);
+
+    let other = sequence(&seeded_pool(root.path(), Some(43))).await;
+    assert_ne!(first, other);
+}
+
+#[tokio::test]
+async fn unseeded_engines_differ() {
+    let root = docroot(&[]);
+    let first = sequence(&seeded_pool(root.path(), None)).await;
+    let second = sequence(&seeded_pool(root.path(), None)).await;
+    assert_ne!(first, second);
+}
+
+#[tokio::test]
+async fn values_stay_in_their_ranges() {
+    let root = docroot(&[]);
+    let pool = seeded_pool(root.path(), None);
+    let out = render(
+        &pool,
+        "<? let ok = true;\
+           for (let i = 0; i < 1000; i++) {\
+             const n = $random.int(-2, 2), f = $random.float();\
+             ok = ok && Number.isInteger(n) && n >= -2 && n <= 2 && f >= 0 && f < 1;\
+           }\
+           const b = $random.bytes(33), u = $random.uuid(); ?>\
+         <?= ok ?> <?= b instanceof Uint8Array && b.length ?> <?= $random.bytes(0).length ?> \
+         <?= /^[0-9a-f]{8}-[0-9a-f]{4}-4[0-9a-f]{3}-[89ab][0-9a-f]{3}-[0-9a-f]{12}$/.test(u) ?> \
+         <?= $random.int(7, 7) ?>",
+        "index.jhp",
+    )
+    .await;
+    assert_eq!(out, "true 33 0 true 7");
+}
+
+#[tokio::test]
+async fn invalid_arguments_throw_type_errors() {
+    let root = docroot(&[]);
+    let pool = seeded_pool(root.path(), None);
+    let out = render(
+        &pool,
+        "<? for (const f of [() => $random.int(1), () => $random.int(1.5, 2),\
+             () => $random.int(3, 1), () => $random.bytes(-1), () => $random.bytes(65537)]) {\
+               try { f() } catch (e) { echo(`${e.name}: ${e.message}\\n`) }\
+           } ?>",
+        "index.jhp",
+    )
+    .await;
+    assert_eq!(
+        out,
+        "TypeError: $random.int(min, max): min and max must be integers\n\
+         TypeError: $random.int(min, max): min and max must be integers\n\
+         TypeError: $random.int(min, max): min must not exceed max\n\
+         TypeError: $random.bytes(n): n must be an integer from 0 to 65536\n\
+         TypeError: $random.bytes(n): n must be an integer from 0 to 65536\n"
+    );
+}
+
+#[tokio::test]
+async fn extensions_receive_the_seed() {
+    let included =
+        "<?= [1, 2, 3, 4, 5].map(() => include('get_quote').get_quote().quote).join('|') ?>";
+    // loaded up front by `load_all`, the library's functions are globals
+    let up_front = "<?= [1, 2, 3, 4, 5].map(() => get_quote().quote).join('|') ?>";
+
+    for (quotes, load_all) in [(included, false), (up_front, true)] {
+        let mut runs = Vec::new();
+        for seed in [7, 7, 8] {
+            // a library of its own for each engine, so they don't share its state
+            let root = docroot(&[]);
+            if !install_extension(root.path(), "get_quote") {
+                return;
+            }
+            let pool = seeded_pool(root.path(), Some(seed));
+            if load_all {
+                pool.modules.load_all();
+            }
+            runs.push(render(&pool, quotes, "index.jhp").await);
+        }
+        assert_eq!(runs[0], runs[1], "{quotes}");
+        assert_ne!(runs[0], runs[2], "{quotes}");
+    }
+}
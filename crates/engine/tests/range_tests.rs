@@ -0,0 +1,156 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use common::{config_for, docroot, get, http_server, send};
+
+const FILES: &[(&str, &str)] = &[("video.txt", "0123456789")];
+
+fn get_range(uri: &str, range: &str) -> Request<Body> {
+    Request::get(uri)
+        .header(header::RANGE, range)
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn single_range_is_partial_content() {
+    let root = docroot(FILES);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, headers, body) = send(&server, get_range("/video.txt", "bytes=2-5")).await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::PARTIAL_CONTENT, "2345")
+    );
+    assert_eq!(headers[header::CONTENT_RANGE], "bytes 2-5/10");
+    assert_eq!(headers[header::ACCEPT_RANGES], "bytes");
+
+    // an open end runs to the end of the file, and a last position past it is clamped
+    for range in ["bytes=7-", "bytes=7-100"] {
+        let (status, headers, body) = send(&server, get_range("/video.txt", range)).await;
+        assert_eq!(
+            (status, body.as_str()),
+            (StatusCode::PARTIAL_CONTENT, "789")
+        );
+        assert_eq!(headers[header::CONTENT_RANGE], "bytes 7-9/10");
+    }
+}
+
+#[tokio::test]
+async fn suffix_range_is_the_end_of_the_file() {
+    let root = docroot(FILES);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, headers, body) = send(&server, get_range("/video.txt", "bytes=-3")).await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::PARTIAL_CONTENT, "789")
+    );
+    assert_eq!(headers[header::CONTENT_RANGE], "bytes 7-9/10");
+
+    let (status, headers, body) = send(&server, get_range("/video.txt", "bytes=-50")).await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::PARTIAL_CONTENT, "0123456789")
+    );
+    assert_eq!(headers[header::CONTENT_RANGE], "bytes 0-9/10");
+}
+
+#[tokio::test]
+async fn unsatisfiable_range_is_416() {
+    let root = docroot(FILES);
+    let server = http_server(&config_for(root.path()));
+
+    for range in ["bytes=10-", "bytes=20-30, 15-", "bytes=-0"] {
+        let (status, headers, _) = send(&server, get_range("/video.txt", range)).await;
+        assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE, "{range}");
+        assert_eq!(headers[header::CONTENT_RANGE], "bytes */10", "{range}");
+    }
+}
+
+#[tokio::test]
+async fn multiple_ranges_are_a_multipart_body() {
+    let root = docroot(FILES);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, headers, body) = send(&server, get_range("/video.txt", "bytes=0-1, 8-")).await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    let content_type = headers[header::CONTENT_TYPE].to_str().unwrap();
+    let boundary = content_type
+        .strip_prefix("multipart/byteranges; boundary=")
+        .unwrap();
+    assert_eq!(
+        body,
+        format!(
+            "\r\n--{b}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Range: bytes 0-1/10\r\n\r\n01\
+             \r\n--{b}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Range: bytes 8-9/10\r\n\r\n89\
+             \r\n--{b}--\r\n",
+            b = boundary
+        )
+    );
+}
+
+#[tokio::test]
+async fn overlapping_and_adjacent_ranges_are_merged() {
+    let root = docroot(FILES);
+    let server = http_server(&config_for(root.path()));
+
+    for range in ["bytes=2-4, 0-3", "bytes=0-1, 2-4", "bytes=0-4, 1-2"] {
+        let (status, headers, body) = send(&server, get_range("/video.txt", range)).await;
+        assert_eq!(
+            (status, body.as_str()),
+            (StatusCode::PARTIAL_CONTENT, "01234"),
+            "{range}"
+        );
+        assert_eq!(headers[header::CONTENT_RANGE], "bytes 0-4/10", "{range}");
+        assert_eq!(
+            headers[header::CONTENT_TYPE],
+            "text/plain; charset=utf-8",
+            "{range}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn invalid_or_stale_ranges_send_the_whole_file() {
+    let root = docroot(FILES);
+    let server = http_server(&config_for(root.path()));
+
+    for range in ["bytes=5-2", "lines=1-2", "bytes=x-"] {
+        let (status, _, body) = send(&server, get_range("/video.txt", range)).await;
+        assert_eq!(
+            (status, body.as_str()),
+            (StatusCode::OK, "0123456789"),
+            "{range}"
+        );
+    }
+
+    let request = Request::get("/video.txt")
+        .header(header::RANGE, "bytes=0-1")
+        .header(header::IF_RANGE, "Thu, 01 Jan 1970 00:00:00 GMT")
+        .body(Body::empty())
+        .unwrap();
+    let (status, _, body) = send(&server, request).await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "0123456789"));
+
+    let (_, headers, _) = get(&server, "/video.txt").await;
+    assert_eq!(headers[header::CONTENT_TYPE], "text/plain; charset=utf-8");
+    let request = Request::get("/video.txt")
+        .header(header::RANGE, "bytes=0-1")
+        .header(header::IF_RANGE, headers[header::LAST_MODIFIED].clone())
+        .body(Body::empty())
+        .unwrap();
+    let (status, _, body) = send(&server, request).await;
+    assert_eq!((status, body.as_str()), (StatusCode::PARTIAL_CONTENT, "01"));
+}
+
+#[tokio::test]
+async fn templates_ignore_ranges() {
+    let root = docroot(&[("page.jhp", "<?= 'rendered' ?>")]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, headers, body) = send(&server, get_range("/page.jhp", "bytes=0-1")).await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "rendered"));
+    assert!(headers.get(header::ACCEPT_RANGES).is_none());
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use axum::http::{StatusCode, header};
+use common::{config_for, docroot, get, http_server};
+
+/// Status, `Location` and body of rendering `template`.
+async fn redirect_of(template: &str) -> (StatusCode, Option<String>, String) {
+    let root = docroot(&[("page.jhp", template)]);
+    let server = http_server(&config_for(root.path()));
+    let (status, headers, body) = get(&server, "/page.jhp").await;
+    let location = headers
+        .get(header::LOCATION)
+        .map(|l| l.to_str().unwrap().to_string());
+    (status, location, body)
+}
+
+#[tokio::test]
+async fn redirect_is_a_302_by_default() {
+    let (status, location, body) =
+        redirect_of("<p>before</p><? redirect('/login?next=%2Fcart'); ?><p>after</p>").await;
+    assert_eq!(status, StatusCode::FOUND);
+    assert_eq!(location.as_deref(), Some("/login?next=%2Fcart"));
+    assert_eq!(body, "");
+}
+
+#[tokio::test]
+async fn redirect_permanent_is_a_301() {
+    let (status, location, body) =
+        redirect_of("<? redirectPermanent('https://example.com/new'); ?>old").await;
+    assert_eq!(status, StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(location.as_deref(), Some("https://example.com/new"));
+    assert_eq!(body, "");
+}
+
+#[tokio::test]
+async fn redirect_takes_an_explicit_status() {
+    for (code, status) in [
+        (301, StatusCode::MOVED_PERMANENTLY),
+        (302, StatusCode::FOUND),
+        (303, StatusCode::SEE_OTHER),
+        (307, StatusCode::TEMPORARY_REDIRECT),
+        (308, StatusCode::PERMANENT_REDIRECT),
+    ] {
+        let template = format!("<? redirect('../done', {}); ?>", code);
+        let (got, location, body) = redirect_of(&template).await;
+        assert_eq!(got, status, "{code}");
+        assert_eq!(location.as_deref(), Some("../done"), "{code}");
+        assert_eq!(body, "", "{code}");
+    }
+}
+
+#[tokio::test]
+async fn redirect_unwinds_through_includes_and_await() {
+    let root = docroot(&[
+        (
+            "part.jhp",
+            "<? await Promise.resolve(); redirect('/elsewhere', 303); ?>",
+        ),
+        ("page.jhp", "a<? include('part.jhp'); ?>b"),
+    ]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, headers, body) = get(&server, "/page.jhp").await;
+    assert_eq!(status, StatusCode::SEE_OTHER);
+    assert_eq!(headers[header::LOCATION], "/elsewhere");
+    assert_eq!(body, "");
+}
+
+#[tokio::test]
+async fn invalid_urls_and_statuses_throw_type_errors() {
+    let template = "<? for (const [url, status] of [\
+        ['/a b'], ['/a\\r\\nSet-Cookie: x=1'], ['/\\\\evil.com'], ['/%zz'], ['1x:y'], [''], [42],\
+        ['/ok', 200], ['/ok', 404], ['/ok', '301']]) {\
+          try { redirect(url, status) } catch (e) { echo(`${e.name}: ${e.message}\\n`) }\
+        } ?>";
+    let (status, location, body) = redirect_of(template).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(location, None);
+    let url_error = "TypeError: redirect: url must be a well-formed absolute or relative URL\n";
+    let status_error =
+        "TypeError: redirect: status must be a redirect status (301, 302, 303, 307 or 308)\n";
+    assert_eq!(body, url_error.repeat(7) + &status_error.repeat(3));
+
+    let (_, _, body) = redirect_of(
+        "<? try { redirectPermanent('a b') } catch (e) { echo(`${e.name}: ${e.message}`) } ?>",
+    )
+    .await;
+    assert_eq!(
+        body,
+        "TypeError: redirectPermanent: url must be a well-formed absolute or relative URL"
+    );
+}
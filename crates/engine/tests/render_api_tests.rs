@@ -0,0 +1,73 @@
+mod common;
+
+use common::{config_for, docroot};
+use jhp_engine::engine::{Engine, RenderError};
+
+#[tokio::test]
+async fn render_str_runs_the_full_pipeline() {
+    let root = docroot(&[("part.jhp", "<em><?= $args.who ?></em>")]);
+    let engine = Engine::new_with_config(1, config_for(root.path()));
+
+    let template = concat!(
+        "<ul>\n",
+        "<? for (const n of [1, 2]) { ?>",
+        "<li><?= n * 10 ?></li>",
+        "<? } ?>\n",
+        "</ul><? include('part.jhp', { who: 'me' }); ?>",
+    );
+    let out = engine.render_str(template, "inline.jhp").await.unwrap();
+    assert_eq!(out, "<ul>\n<li>10</li><li>20</li>\n</ul><em>me</em>");
+}
+
+#[tokio::test]
+async fn render_str_reports_errors_at_template_lines_across_blocks() {
+    let root = docroot(&[]);
+    let engine = Engine::new_with_config(1, config_for(root.path()));
+
+    let template = "<? for (const n of [1]) { ?>\n<?= n.missing.value ?><? } ?>";
+    let out = engine.render_str(template, "inline.jhp").await.unwrap();
+    assert!(
+        out.starts_with("\n\n<!-- ERROR -->\ninline.jhp:2:"),
+        "unexpected output: {out}"
+    );
+    assert!(out.contains("TypeError"), "unexpected output: {out}");
+}
+
+#[tokio::test]
+async fn render_file_reads_the_template() {
+    let root = docroot(&[("page.jhp", "<p><?= 6 * 7 ?></p>")]);
+    let engine = Engine::new_with_config(1, config_for(root.path()));
+
+    let out = engine
+        .render_file(root.path().join("page.jhp"))
+        .await
+        .unwrap();
+    assert_eq!(out, "<p>42</p>");
+
+    let err = engine
+        .render_file(root.path().join("missing.jhp"))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, RenderError::Io(_)), "unexpected error: {err}");
+}
+
+#[tokio::test]
+async fn consecutive_renders_on_one_worker_keep_separate_output() {
+    let root = docroot(&[]);
+    let engine = Engine::new_with_config(1, config_for(root.path()));
+
+    let big = engine
+        .render_str("<? echo('x'.repeat(100000)); ?>", "big.jhp")
+        .await
+        .unwrap();
+    assert_eq!(big.len(), 100000);
+
+    let small = engine.render_str("a<?= 1 ?>b", "small.jhp").await.unwrap();
+    assert_eq!(small, "a1b");
+
+    let empty = engine.render_str("", "empty.jhp").await.unwrap();
+    assert_eq!(empty, "");
+
+    let again = engine.render_str("a<?= 2 ?>b", "small.jhp").await.unwrap();
+    assert_eq!(again, "a2b");
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use common::{config_for, docroot, get, http_server, send};
+
+const NEGOTIATE: &str = "<?= $request.accepts(['text/html', 'application/json']) ?>";
+
+async fn negotiate(accept: &str) -> String {
+    let root = docroot(&[("n.jhp", NEGOTIATE)]);
+    let server = http_server(&config_for(root.path()));
+    let request = Request::get("/n.jhp")
+        .header("accept", accept)
+        .body(Body::empty())
+        .unwrap();
+    send(&server, request).await.2
+}
+
+#[tokio::test]
+async fn browser_accept_prefers_html() {
+    assert_eq!(
+        negotiate("text/html,application/json;q=0.9").await,
+        "text/html"
+    );
+}
+
+#[tokio::test]
+async fn json_only_client_gets_json() {
+    assert_eq!(negotiate("application/json").await, "application/json");
+}
+
+#[tokio::test]
+async fn unacceptable_types_return_null() {
+    let root = docroot(&[("n.jhp", "<?= $request.accepts(['image/png']) === null ?>")]);
+    let server = http_server(&config_for(root.path()));
+    let request = Request::get("/n.jhp")
+        .header("accept", "text/html")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(&server, request).await.2, "true");
+}
+
+#[tokio::test]
+async fn request_exposes_method_path_query_and_headers() {
+    let root = docroot(&[(
+        "info.jhp",
+        "<?= $request.method ?> <?= $request.path ?> <?= $request.query ?> <?= $request.header('X-Trace') ?> <?= $request.header('x-none') ?>",
+    )]);
+    let server = http_server(&config_for(root.path()));
+    let request = Request::get("/info.jhp?a=1&b=2")
+        .header("x-trace", "abc")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(&server, request).await.2, "GET /info.jhp a=1&b=2 abc ");
+
+    // no Accept header accepts anything: the first type wins
+    let root = docroot(&[("n.jhp", NEGOTIATE)]);
+    let server = http_server(&config_for(root.path()));
+    assert_eq!(get(&server, "/n.jhp").await.2, "text/html");
+}
+
+#[tokio::test]
+async fn conditional_headers_are_parsed() {
+    let root = docroot(&[(
+        "c.jhp",
+        "<?= JSON.stringify([$request.ifMatch, $request.ifNoneMatch,\
+            $request.ifModifiedSince, $request.ifUnmodifiedSince]) ?>",
+    )]);
+    let server = http_server(&config_for(root.path()));
+    let request = Request::get("/c.jhp")
+        .header("if-match", r#""v1", W/"v0""#)
+        .header("if-unmodified-since", "Sun, 09 Sep 2001 01:46:40 GMT")
+        .header("if-modified-since", "yesterday")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(
+        send(&server, request).await.2,
+        r#"[["\"v1\"","W/\"v0\""],null,null,1000000000000]"#
+    );
+}
+
+#[tokio::test]
+async fn preconditions_met_follows_if_match() {
+    let root = docroot(&[(
+        "w.jhp",
+        "<? if (!$request.preconditionsMet('v2', new Date(0))) abort(412); ?>saved",
+    )]);
+    let server = http_server(&config_for(root.path()));
+
+    for (if_match, status) in [
+        (Some(r#""v2""#), StatusCode::OK),
+        (Some(r#""v1""#), StatusCode::PRECONDITION_FAILED),
+        (Some("*"), StatusCode::OK),
+        // an unconditional request always goes ahead
+        (None, StatusCode::OK),
+    ] {
+        let mut request = Request::get("/w.jhp");
+        if let Some(if_match) = if_match {
+            request = request.header("if-match", if_match);
+        }
+        let (got, _, _) = send(&server, request.body(Body::empty()).unwrap()).await;
+        assert_eq!(got, status, "{if_match:?}");
+    }
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use axum::http::{StatusCode, header};
+use common::{config_for, docroot, get, http_server};
+
+#[tokio::test]
+async fn json_response_defaults_to_200() {
+    let root = docroot(&[(
+        "api.jhp",
+        "<? $response.json({ ok: true, items: [1, 2] }); ?>",
+    )]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, headers, body) = get(&server, "/api.jhp").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::CONTENT_TYPE], "application/json");
+    assert_eq!(body, r#"{"ok":true,"items":[1,2]}"#);
+}
+
+#[tokio::test]
+async fn json_response_sets_the_given_status() {
+    let root = docroot(&[(
+        "api.jhp",
+        "<?@ contentType(\"text/plain\") ?><? const err = { error: 'invalid email' }; $response.json(err, 422); ?>",
+    )]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, headers, body) = get(&server, "/api.jhp").await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(headers[header::CONTENT_TYPE], "application/json");
+    assert_eq!(body, r#"{"error":"invalid email"}"#);
+}
+
+#[tokio::test]
+async fn json_response_after_output_is_an_error() {
+    let root = docroot(&[(
+        "api.jhp",
+        "<p>hi</p><? try { $response.json({}) } catch (e) { echo(' ' + e.message) } ?>",
+    )]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, headers, body) = get(&server, "/api.jhp").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(
+        headers[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html")
+    );
+    assert_eq!(
+        body,
+        "<p>hi</p> $response.json(): output was already echoed; the JSON must be the whole body"
+    );
+}
+
+#[tokio::test]
+async fn json_response_rejects_invalid_statuses() {
+    let root = docroot(&[(
+        "api.jhp",
+        "<? for (const s of [99, 600, '200']) { try { $response.json(1, s) } catch (e) { echo(e.name + ';') } } ?>",
+    )]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, _, body) = get(&server, "/api.jhp").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "TypeError;TypeError;TypeError;");
+}
+
+#[tokio::test]
+async fn response_settings_do_not_carry_over_to_the_next_render() {
+    let root = docroot(&[
+        ("api.jhp", "<? $response.json([], 201); ?>"),
+        ("page.jhp", "<p>page</p>"),
+    ]);
+    // the single worker renders both, so its state from the first must be reset
+    let server = http_server(&config_for(root.path()));
+
+    assert_eq!(get(&server, "/api.jhp").await.0, StatusCode::CREATED);
+    let (status, headers, body) = get(&server, "/page.jhp").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(
+        headers[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html")
+    );
+    assert_eq!(body, "<p>page</p>");
+}
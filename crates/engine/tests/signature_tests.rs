@@ -0,0 +1,80 @@
+mod common;
+
+use common::{config_for, docroot, install_extension, render};
+use jhp_engine::engine::ExecutorPool;
+
+/// A pool whose documents can `include('get_quote')`, or `None` when the extension
+/// isn't built.
+fn get_quote_pool() -> Option<(tempfile::TempDir, ExecutorPool)> {
+    let root = docroot(&[]);
+    if !install_extension(root.path(), "get_quote") {
+        return None;
+    }
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+    Some((root, pool))
+}
+
+/// What calling `q.get_quote_at(<args>)` echoes: its result, or the error it throws.
+const CALL: &str = "<? const q = include('get_quote'); \
+    try { echo(q.get_quote_at(ARGS)) } catch (e) { echo(`${e.name}: ${e.message}`) } ?>";
+
+async fn call(pool: &ExecutorPool, args: &str) -> String {
+    render(pool, &CALL.replace("ARGS", args), "index.jhp").await
+}
+
+#[tokio::test]
+async fn matching_arguments_reach_the_extension() {
+    let Some((_root, pool)) = get_quote_pool() else {
+        return;
+    };
+    assert_eq!(
+        call(&pool, "'> ', 0").await,
+        "> Talk is cheap. Show me the code. - Linus Torvalds"
+    );
+}
+
+#[tokio::test]
+async fn wrong_types_throw_a_type_error_naming_the_parameter() {
+    let Some((_root, pool)) = get_quote_pool() else {
+        return;
+    };
+    assert_eq!(
+        call(&pool, "'> ', '0'").await,
+        "TypeError: get_quote_at(prefix: string, index: number): \
+         argument 2 ('index') must be number, got string"
+    );
+    assert_eq!(
+        call(&pool, "null, 1").await,
+        "TypeError: get_quote_at(prefix: string, index: number): \
+         argument 1 ('prefix') must be string, got null"
+    );
+}
+
+#[tokio::test]
+async fn wrong_arity_throws_a_type_error() {
+    let Some((_root, pool)) = get_quote_pool() else {
+        return;
+    };
+    assert_eq!(
+        call(&pool, "'> '").await,
+        "TypeError: get_quote_at(prefix: string, index: number): expected 2 arguments, got 1"
+    );
+    assert_eq!(
+        call(&pool, "'> ', 1, 2").await,
+        "TypeError: get_quote_at(prefix: string, index: number): expected 2 arguments, got 3"
+    );
+}
+
+#[tokio::test]
+async fn functions_without_a_signature_take_any_arguments() {
+    let Some((_root, pool)) = get_quote_pool() else {
+        return;
+    };
+    let out = render(
+        &pool,
+        "<?= typeof include('get_quote').get_quote(1, 'x', {}).quote ?>",
+        "index.jhp",
+    )
+    .await;
+    assert_eq!(out, "string");
+}
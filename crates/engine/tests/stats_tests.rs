@@ -0,0 +1,78 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{config_for, docroot, get, http_server};
+use jhp_engine::engine::ExecutorPool;
+use jhp_executor::RequestInfo;
+use jhp_parser::Parser;
+
+#[tokio::test]
+async fn stats_endpoint_reports_workers_as_json() {
+    let root = docroot(&[("index.jhp", "<?= 1 + 1 ?>"), ("__jhp/stats", "shadow")]);
+    let mut config = config_for(root.path());
+    config.stats_endpoint = true;
+    let server = http_server(&config);
+
+    let (status, _, body) = get(&server, "/").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "2"));
+
+    let (status, headers, body) = get(&server, "/__jhp/stats").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-type"], "application/json");
+
+    let stats: serde_json::Value = serde_json::from_str(&body).expect("valid JSON");
+    assert_eq!(stats["requests_served"], 1);
+    assert_eq!(stats["in_flight"], 0);
+    let worker = &stats["workers"][0];
+    for key in ["id", "requests_served", "in_flight", "queued"] {
+        assert!(worker[key].is_u64(), "missing {key}: {body}");
+    }
+    assert!(worker["heap"]["used"].as_u64().unwrap() > 0);
+    assert!(worker["heap"]["limit"].as_u64().unwrap() >= worker["heap"]["used"].as_u64().unwrap());
+    assert_eq!(worker["renders"]["blocks"], 1);
+    assert_eq!(worker["renders"]["output_bytes"], 1);
+}
+
+#[tokio::test]
+async fn renders_report_their_metrics() {
+    let root = docroot(&[]);
+    let pool = ExecutorPool::new(1, &config_for(root.path()));
+    // every block is complete on its own, so the count doesn't depend on how they're run
+    let template = "<ul><?= [0, 1, 2].map((i) => `<li>${i}</li>`).join('') ?></ul>\
+        <? let sum = 0; for (let i = 0; i < 100000; i++) { sum += i; } ?><?= sum ?>";
+    let blocks = Parser::new(template).parse().blocks;
+    let count = blocks.len();
+
+    let output = pool
+        .render(blocks, "index.jhp", RequestInfo::default())
+        .await
+        .unwrap();
+    let metrics = output.metrics;
+    assert_eq!(metrics.blocks, count);
+    assert!(metrics.compile_time.as_nanos() > 0, "{metrics:?}");
+    assert!(metrics.run_time.as_nanos() > 0, "{metrics:?}");
+    assert_eq!(metrics.output_bytes, output.body.len());
+    assert_eq!(
+        output.body,
+        "<ul><li>0</li><li>1</li><li>2</li></ul>4999950000"
+    );
+
+    // the blocks run as one script, so they all count even when one of them throws
+    let blocks = Parser::new("a<? missing() ?>b<?= 1 ?>").parse().blocks;
+    let count = blocks.len();
+    let output = pool
+        .render(blocks, "index.jhp", RequestInfo::default())
+        .await
+        .unwrap();
+    assert!(output.error.is_some());
+    assert_eq!(output.metrics.blocks, count);
+}
+
+#[tokio::test]
+async fn stats_endpoint_is_off_by_default() {
+    let root = docroot(&[("__jhp/stats", "just a file")]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, _, body) = get(&server, "/__jhp/stats").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "just a file"));
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::{config_for, docroot, eval, render};
+use jhp_engine::engine::ExecutorPool;
+use std::sync::Arc;
+
+// The store is process-wide, so each test uses its own keys.
+
+#[tokio::test]
+async fn value_set_in_one_request_is_visible_in_another() {
+    let root = docroot(&[]);
+    let pool = ExecutorPool::new(2, &config_for(root.path()));
+
+    render(
+        &pool,
+        "<? $store.set('shared:user', { name: 'ada', tags: [1, 2] }); ?>",
+        "a.jhp",
+    )
+    .await;
+    let out = render(
+        &pool,
+        "<?= JSON.stringify($store.get('shared:user')) ?>",
+        "b.jhp",
+    )
+    .await;
+    assert_eq!(out, r#"{"name":"ada","tags":[1,2]}"#);
+
+    let out = render(
+        &pool,
+        "<?= $store.delete('shared:user') ?>,<?= $store.delete('shared:user') ?>,<?= typeof $store.get('shared:user') ?>",
+        "c.jhp",
+    )
+    .await;
+    assert_eq!(out, "true,false,undefined");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn incr_is_atomic_across_executors() {
+    let root = docroot(&[]);
+    let pool = Arc::new(ExecutorPool::new(4, &config_for(root.path())));
+
+    let mut tasks = tokio::task::JoinSet::new();
+    for _ in 0..50 {
+        let pool = pool.clone();
+        tasks.spawn(async move {
+            render(
+                &pool,
+                "<? for (let i = 0; i < 10; i++) $store.incr('atomic:hits'); ?>",
+                "i.jhp",
+            )
+            .await
+        });
+    }
+    while let Some(out) = tasks.join_next().await {
+        assert_eq!(out.unwrap(), "");
+    }
+
+    let out = render(
+        &pool,
+        "<?= $store.get('atomic:hits') ?>|<?= $store.incr('atomic:hits', -500) ?>",
+        "r.jhp",
+    )
+    .await;
+    assert_eq!(out, "500|0");
+}
+
+#[tokio::test]
+async fn incr_rejects_non_numeric_values() {
+    let out = eval(
+        "<? $store.set('bad:counter', 'x'); try { $store.incr('bad:counter'); } catch (e) { echo(e.message); } ?>",
+    )
+    .await;
+    assert_eq!(
+        out,
+        "$store.incr('bad:counter'): stored value is not a number"
+    );
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use common::{config_for, docroot, get, http_server};
+use futures_util::StreamExt;
+use jhp_engine::engine::ExecutorPool;
+use jhp_engine::http::HttpServer;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+/// Flushes three chunks, then holds the render until `/go.jhp` was requested (or 10s
+/// passed), so the chunks can only have arrived while it was still running.
+const FLUSHING: &str = "<? for (const i of [1, 2, 3]) { echo(`chunk ${i};`); flush(); }\
+    const start = Date.now();\
+    while (!$store.get('stream_tests.go') && Date.now() - start < 10000) {} ?>done";
+
+#[tokio::test]
+async fn flushed_chunks_arrive_while_the_render_goes_on() {
+    let root = docroot(&[
+        ("page.jhp", FLUSHING),
+        ("go.jhp", "<? $store.set('stream_tests.go', true); ?>ok"),
+    ]);
+    let config = config_for(root.path());
+    // a second worker renders /go.jhp while the first is held up
+    let server = HttpServer::new(Arc::new(ExecutorPool::new(2, &config)), config.http());
+
+    let response = server
+        .router()
+        .oneshot(Request::get("/page.jhp").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/html; charset=utf-8"
+    );
+    assert!(response.headers().get(header::ETAG).is_none());
+
+    let mut body = response.into_body().into_data_stream();
+    for i in 1..=3 {
+        let chunk = body.next().await.unwrap().unwrap();
+        assert_eq!(chunk, format!("chunk {i};"));
+    }
+
+    let (_, _, go) = get(&server, "/go.jhp").await;
+    assert_eq!(go, "ok");
+    let rest = body.next().await.unwrap().unwrap();
+    assert_eq!(rest, "done");
+    assert!(body.next().await.is_none());
+}
+
+#[tokio::test]
+async fn templates_that_never_flush_are_buffered() {
+    let root = docroot(&[("page.jhp", "<p>a</p><? echo('b'); ?>")]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, headers, body) = get(&server, "/page.jhp").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "<p>a</p>b"));
+    // only a whole body can get an ETag
+    assert!(headers.get(header::ETAG).is_some());
+    assert_eq!(headers[header::CONTENT_LENGTH], "9");
+}
+
+#[tokio::test]
+async fn an_error_before_the_first_flush_is_a_500() {
+    let root = docroot(&[("page.jhp", "a<? missing(); flush(); ?>b")]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, _, body) = get(&server, "/page.jhp").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(body.starts_with("a\n<!-- ERROR -->\n"), "{body}");
+}
+
+#[tokio::test]
+async fn the_first_flush_decides_the_status() {
+    let root = docroot(&[
+        ("error.jhp", "a<? flush(); missing(); ?>b"),
+        ("abort.jhp", "a<? flush(); abort(404); ?>b"),
+    ]);
+    let server = http_server(&config_for(root.path()));
+
+    // the status was sent with the first chunk; the error only shows in the body
+    let (status, _, body) = get(&server, "/error.jhp").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.starts_with("a\n<!-- ERROR -->\n"), "{body}");
+
+    // an abort fails the body after what was flushed, so it doesn't look complete
+    let response = server
+        .router()
+        .oneshot(Request::get("/abort.jhp").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let mut body = response.into_body().into_data_stream();
+    assert_eq!(body.next().await.unwrap().unwrap(), "a");
+    assert!(body.next().await.unwrap().is_err());
+}
+
+#[tokio::test]
+async fn json_responses_cannot_follow_a_flush() {
+    let root = docroot(&[(
+        "api.jhp",
+        "<? flush(); try { $response.json({}) } catch (e) { echo(e.message) } ?>",
+    )]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, _, body) = get(&server, "/api.jhp").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body,
+        "$response.json(): output was already echoed; the JSON must be the whole body"
+    );
+}
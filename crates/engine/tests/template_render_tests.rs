@@ -0,0 +1,57 @@
+mod common;
+
+use common::eval_in;
+
+#[tokio::test]
+async fn render_returns_template_output_with_data_as_locals() {
+    let card = "<div class=\"card\"><h2><?= title ?></h2><ul><? for (const tag of tags) { ?><li><?= tag ?></li><? } ?></ul></div>";
+    let out = eval_in(
+        &[("card.tpl", card)],
+        "<? const html = render(readFile('card.tpl'), { title: 'Hello', tags: ['a', 'b'] }); ?>before [<?= html ?>]",
+    )
+    .await;
+    assert_eq!(
+        out,
+        "before [<div class=\"card\"><h2>Hello</h2><ul><li>a</li><li>b</li></ul></div>]"
+    );
+}
+
+#[tokio::test]
+async fn rendered_template_does_not_leak_declarations() {
+    let out = eval_in(
+        &[("t.tpl", "<? const x = n * 2; ?><?= x ?>")],
+        "<? const t = readFile('t.tpl'); ?><?= render(t, { n: 1 }) ?>,<?= render(t, { n: 2 }) ?>,<?= typeof x ?>",
+    )
+    .await;
+    assert_eq!(out, "2,4,undefined");
+}
+
+#[tokio::test]
+async fn render_settles_awaiting_templates() {
+    let out = eval_in(
+        &[("t.tpl", "<?= await Promise.resolve(name) ?>!")],
+        "<?= render(readFile('t.tpl'), { name: 'done' }) ?>",
+    )
+    .await;
+    assert_eq!(out, "done!");
+}
+
+#[tokio::test]
+async fn render_errors_propagate_to_the_caller() {
+    let out = eval_in(
+        &[("t.tpl", "<? missing(); ?>")],
+        "<? try { render(readFile('t.tpl')) } catch (e) { echo('caught ' + e.name) } ?>|<?= 'after' ?>",
+    )
+    .await;
+    assert_eq!(out, "caught ReferenceError|after");
+}
+
+#[tokio::test]
+async fn self_rendering_template_hits_the_depth_limit() {
+    let out = eval_in(
+        &[("loop.tpl", "<?= render(self, { self }) ?>")],
+        "<? try { render(readFile('loop.tpl'), { self: readFile('loop.tpl') }) } catch (e) { echo(e.message) } ?>",
+    )
+    .await;
+    assert_eq!(out, "render(): templates nested more than 32 deep");
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use common::{config_for, docroot, eval, get, http_server};
+
+#[tokio::test]
+async fn now_and_hrtime_are_monotonic_within_a_render() {
+    let out = eval(concat!(
+        "<? const a = now(); let x = 0; for (let i = 0; i < 100000; i++) x += i; ",
+        "const b = now(); const h1 = hrtime(); const h2 = hrtime(); ?>",
+        "<?= typeof a ?> <?= b >= a ?> <?= b - a < 10000 ?> ",
+        "<?= typeof h1 ?> <?= h2 >= h1 ?>",
+    ))
+    .await;
+    assert_eq!(out, "number true true bigint true");
+}
+
+#[tokio::test]
+async fn request_start_time_is_populated() {
+    let root = docroot(&[(
+        "t.jhp",
+        "<?= typeof $request.startTime ?> <?= Date.now() - $request.startTime ?>",
+    )]);
+    let server = http_server(&config_for(root.path()));
+
+    let (_, _, body) = get(&server, "/t.jhp").await;
+    let (kind, elapsed) = body.split_once(' ').unwrap();
+    assert_eq!(kind, "number");
+    let elapsed: f64 = elapsed.parse().unwrap();
+    assert!((0.0..60_000.0).contains(&elapsed), "{body}");
+
+    // renders outside HTTP get the time the render began
+    assert_eq!(eval("<?= $request.startTime > 0 ?>").await, "true");
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{config_for, docroot, free_port, get, http_server};
+use std::io::{ErrorKind, Read, Write};
+use std::time::{Duration, Instant};
+
+const BUSY_300MS: &str = "<? const t = Date.now(); while (Date.now() - t < 300) {} ?>";
+
+#[tokio::test]
+async fn slow_requests_time_out_with_408() {
+    let root = docroot(&[("slow.jhp", "<? while (true) {} ?>"), ("fast.jhp", "fast")]);
+    let config = config_for(root.path()).set_request_timeout(Duration::from_millis(200));
+    let server = http_server(&config);
+
+    let (status, _, _) = get(&server, "/slow.jhp").await;
+    assert_eq!(status, StatusCode::REQUEST_TIMEOUT);
+
+    // the endless render is terminated, which frees the worker for the next request
+    let started = Instant::now();
+    let (status, _, body) = get(&server, "/fast.jhp").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "fast");
+    assert!(started.elapsed() < Duration::from_millis(1000));
+}
+
+#[tokio::test]
+async fn requests_are_unlimited_by_default() {
+    let slow = format!("{BUSY_300MS}done");
+    let root = docroot(&[("slow.jhp", &slow)]);
+    let server = http_server(&config_for(root.path()));
+
+    let (status, _, body) = get(&server, "/slow.jhp").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "done");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn clients_sending_headers_too_slowly_are_dropped() {
+    let root = docroot(&[("index.jhp", "hi")]);
+    let mut config = config_for(root.path());
+    config.port = free_port();
+    config.header_read_timeout = Some(Duration::from_millis(200));
+    let addr = config.addr();
+    let server = http_server(&config);
+    tokio::spawn(async move { server.start().await });
+
+    let outcome = tokio::task::spawn_blocking(move || {
+        let mut stream = (0..50)
+            .find_map(|_| {
+                std::net::TcpStream::connect(&addr)
+                    .inspect_err(|_| std::thread::sleep(Duration::from_millis(50)))
+                    .ok()
+            })
+            .expect("server did not start");
+        // the request line and one header, but never the blank line ending the headers
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n")
+            .unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).map(|_| response)
+    })
+    .await
+    .unwrap();
+
+    // the server gives up long before the client's own read timeout: it closes the
+    // connection, possibly after a 408
+    match outcome {
+        Ok(response) => {
+            let response = String::from_utf8_lossy(&response);
+            assert!(
+                response.is_empty() || response.starts_with("HTTP/1.1 408"),
+                "{response}"
+            );
+        }
+        Err(e) => assert!(
+            !matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut),
+            "connection still open: {e}"
+        ),
+    }
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use common::{config_for, docroot, free_port, http_server};
+use std::time::Duration;
+
+#[tokio::test]
+async fn serves_https_with_a_self_signed_certificate() {
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    let root = docroot(&[("index.jhp", "<?= 'secure' ?>")]);
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let (cert_path, key_path) = (root.path().join("cert.pem"), root.path().join("key.pem"));
+    std::fs::write(&cert_path, cert.cert.pem()).unwrap();
+    std::fs::write(&key_path, cert.key_pair.serialize_pem()).unwrap();
+
+    let port = free_port();
+    let mut config = config_for(root.path()).set_tls(&cert_path, &key_path);
+    config.port = port;
+    let server = http_server(&config);
+    tokio::spawn(async move { server.start().await });
+
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let url = format!("https://127.0.0.1:{port}/");
+    let mut attempts = 0;
+    let response = loop {
+        match client.get(&url).send().await {
+            Ok(response) => break response,
+            Err(_) if attempts < 50 => {
+                attempts += 1;
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            Err(e) => panic!("HTTPS request failed: {e}"),
+        }
+    };
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().await.unwrap(), "secure");
+}
+
+#[tokio::test]
+async fn invalid_tls_key_fails_at_startup() {
+    let root = docroot(&[("cert.pem", "not a certificate"), ("key.pem", "nope")]);
+    let mut config =
+        config_for(root.path()).set_tls(root.path().join("cert.pem"), root.path().join("key.pem"));
+    config.port = free_port();
+
+    let err = http_server(&config).start().await.unwrap_err();
+    assert!(
+        err.starts_with("failed to load TLS certificate"),
+        "unexpected error: {err}"
+    );
+}
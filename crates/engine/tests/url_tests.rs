@@ -0,0 +1,72 @@
+mod common;
+
+use common::{config_for, docroot, eval, get, http_server};
+
+#[tokio::test]
+async fn url_encode_escapes_reserved_characters() {
+    let out = eval("<?= urlEncode('a b&c=d/e?f#g+h~i') ?>|<?= urlEncode('é') ?>").await;
+    assert_eq!(out, "a%20b%26c%3Dd%2Fe%3Ff%23g%2Bh~i|%C3%A9");
+}
+
+#[tokio::test]
+async fn url_decode_keeps_plus() {
+    let out = eval("<?= urlDecode('1+1%3D2%21') ?>|<?= urlDecode('100%25 sure') ?>").await;
+    assert_eq!(out, "1+1=2!|100% sure");
+}
+
+#[tokio::test]
+async fn form_decode_reads_plus_as_space() {
+    let out = eval("<?= formDecode('hello+world%21') ?>|<?= formDecode('100%+sure') ?>").await;
+    assert_eq!(out, "hello world!|100% sure");
+}
+
+#[tokio::test]
+async fn spaces_differ_between_component_and_form_encoding() {
+    let out = eval(concat!(
+        "<?= urlEncode('a b+c') ?>|<?= formEncode('a b+c') ?>|",
+        "<?= urlDecode(urlEncode('a b+c')) ?>|<?= formDecode(formEncode('a b+c')) ?>|",
+        "<?= encodeURIComponent('a b') === urlEncode('a b') ?>",
+    ))
+    .await;
+    assert_eq!(out, "a%20b%2Bc|a+b%2Bc|a b+c|a b+c|true");
+}
+
+#[tokio::test]
+async fn build_query_repeats_arrays_and_nests_objects() {
+    let out = eval(concat!(
+        "<?= buildQuery({ a: 1, b: 'hello world', tag: ['x', 'y'], skip: null, ",
+        "user: { name: 'Ann Lee', roles: ['admin'] } }) ?>",
+    ))
+    .await;
+    assert_eq!(
+        out,
+        "a=1&b=hello+world&tag=x&tag=y&user%5Bname%5D=Ann+Lee&user%5Broles%5D=admin"
+    );
+}
+
+#[tokio::test]
+async fn build_query_rejects_cycles() {
+    let out = eval(concat!(
+        "<? const o = {}; o.self = o; ",
+        "try { buildQuery(o); } catch (e) { echo(e.message); } ?>",
+    ))
+    .await;
+    assert!(out.starts_with("buildQuery: "), "unexpected output: {out}");
+}
+
+#[tokio::test]
+async fn query_round_trips_through_build_query() {
+    let root = docroot(&[(
+        "q.jhp",
+        "<?= JSON.stringify($query) ?>|<?= buildQuery($query) ?>",
+    )]);
+    let server = http_server(&config_for(root.path()));
+    let (_, _, body) = get(&server, "/q.jhp?q=fish+%26+chips&tag=a&tag=b&empty").await;
+    assert_eq!(
+        body,
+        concat!(
+            r#"{"q":"fish & chips","tag":["a","b"],"empty":""}"#,
+            "|q=fish+%26+chips&tag=a&tag=b&empty="
+        )
+    );
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use common::{capture_logs, config_for, docroot, free_port, http_server};
+use futures_util::{SinkExt, StreamExt};
+use jhp_engine::log::LogLevel;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+
+const HANDLER: &str = "function onOpen(socket) {
+    $store.set('websocket_tests.' + socket.id, 0);
+    socket.send('welcome ' + socket.id);
+}
+function onMessage(socket, message) {
+    if (message.startsWith('all:')) {
+        socket.broadcast(message.slice(4));
+        return;
+    }
+    const key = 'websocket_tests.' + socket.id;
+    $store.set(key, $store.get(key) + 1);
+    return `echo ${$store.get(key)}: ${message}`;
+}";
+
+type Client =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Connect to `url`, retrying while the server starts.
+async fn connect(url: &str) -> Client {
+    for _ in 0..50 {
+        if let Ok((client, _)) = tokio_tungstenite::connect_async(url).await {
+            return client;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("could not connect to {url}");
+}
+
+async fn next_text(client: &mut Client) -> String {
+    let message = tokio::time::timeout(Duration::from_secs(10), client.next())
+        .await
+        .expect("no message in time")
+        .expect("connection closed")
+        .unwrap();
+    message.into_text().unwrap().as_str().to_string()
+}
+
+#[tokio::test]
+async fn messages_are_echoed_by_the_handler() {
+    let root = docroot(&[("ws.js", HANDLER)]);
+    let mut config = config_for(root.path()).add_websocket("/ws", "ws.js");
+    let port = free_port();
+    config.port = port;
+    let server = http_server(&config);
+    tokio::spawn(async move { server.start().await });
+
+    let url = format!("ws://127.0.0.1:{port}/ws");
+    let mut client = connect(&url).await;
+    let welcome = next_text(&mut client).await;
+    assert!(welcome.starts_with("welcome "), "{welcome}");
+
+    client.send(Message::text("hello")).await.unwrap();
+    assert_eq!(next_text(&mut client).await, "echo 1: hello");
+    client.send(Message::text("again")).await.unwrap();
+    assert_eq!(next_text(&mut client).await, "echo 2: again");
+
+    // a broadcast reaches every connection of the endpoint
+    let mut other = connect(&url).await;
+    next_text(&mut other).await;
+    client.send(Message::text("all:news")).await.unwrap();
+    assert_eq!(next_text(&mut client).await, "news");
+    assert_eq!(next_text(&mut other).await, "news");
+}
+
+#[tokio::test]
+async fn a_failing_hook_closes_the_connection() {
+    let root = docroot(&[("ws.js", "function onMessage() { missing(); }")]);
+    let (sink, entries) = capture_logs();
+    let mut config = config_for(root.path()).add_websocket("/ws", "ws.js");
+    config.log_sink = sink;
+    let port = free_port();
+    config.port = port;
+    let server = http_server(&config);
+    tokio::spawn(async move { server.start().await });
+
+    let mut client = connect(&format!("ws://127.0.0.1:{port}/ws")).await;
+    client.send(Message::text("hello")).await.unwrap();
+    let message = tokio::time::timeout(Duration::from_secs(10), client.next())
+        .await
+        .expect("no close in time");
+    match message {
+        Some(Ok(Message::Close(Some(frame)))) => assert_eq!(u16::from(frame.code), 1011),
+        other => panic!("expected a close frame, got {other:?}"),
+    }
+
+    // the failure goes to the server log
+    let entries = entries.lock().unwrap();
+    assert_eq!(entries.len(), 1, "{entries:?}");
+    assert_eq!(entries[0].level, LogLevel::Error);
+    assert!(
+        entries[0].message.starts_with("websocket /ws: onMessage: ")
+            && entries[0].message.contains("missing is not defined"),
+        "{}",
+        entries[0].message
+    );
+}
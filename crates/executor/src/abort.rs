@@ -0,0 +1,102 @@
+//! Stopping a render with an HTTP status, as `abort(status, message?)` and the redirect
+//! helpers do.
+
+/// Private keys of the exception thrown by [`throw`]; scripts can't read or forge them.
+const STATUS_KEY: &str = "jhp.abort.status";
+const MESSAGE_KEY: &str = "jhp.abort.message";
+const LOCATION_KEY: &str = "jhp.abort.location";
+
+/// A render stopped on purpose, to be answered with `status` instead of the output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Abort {
+    pub status: u16,
+    /// Response body; the status' reason phrase is used when there is none.
+    pub message: Option<String>,
+    /// Target of a redirect, sent as `Location` with an empty body.
+    pub location: Option<String>,
+}
+
+/// Throw the exception of an abort: an `Error` carrying `abort` under private keys, so
+/// the render loop can tell it from ordinary errors even after it crossed `include()`s
+/// or an `await`.
+pub fn throw(scope: &mut v8::HandleScope, abort: &Abort) {
+    let text = format!("aborted with status {}", abort.status);
+    let Some(text) = v8::String::new(scope, &text) else {
+        return;
+    };
+    let exception = v8::Exception::error(scope, text);
+    if let Some(object) = exception.to_object(scope) {
+        let status = v8::Integer::new(scope, abort.status.into());
+        set_private(scope, object, STATUS_KEY, status.into());
+        if let Some(message) = abort
+            .message
+            .as_deref()
+            .and_then(|m| v8::String::new(scope, m))
+        {
+            set_private(scope, object, MESSAGE_KEY, message.into());
+        }
+        if let Some(location) = abort
+            .location
+            .as_deref()
+            .and_then(|l| v8::String::new(scope, l))
+        {
+            set_private(scope, object, LOCATION_KEY, location.into());
+        }
+    }
+    scope.throw_exception(exception);
+}
+
+/// The abort an exception stands for, if it was thrown by [`throw`].
+pub(crate) fn recognize(
+    scope: &mut v8::HandleScope,
+    exception: v8::Local<v8::Value>,
+) -> Option<Abort> {
+    if !exception.is_object() {
+        return None;
+    }
+    let object = exception.to_object(scope)?;
+    let status = get_private(scope, object, STATUS_KEY)?;
+    if !status.is_number() {
+        return None;
+    }
+    let status = u16::try_from(status.integer_value(scope)?).ok()?;
+    let message = get_private(scope, object, MESSAGE_KEY)
+        .filter(|m| m.is_string())
+        .map(|m| m.to_rust_string_lossy(scope));
+    let location = get_private(scope, object, LOCATION_KEY)
+        .filter(|l| l.is_string())
+        .map(|l| l.to_rust_string_lossy(scope));
+    Some(Abort {
+        status,
+        message,
+        location,
+    })
+}
+
+fn private_key<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    name: &str,
+) -> Option<v8::Local<'s, v8::Private>> {
+    let name = v8::String::new(scope, name)?;
+    Some(v8::Private::for_api(scope, Some(name)))
+}
+
+fn set_private(
+    scope: &mut v8::HandleScope,
+    object: v8::Local<v8::Object>,
+    name: &str,
+    value: v8::Local<v8::Value>,
+) {
+    if let Some(key) = private_key(scope, name) {
+        object.set_private(scope, key, value);
+    }
+}
+
+fn get_private<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    object: v8::Local<v8::Object>,
+    name: &str,
+) -> Option<v8::Local<'s, v8::Value>> {
+    let key = private_key(scope, name)?;
+    object.get_private(scope, key)
+}
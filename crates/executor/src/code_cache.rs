@@ -0,0 +1,91 @@
+//! V8 code cache for the scripts template blocks compile to, kept per executor.
+
+use crate::stats::WorkerStats;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+use v8::script_compiler::{self, CachedData, CompileOptions, NoCacheReason};
+
+/// Entries kept before the cache starts over, so a site with many templates (or many
+/// edits of one) doesn't grow it forever.
+const MAX_ENTRIES: usize = 4096;
+
+/// Code cache data of compiled scripts, by hash of their source. It lives in an isolate
+/// slot, so each executor has its own. A template that changed compiles to a different
+/// source and so misses the cache; V8 also rejects data that doesn't match the source.
+pub(crate) struct CodeCache {
+    entries: HashMap<u64, Vec<u8>>,
+    stats: Arc<WorkerStats>,
+}
+
+impl CodeCache {
+    pub fn new(stats: Arc<WorkerStats>) -> Self {
+        Self {
+            entries: HashMap::new(),
+            stats,
+        }
+    }
+
+    fn insert(&mut self, key: u64, data: Vec<u8>) {
+        if self.entries.len() >= MAX_ENTRIES {
+            self.entries.clear();
+        }
+        self.entries.insert(key, data);
+    }
+}
+
+fn key(code: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    code.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Compile `code` (already in `source`) as a script, from the isolate's [`CodeCache`]
+/// when it has the same source, and adding the code to it otherwise. Isolates without a
+/// cache simply compile.
+pub(crate) fn compile<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    code: &str,
+    source: v8::Local<v8::String>,
+    origin: &v8::ScriptOrigin,
+) -> Option<v8::Local<'s, v8::Script>> {
+    let key = key(code);
+    let Some(cached) = scope
+        .get_slot::<CodeCache>()
+        .map(|cache| cache.entries.get(&key).cloned())
+    else {
+        return v8::Script::compile(scope, source, Some(origin));
+    };
+
+    let (script, hit) = match cached {
+        Some(data) => {
+            let mut source = script_compiler::Source::new_with_cached_data(
+                source,
+                Some(origin),
+                CachedData::new(&data),
+            );
+            let script = script_compiler::compile(
+                scope,
+                &mut source,
+                CompileOptions::ConsumeCodeCache,
+                NoCacheReason::NoReason,
+            );
+            let hit = source
+                .get_cached_data()
+                .is_some_and(|data| !data.rejected());
+            (script, hit)
+        }
+        None => (v8::Script::compile(scope, source, Some(origin)), false),
+    };
+    let script = script?;
+    let data = (!hit)
+        .then(|| script.get_unbound_script(scope).create_code_cache())
+        .flatten();
+    if let Some(cache) = scope.get_slot_mut::<CodeCache>() {
+        cache.stats.record_compile(hit);
+        if let Some(data) = data {
+            cache.insert(key, data.to_vec());
+        }
+    }
+    Some(script)
+}
@@ -0,0 +1,91 @@
+//! Errors thrown by template code, kept structured until they are shown or logged.
+
+use crate::{Abort, RenderMetrics};
+use std::fmt;
+
+/// An uncaught exception (or rejected `await`) that stopped a render.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScriptError {
+    /// Template or script the error points into, e.g. `index.jhp` or an included file.
+    pub resource: String,
+    /// 1-based line of the error; 0 when V8 reported no position.
+    pub line: usize,
+    /// 1-based column where the offending range starts; 0 when unknown.
+    pub column: usize,
+    /// 1-based column just past the offending range.
+    pub end_column: usize,
+    /// The exception converted to a string, e.g. `ReferenceError: x is not defined`.
+    pub message: String,
+    /// The JS stack trace, empty when the thrown value has none.
+    pub stack: String,
+    /// The source line the error points at, when V8 has it.
+    pub source_line: Option<String>,
+}
+
+impl ScriptError {
+    /// An error without source details, for failures outside the script itself.
+    pub fn new(resource: &str, line: usize, message: impl Into<String>) -> Self {
+        Self {
+            resource: resource.to_string(),
+            line,
+            column: 1,
+            end_column: 2,
+            message: message.into(),
+            ..Self::default()
+        }
+    }
+
+    /// The source line followed by a line with `^` under the offending range.
+    fn snippet(&self) -> Option<String> {
+        let source = self.source_line.as_deref()?;
+        let start = self.column.saturating_sub(1);
+        let end = self.end_column.saturating_sub(1).max(start + 1);
+        // keep tabs so the carets line up however the terminal renders them
+        let indent: String = source
+            .chars()
+            .take(start)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        Some(format!("{}\n{}{}", source, indent, "^".repeat(end - start)))
+    }
+}
+
+/// `resource:line:column`, the source snippet, then the stack (or just the message when
+/// there is no stack) — the text appended to the output under `<!-- ERROR -->`.
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.resource, self.line, self.column)?;
+        if let Some(snippet) = self.snippet() {
+            write!(f, "\n{}", snippet)?;
+        }
+        let stack = self.stack.trim();
+        if stack.is_empty() {
+            write!(f, "\n{}", self.message)
+        } else if stack.starts_with(&self.message) {
+            // the stack already starts with the exception summary; don't duplicate it
+            write!(f, "\n{}", stack)
+        } else {
+            write!(f, "\n{}\n{}", self.message, stack)
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// What a render produced: the output and, if a block threw, the error that stopped it.
+/// Depending on [`ErrorOutput`](crate::ErrorOutput), `body` may already include the
+/// formatted error.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderOutput {
+    pub body: String,
+    pub error: Option<ScriptError>,
+    /// Set when the template called `abort()`; the response should use its status and
+    /// message instead of `body`.
+    pub abort: Option<Abort>,
+    /// Status set by the template (e.g. through `$response.json`); 200 when `None`.
+    pub status: Option<u16>,
+    /// Content type set by the template, overriding a `contentType` directive.
+    pub content_type: Option<String>,
+    /// How long the render took and how much it did.
+    pub metrics: RenderMetrics,
+}
@@ -0,0 +1,185 @@
+//! `layout(path)` and named sections: wrap a page's output in a shared layout template.
+
+use crate::response::{ResponseState, throw};
+use jhp_parser::{CodeBlock, CodeBlockContent, js_string};
+use std::rc::Rc;
+
+/// How many layouts may wrap one page (a layout can declare a layout of its own); the
+/// next `layout()` call throws, so a layout naming itself fails instead of looping.
+pub(crate) const MAX_LAYOUTS: usize = 8;
+
+/// Global holding the wrapped output while a layout renders.
+const CONTENT_GLOBAL: &str = "$content";
+
+/// Install the layout functions into the current context:
+/// - `layout(path)`: once the page has rendered, its output becomes `$content` and the
+///   template at `path` is rendered in its place, found as `include(path)` would find it:
+///   from the document root and include paths, never relative to the calling template, so
+///   a layout under `layouts/` names the one wrapping it `layouts/base.jhp` too.
+///   The last call wins; a layout may call it again to be wrapped in turn.
+/// - `section(name, content?)`: set the section `name` to `content`, or without it,
+///   capture everything output from here to the matching `endSection()` instead.
+/// - `endSection()`: close the innermost open section.
+/// - `yield(name, fallback?)`: the content of section `name`, or `fallback` (`""` by
+///   default) when it wasn't set; for the layout to place with `<?= yield('title') ?>`.
+///
+/// The page's output is wrapped only when it rendered without an error or `abort()`.
+/// `flush()` does nothing while a layout is pending or a section is open, and `layout()`
+/// throws once output was flushed.
+pub(crate) fn install(scope: &mut v8::ContextScope<v8::HandleScope>, state: &Rc<ResponseState>) {
+    let global = scope.get_current_context().global(scope);
+
+    // SAFETY: the state is owned by the executor, which outlives every context it renders in
+    let state_ptr = Rc::as_ptr(state) as *mut std::ffi::c_void;
+    let external = v8::External::new(scope, state_ptr);
+
+    let layout_fn = v8::Function::builder(
+        |scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _rv: v8::ReturnValue| {
+            let Some(state) = state_of(&args) else {
+                return;
+            };
+            let path = args.get(0);
+            if !path.is_string() {
+                throw(scope, "layout(path): path must be a string", true);
+                return;
+            }
+            if state.flushed.get() {
+                throw(scope, "layout(): output was already flushed", false);
+                return;
+            }
+            if state.layouts_applied.get() >= MAX_LAYOUTS {
+                let message = format!("layout(): layouts nested more than {} deep", MAX_LAYOUTS);
+                throw(scope, &message, false);
+                return;
+            }
+            let path = path.to_rust_string_lossy(scope);
+            state.layout.replace(Some(path));
+        },
+    )
+    .data(external.into())
+    .build(scope)
+    .expect("Failed to create layout function");
+
+    let section_fn = v8::Function::builder(
+        |scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _rv: v8::ReturnValue| {
+            let Some(state) = state_of(&args) else {
+                return;
+            };
+            let name = args.get(0);
+            if !name.is_string() {
+                throw(scope, "section(name, content): name must be a string", true);
+                return;
+            }
+            let name = name.to_rust_string_lossy(scope);
+            let content = args.get(1);
+            if content.is_undefined() {
+                let start = state.output.borrow().len();
+                state.open_sections.borrow_mut().push((name, start));
+            } else {
+                let content = content.to_rust_string_lossy(scope);
+                state.sections.borrow_mut().insert(name, content);
+            }
+        },
+    )
+    .data(external.into())
+    .build(scope)
+    .expect("Failed to create section function");
+
+    let end_section_fn = v8::Function::builder(
+        |scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _rv: v8::ReturnValue| {
+            let Some(state) = state_of(&args) else {
+                return;
+            };
+            if !close_section(state) {
+                throw(scope, "endSection(): no section is open", false);
+            }
+        },
+    )
+    .data(external.into())
+    .build(scope)
+    .expect("Failed to create endSection function");
+
+    let yield_fn = v8::Function::builder(
+        |scope: &mut v8::HandleScope,
+         args: v8::FunctionCallbackArguments,
+         mut rv: v8::ReturnValue| {
+            let Some(state) = state_of(&args) else {
+                return;
+            };
+            let name = args.get(0).to_rust_string_lossy(scope);
+            let content = state.sections.borrow().get(&name).cloned();
+            let content = content.unwrap_or_else(|| {
+                let fallback = args.get(1);
+                if fallback.is_undefined() {
+                    String::new()
+                } else {
+                    fallback.to_rust_string_lossy(scope)
+                }
+            });
+            if let Some(s) = v8::String::new(scope, &content) {
+                rv.set(s.into());
+            }
+        },
+    )
+    .data(external.into())
+    .build(scope)
+    .expect("Failed to create yield function");
+
+    for (name, function) in [
+        ("layout", layout_fn),
+        ("section", section_fn),
+        ("endSection", end_section_fn),
+        ("yield", yield_fn),
+    ] {
+        if let Some(key) = v8::String::new(scope, name) {
+            let _ = global.set(scope, key.into(), function.into());
+        }
+    }
+}
+
+/// The state a layout function was installed with.
+fn state_of<'a>(args: &v8::FunctionCallbackArguments) -> Option<&'a ResponseState> {
+    let external = v8::Local::<v8::External>::try_from(args.data()).ok()?;
+    // SAFETY: see `install`; the state outlives the context the function runs in
+    Some(unsafe { &*(external.value() as *const ResponseState) })
+}
+
+/// Move the output since the innermost open section began into that section; `false`
+/// when none is open.
+pub(crate) fn close_section(state: &ResponseState) -> bool {
+    let Some((name, start)) = state.open_sections.borrow_mut().pop() else {
+        return false;
+    };
+    let mut output = state.output.borrow_mut();
+    let start = start.min(output.len());
+    let content = output.split_off(start);
+    state.sections.borrow_mut().insert(name, content);
+    true
+}
+
+/// The block rendering the layout the page asked for, if any, with the page's output
+/// moved into `$content`. Sections still open are closed first.
+pub(crate) fn take_layout_block(
+    scope: &mut v8::ContextScope<v8::HandleScope>,
+    state: &ResponseState,
+) -> Option<Box<CodeBlock>> {
+    while close_section(state) {}
+    let path = state.layout.take()?;
+    state.layouts_applied.set(state.layouts_applied.get() + 1);
+
+    let content = std::mem::take(&mut *state.output.borrow_mut());
+    let global = scope.get_current_context().global(scope);
+    if let (Some(key), Some(content)) = (
+        v8::String::new(scope, CONTENT_GLOBAL),
+        v8::String::new(scope, &content),
+    ) {
+        let _ = global.set(scope, key.into(), content.into());
+    }
+    Some(Box::new(CodeBlock::Javascript(CodeBlockContent {
+        lineno: 1,
+        end_lineno: 1,
+        colno: 1,
+        content: format!("include({})", js_string(&path)),
+        level: 0,
+    })))
+}
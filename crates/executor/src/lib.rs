@@ -1,10 +1,28 @@
-use jhp_parser::CodeBlock;
+use jhp_parser::{CodeBlock, ExpressionOutput};
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::sync::{Arc, Once};
 use tokio::sync::{mpsc, oneshot};
+use v8utils::Stop;
+use watchdog::Watchdog;
 
+pub mod abort;
+mod code_cache;
+mod error;
+mod layout;
+pub mod query;
+pub mod request;
+mod response;
+pub mod stats;
+mod stream;
 pub mod v8utils;
+mod watchdog;
+
+pub use abort::Abort;
+pub use error::{RenderOutput, ScriptError};
+pub use request::RequestInfo;
+pub use stats::{RenderMetrics, WorkerSnapshot, WorkerStats};
+pub use stream::Chunk;
 
 pub enum Op {
     Javascript(String),
@@ -12,10 +30,66 @@ pub enum Op {
     Render {
         blocks: Vec<Box<CodeBlock>>,
         resource_name: String,
-        respond_to: oneshot::Sender<String>,
+        /// Exposed to the template as `$request`.
+        request: RequestInfo,
+        /// Where `flush()` sends output ahead of the [`RenderOutput`]; without one,
+        /// `flush()` does nothing and the whole output comes back at the end.
+        stream: Option<mpsc::UnboundedSender<Chunk>>,
+        respond_to: oneshot::Sender<RenderOutput>,
     },
 }
 
+/// What a render's output contains when a block throws. Rendering always stops at the
+/// failing block, and anything echoed before the throw (in that block or earlier) is kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorOutput {
+    /// Follow the partial output with an `<!-- ERROR -->` marker and the formatted error.
+    #[default]
+    Append,
+    /// End the output where the error happened; the error only goes to the
+    /// [`ErrorHook`].
+    Truncate,
+}
+
+/// Where the errors of renders that leave them out of the output go (see
+/// [`ErrorOutput::Truncate`]). Cloning shares the same destination.
+#[derive(Clone)]
+pub struct ErrorHook(Arc<dyn Fn(&ScriptError) + Send + Sync>);
+
+impl ErrorHook {
+    pub fn new<F: Fn(&ScriptError) + Send + Sync + 'static>(f: F) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub fn report(&self, err: &ScriptError) {
+        (self.0)(err)
+    }
+}
+
+/// Writes each error to stderr.
+impl Default for ErrorHook {
+    fn default() -> Self {
+        Self::new(|err| eprintln!("render error: {}", err))
+    }
+}
+
+impl std::fmt::Debug for ErrorHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ErrorHook")
+    }
+}
+
+/// Per-executor settings derived from the engine configuration.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutorConfig {
+    /// How `<?= expr ?>` values are printed.
+    pub expression_output: ExpressionOutput,
+    /// How a thrown error shows up in the rendered output.
+    pub error_output: ErrorOutput,
+    /// Told about errors the output doesn't show.
+    pub error_hook: ErrorHook,
+}
+
 pub struct Executor {
     pub id: usize,
     pub isolate: v8::OwnedIsolate,
@@ -23,8 +97,21 @@ pub struct Executor {
     // Hold no long-lived context; we create a fresh one per request to avoid identifier redeclarations.
     context: v8::Global<v8::Context>,
     installers: Arc<Vec<BindingInstaller>>,
+    stats: Arc<WorkerStats>,
+    config: ExecutorConfig,
+    /// Output buffer reused by every render on this executor, so its capacity carries
+    /// over instead of growing from zero each time.
+    output: Rc<RefCell<String>>,
+    /// What the template set through `$response`, shared with its bindings.
+    response: Rc<response::ResponseState>,
+    /// Terminates the render in progress once its caller is gone.
+    watchdog: Watchdog,
 }
 
+/// Largest output buffer capacity kept between renders; one huge page shouldn't pin
+/// that much memory for the life of the worker.
+const MAX_RETAINED_OUTPUT: usize = 1 << 20;
+
 /// A binding installer is a function that gets a chance to attach globals/APIs to the context
 pub type BindingInstaller =
     Arc<dyn Fn(&mut v8::ContextScope<v8::HandleScope>) + Send + Sync + 'static>;
@@ -34,6 +121,8 @@ impl Executor {
         id: usize,
         receiver: mpsc::Receiver<Op>,
         installers: Arc<Vec<BindingInstaller>>,
+        stats: Arc<WorkerStats>,
+        config: ExecutorConfig,
     ) -> Self {
         static INIT: Once = Once::new();
         INIT.call_once(|| {
@@ -42,6 +131,7 @@ impl Executor {
             v8::V8::initialize();
         });
         let mut isolate = v8::Isolate::new(Default::default());
+        isolate.set_slot(code_cache::CodeCache::new(stats.clone()));
 
         // create a bootstrap context to run installers that shouldn't depend on per-request state
         let installers_for_init = installers.clone();
@@ -60,6 +150,10 @@ impl Executor {
             // create a Global from the same handlescope after cs dropped
             v8::Global::new(hs1, context_local)
         };
+        stats.record_heap(&mut isolate);
+        stats.mark_ready();
+        let output = Rc::new(RefCell::new(String::new()));
+        let watchdog = Watchdog::new(id, isolate.thread_safe_handle());
 
         Self {
             id,
@@ -67,6 +161,11 @@ impl Executor {
             receiver,
             context: context_global,
             installers,
+            stats,
+            config,
+            response: Rc::new(response::ResponseState::new(output.clone())),
+            output,
+            watchdog,
         }
     }
 
@@ -90,41 +189,116 @@ impl Executor {
                 Op::Render {
                     blocks,
                     resource_name,
+                    request,
+                    stream,
                     respond_to,
                 } => {
-                    // create a fresh context per render to avoid re-declaration conflicts
-                    let hs = &mut v8::HandleScope::new(&mut self.isolate);
+                    self.stats.begin_request();
+                    // a closed sender means the caller (e.g. a disconnected HTTP client)
+                    // dropped the render: the watchdog terminates its JS, even mid-block
+                    self.watchdog.start(respond_to);
+                    let out = self.render(blocks, &resource_name, &request, stream);
+                    let respond_to = self.watchdog.finish();
+                    // a termination requested as the render ended would hit the next one
+                    self.isolate.cancel_terminate_execution();
+                    self.stats.record_render(&out.metrics);
+                    self.stats.record_heap(&mut self.isolate);
+                    self.stats.end_request();
+                    if let Some(respond_to) = respond_to {
+                        let _ = respond_to.send(out);
+                    }
+                }
+                Op::Shutdown => break,
+            }
+        }
+    }
 
-                    // derive a new context so that each request has isolated globals
-                    let mut req_scope = {
-                        let context_local = v8::Context::new(hs, v8::ContextOptions::default());
-                        v8::ContextScope::new(hs, context_local)
-                    };
+    /// Render parsed blocks in a fresh context and return the produced output along with
+    /// the error that stopped it, if any; output the template flushed went to `stream`
+    /// instead. Stops early once the watchdog sees the caller is gone.
+    fn render(
+        &mut self,
+        blocks: Vec<Box<CodeBlock>>,
+        resource_name: &str,
+        request: &RequestInfo,
+        stream: Option<mpsc::UnboundedSender<Chunk>>,
+    ) -> RenderOutput {
+        crate::v8utils::set_render_resource(&mut self.isolate, resource_name);
+        let watchdog = &self.watchdog;
+        let cancelled = || watchdog.cancelled();
+        // create a fresh context per render to avoid re-declaration conflicts
+        let hs = &mut v8::HandleScope::new(&mut self.isolate);
 
-                    // reinstall bindings that should exist in each fresh request context
-                    for install in self.installers.iter() {
-                        install(&mut req_scope);
-                    }
+        // derive a new context so that each request has isolated globals
+        let mut req_scope = {
+            let context_local = v8::Context::new(hs, v8::ContextOptions::default());
+            v8::ContextScope::new(hs, context_local)
+        };
 
-                    // install per-request echo bound to a fresh buffer
-                    let buffer: Rc<RefCell<String>> = Rc::new(RefCell::new(String::new()));
-                    if let Err(e) = Self::install_echo_fn(&mut req_scope, buffer.clone()) {
-                        eprintln!("install_echo_fn error: {}", e);
-                    }
+        // reinstall bindings that should exist in each fresh request context
+        for install in self.installers.iter() {
+            install(&mut req_scope);
+        }
+        request::install(&mut req_scope, request);
+        self.response.reset(stream);
+        response::install(&mut req_scope, &self.response);
+        stream::install(&mut req_scope, &self.response);
+        layout::install(&mut req_scope, &self.response);
 
-                    // execute each JHP block; HTML bypasses V8 for speed
-                    let _ = crate::v8utils::run_jhp_blocks_with_origin(
-                        &mut req_scope,
-                        blocks,
-                        &resource_name,
-                        buffer.clone(),
-                    );
+        // install per-request echo bound to the executor's buffer, emptied but not shrunk
+        let buffer = self.output.clone();
+        {
+            let mut out = buffer.borrow_mut();
+            out.clear();
+            out.shrink_to(MAX_RETAINED_OUTPUT);
+        }
+        if let Err(e) = Self::install_echo_fn(&mut req_scope, buffer.clone()) {
+            eprintln!("install_echo_fn error: {}", e);
+        }
 
-                    let out = buffer.borrow().clone();
-                    let _ = respond_to.send(out);
-                }
-                Op::Shutdown => break,
-            }
+        // run the blocks as one script; a page of HTML alone bypasses V8
+        let mut metrics = RenderMetrics::default();
+        let mut result = crate::v8utils::run_jhp_blocks_with_origin(
+            &mut req_scope,
+            blocks,
+            resource_name,
+            buffer.clone(),
+            &self.config,
+            &cancelled,
+            &mut metrics,
+        );
+        // wrap the output in the layout it asked for, which may ask for one in turn
+        while result.is_ok() {
+            let Some(block) = layout::take_layout_block(&mut req_scope, &self.response) else {
+                break;
+            };
+            result = crate::v8utils::run_jhp_blocks_with_origin(
+                &mut req_scope,
+                vec![block],
+                resource_name,
+                buffer.clone(),
+                &self.config,
+                &cancelled,
+                &mut metrics,
+            );
+        }
+
+        let (error, abort) = match result {
+            Ok(()) => (None, None),
+            Err(Stop::Error(error)) => (Some(error), None),
+            Err(Stop::Abort(abort)) => (None, Some(abort)),
+        };
+        // closes the stream, so its reader knows no chunk follows the output
+        self.response.stream.replace(None);
+        let body = buffer.borrow().clone();
+        metrics.output_bytes = self.response.flushed_bytes.get() + body.len();
+        RenderOutput {
+            body,
+            error,
+            abort,
+            status: self.response.status.get(),
+            content_type: self.response.content_type.borrow().clone(),
+            metrics,
         }
     }
 
@@ -0,0 +1,82 @@
+//! Percent-encoding and query-string parsing shared by `$query` and the URL bindings.
+
+/// Percent-encode `input` (as UTF-8) as an RFC 3986 URI component, leaving only the
+/// unreserved characters `A-Z a-z 0-9 - _ . ~` as-is. Spaces become `%20`.
+pub fn encode_component(input: &str) -> String {
+    percent_encode(input, false)
+}
+
+/// Encode `input` for an `application/x-www-form-urlencoded` body or query string: as
+/// [`encode_component`], except that spaces become `+`.
+pub fn encode_form_component(input: &str) -> String {
+    percent_encode(input, true)
+}
+
+fn percent_encode(input: &str, space_as_plus: bool) -> String {
+    let mut out = String::with_capacity(input.len());
+    for b in input.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+            out.push(b as char);
+        } else if b == b' ' && space_as_plus {
+            out.push('+');
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+/// Decode a query-string (form) component: `+` becomes a space and `%XX` a byte.
+/// Malformed escapes are kept literally and invalid UTF-8 is replaced, so decoding never
+/// fails.
+pub fn decode_component(input: &str) -> String {
+    percent_decode(input, true)
+}
+
+/// Decode an RFC 3986 URI component, as [`decode_component`] but keeping `+` as is.
+pub fn decode_uri_component(input: &str) -> String {
+    percent_decode(input, false)
+}
+
+fn percent_decode(input: &str, plus_as_space: bool) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' if plus_as_space => out.push(b' '),
+            b'%' => {
+                let hex = bytes
+                    .get(i + 1..i + 3)
+                    .and_then(|h| std::str::from_utf8(h).ok());
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok().filter(|_| is_hex(h))) {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn is_hex(s: &str) -> bool {
+    s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Split a query string (without the leading `?`) into decoded key/value pairs, in order.
+/// Empty segments are skipped and a key without `=` gets an empty value.
+pub fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| {
+            let (key, value) = segment.split_once('=').unwrap_or((segment, ""));
+            (decode_component(key), decode_component(value))
+        })
+        .collect()
+}
@@ -0,0 +1,517 @@
+//! Per-request data exposed to templates as `$request` and `$query`.
+
+use std::cmp::Reverse;
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The parts of the HTTP request a template can see. Header names are stored
+/// lowercased, in the order they were received.
+#[derive(Debug, Clone, Default)]
+pub struct RequestInfo {
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub headers: Vec<(String, String)>,
+    /// When the request began; a render without one uses the time it started instead.
+    pub start_time: Option<SystemTime>,
+    /// The client's address, as reported by a trusted proxy or else the connection's peer.
+    pub remote_addr: Option<IpAddr>,
+    /// `http` or `https`; empty for renders that didn't come from an HTTP request.
+    pub scheme: String,
+    /// The host the client asked for (`Host` or a trusted `X-Forwarded-Host`), if any.
+    pub host: Option<String>,
+}
+
+impl RequestInfo {
+    pub fn new<M, P>(method: M, path: P) -> Self
+    where
+        M: Into<String>,
+        P: Into<String>,
+    {
+        Self {
+            method: method.into(),
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn set_query<Q: Into<String>>(mut self, query: Q) -> Self {
+        self.query = query.into();
+        self
+    }
+
+    pub fn set_start_time(mut self, start_time: SystemTime) -> Self {
+        self.start_time = Some(start_time);
+        self
+    }
+
+    pub fn set_remote_addr(mut self, remote_addr: IpAddr) -> Self {
+        self.remote_addr = Some(remote_addr);
+        self
+    }
+
+    pub fn set_scheme<S: Into<String>>(mut self, scheme: S) -> Self {
+        self.scheme = scheme.into();
+        self
+    }
+
+    pub fn set_host<H: Into<String>>(mut self, host: H) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    pub fn add_header<N: AsRef<str>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.headers
+            .push((name.as_ref().to_ascii_lowercase(), value.into()));
+        self
+    }
+
+    /// All values of header `name` joined with `", "`, or `None` if it wasn't sent.
+    pub fn header(&self, name: &str) -> Option<String> {
+        let values: Vec<&str> = self
+            .headers
+            .iter()
+            .filter(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+            .collect();
+        (!values.is_empty()).then(|| values.join(", "))
+    }
+
+    /// The entry of `types` this request's `Accept` header prefers; see [`best_match`].
+    pub fn accepts<'t>(&self, types: &[&'t str]) -> Option<&'t str> {
+        best_match(self.header("accept").as_deref(), types)
+    }
+}
+
+/// One `type/subtype;q=...` entry of an `Accept` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MediaRange<'h> {
+    pub type_: &'h str,
+    pub subtype: &'h str,
+    /// Quality in thousandths (`q=0.9` is 900), which is all the precision RFC 9110 allows.
+    pub quality: u16,
+}
+
+impl MediaRange<'_> {
+    /// How specifically this range matches `type_/subtype`: 3 for an exact match,
+    /// 2 for `type/*`, 1 for `*/*`; `None` if it doesn't match.
+    fn specificity(&self, type_: &str, subtype: &str) -> Option<u8> {
+        match (self.type_, self.subtype) {
+            ("*", "*") => Some(1),
+            (t, "*") if t.eq_ignore_ascii_case(type_) => Some(2),
+            (t, s) if t.eq_ignore_ascii_case(type_) && s.eq_ignore_ascii_case(subtype) => Some(3),
+            _ => None,
+        }
+    }
+}
+
+/// Parse an `Accept` header into its media ranges, in header order. Entries that aren't
+/// `type/subtype` (a bare `*` counts as `*/*`) or carry an invalid `q` are skipped.
+pub fn parse_accept(header: &str) -> Vec<MediaRange<'_>> {
+    header
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let range = parts.next()?.trim();
+            let (type_, subtype) = match range.split_once('/') {
+                Some((t, s)) if !t.trim().is_empty() && !s.trim().is_empty() => {
+                    (t.trim(), s.trim())
+                }
+                None if range == "*" => ("*", "*"),
+                _ => return None,
+            };
+            if type_ == "*" && subtype != "*" {
+                return None;
+            }
+            let mut quality = 1000;
+            for param in parts {
+                let Some((name, value)) = param.split_once('=') else {
+                    continue;
+                };
+                if name.trim().eq_ignore_ascii_case("q") {
+                    quality = parse_quality(value.trim())?;
+                }
+            }
+            Some(MediaRange {
+                type_,
+                subtype,
+                quality,
+            })
+        })
+        .collect()
+}
+
+fn parse_quality(value: &str) -> Option<u16> {
+    let q: f32 = value.parse().ok()?;
+    (0.0..=1.0)
+        .contains(&q)
+        .then(|| (q * 1000.0).round() as u16)
+}
+
+/// Pick the entry of `types` (full media types like `"application/json"`) that the client
+/// prefers according to `accept`. Each type takes the quality of the most specific range
+/// matching it; the highest quality wins, then the more specific match, then the range
+/// listed first in the header, then the earlier entry of `types`. Types with quality 0 are
+/// never chosen. A missing or empty header accepts anything, so the first type is returned.
+pub fn best_match<'t>(accept: Option<&str>, types: &[&'t str]) -> Option<&'t str> {
+    let Some(accept) = accept.filter(|a| !a.trim().is_empty()) else {
+        return types.first().copied();
+    };
+    let ranges = parse_accept(accept);
+
+    types
+        .iter()
+        .enumerate()
+        .filter_map(|(position, &candidate)| {
+            let media_type = candidate.split(';').next().unwrap_or("").trim();
+            let (type_, subtype) = media_type.split_once('/')?;
+            // the most specific matching range decides this candidate's quality
+            let (specificity, order, quality) = ranges
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, r)| {
+                    r.specificity(type_, subtype)
+                        .map(|s| (s, Reverse(idx), r.quality))
+                })
+                .max()?;
+            (quality > 0).then_some(((quality, specificity, order, Reverse(position)), candidate))
+        })
+        .max_by_key(|(rank, _)| *rank)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Entity tags listed in an `If-Match` or `If-None-Match` header, each as sent (quotes and
+/// any `W/` prefix included), with `*` for the wildcard. Malformed entries are skipped.
+pub fn parse_entity_tags(header: &str) -> Vec<&str> {
+    let mut tags = Vec::new();
+    let mut rest = header;
+    while !rest.is_empty() {
+        let entry = rest.trim_start();
+        let len = if entry.starts_with('*') {
+            Some(1)
+        } else {
+            let opaque = entry.strip_prefix("W/").unwrap_or(entry);
+            opaque
+                .strip_prefix('"')
+                .and_then(|tag| tag.find('"'))
+                .map(|end| entry.len() - opaque.len() + end + 2)
+        };
+        // a quoted tag may contain commas, so the entry ends at the first one after it
+        let after = &entry[len.unwrap_or(0)..];
+        let (tail, next) = after.split_once(',').unwrap_or((after, ""));
+        if let Some(len) = len.filter(|_| tail.trim().is_empty()) {
+            tags.push(&entry[..len]);
+        }
+        rest = next;
+    }
+    tags
+}
+
+/// An HTTP date header value as Unix milliseconds, or `None` when it isn't a valid date.
+fn http_date_millis(value: &str) -> Option<f64> {
+    let time = httpdate::parse_http_date(value.trim()).ok()?;
+    Some(time.duration_since(UNIX_EPOCH).ok()?.as_millis() as f64)
+}
+
+/// The headers that make a state-changing request conditional (RFC 9110 §13.1), as sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Preconditions<'h> {
+    pub if_match: Option<&'h str>,
+    pub if_none_match: Option<&'h str>,
+    pub if_unmodified_since: Option<&'h str>,
+}
+
+impl Preconditions<'_> {
+    /// Whether a request changing a resource may go ahead, evaluated as RFC 9110 §13.2.2
+    /// orders it: `If-Match` (strong comparison, `*` needing the resource to exist), or
+    /// without it `If-Unmodified-Since`, then `If-None-Match` (weak comparison, `*`
+    /// needing it not to exist). `etag` is the resource's current tag, with or without its
+    /// quotes, and `None` when it doesn't exist; `last_modified` is when it last changed,
+    /// if known. `false` means the request should be answered `412 Precondition Failed`.
+    pub fn hold(&self, etag: Option<&str>, last_modified: Option<SystemTime>) -> bool {
+        let etag = etag.map(|tag| {
+            if tag.starts_with('"') || tag.starts_with("W/\"") {
+                tag.to_string()
+            } else {
+                format!("\"{}\"", tag)
+            }
+        });
+        let etag = etag.as_deref();
+        // `*` matches any tag, so it holds exactly when the resource exists
+        let listed = |header: &str, same: fn(&str, &str) -> bool| {
+            parse_entity_tags(header).into_iter().any(|tag| match etag {
+                Some(_) if tag == "*" => true,
+                Some(etag) => same(tag, etag),
+                None => false,
+            })
+        };
+        let strong = |a: &str, b: &str| a == b && !a.starts_with("W/");
+        let weak = |a: &str, b: &str| {
+            a.strip_prefix("W/").unwrap_or(a) == b.strip_prefix("W/").unwrap_or(b)
+        };
+
+        if let Some(if_match) = self.if_match {
+            if !listed(if_match, strong) {
+                return false;
+            }
+        } else if let (Some(since), Some(modified)) = (self.if_unmodified_since, last_modified) {
+            // HTTP dates have whole seconds, so a change within the same second still counts
+            let since = httpdate::parse_http_date(since.trim()).ok();
+            let modified = modified.duration_since(UNIX_EPOCH).map_or(UNIX_EPOCH, |d| {
+                UNIX_EPOCH + Duration::from_secs(d.as_secs())
+            });
+            if since.is_some_and(|since| modified > since) {
+                return false;
+            }
+        }
+        if self
+            .if_none_match
+            .is_some_and(|if_none_match| listed(if_none_match, weak))
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// Install `$request` into the current context: `method`, `path`, `query`, `scheme`,
+/// `host` and `remoteAddr` (both `null` when unknown), `startTime` (Unix milliseconds),
+/// `headers` (lowercased names; repeated headers joined with `", "`), `header(name)` and
+/// `accepts(types)`, which returns the preferred entry of `types` or `null`. The
+/// conditional headers are parsed into `ifMatch` and `ifNoneMatch` (arrays of entity
+/// tags, see [`parse_entity_tags`]) and `ifModifiedSince` and `ifUnmodifiedSince` (Unix
+/// milliseconds), each `null` when not sent or invalid; `preconditionsMet(etag,
+/// lastModified?)` evaluates them for a write, see [`Preconditions::hold`]. Also installs
+/// `$query`, the query string decoded by [`crate::query::parse_query`].
+///
+/// And `$ctx`, an empty object for the render's own values: the page, every file it
+/// `include()`s and its layouts all see the same object, so a property one of them sets
+/// is there for the others once it ran. Each render gets a new one, nothing carries over
+/// to the next request. The global itself can't be reassigned or deleted, only its
+/// properties change.
+pub(crate) fn install(scope: &mut v8::ContextScope<v8::HandleScope>, request: &RequestInfo) {
+    let global = scope.get_current_context().global(scope);
+    let obj = v8::Object::new(scope);
+
+    for (name, value) in [
+        ("method", request.method.as_str()),
+        ("path", request.path.as_str()),
+        ("query", request.query.as_str()),
+        ("scheme", request.scheme.as_str()),
+    ] {
+        set_string(scope, obj, name, value);
+    }
+    let remote_addr = request.remote_addr.map(|ip| ip.to_string());
+    for (name, value) in [("host", &request.host), ("remoteAddr", &remote_addr)] {
+        match value {
+            Some(value) => set_string(scope, obj, name, value),
+            None => {
+                if let Some(key) = v8::String::new(scope, name) {
+                    let null = v8::null(scope);
+                    let _ = obj.set(scope, key.into(), null.into());
+                }
+            }
+        }
+    }
+    let start_millis = request
+        .start_time
+        .unwrap_or_else(SystemTime::now)
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_millis() as f64);
+    let start_time = v8::Number::new(scope, start_millis);
+    if let Some(key) = v8::String::new(scope, "startTime") {
+        let _ = obj.set(scope, key.into(), start_time.into());
+    }
+
+    let headers = v8::Object::new(scope);
+    for (name, _) in &request.headers {
+        if let Some(value) = request.header(name) {
+            set_string(scope, headers, name, &value);
+        }
+    }
+    if let Some(key) = v8::String::new(scope, "headers") {
+        let _ = obj.set(scope, key.into(), headers.into());
+    }
+
+    for (name, header) in [("ifMatch", "if-match"), ("ifNoneMatch", "if-none-match")] {
+        let value: v8::Local<v8::Value> = match request.header(header) {
+            Some(value) => {
+                let tags: Vec<v8::Local<v8::Value>> = parse_entity_tags(&value)
+                    .into_iter()
+                    .filter_map(|tag| v8::String::new(scope, tag).map(Into::into))
+                    .collect();
+                v8::Array::new_with_elements(scope, &tags).into()
+            }
+            None => v8::null(scope).into(),
+        };
+        if let Some(key) = v8::String::new(scope, name) {
+            let _ = obj.set(scope, key.into(), value);
+        }
+    }
+    for (name, header) in [
+        ("ifModifiedSince", "if-modified-since"),
+        ("ifUnmodifiedSince", "if-unmodified-since"),
+    ] {
+        let value: v8::Local<v8::Value> =
+            match request.header(header).as_deref().and_then(http_date_millis) {
+                Some(millis) => v8::Number::new(scope, millis).into(),
+                None => v8::null(scope).into(),
+            };
+        if let Some(key) = v8::String::new(scope, name) {
+            let _ = obj.set(scope, key.into(), value);
+        }
+    }
+
+    let header_fn = v8::Function::builder(
+        |scope: &mut v8::HandleScope,
+         args: v8::FunctionCallbackArguments,
+         mut rv: v8::ReturnValue| {
+            let Ok(headers) = v8::Local::<v8::Object>::try_from(args.data()) else {
+                return;
+            };
+            let name = args.get(0).to_rust_string_lossy(scope).to_ascii_lowercase();
+            let value = v8::String::new(scope, &name)
+                .and_then(|key| headers.get(scope, key.into()))
+                .filter(|v| !v.is_undefined());
+            match value {
+                Some(value) => rv.set(value),
+                None => rv.set_null(),
+            }
+        },
+    )
+    .data(headers.into())
+    .build(scope)
+    .expect("Failed to create $request.header function");
+
+    // the Accept header travels as the function's data, so no Rust state outlives the render
+    let accept: v8::Local<v8::Value> = match request.header("accept") {
+        Some(accept) => v8::String::new(scope, &accept)
+            .map(Into::into)
+            .unwrap_or_else(|| v8::undefined(scope).into()),
+        None => v8::undefined(scope).into(),
+    };
+    let accepts_fn = v8::Function::builder(
+        |scope: &mut v8::HandleScope,
+         args: v8::FunctionCallbackArguments,
+         mut rv: v8::ReturnValue| {
+            let accept = args.data();
+            let accept = (!accept.is_undefined()).then(|| accept.to_rust_string_lossy(scope));
+
+            let list = args.get(0);
+            let types: Vec<String> = match v8::Local::<v8::Array>::try_from(list) {
+                Ok(array) => (0..array.length())
+                    .filter_map(|i| array.get_index(scope, i))
+                    .map(|v| v.to_rust_string_lossy(scope))
+                    .collect(),
+                Err(_) => (0..args.length())
+                    .map(|i| args.get(i).to_rust_string_lossy(scope))
+                    .collect(),
+            };
+            let refs: Vec<&str> = types.iter().map(String::as_str).collect();
+
+            match best_match(accept.as_deref(), &refs).and_then(|t| v8::String::new(scope, t)) {
+                Some(choice) => rv.set(choice.into()),
+                None => rv.set_null(),
+            }
+        },
+    )
+    .data(accept)
+    .build(scope)
+    .expect("Failed to create $request.accepts function");
+
+    let preconditions_fn = v8::Function::builder(
+        |scope: &mut v8::HandleScope,
+         args: v8::FunctionCallbackArguments,
+         mut rv: v8::ReturnValue| {
+            let Ok(headers) = v8::Local::<v8::Object>::try_from(args.data()) else {
+                return;
+            };
+            let mut header = |name: &str| {
+                v8::String::new(scope, name)
+                    .and_then(|key| headers.get(scope, key.into()))
+                    .filter(|v| v.is_string())
+                    .map(|v| v.to_rust_string_lossy(scope))
+            };
+            let (if_match, if_none_match, if_unmodified_since) = (
+                header("if-match"),
+                header("if-none-match"),
+                header("if-unmodified-since"),
+            );
+            let preconditions = Preconditions {
+                if_match: if_match.as_deref(),
+                if_none_match: if_none_match.as_deref(),
+                if_unmodified_since: if_unmodified_since.as_deref(),
+            };
+
+            let etag = args.get(0);
+            let etag = (!etag.is_null_or_undefined()).then(|| etag.to_rust_string_lossy(scope));
+            // a Date converts to its milliseconds, like a number
+            let modified = args.get(1);
+            let modified = (!modified.is_null_or_undefined())
+                .then(|| modified.number_value(scope))
+                .flatten()
+                .filter(|ms| ms.is_finite() && *ms >= 0.0)
+                .map(|ms| UNIX_EPOCH + Duration::from_millis(ms as u64));
+            rv.set_bool(preconditions.hold(etag.as_deref(), modified));
+        },
+    )
+    .data(headers.into())
+    .build(scope)
+    .expect("Failed to create $request.preconditionsMet function");
+
+    for (name, f) in [
+        ("header", header_fn),
+        ("accepts", accepts_fn),
+        ("preconditionsMet", preconditions_fn),
+    ] {
+        if let Some(key) = v8::String::new(scope, name) {
+            let _ = obj.set(scope, key.into(), f.into());
+        }
+    }
+    if let Some(key) = v8::String::new(scope, "$request") {
+        let _ = global.set(scope, key.into(), obj.into());
+    }
+
+    // $query: decoded query parameters; a repeated key collects its values in an array
+    let params = v8::Object::new(scope);
+    for (name, value) in crate::query::parse_query(&request.query) {
+        let (Some(key), Some(value)) = (
+            v8::String::new(scope, &name),
+            v8::String::new(scope, &value),
+        ) else {
+            continue;
+        };
+        let existing = params
+            .has_own_property(scope, key.into())
+            .filter(|own| *own)
+            .and_then(|_| params.get(scope, key.into()));
+        let value: v8::Local<v8::Value> = match existing {
+            Some(existing) => match v8::Local::<v8::Array>::try_from(existing) {
+                Ok(array) => {
+                    let _ = array.set_index(scope, array.length(), value.into());
+                    continue;
+                }
+                Err(_) => v8::Array::new_with_elements(scope, &[existing, value.into()]).into(),
+            },
+            None => value.into(),
+        };
+        // define rather than assign, so a `__proto__` parameter stays a plain property
+        let _ = params.create_data_property(scope, key.into(), value);
+    }
+    if let Some(key) = v8::String::new(scope, "$query") {
+        let _ = global.set(scope, key.into(), params.into());
+    }
+
+    let ctx = v8::Object::new(scope);
+    if let Some(key) = v8::String::new(scope, "$ctx") {
+        let attributes = v8::PropertyAttribute::READ_ONLY | v8::PropertyAttribute::DONT_DELETE;
+        let _ = global.define_own_property(scope, key.into(), ctx.into(), attributes);
+    }
+}
+
+fn set_string(scope: &mut v8::HandleScope, obj: v8::Local<v8::Object>, name: &str, value: &str) {
+    if let (Some(key), Some(value)) = (v8::String::new(scope, name), v8::String::new(scope, value))
+    {
+        let _ = obj.set(scope, key.into(), value.into());
+    }
+}
@@ -0,0 +1,144 @@
+//! `$response`: lets a template decide the status and content type of its response.
+
+use crate::stream::Chunk;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use tokio::sync::mpsc;
+
+/// Response settings made by the template during one render, plus the output buffer
+/// they apply to. Owned by the executor and reset before every render.
+pub(crate) struct ResponseState {
+    pub output: Rc<RefCell<String>>,
+    pub status: Cell<Option<u16>>,
+    pub content_type: RefCell<Option<String>>,
+    /// Where `flush()` sends output early; `None` when the render is only used whole.
+    pub stream: RefCell<Option<mpsc::UnboundedSender<Chunk>>>,
+    /// Whether `flush()` already sent the status and headers.
+    pub flushed: Cell<bool>,
+    /// Bytes of output `flush()` sent so far.
+    pub flushed_bytes: Cell<usize>,
+    /// Template the output is to be wrapped in, set by `layout()`.
+    pub layout: RefCell<Option<String>>,
+    /// How many layouts already wrapped the output.
+    pub layouts_applied: Cell<usize>,
+    /// Named sections for the layout, by `section()`.
+    pub sections: RefCell<HashMap<String, String>>,
+    /// Sections being captured: name and the output length where each began.
+    pub open_sections: RefCell<Vec<(String, usize)>>,
+}
+
+impl ResponseState {
+    pub fn new(output: Rc<RefCell<String>>) -> Self {
+        Self {
+            output,
+            status: Cell::new(None),
+            content_type: RefCell::new(None),
+            stream: RefCell::new(None),
+            flushed: Cell::new(false),
+            flushed_bytes: Cell::new(0),
+            layout: RefCell::new(None),
+            layouts_applied: Cell::new(0),
+            sections: RefCell::new(HashMap::new()),
+            open_sections: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn reset(&self, stream: Option<mpsc::UnboundedSender<Chunk>>) {
+        self.status.set(None);
+        self.content_type.replace(None);
+        self.stream.replace(stream);
+        self.flushed.set(false);
+        self.flushed_bytes.set(0);
+        self.layout.replace(None);
+        self.layouts_applied.set(0);
+        self.sections.borrow_mut().clear();
+        self.open_sections.borrow_mut().clear();
+    }
+}
+
+/// Install `$response` into the current context, with
+/// `json(value, status?)`: make `value` serialized with `JSON.stringify` the whole body,
+/// sent as `application/json` with `status` (200 by default). Throws if anything was
+/// echoed or flushed before, since that output would otherwise be lost or corrupt the JSON.
+pub(crate) fn install(scope: &mut v8::ContextScope<v8::HandleScope>, state: &Rc<ResponseState>) {
+    let global = scope.get_current_context().global(scope);
+    let obj = v8::Object::new(scope);
+
+    // SAFETY: the state is owned by the executor, which outlives every context it renders in
+    let state_ptr = Rc::as_ptr(state) as *mut std::ffi::c_void;
+    let json_fn = v8::Function::builder(
+        |scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _rv: v8::ReturnValue| {
+            let Ok(external) = v8::Local::<v8::External>::try_from(args.data()) else {
+                return;
+            };
+            let state = unsafe { &*(external.value() as *const ResponseState) };
+
+            let status = args.get(1);
+            let status = if status.is_undefined() {
+                Some(200)
+            } else if status.is_number() {
+                status
+                    .integer_value(scope)
+                    .and_then(|s| u16::try_from(s).ok())
+                    .filter(|s| (100..=599).contains(s))
+            } else {
+                None
+            };
+            let Some(status) = status else {
+                throw(
+                    scope,
+                    "$response.json(value, status): status must be an HTTP status code (100-599)",
+                    true,
+                );
+                return;
+            };
+            if state.flushed.get() || !state.output.borrow().is_empty() {
+                throw(
+                    scope,
+                    "$response.json(): output was already echoed; the JSON must be the whole body",
+                    false,
+                );
+                return;
+            }
+
+            let value = args.get(0);
+            let json = if value.is_undefined() || value.is_function() || value.is_symbol() {
+                "null".to_string()
+            } else {
+                // None means JSON.stringify threw; the exception is already pending
+                let Some(json) = v8::json::stringify(scope, value) else {
+                    return;
+                };
+                json.to_rust_string_lossy(scope)
+            };
+            state.output.borrow_mut().push_str(&json);
+            state.status.set(Some(status));
+            state
+                .content_type
+                .replace(Some("application/json".to_string()));
+        },
+    )
+    .data(v8::External::new(scope, state_ptr).into())
+    .build(scope)
+    .expect("Failed to create $response.json function");
+
+    if let Some(key) = v8::String::new(scope, "json") {
+        let _ = obj.set(scope, key.into(), json_fn.into());
+    }
+    if let Some(key) = v8::String::new(scope, "$response") {
+        let _ = global.set(scope, key.into(), obj.into());
+    }
+}
+
+pub(crate) fn throw(scope: &mut v8::HandleScope, message: &str, type_error: bool) {
+    let Some(message) = v8::String::new(scope, message) else {
+        return;
+    };
+    let exception = if type_error {
+        v8::Exception::type_error(scope, message)
+    } else {
+        v8::Exception::error(scope, message)
+    };
+    scope.throw_exception(exception);
+}
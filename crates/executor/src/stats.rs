@@ -0,0 +1,119 @@
+//! Per-worker runtime counters shared between an executor thread and the engine.
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// What one render did, measured by its executor around the template's blocks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderMetrics {
+    /// Blocks rendered, HTML ones included; none when the render was cancelled before
+    /// it started.
+    pub blocks: usize,
+    /// Time spent compiling the template's code blocks, or loading them from the code cache.
+    pub compile_time: Duration,
+    /// Time spent running them, including the files they include and the promises they await.
+    pub run_time: Duration,
+    /// Bytes of output produced, flushed ones included.
+    pub output_bytes: usize,
+}
+
+/// Counters updated by an executor as it serves renders. Reads are lock-free and may be
+/// slightly stale; heap figures are refreshed after bootstrap and after every render.
+#[derive(Debug, Default)]
+pub struct WorkerStats {
+    ready: AtomicBool,
+    requests_served: AtomicU64,
+    in_flight: AtomicUsize,
+    heap_used: AtomicUsize,
+    heap_total: AtomicUsize,
+    heap_limit: AtomicUsize,
+    scripts_compiled: AtomicU64,
+    code_cache_hits: AtomicU64,
+    blocks_executed: AtomicU64,
+    compile_micros: AtomicU64,
+    run_micros: AtomicU64,
+    output_bytes: AtomicU64,
+}
+
+/// A point-in-time copy of [`WorkerStats`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WorkerSnapshot {
+    /// Whether the executor has finished setting up its bootstrap context.
+    pub ready: bool,
+    pub requests_served: u64,
+    pub in_flight: usize,
+    pub heap_used: usize,
+    pub heap_total: usize,
+    pub heap_limit: usize,
+    /// Template scripts compiled from source.
+    pub scripts_compiled: u64,
+    /// Template scripts compiled from the executor's code cache instead.
+    pub code_cache_hits: u64,
+    /// Sums of the [`RenderMetrics`] of every render served so far.
+    pub blocks_executed: u64,
+    pub compile_micros: u64,
+    pub run_micros: u64,
+    pub output_bytes: u64,
+}
+
+impl WorkerStats {
+    pub fn snapshot(&self) -> WorkerSnapshot {
+        WorkerSnapshot {
+            ready: self.ready.load(Ordering::Acquire),
+            requests_served: self.requests_served.load(Ordering::Relaxed),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            heap_used: self.heap_used.load(Ordering::Relaxed),
+            heap_total: self.heap_total.load(Ordering::Relaxed),
+            heap_limit: self.heap_limit.load(Ordering::Relaxed),
+            scripts_compiled: self.scripts_compiled.load(Ordering::Relaxed),
+            code_cache_hits: self.code_cache_hits.load(Ordering::Relaxed),
+            blocks_executed: self.blocks_executed.load(Ordering::Relaxed),
+            compile_micros: self.compile_micros.load(Ordering::Relaxed),
+            run_micros: self.run_micros.load(Ordering::Relaxed),
+            output_bytes: self.output_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn mark_ready(&self) {
+        self.ready.store(true, Ordering::Release);
+    }
+
+    pub(crate) fn begin_request(&self) {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn end_request(&self) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+        self.requests_served.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_compile(&self, cache_hit: bool) {
+        let counter = if cache_hit {
+            &self.code_cache_hits
+        } else {
+            &self.scripts_compiled
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_render(&self, metrics: &RenderMetrics) {
+        self.blocks_executed
+            .fetch_add(metrics.blocks as u64, Ordering::Relaxed);
+        self.compile_micros
+            .fetch_add(metrics.compile_time.as_micros() as u64, Ordering::Relaxed);
+        self.run_micros
+            .fetch_add(metrics.run_time.as_micros() as u64, Ordering::Relaxed);
+        self.output_bytes
+            .fetch_add(metrics.output_bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_heap(&self, isolate: &mut v8::Isolate) {
+        let mut heap = v8::HeapStatistics::default();
+        isolate.get_heap_statistics(&mut heap);
+        self.heap_used
+            .store(heap.used_heap_size(), Ordering::Relaxed);
+        self.heap_total
+            .store(heap.total_heap_size(), Ordering::Relaxed);
+        self.heap_limit
+            .store(heap.heap_size_limit(), Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,67 @@
+//! `flush()`: send what a template echoed so far ahead of the rest of its render.
+
+use crate::response::ResponseState;
+use std::rc::Rc;
+
+/// Output a streaming render flushed before it finished; the final
+/// [`RenderOutput`](crate::RenderOutput) only holds what was echoed after the last flush.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Chunk {
+    pub body: String,
+    /// Status set by the template when the chunk was flushed. The first chunk's status
+    /// is the response's, since nothing can change it once bytes were sent.
+    pub status: Option<u16>,
+    /// Content type set by the template when the chunk was flushed.
+    pub content_type: Option<String>,
+}
+
+/// Install `flush()` into the current context: hand the output echoed so far to the
+/// render's stream and empty the buffer. Does nothing when the render has no stream, so
+/// a template that flushes still works where its output is only used whole, nor while
+/// the output awaits a layout or a section.
+pub(crate) fn install(scope: &mut v8::ContextScope<v8::HandleScope>, state: &Rc<ResponseState>) {
+    let global = scope.get_current_context().global(scope);
+
+    // SAFETY: the state is owned by the executor, which outlives every context it renders in
+    let state_ptr = Rc::as_ptr(state) as *mut std::ffi::c_void;
+    let flush_fn = v8::Function::builder(
+        |_scope: &mut v8::HandleScope,
+         args: v8::FunctionCallbackArguments,
+         _rv: v8::ReturnValue| {
+            let Ok(external) = v8::Local::<v8::External>::try_from(args.data()) else {
+                return;
+            };
+            let state = unsafe { &*(external.value() as *const ResponseState) };
+            let stream = state.stream.borrow();
+            let Some(stream) = stream.as_ref() else {
+                return;
+            };
+            // the output is still to be wrapped or cut into a section
+            if state.layout.borrow().is_some() || !state.open_sections.borrow().is_empty() {
+                return;
+            }
+            let body = std::mem::take(&mut *state.output.borrow_mut());
+            // the first flush sends the headers even without a body; later empty ones are moot
+            if body.is_empty() && state.flushed.get() {
+                return;
+            }
+            state.flushed.set(true);
+            state
+                .flushed_bytes
+                .set(state.flushed_bytes.get() + body.len());
+            // a closed stream means the client is gone; the watchdog then stops the render
+            let _ = stream.send(Chunk {
+                body,
+                status: state.status.get(),
+                content_type: state.content_type.borrow().clone(),
+            });
+        },
+    )
+    .data(v8::External::new(scope, state_ptr).into())
+    .build(scope)
+    .expect("Failed to create flush function");
+
+    if let Some(key) = v8::String::new(scope, "flush") {
+        let _ = global.set(scope, key.into(), flush_fn.into());
+    }
+}
@@ -1,8 +1,55 @@
 //! Shared V8 utilities to reduce boilerplate and keep hot paths fast.
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
-use jhp_parser::{CodeBlock, CodeBlockContent};
+use crate::abort::{self, Abort};
+use crate::code_cache;
+use crate::{ErrorOutput, ExecutorConfig, RenderMetrics, ScriptError};
+use jhp_parser::{CodeBlock, CodeBlockContent, template_to_js};
+
+/// The template being rendered on an isolate, kept in an isolate slot for the duration
+/// of the render.
+struct RenderResource(String);
+
+/// Template lines of the scripts generated from templates during a render, by resource
+/// name, so errors point into the templates rather than the generated code.
+#[derive(Default)]
+struct LineMaps(HashMap<String, Vec<usize>>);
+
+/// Record `resource_name` as the template being rendered on `isolate`.
+pub(crate) fn set_render_resource(isolate: &mut v8::Isolate, resource_name: &str) {
+    isolate.set_slot(RenderResource(resource_name.to_string()));
+    isolate.set_slot(LineMaps::default());
+}
+
+/// Record that the script named `resource_name` was generated from a template, with the
+/// template line of each of its lines (see [`jhp_parser::ScriptJs::lines`]). Errors in it
+/// are then reported at template lines, until the render ends.
+pub fn set_line_map(isolate: &mut v8::Isolate, resource_name: &str, lines: Vec<usize>) {
+    match isolate.get_slot_mut::<LineMaps>() {
+        Some(maps) => {
+            maps.0.insert(resource_name.to_string(), lines);
+        }
+        None => {
+            let maps = HashMap::from([(resource_name.to_string(), lines)]);
+            isolate.set_slot(LineMaps(maps));
+        }
+    }
+}
+
+/// Name of the code currently running: the innermost script on the JS stack (e.g. an
+/// included file), or the template being rendered when no script is running.
+pub fn current_resource(scope: &mut v8::HandleScope) -> String {
+    v8::StackTrace::current_stack_trace(scope, 1)
+        .and_then(|trace| trace.get_frame(scope, 0))
+        .and_then(|frame| frame.get_script_name(scope))
+        .map(|name| name.to_rust_string_lossy(scope))
+        .filter(|name| !name.is_empty())
+        .or_else(|| scope.get_slot::<RenderResource>().map(|r| r.0.clone()))
+        .unwrap_or_default()
+}
 
 /// Compile and run JavaScript `code` in the current context of the provided HandleScope.
 /// `resource_name` is used for stack traces and debugging.
@@ -65,70 +112,76 @@ pub fn compile_script<'s>(
         .ok_or_else(|| "Failed to compile a script block".to_string())
 }
 
-/// Execute parsed JHP blocks one-by-one with per-block ScriptOrigin for accurate
-/// line/column reporting. If an error occurs, append a formatted stack trace to the
-/// provided output buffer and return Err.
+/// Why the blocks of a render stopped before the last one.
+#[derive(Debug)]
+pub enum Stop {
+    /// A block threw (or an awaited promise rejected).
+    Error(ScriptError),
+    /// A block called `abort(status, message?)`.
+    Abort(Abort),
+}
+
+impl From<ScriptError> for Stop {
+    fn from(error: ScriptError) -> Self {
+        Stop::Error(error)
+    }
+}
+
+/// Execute parsed JHP blocks as one script, generated by [`template_to_js`] exactly as
+/// for an included template, so statements can span blocks. If an error occurs,
+/// execution stops and, depending on `config.error_output`, a formatted stack trace is
+/// appended to the output buffer after whatever was echoed so far; the error is returned
+/// either way. An abort stops execution too, but leaves the output alone. A script using
+/// `await` is settled before this returns.
+///
+/// `cancelled` is checked first; once it returns true nothing runs, with nothing added
+/// to the output.
 pub fn run_jhp_blocks_with_origin<'h>(
     hs: &mut v8::HandleScope<'h>,
     blocks: Vec<Box<CodeBlock>>,
     resource_name: &str,
     output_buffer: Rc<RefCell<String>>,
-) -> Result<(), String> {
-    for block in blocks {
-        match *block {
-            CodeBlock::Html(CodeBlockContent {
-                content, lineno, ..
-            }) => {
-                let _lineno = lineno;
-                output_buffer.borrow_mut().push_str(&content);
-            }
-            CodeBlock::Expression(CodeBlockContent {
-                content,
-                lineno,
-                colno,
-                ..
-            }) => {
-                let src = format!("echo(String({}));", content.trim());
-                // Column offset is the original column where the first expr char appears,
-                // but the generated source adds "echo(String(" before it. V8's reported column
-                // is relative to generated code; by providing the original column as the origin's
-                // start column, V8 (start_column + generated_column) will align. To make the final
-                // column equal to the original JHP column, we subtract the generated prefix length
-                // from the origin's column offset so that when V8 adds the generated position we end up at colno.
-                let generated_prefix = 12; // len("echo(String(")
-                let col_off = (colno as i32 - 1).saturating_sub(generated_prefix as i32);
-                if let Err(e) = compile_and_run_current_with_origin(
-                    hs,
-                    &src,
-                    resource_name,
-                    lineno as i32 - 1,
-                    col_off,
-                ) {
-                    push_error(&output_buffer, &e);
-                    return Err(e);
-                }
-            }
-            CodeBlock::Javascript(CodeBlockContent {
-                content,
-                lineno,
-                colno,
-                ..
-            }) => {
-                // Adjust origin starting line to the block's starting line (1-based)
-                if let Err(e) = compile_and_run_current_with_origin(
-                    hs,
-                    &content,
-                    resource_name,
-                    lineno as i32 - 1,
-                    colno as i32 - 1,
-                ) {
-                    push_error(&output_buffer, &e);
-                    return Err(e);
-                }
+    config: &ExecutorConfig,
+    cancelled: &dyn Fn() -> bool,
+    metrics: &mut RenderMetrics,
+) -> Result<(), Stop> {
+    if cancelled() {
+        return Ok(());
+    }
+    metrics.blocks += blocks.len();
+    // HTML alone goes straight to the output, which is what its generated `echo` would do
+    if blocks
+        .iter()
+        .all(|b| matches!(b.as_ref(), CodeBlock::Html(_)))
+    {
+        let mut output = output_buffer.borrow_mut();
+        for block in blocks {
+            if let CodeBlock::Html(CodeBlockContent { content, .. }) = *block {
+                output.push_str(&content);
             }
         }
+        return Ok(());
     }
-    Ok(())
+
+    let js = template_to_js(blocks, config.expression_output);
+    set_line_map(hs, resource_name, js.lines);
+    let started = Instant::now();
+    let compiled_before = metrics.compile_time;
+    let result = run_block(
+        hs,
+        &js.code,
+        resource_name,
+        (0, 0),
+        js.is_async,
+        &mut metrics.compile_time,
+    );
+    metrics.run_time += started
+        .elapsed()
+        .saturating_sub(metrics.compile_time - compiled_before);
+    if let Err(e) = &result {
+        report_error(&output_buffer, e, config);
+    }
+    result
 }
 
 /// Compile and run in current context with specific origin line/column offsets.
@@ -139,13 +192,34 @@ pub fn compile_and_run_current_with_origin<'h>(
     line_offset: i32,
     column_offset: i32,
 ) -> Result<(), String> {
+    let origin = (line_offset, column_offset);
+    let mut compile_time = Duration::ZERO;
+    let result = run_block(hs, code, resource_name, origin, false, &mut compile_time);
+    result.map_err(|stop| match stop {
+        Stop::Error(e) => e.to_string(),
+        Stop::Abort(a) => format!("aborted with status {}", a.status),
+    })
+}
+
+/// Compile and run `code` with the given origin offsets. With `settle`, the script's
+/// completion value is a promise (an async wrapper); microtasks are pumped until it
+/// settles and a rejection is reported like a thrown error. The time spent compiling is
+/// added to `compile_time`.
+fn run_block(
+    hs: &mut v8::HandleScope,
+    code: &str,
+    resource_name: &str,
+    (line_offset, column_offset): (i32, i32),
+    settle: bool,
+    compile_time: &mut Duration,
+) -> Result<(), Stop> {
     let tc = &mut v8::TryCatch::new(hs);
     let context = tc.get_current_context();
     let mut cscope = v8::ContextScope::new(tc, context);
-    let source =
-        v8::String::new(&mut cscope, code).ok_or_else(|| "Failed to create source".to_string())?;
+    let source = v8::String::new(&mut cscope, code)
+        .ok_or_else(|| ScriptError::new(resource_name, 0, "Failed to create source"))?;
     let name = v8::String::new(&mut cscope, resource_name)
-        .ok_or_else(|| "Failed to create resource name".to_string())?;
+        .ok_or_else(|| ScriptError::new(resource_name, 0, "Failed to create resource name"))?;
     let origin = v8::ScriptOrigin::new(
         &mut cscope,
         name.into(),
@@ -160,62 +234,174 @@ pub fn compile_and_run_current_with_origin<'h>(
         None,
     );
     let mut had_error = false;
-    if let Some(script) = v8::Script::compile(&mut cscope, source, Some(&origin)) {
-        if script.run(&mut cscope).is_none() {
-            had_error = true;
+    let mut completion = None;
+    let compiling = Instant::now();
+    let script = code_cache::compile(&mut cscope, code, source, &origin);
+    *compile_time += compiling.elapsed();
+    if let Some(script) = script {
+        match script.run(&mut cscope) {
+            Some(value) => completion = Some(v8::Global::new(&mut cscope, value)),
+            None => had_error = true,
         }
     } else {
         had_error = true;
     }
     drop(cscope); // release borrow before inspecting tc
     if had_error {
-        Err(format_v8_exception(tc, resource_name))
-    } else {
-        Ok(())
+        if let Some(abort) = tc.exception().and_then(|e| abort::recognize(tc, e)) {
+            return Err(Stop::Abort(abort));
+        }
+        return Err(format_v8_exception(tc, resource_name).into());
+    }
+
+    let Some(promise) = completion
+        .filter(|_| settle)
+        .map(|value| v8::Local::new(tc, value))
+        .and_then(|value| v8::Local::<v8::Promise>::try_from(value).ok())
+    else {
+        return Ok(());
+    };
+    tc.perform_microtask_checkpoint();
+    match promise.state() {
+        v8::PromiseState::Fulfilled => Ok(()),
+        v8::PromiseState::Rejected => {
+            let reason = promise.result(tc);
+            match abort::recognize(tc, reason) {
+                Some(abort) => Err(Stop::Abort(abort)),
+                None => Err(format_rejection(tc, reason, resource_name).into()),
+            }
+        }
+        v8::PromiseState::Pending => Err(ScriptError::new(
+            resource_name,
+            template_line(tc, resource_name, (line_offset + 1).max(0) as usize),
+            "Error: awaited promise never settled",
+        )
+        .into()),
     }
 }
 
-fn push_error(buffer: &Rc<RefCell<String>>, err: &str) {
+fn report_error(buffer: &Rc<RefCell<String>>, stop: &Stop, config: &ExecutorConfig) {
+    let Stop::Error(err) = stop else {
+        return;
+    };
+    match config.error_output {
+        ErrorOutput::Append => push_error(buffer, err),
+        ErrorOutput::Truncate => config.error_hook.report(err),
+    }
+}
+
+fn push_error(buffer: &Rc<RefCell<String>>, err: &ScriptError) {
     let msg = format!("\n<!-- ERROR -->\n{}\n", err);
     buffer.borrow_mut().push_str(&msg);
 }
 
-fn format_v8_exception(scope: &mut v8::TryCatch<v8::HandleScope>, fallback_name: &str) -> String {
-    let exception_str = scope
-        .exception()
-        .and_then(|e| e.to_string(scope.as_mut()))
-        .map(|s| s.to_rust_string_lossy(scope.as_mut()))
-        .unwrap_or_else(|| "Uncaught exception".to_string());
-
-    let (resource_name, line, column) = if let Some(msg) = scope.message() {
-        let name = msg
-            .get_script_resource_name(scope.as_mut())
-            .and_then(|v| v.to_string(scope.as_mut()))
-            .map(|s| s.to_rust_string_lossy(scope.as_mut()))
-            .unwrap_or_else(|| fallback_name.to_string());
-        let line = msg.get_line_number(scope.as_mut()).unwrap_or(0);
-        let column = msg.get_start_column();
-        (name, line, column)
-    } else {
-        (fallback_name.to_string(), 0, 0)
-    };
-
+fn format_v8_exception(
+    scope: &mut v8::TryCatch<v8::HandleScope>,
+    fallback_name: &str,
+) -> ScriptError {
+    let exception = scope.exception();
+    let message = scope.message();
     let stack = scope
         .stack_trace()
         .and_then(|v| v8::Local::<v8::String>::try_from(v).ok())
         .map(|s| s.to_rust_string_lossy(scope.as_mut()))
         .unwrap_or_default();
+    format_exception(scope, exception, message, &stack, fallback_name)
+}
 
-    let header = format!("{}:{}:{}", resource_name, line, column);
-    let stack_trim = stack.trim();
-    if !stack_trim.is_empty() {
-        // If the stack already starts with the exception summary, don't duplicate it.
-        if stack_trim.starts_with(&exception_str) {
-            format!("{}\n{}", header, stack_trim)
-        } else {
-            format!("{}\n{}\n{}", header, exception_str, stack_trim)
-        }
-    } else {
-        format!("{}\n{}", header, exception_str)
+/// Format the reason of a rejected promise like a thrown exception. V8 rebuilds the
+/// message (and so the position) from the stack captured when the error was created.
+fn format_rejection(
+    scope: &mut v8::HandleScope,
+    reason: v8::Local<v8::Value>,
+    fallback_name: &str,
+) -> ScriptError {
+    let message = v8::Exception::create_message(scope, reason);
+    let stack = reason
+        .to_object(scope)
+        .zip(v8::String::new(scope, "stack"))
+        .and_then(|(obj, key)| obj.get(scope, key.into()))
+        .filter(|v| v.is_string())
+        .map(|v| v.to_rust_string_lossy(scope))
+        .unwrap_or_default();
+    format_exception(scope, Some(reason), Some(message), &stack, fallback_name)
+}
+
+/// Gather the details of an exception into a [`ScriptError`].
+fn format_exception(
+    scope: &mut v8::HandleScope,
+    exception: Option<v8::Local<v8::Value>>,
+    message: Option<v8::Local<v8::Message>>,
+    stack: &str,
+    fallback_name: &str,
+) -> ScriptError {
+    let mut error = ScriptError {
+        message: exception
+            .and_then(|e| e.to_string(scope))
+            .map(|s| s.to_rust_string_lossy(scope))
+            .unwrap_or_else(|| "Uncaught exception".to_string()),
+        stack: stack.trim().to_string(),
+        ..ScriptError::new(fallback_name, 0, "")
+    };
+    let Some(msg) = message else {
+        error.column = 0;
+        return error;
+    };
+    if let Some(name) = msg
+        .get_script_resource_name(scope)
+        .and_then(|v| v.to_string(scope))
+        .map(|s| s.to_rust_string_lossy(scope))
+    {
+        error.resource = name;
+    }
+    error.line = msg.get_line_number(scope).unwrap_or(0);
+    // V8 columns are 0-based; report them 1-based like the line number.
+    error.column = msg.get_start_column() + 1;
+    error.end_column = msg.get_end_column() + 1;
+    error.source_line = msg
+        .get_source_line(scope)
+        .map(|s| s.to_rust_string_lossy(scope).trim_end().to_string())
+        .filter(|s| !s.trim_start().is_empty());
+    error.line = template_line(scope, &error.resource, error.line);
+    error.stack = error
+        .stack
+        .lines()
+        .map(|line| map_stack_frame(scope, line))
+        .collect::<Vec<_>>()
+        .join("\n");
+    error
+}
+
+/// The template line of `line` in the script named `resource`, or `line` itself when
+/// the script wasn't generated from a template.
+fn template_line(isolate: &v8::Isolate, resource: &str, line: usize) -> usize {
+    isolate
+        .get_slot::<LineMaps>()
+        .and_then(|maps| maps.0.get(resource))
+        .and_then(|lines| lines.get(line.checked_sub(1)?).copied())
+        .unwrap_or(line)
+}
+
+/// A line of a stack trace with its `resource:line:column` position, if any, mapped
+/// to the template line.
+fn map_stack_frame(isolate: &v8::Isolate, frame: &str) -> String {
+    let position = frame.trim_end_matches(')');
+    let closing = &frame[position.len()..];
+    let Some((rest, column)) = position.rsplit_once(':') else {
+        return frame.to_string();
+    };
+    let Some((location, line)) = rest.rsplit_once(':') else {
+        return frame.to_string();
+    };
+    let resource = &location[location.rfind(['(', ' ']).map_or(0, |i| i + 1)..];
+    match line.parse::<usize>() {
+        Ok(line) if column.parse::<usize>().is_ok() => format!(
+            "{}:{}:{}{}",
+            location,
+            template_line(isolate, resource, line),
+            column,
+            closing
+        ),
+        _ => frame.to_string(),
     }
 }
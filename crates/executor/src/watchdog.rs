@@ -0,0 +1,94 @@
+//! Stop a render whose caller gave up, even in the middle of a block that never returns.
+
+use crate::RenderOutput;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// How often the caller of a running render is checked on.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A thread per executor, watching the reply channel of the render in progress. Once the
+/// caller drops its end (e.g. a disconnected client or an elapsed `request_timeout`),
+/// the isolate's JS is terminated, so a busy loop can't hold the worker forever.
+pub(crate) struct Watchdog {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    slot: Mutex<Slot>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct Slot {
+    /// Where the output of the render in progress goes, if one is.
+    reply: Option<oneshot::Sender<RenderOutput>>,
+    /// The render in progress was already terminated.
+    terminated: bool,
+    shutdown: bool,
+}
+
+impl Watchdog {
+    pub(crate) fn new(id: usize, isolate: v8::IsolateHandle) -> Self {
+        let shared = Arc::new(Shared {
+            slot: Mutex::new(Slot::default()),
+            changed: Condvar::new(),
+        });
+        let watched = shared.clone();
+        thread::Builder::new()
+            .name(format!("jhp-watchdog-{}", id))
+            .spawn(move || watch(&watched, &isolate))
+            .expect("Failed to spawn the watchdog thread");
+        Self { shared }
+    }
+
+    /// Watch a render until [`Watchdog::finish`], which hands `reply` back.
+    pub(crate) fn start(&self, reply: oneshot::Sender<RenderOutput>) {
+        let mut slot = self.shared.slot.lock().unwrap();
+        slot.reply = Some(reply);
+        slot.terminated = false;
+        self.shared.changed.notify_one();
+    }
+
+    /// Whether the caller of the watched render went away.
+    pub(crate) fn cancelled(&self) -> bool {
+        let slot = self.shared.slot.lock().unwrap();
+        slot.reply.as_ref().is_some_and(|reply| reply.is_closed())
+    }
+
+    /// Stop watching and take back the render's reply channel. Once this returns, the
+    /// render can no longer be terminated, though a termination already requested may
+    /// still be pending on the isolate.
+    pub(crate) fn finish(&self) -> Option<oneshot::Sender<RenderOutput>> {
+        self.shared.slot.lock().unwrap().reply.take()
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.shared.slot.lock().unwrap().shutdown = true;
+        self.shared.changed.notify_one();
+    }
+}
+
+fn watch(shared: &Shared, isolate: &v8::IsolateHandle) {
+    let mut slot = shared.slot.lock().unwrap();
+    loop {
+        if slot.shutdown {
+            return;
+        }
+        if slot.reply.is_none() || slot.terminated {
+            slot = shared.changed.wait(slot).unwrap();
+            continue;
+        }
+        // terminated under the lock, so the render can't have been finished meanwhile
+        if slot.reply.as_ref().is_some_and(|reply| reply.is_closed()) {
+            isolate.terminate_execution();
+            slot.terminated = true;
+            continue;
+        }
+        slot = shared.changed.wait_timeout(slot, POLL_INTERVAL).unwrap().0;
+    }
+}
@@ -0,0 +1,46 @@
+use jhp_executor::query::{
+    decode_component, decode_uri_component, encode_component, encode_form_component, parse_query,
+};
+
+#[test]
+fn encodes_reserved_characters() {
+    assert_eq!(encode_component("hello world"), "hello%20world");
+    assert_eq!(
+        encode_component("a&b=c/d?e#f+g"),
+        "a%26b%3Dc%2Fd%3Fe%23f%2Bg"
+    );
+    assert_eq!(encode_component("-_.~AZaz09"), "-_.~AZaz09");
+    assert_eq!(encode_component("é"), "%C3%A9");
+}
+
+#[test]
+fn decodes_plus_as_space_and_keeps_malformed_escapes() {
+    assert_eq!(decode_component("hello+world%21"), "hello world!");
+    assert_eq!(decode_component("%C3%A9"), "é");
+    assert_eq!(decode_component("100%"), "100%");
+    assert_eq!(decode_component("%zz%2"), "%zz%2");
+    assert_eq!(decode_component("%+1"), "% 1");
+    assert_eq!(decode_component("%FF"), "\u{FFFD}");
+}
+
+#[test]
+fn form_encoding_differs_from_components_only_in_spaces() {
+    assert_eq!(encode_form_component("a b+c&d"), "a+b%2Bc%26d");
+    assert_eq!(decode_component("a+b%2Bc%26d"), "a b+c&d");
+    assert_eq!(decode_uri_component("a+b%20c%2B"), "a+b c+");
+    assert_eq!(decode_uri_component("%zz%"), "%zz%");
+}
+
+#[test]
+fn parses_query_pairs_in_order() {
+    assert_eq!(
+        parse_query("a=1&tag=x&tag=y+z&&flag&e=%3D"),
+        [
+            ("a".to_string(), "1".to_string()),
+            ("tag".to_string(), "x".to_string()),
+            ("tag".to_string(), "y z".to_string()),
+            ("flag".to_string(), String::new()),
+            ("e".to_string(), "=".to_string()),
+        ]
+    );
+}
@@ -0,0 +1,144 @@
+use jhp_executor::request::{
+    Preconditions, RequestInfo, best_match, parse_accept, parse_entity_tags,
+};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[test]
+fn prefers_higher_quality() {
+    let accept = Some("text/html,application/json;q=0.9");
+    assert_eq!(
+        best_match(accept, &["application/json", "text/html"]),
+        Some("text/html")
+    );
+}
+
+#[test]
+fn json_only_client_gets_json() {
+    let accept = Some("application/json");
+    assert_eq!(
+        best_match(accept, &["text/html", "application/json"]),
+        Some("application/json")
+    );
+    assert_eq!(best_match(accept, &["text/html"]), None);
+}
+
+#[test]
+fn wildcards_and_specificity() {
+    // text/html is excluded explicitly even though text/* would allow it
+    let accept = Some("text/*;q=0.5, text/html;q=0, */*;q=0.1");
+    assert_eq!(
+        best_match(accept, &["text/html", "image/png", "text/plain"]),
+        Some("text/plain")
+    );
+    assert_eq!(best_match(accept, &["text/html"]), None);
+    assert_eq!(best_match(accept, &["image/png"]), Some("image/png"));
+    assert_eq!(best_match(Some("*"), &["a/b"]), Some("a/b"));
+}
+
+#[test]
+fn ties_follow_header_then_caller_order() {
+    let accept = Some("application/json, text/html");
+    assert_eq!(
+        best_match(accept, &["text/html", "application/json"]),
+        Some("application/json")
+    );
+    assert_eq!(
+        best_match(Some("*/*"), &["text/html", "application/json"]),
+        Some("text/html")
+    );
+}
+
+#[test]
+fn missing_header_accepts_anything() {
+    assert_eq!(
+        best_match(None, &["text/plain", "text/html"]),
+        Some("text/plain")
+    );
+    assert_eq!(best_match(Some("  "), &["text/plain"]), Some("text/plain"));
+    assert_eq!(best_match(None, &[]), None);
+}
+
+#[test]
+fn parse_accept_skips_malformed_entries() {
+    let ranges = parse_accept("text/html;level=1;q=0.7, bogus, */html, image/png;q=2, TEXT/Plain");
+    let summary: Vec<_> = ranges
+        .iter()
+        .map(|r| (r.type_, r.subtype, r.quality))
+        .collect();
+    assert_eq!(summary, [("text", "html", 700), ("TEXT", "Plain", 1000)]);
+}
+
+#[test]
+fn request_info_joins_repeated_headers() {
+    let request = RequestInfo::new("GET", "/")
+        .add_header("Accept", "text/html;q=0.5")
+        .add_header("accept", "application/json");
+    assert_eq!(
+        request.header("ACCEPT").as_deref(),
+        Some("text/html;q=0.5, application/json")
+    );
+    assert_eq!(
+        request.accepts(&["text/html", "application/json"]),
+        Some("application/json")
+    );
+    assert_eq!(request.header("x-missing"), None);
+}
+
+#[test]
+fn entity_tags_keep_their_quotes_and_weakness() {
+    assert_eq!(
+        parse_entity_tags(r#""a", W/"b" ,"c,d", *"#),
+        [r#""a""#, r#"W/"b""#, r#""c,d""#, "*"]
+    );
+    // unquoted and unterminated tags are skipped, the rest kept
+    assert_eq!(parse_entity_tags(r#"a, "b" x, "c", "d"#), [r#""c""#]);
+    assert!(parse_entity_tags("").is_empty());
+}
+
+#[test]
+fn if_match_needs_a_strong_match() {
+    let if_match = |header| Preconditions {
+        if_match: Some(header),
+        ..Default::default()
+    };
+    assert!(if_match(r#""x", "v1""#).hold(Some("v1"), None));
+    assert!(if_match(r#""v1""#).hold(Some(r#""v1""#), None));
+    assert!(!if_match(r#""v2""#).hold(Some("v1"), None));
+    assert!(!if_match(r#"W/"v1""#).hold(Some("v1"), None));
+    assert!(if_match("*").hold(Some("v1"), None));
+    assert!(!if_match("*").hold(None, None));
+}
+
+#[test]
+fn if_unmodified_since_compares_whole_seconds() {
+    let since = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    let header = httpdate::fmt_http_date(since);
+    let preconditions = Preconditions {
+        if_unmodified_since: Some(&header),
+        ..Default::default()
+    };
+    assert!(preconditions.hold(None, Some(since + Duration::from_millis(999))));
+    assert!(!preconditions.hold(None, Some(since + Duration::from_secs(1))));
+    // without a modification time there is nothing to compare
+    assert!(preconditions.hold(None, None));
+
+    // If-Match takes precedence
+    let preconditions = Preconditions {
+        if_match: Some("*"),
+        ..preconditions
+    };
+    assert!(preconditions.hold(Some("v1"), Some(since + Duration::from_secs(1))));
+}
+
+#[test]
+fn if_none_match_fails_on_a_weak_match() {
+    let if_none_match = |header| Preconditions {
+        if_none_match: Some(header),
+        ..Default::default()
+    };
+    assert!(!if_none_match(r#"W/"v1""#).hold(Some("v1"), None));
+    assert!(if_none_match(r#""v2""#).hold(Some("v1"), None));
+    // create-only writes: `*` fails once the resource exists
+    assert!(if_none_match("*").hold(None, None));
+    assert!(!if_none_match("*").hold(Some("v1"), None));
+}
@@ -3,6 +3,10 @@
 //! - Provides v1 JSON ABI types shared with the engine
 //! - Utilities to return JSON easily and free buffers correctly
 //! - Macros to export functions and register tables
+//! - Optional constant values exported next to the functions
+//! - An optional init hook receiving the extension's settings from `ext/manifest.toml`
+//! - Optional parameter declarations the engine checks arguments against
+//! - Optional cache TTLs for functions whose results may be reused
 
 pub use libc as __libc;
 use libc::c_uchar;
@@ -38,6 +42,55 @@ pub struct JhpRegisterV1 {
     pub free_fn: ExtFreeV1,
 }
 
+/// A named constant: `json` is a NUL-terminated JSON document.
+#[repr(C)]
+pub struct JhpValueDescV1 {
+    pub name: *const libc::c_char,
+    pub json: *const libc::c_char,
+}
+
+/// Returned by the optional `jhp_register_values_v1` symbol. The engine never frees
+/// the table or its strings, so they must live as long as the library.
+#[repr(C)]
+pub struct JhpValuesV1 {
+    pub abi_version: u32,
+    pub values: *const JhpValueDescV1,
+    pub len: usize,
+}
+
+/// A function's parameters: `params` is a NUL-terminated JSON array of
+/// `{"name": ..., "type": ..., "optional": ...}` objects, see [`leak_signature`].
+#[repr(C)]
+pub struct JhpSignatureDescV1 {
+    pub name: *const libc::c_char,
+    pub params: *const libc::c_char,
+}
+
+/// Returned by the optional `jhp_register_signatures_v1` symbol. Like the value table,
+/// it is never freed.
+#[repr(C)]
+pub struct JhpSignaturesV1 {
+    pub abi_version: u32,
+    pub signatures: *const JhpSignatureDescV1,
+    pub len: usize,
+}
+
+/// How long the engine may reuse a function's results for calls with the same arguments.
+#[repr(C)]
+pub struct JhpCacheDescV1 {
+    pub name: *const libc::c_char,
+    pub ttl_ms: u64,
+}
+
+/// Returned by the optional `jhp_register_cache_v1` symbol. Like the value table, it is
+/// never freed.
+#[repr(C)]
+pub struct JhpCachesV1 {
+    pub abi_version: u32,
+    pub caches: *const JhpCacheDescV1,
+    pub len: usize,
+}
+
 /// Allocate a JSON payload from any Serialize value.
 pub fn ok_json<T: Serialize>(val: &T) -> JhpCallResult {
     let bytes = match serde_json::to_vec(val) {
@@ -82,6 +135,33 @@ pub extern "C" fn free_v1(ptr: *const c_uchar, len: usize) {
     }
 }
 
+/// Serialize `val` into a NUL-terminated JSON string that is never freed, for
+/// `JhpValueDescV1::json`. Values that fail to serialize become `null`.
+pub fn leak_json<T: Serialize + ?Sized>(val: &T) -> *const libc::c_char {
+    let json = serde_json::to_string(val).unwrap_or_else(|_| "null".to_string());
+    // serde_json escapes NUL inside strings, so the JSON text never contains one
+    std::ffi::CString::new(json).unwrap_or_default().into_raw()
+}
+
+/// Serialize `(name, type)` pairs into the never-freed JSON of
+/// `JhpSignatureDescV1::params`. A type is one of `string`, `number`, `integer`,
+/// `boolean`, `object`, `array`, `null` or `any`; alternatives are separated by `|`
+/// (`"string|null"`), and a trailing `?` makes the parameter optional (`"number?"`).
+pub fn leak_signature(params: &[(&str, &str)]) -> *const libc::c_char {
+    let params: Vec<serde_json::Value> = params
+        .iter()
+        .map(|(name, ty)| {
+            let (ty, optional) = match ty.strip_suffix('?') {
+                Some(ty) => (ty, true),
+                None => (*ty, false),
+            };
+            let types: Vec<&str> = ty.split('|').map(str::trim).collect();
+            serde_json::json!({ "name": name, "type": types, "optional": optional })
+        })
+        .collect();
+    leak_json(&params)
+}
+
 /// Parse incoming JhpBuf as a serde_json::Value array.
 pub fn parse_args(buf: JhpBuf) -> Result<Vec<serde_json::Value>, ()> {
     let slice = unsafe { std::slice::from_raw_parts(buf.ptr, buf.len) };
@@ -123,6 +203,9 @@ macro_rules! cstr {
 ///   fn_name => extern "C" fn(JhpBuf) -> JhpCallResult,
 ///   ...
 /// )
+///
+/// Functions are called synchronously on the worker running the template, so a slow one
+/// blocks that worker for as long as it runs; a render timeout can't cancel it.
 #[macro_export]
 macro_rules! export_jhp_v1 {
     ($($name:expr => $func:path),+ $(,)?) => {
@@ -137,3 +220,89 @@ macro_rules! export_jhp_v1 {
         }
     };
 }
+
+/// Export constants next to the function table. Each value is serialized to JSON once,
+/// when the engine loads the library; the engine sets it on the global object (or the
+/// module object when loaded through `include()`).
+/// Usage: export_jhp_values_v1!(
+///   "VERSION" => env!("CARGO_PKG_VERSION"),
+///   "MAX_ROWS" => 1000,
+/// )
+#[macro_export]
+macro_rules! export_jhp_values_v1 {
+    ($($name:expr => $value:expr),+ $(,)?) => {
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn jhp_register_values_v1() -> $crate::JhpValuesV1 {
+            let boxed: Box<[$crate::JhpValueDescV1]> = vec![
+                $( $crate::JhpValueDescV1 { name: $crate::cstr!($name), json: $crate::leak_json(&$value) }, )+
+            ].into_boxed_slice();
+            let len = boxed.len();
+            let ptr = Box::into_raw(boxed) as *const $crate::JhpValueDescV1;
+            $crate::JhpValuesV1 { abi_version: 1, values: ptr, len }
+        }
+    };
+}
+
+/// Export an init hook. The engine calls it once, right after loading the library, with
+/// the extension's table from `ext/manifest.toml` as a JSON object (`{}` when there is
+/// none). Returning an error (e.g. from `err_message`) keeps the library from loading.
+/// When the engine is configured with an RNG seed, the settings also carry it as
+/// `rng_seed` (unless the manifest sets one), for extensions with random output to seed
+/// their generator from and be reproducible.
+/// Usage: export_jhp_init_v1!(init);
+/// where `init` is an `extern "C" fn(JhpBuf) -> JhpCallResult`.
+#[macro_export]
+macro_rules! export_jhp_init_v1 {
+    ($func:path) => {
+        #[unsafe(no_mangle)]
+        pub extern "C" fn jhp_init_v1(settings: $crate::JhpBuf) -> $crate::JhpCallResult {
+            $func(settings)
+        }
+    };
+}
+
+/// Declare the parameters of exported functions; the engine then rejects calls with the
+/// wrong number or types of arguments with a `TypeError` before they reach the function.
+/// Functions left out receive any arguments. See [`leak_signature`] for the types.
+/// Usage: export_jhp_signatures_v1!(
+///   "get_quote_at" => [("prefix", "string"), ("index", "integer?")],
+/// )
+#[macro_export]
+macro_rules! export_jhp_signatures_v1 {
+    ($($name:expr => [$(($param:expr, $ty:expr)),* $(,)?]),+ $(,)?) => {
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn jhp_register_signatures_v1() -> $crate::JhpSignaturesV1 {
+            let boxed: Box<[$crate::JhpSignatureDescV1]> = vec![
+                $( $crate::JhpSignatureDescV1 {
+                    name: $crate::cstr!($name),
+                    params: $crate::leak_signature(&[$(($param, $ty)),*]),
+                }, )+
+            ].into_boxed_slice();
+            let len = boxed.len();
+            let ptr = Box::into_raw(boxed) as *const $crate::JhpSignatureDescV1;
+            $crate::JhpSignaturesV1 { abi_version: 1, signatures: ptr, len }
+        }
+    };
+}
+
+/// Declare functions whose results the engine may cache, with a TTL in milliseconds:
+/// within it, a call with the same (JSON-equal) arguments as an earlier successful one
+/// returns that result without reaching the function. Only suits functions that are pure
+/// or change slowly. The engine's config can override the TTLs or turn caching off.
+/// Usage: export_jhp_cache_v1!(
+///   "lookup" => 60_000,
+/// )
+#[macro_export]
+macro_rules! export_jhp_cache_v1 {
+    ($($name:expr => $ttl_ms:expr),+ $(,)?) => {
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn jhp_register_cache_v1() -> $crate::JhpCachesV1 {
+            let boxed: Box<[$crate::JhpCacheDescV1]> = vec![
+                $( $crate::JhpCacheDescV1 { name: $crate::cstr!($name), ttl_ms: $ttl_ms }, )+
+            ].into_boxed_slice();
+            let len = boxed.len();
+            let ptr = Box::into_raw(boxed) as *const $crate::JhpCacheDescV1;
+            $crate::JhpCachesV1 { abi_version: 1, caches: ptr, len }
+        }
+    };
+}
@@ -1,7 +1,8 @@
 use clap::Parser;
 use jhp_engine::config::EngineConfig;
 use jhp_engine::engine::Engine;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Parser)]
 #[command(
@@ -11,6 +12,28 @@ use std::path::PathBuf;
     disable_help_subcommand = true
 )]
 struct Cli {
+    /// Render this template file to stdout and exit
+    #[arg(value_name = "FILE", conflicts_with = "serve")]
+    file: Option<PathBuf>,
+
+    /// Render a template given inline to stdout and exit, e.g. --eval '<?= 1 + 1 ?>'
+    #[arg(
+        short = 'e',
+        long = "eval",
+        value_name = "TEMPLATE",
+        conflicts_with_all = ["serve", "file"]
+    )]
+    eval: Option<String>,
+
+    /// Parse the template, or every .jhp file under the directory, without running it,
+    /// report problems as FILE:LINE:COLUMN and exit non-zero if there are any
+    #[arg(
+        long = "check",
+        value_name = "PATH",
+        conflicts_with_all = ["serve", "file", "eval"]
+    )]
+    check: Option<PathBuf>,
+
     /// Start the built-in HTTP server at HOST:PORT
     #[arg(short = 'S', value_name = "HOST:PORT")]
     serve: Option<String>,
@@ -18,6 +41,14 @@ struct Cli {
     /// Set the document root to serve from
     #[arg(short = 't', long = "docroot", value_name = "DIR")]
     docroot: Option<PathBuf>,
+
+    /// Serve HTTPS using this PEM certificate chain (requires --tls-key)
+    #[arg(long = "tls-cert", value_name = "FILE", requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM private key for --tls-cert
+    #[arg(long = "tls-key", value_name = "FILE", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
 }
 
 fn parse_host_port(s: &str) -> Result<(String, u16), String> {
@@ -45,6 +76,10 @@ fn parse_host_port(s: &str) -> Result<(String, u16), String> {
 async fn main() {
     let cli = Cli::parse();
 
+    if let Some(path) = cli.check.as_deref() {
+        check_templates(path);
+    }
+
     let mut config = EngineConfig::default();
     if let Some(addr) = cli.serve.as_deref() {
         match parse_host_port(addr) {
@@ -63,9 +98,121 @@ async fn main() {
         config = config.set_document_root(docroot);
     }
 
+    if let (Some(cert), Some(key)) = (cli.tls_cert, cli.tls_key) {
+        config = config.set_tls(cert, key);
+    }
+
+    // clap lets at most one of --eval and FILE through
+    let template = match (cli.eval, cli.file) {
+        (Some(source), _) => Some(Template::Inline(source)),
+        (None, Some(file)) => Some(Template::File(file)),
+        (None, None) => None,
+    };
+    if let Some(template) = template {
+        render_once(config, template).await;
+    }
+
     let threads = std::thread::available_parallelism()
         .map(|n| n.get())
         .unwrap_or(4);
     let mut engine = Engine::new_with_config(threads, config);
-    engine.run().await.unwrap();
+    if let Err(e) = engine.run().await {
+        eprintln!("jhp: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// What [`render_once`] renders.
+enum Template {
+    /// A template given on the command line.
+    Inline(String),
+    /// A template file, relative to the working directory.
+    File(PathBuf),
+}
+
+/// Render an inline template or a template file on a single executor, write the output
+/// to stdout and exit, without starting the HTTP server. The exit status is non-zero
+/// when the template failed or called `abort()`.
+async fn render_once(config: EngineConfig, template: Template) -> ! {
+    let (source, name) = match template {
+        Template::Inline(source) => (source, "eval".to_string()),
+        Template::File(file) => match tokio::fs::read_to_string(&file).await {
+            Ok(source) => (source, file.to_string_lossy().into_owned()),
+            Err(e) => {
+                eprintln!("jhp: {}: {}", file.display(), e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let engine = Engine::new_with_config(1, config);
+    let blocks = jhp_parser::Parser::new(&source).parse().blocks;
+    let output = match engine
+        .executor_pool
+        .render(blocks, &name, Default::default())
+        .await
+    {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("jhp: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(abort) = output.abort {
+        match abort.message.or(abort.location) {
+            Some(message) => eprintln!("jhp: aborted with status {}: {}", abort.status, message),
+            None => eprintln!("jhp: aborted with status {}", abort.status),
+        }
+        std::process::exit(1);
+    }
+    let mut stdout = std::io::stdout();
+    let _ = stdout.write_all(output.body.as_bytes());
+    let _ = stdout.flush();
+    std::process::exit(if output.error.is_some() { 1 } else { 0 });
+}
+
+/// Check `path` (a template, or a directory searched recursively for `.jhp` files) with
+/// the parser alone, print each problem to stderr and exit: 0 when all files passed, 1
+/// otherwise.
+fn check_templates(path: &Path) -> ! {
+    let mut files = Vec::new();
+    if let Err(e) = collect_templates(path, &mut files) {
+        eprintln!("jhp: {}: {}", path.display(), e);
+        std::process::exit(1);
+    }
+    files.sort();
+
+    let mut failed = false;
+    for file in &files {
+        let source = match std::fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("{}: {}", file.display(), e);
+                failed = true;
+                continue;
+            }
+        };
+        for d in jhp_parser::check(&source) {
+            eprintln!("{}:{}:{}: {}", file.display(), d.lineno, d.colno, d.message);
+            failed = true;
+        }
+    }
+    std::process::exit(if failed { 1 } else { 0 });
+}
+
+fn collect_templates(path: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if !path.is_dir() {
+        // a file given by name is checked whatever its extension; make sure it exists
+        std::fs::metadata(path)?;
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_templates(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "jhp") {
+            files.push(path);
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,87 @@
+use std::process::Command;
+
+fn jhp() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_jhp"))
+}
+
+#[test]
+fn eval_renders_to_stdout_and_exits() {
+    let output = jhp()
+        .args(["--eval", "<p><?= 1 + 1 ?></p>"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "<p>2</p>");
+}
+
+#[test]
+fn file_argument_renders_the_template() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("page.jhp");
+    std::fs::write(&file, "<? const n = 3; ?>n=<?= n * 2 ?>").unwrap();
+
+    let output = jhp().arg(&file).output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "n=6");
+}
+
+#[test]
+fn failing_templates_exit_non_zero() {
+    let output = jhp()
+        .args(["--eval", "a<? missing(); ?>"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("a"), "{stdout}");
+
+    let output = jhp()
+        .args(["--eval", "a<? abort(404, 'gone'); ?>"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("404") && stderr.contains("gone"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn eval_and_serve_are_mutually_exclusive() {
+    let output = jhp()
+        .args(["--eval", "x", "-S", "127.0.0.1:0"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("cannot be used with"), "{stderr}");
+}
+
+#[test]
+fn check_reports_malformed_templates_without_running_them() {
+    let root = tempfile::tempdir().unwrap();
+    let dir = root.path();
+    std::fs::create_dir_all(dir.join("partials")).unwrap();
+    std::fs::write(dir.join("good.jhp"), "<? if (a) { ?><?= b ?><? } ?>").unwrap();
+    std::fs::write(dir.join("partials/bad.jhp"), "<p>\n<? if (a) { ?>x").unwrap();
+
+    let good = jhp()
+        .arg("--check")
+        .arg(dir.join("good.jhp"))
+        .output()
+        .unwrap();
+    let all = jhp().arg("--check").arg(dir).output().unwrap();
+
+    // `a` and `b` are undefined, so running the template would have failed
+    assert!(good.status.success(), "{good:?}");
+    assert!(good.stderr.is_empty(), "{good:?}");
+    assert_eq!(all.status.code(), Some(1), "{all:?}");
+    let stderr = String::from_utf8_lossy(&all.stderr);
+    let expected = format!(
+        "{}:2:11: unclosed `{{`",
+        dir.join("partials/bad.jhp").display()
+    );
+    assert_eq!(stderr.trim_end(), expected);
+}
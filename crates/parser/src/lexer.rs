@@ -0,0 +1,246 @@
+//! Just enough of a JavaScript lexer to tell code from strings, template literals,
+//! regular expressions and comments, for the checks made on code without running it.
+
+/// Words after which a `/` starts a regular expression rather than a division.
+const KEYWORDS_BEFORE_EXPRESSION: &[&str] = &[
+    "await",
+    "case",
+    "delete",
+    "do",
+    "else",
+    "in",
+    "instanceof",
+    "new",
+    "of",
+    "return",
+    "throw",
+    "typeof",
+    "void",
+    "yield",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    /// An identifier, keyword or number.
+    Word,
+    /// Any other character of code, one at a time.
+    Punct(char),
+    /// A string literal.
+    Str,
+    /// A regular expression literal, with its flags.
+    Regex,
+    /// A template literal, or one of its parts around substitutions: a part after the
+    /// first starts with the `}` closing a substitution, and a part before the last ends
+    /// with the `${` opening one. The code of a substitution comes as tokens in between.
+    Template {
+        after_substitution: bool,
+        before_substitution: bool,
+    },
+    LineComment,
+    BlockComment,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Token {
+    pub(crate) kind: Kind,
+    /// Byte range of the token in the source.
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    /// `false` for a literal or comment the source ends in, or for a string or regular
+    /// expression a line ends in, before it is closed.
+    pub(crate) terminated: bool,
+}
+
+/// The tokens of some JavaScript source, whitespace left out. Whether a `/` divides or
+/// starts a regular expression is decided by the token before it, as most tools do.
+pub(crate) struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+    /// open `${` substitutions, each with its count of unclosed `{` inside
+    substitutions: Vec<usize>,
+    regex_allowed: bool,
+}
+
+impl<'a> Lexer<'a> {
+    pub(crate) fn new(src: &'a str) -> Self {
+        Self {
+            src,
+            pos: 0,
+            substitutions: Vec::new(),
+            regex_allowed: true,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    /// Consume a string literal after its opening `quote`; `true` once it is closed.
+    fn string(&mut self, quote: char) -> bool {
+        while let Some(c) = self.peek() {
+            if c == '\n' {
+                return false;
+            }
+            self.bump();
+            if c == '\\' {
+                self.bump();
+            } else if c == quote {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Consume a regular expression after its opening `/`, then its flags; `true` once
+    /// it is closed.
+    fn regex(&mut self) -> bool {
+        let mut in_class = false;
+        while let Some(c) = self.peek() {
+            if c == '\n' {
+                return false;
+            }
+            self.bump();
+            match c {
+                '\\' if self.peek() != Some('\n') => {
+                    self.bump();
+                }
+                '[' => in_class = true,
+                ']' => in_class = false,
+                '/' if !in_class => {
+                    while self.peek().is_some_and(is_word_char) {
+                        self.bump();
+                    }
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Consume template text up to its closing backtick or the next `${`, which opens a
+    /// substitution. Returns whether it closed, and whether at a `${`.
+    fn template(&mut self) -> (bool, bool) {
+        while let Some(c) = self.bump() {
+            match c {
+                '\\' => {
+                    self.bump();
+                }
+                '`' => return (true, false),
+                '$' if self.peek() == Some('{') => {
+                    self.bump();
+                    self.substitutions.push(0);
+                    return (true, true);
+                }
+                _ => {}
+            }
+        }
+        (false, false)
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+        let start = self.pos;
+        let c = self.bump()?;
+        let mut terminated = true;
+        let kind = match c {
+            '/' if self.peek() == Some('/') => {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.bump();
+                }
+                Kind::LineComment
+            }
+            '/' if self.peek() == Some('*') => {
+                self.bump();
+                terminated = false;
+                while let Some(c) = self.bump() {
+                    if c == '*' && self.peek() == Some('/') {
+                        self.bump();
+                        terminated = true;
+                        break;
+                    }
+                }
+                Kind::BlockComment
+            }
+            '/' if self.regex_allowed => {
+                terminated = self.regex();
+                Kind::Regex
+            }
+            '\'' | '"' => {
+                terminated = self.string(c);
+                Kind::Str
+            }
+            '`' => {
+                let (closed, before_substitution) = self.template();
+                terminated = closed;
+                Kind::Template {
+                    after_substitution: false,
+                    before_substitution,
+                }
+            }
+            '}' if self.substitutions.last() == Some(&0) => {
+                self.substitutions.pop();
+                let (closed, before_substitution) = self.template();
+                terminated = closed;
+                Kind::Template {
+                    after_substitution: true,
+                    before_substitution,
+                }
+            }
+            c if is_word_char(c) => {
+                while self.peek().is_some_and(is_word_char) {
+                    self.bump();
+                }
+                Kind::Word
+            }
+            c => {
+                match (c, self.substitutions.last_mut()) {
+                    ('{', Some(open)) => *open += 1,
+                    ('}', Some(open)) => *open -= 1,
+                    _ => {}
+                }
+                Kind::Punct(c)
+            }
+        };
+        match kind {
+            Kind::LineComment | Kind::BlockComment => {}
+            Kind::Word => {
+                self.regex_allowed =
+                    KEYWORDS_BEFORE_EXPRESSION.contains(&&self.src[start..self.pos])
+            }
+            Kind::Punct(c) => self.regex_allowed = !matches!(c, ')' | ']' | '}'),
+            Kind::Template {
+                before_substitution,
+                ..
+            } => self.regex_allowed = before_substitution,
+            Kind::Str | Kind::Regex => self.regex_allowed = false,
+        }
+        Some(Token {
+            kind,
+            start,
+            end: self.pos,
+            terminated,
+        })
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// The tokens of `src` that aren't comments.
+pub(crate) fn code_tokens(src: &str) -> impl Iterator<Item = Token> + '_ {
+    Lexer::new(src).filter(|t| !matches!(t.kind, Kind::LineComment | Kind::BlockComment))
+}
@@ -1,6 +1,13 @@
+mod lexer;
+
+use lexer::{Kind, Token, code_tokens};
+
 #[derive(Debug)]
 pub struct CodeBlockContent {
     pub lineno: usize,
+    /// Line of the block's last character in the source; for a code block, the line of
+    /// its closing `?>`.
+    pub end_lineno: usize,
     pub colno: usize,
     pub content: String,
     pub level: usize,
@@ -13,22 +20,133 @@ pub enum CodeBlock {
     Expression(CodeBlockContent),
 }
 
+/// How the value of a `<?= expr ?>` block is turned into output text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExpressionOutput {
+    /// `null` and `undefined` print nothing (like PHP); anything else goes through `String()`,
+    /// so `0`, `false` and `""` still print as-is.
+    #[default]
+    OmitNullish,
+    /// Plain `String(value)`, which prints `null` and `undefined` literally.
+    String,
+    /// The function in the global [`STRINGIFIER_GLOBAL`], installed by the embedder, which
+    /// gets every value as is, nullish ones included.
+    Stringifier,
+}
+
+/// Global holding the function `<?= expr ?>` values are printed with under
+/// [`ExpressionOutput::Stringifier`].
+pub const STRINGIFIER_GLOBAL: &str = "__jhp_stringify";
+
+impl ExpressionOutput {
+    /// JavaScript emitted before and after an expression's source to echo its value.
+    pub fn wrapper(self) -> (&'static str, &'static str) {
+        match self {
+            ExpressionOutput::OmitNullish => ("echo(String((", ") ?? ''));"),
+            ExpressionOutput::String => ("echo(String(", "));"),
+            ExpressionOutput::Stringifier => ("echo(__jhp_stringify(", "));"),
+        }
+    }
+}
+
+/// A per-file setting declared at the top of a template, e.g.
+/// `<?@ contentType("application/json") ?>` or `<?jhp @contentType("text/plain") ?>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Directive {
+    pub name: String,
+    /// Arguments with surrounding quotes removed; unquoted arguments are kept verbatim.
+    pub args: Vec<String>,
+    pub lineno: usize,
+}
+
+/// A problem found in a template without running it: where it is and what is wrong.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub lineno: usize,
+    /// 1-based column, counted in characters.
+    pub colno: usize,
+    pub message: String,
+}
+
+/// Sides of a code block whose adjacent whitespace is stripped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Trim {
+    /// Strip spaces and tabs before `<?`, and the newline before them.
+    pub before: bool,
+    /// Strip spaces and tabs after `?>`, and the newline after them.
+    pub after: bool,
+}
+
+impl Trim {
+    pub const NONE: Trim = Trim {
+        before: false,
+        after: false,
+    };
+    pub const BOTH: Trim = Trim {
+        before: true,
+        after: true,
+    };
+
+    /// The trim asked for by a `trim`, `ltrim` or `rtrim` directive; none for others.
+    fn for_directive(name: &str) -> Trim {
+        match name {
+            "trim" => Trim::BOTH,
+            "ltrim" => Trim {
+                before: true,
+                after: false,
+            },
+            "rtrim" => Trim {
+                before: false,
+                after: true,
+            },
+            _ => Trim::NONE,
+        }
+    }
+
+    fn union(self, other: Trim) -> Trim {
+        Trim {
+            before: self.before || other.before,
+            after: self.after || other.after,
+        }
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct ParseResults {
     pub blocks: Vec<Box<CodeBlock>>,
+    /// Directives from the start of the file, in declaration order. They are not emitted as code.
+    pub directives: Vec<Directive>,
+    /// Problems noticed while splitting the template, such as a `<?` without its `?>`.
+    /// The blocks are usable anyway; see [`check`] for a full validation.
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 impl ParseResults {
-    fn add_block(&mut self, block: Box<CodeBlock>) {
-        self.blocks.push(block);
+    /// The last directive declared with `name`, if any.
+    pub fn directive(&self, name: &str) -> Option<&Directive> {
+        self.directives.iter().rev().find(|d| d.name == name)
     }
 }
 
+/// Splits a template into HTML, code and expression blocks.
+///
+/// `<?-` strips the whitespace before a tag and `-?>` the whitespace after it, each up to
+/// and including one newline, so control-flow tags on their own lines leave no blank
+/// lines behind. The `trim`, `ltrim` and `rtrim` directives apply that to every tag of the
+/// file (both sides, before only, after only).
 pub struct Parser<'a> {
     content: &'a str,
     pos: usize,
     line: usize,
     nesting: usize,
+    markers: Trim,
+    trim: Trim,
+    /// `trim` plus whatever the file's directives ask for, for the current parse.
+    file_trim: Trim,
+    /// Whether the preamble has been read, which the first block of a parse does.
+    started: bool,
+    directives: Vec<Directive>,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl<'a> Parser<'a> {
@@ -38,59 +156,220 @@ impl<'a> Parser<'a> {
             pos: 0,
             line: 1,
             nesting: 0,
+            markers: Trim::BOTH,
+            trim: Trim::NONE,
+            file_trim: Trim::NONE,
+            started: false,
+            directives: Vec::new(),
+            diagnostics: Vec::new(),
         }
     }
 
+    /// Which trim markers are recognised: `<?-` (`before`) and `-?>` (`after`); both by
+    /// default. The `-` of a disabled marker is left in the code.
+    pub fn set_trim_markers(&mut self, markers: Trim) -> &mut Self {
+        self.markers = markers;
+        self
+    }
+
+    /// Trim every tag as if it carried the markers for `trim`; none by default.
+    pub fn set_trim(&mut self, trim: Trim) -> &mut Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Split the whole template at once: [`Parser::next_block`] until the end, along with
+    /// the directives and diagnostics.
     pub fn parse(&mut self) -> ParseResults {
-        self.pos = 0;
-        self.line = 1;
-        self.nesting = 0;
+        self.reset();
+        let mut blocks = Vec::new();
+        while let Some(block) = self.next_block() {
+            blocks.push(block);
+        }
+        ParseResults {
+            blocks,
+            directives: self.directives.clone(),
+            diagnostics: self.diagnostics.clone(),
+        }
+    }
 
-        let mut results = ParseResults::default();
+    /// The next block of the template, or `None` once it is exhausted, for tools that
+    /// work through a template a block at a time. The blocks are the ones
+    /// [`Parser::parse`] returns, in the same order. The first call reads the directives
+    /// at the top of the file, available from [`Parser::directives`] afterwards.
+    pub fn next_block(&mut self) -> Option<Box<CodeBlock>> {
+        if !self.started {
+            self.started = true;
+            self.parse_preamble();
+            self.file_trim = self.directives.iter().fold(self.trim, |trim, d| {
+                trim.union(Trim::for_directive(&d.name))
+            });
+        }
         while self.pos < self.content.len() {
-            if self.lookahead("<?") {
-                results.add_block(Box::new(self.parse_js_block()));
+            let mut block = if self.at_raw_open() {
+                self.parse_raw_block()
+            } else if self.lookahead("<?") {
+                self.parse_js_block()
             } else {
-                results.add_block(Box::new(self.parse_html_block()));
+                self.parse_html_block()
+            };
+            if self.trim_before_tag(&mut block) {
+                return Some(Box::new(block));
             }
         }
-        results
+        None
+    }
+
+    /// Where the next block starts: its 1-based line and column (in characters), and
+    /// its byte offset in the content.
+    pub fn position(&self) -> (usize, usize, usize) {
+        (self.line, self.column_at(self.pos), self.pos)
+    }
+
+    /// The directives read so far; all of them once the first block was returned.
+    pub fn directives(&self) -> &[Directive] {
+        &self.directives
+    }
+
+    /// Problems noticed in the blocks returned so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
     }
 
     pub fn set_content(&mut self, content: &'a str) {
         self.content = content;
+        self.reset();
+    }
+
+    /// Start over from the beginning of the content.
+    fn reset(&mut self) {
         self.pos = 0;
         self.line = 1;
         self.nesting = 0;
+        self.started = false;
+        self.directives.clear();
+        self.diagnostics.clear();
+    }
+
+    /// Skip a leading `#!` line and extract any directive blocks that precede the first
+    /// HTML or code. A single newline after each directive is consumed with it so
+    /// directives don't leave blank lines in the output.
+    fn parse_preamble(&mut self) {
+        if self.lookahead("#!") {
+            while self.pos < self.content.len() {
+                if self.consume() == '\n' {
+                    self.line += 1;
+                    break;
+                }
+            }
+        }
+
+        loop {
+            let (saved_pos, saved_line) = (self.pos, self.line);
+            while self.pos < self.content.len() {
+                let c = self.content[self.pos..].chars().next().unwrap_or('\0');
+                if !c.is_whitespace() {
+                    break;
+                }
+                if self.consume() == '\n' {
+                    self.line += 1;
+                }
+            }
+
+            let rest = &self.content[self.pos..];
+            let body_start = if let Some(after) = rest.strip_prefix("<?@") {
+                Some(rest.len() - after.len())
+            } else if let Some(after) = rest.strip_prefix("<?jhp") {
+                let trimmed = after.trim_start();
+                (after.len() != trimmed.len() && trimmed.starts_with('@'))
+                    .then(|| rest.len() - trimmed.len() + 1)
+            } else {
+                None
+            };
+            let Some(body_start) = body_start else {
+                self.pos = saved_pos;
+                self.line = saved_line;
+                return;
+            };
+            let Some(body_len) = rest[body_start..].find("?>") else {
+                self.pos = saved_pos;
+                self.line = saved_line;
+                return;
+            };
+
+            let body = &rest[body_start..body_start + body_len];
+            let body = body.trim();
+            let body = body.strip_suffix('@').unwrap_or(body);
+            self.directives.push(parse_directive(body, self.line));
+
+            let consumed = &rest[..body_start + body_len + 2];
+            self.line += consumed.matches('\n').count();
+            self.pos += consumed.len();
+            if self.lookahead("\r\n") {
+                self.pos += 2;
+                self.line += 1;
+            } else if self.lookahead("\n") {
+                self.pos += 1;
+                self.line += 1;
+            }
+        }
     }
 
     fn parse_html_block(&mut self) -> CodeBlock {
         let start_line = self.line;
         let start_col = self.column_at(self.pos);
+        let mut end_line = start_line;
         let mut buf = String::new();
 
         while self.pos < self.content.len() && !self.lookahead("<?") {
             let c = self.consume();
+            end_line = self.line;
             if c == '\n' {
                 self.line += 1;
             }
-            // HTML-encode JS-breaking characters so they don't break echo(`...`) literals
-            match c {
-                '\'' => buf.push_str("\\'"), // single quote
-                '`' => buf.push_str("\\`"),  // backtick
-                '"' => buf.push_str("\\\""), // double quote
-                _ => buf.push(c),
-            }
+            buf.push(c);
         }
 
         CodeBlock::Html(CodeBlockContent {
             lineno: start_line,
+            end_lineno: end_line,
             colno: start_col,
             content: buf,
             level: self.nesting,
         })
     }
 
+    /// Whether a raw block starts here: `<?raw` followed by whitespace.
+    fn at_raw_open(&self) -> bool {
+        self.lookahead(RAW_OPEN)
+            && self.content[self.pos + RAW_OPEN.len()..]
+                .chars()
+                .next()
+                .is_some_and(char::is_whitespace)
+    }
+
+    /// A `<?raw ... raw?>` block: everything between the markers, `<?` and `?>` included,
+    /// is emitted as HTML text exactly as written. An unterminated block runs to the end
+    /// of the file.
+    fn parse_raw_block(&mut self) -> CodeBlock {
+        let start_line = self.line;
+        self.pos += RAW_OPEN.len();
+        let start_col = self.column_at(self.pos);
+
+        let rest = &self.content[self.pos..];
+        let raw = &rest[..rest.find(RAW_CLOSE).unwrap_or(rest.len())];
+        self.line += raw.matches('\n').count();
+        self.pos = (self.pos + raw.len() + RAW_CLOSE.len()).min(self.content.len());
+
+        CodeBlock::Html(CodeBlockContent {
+            lineno: start_line,
+            end_lineno: self.line,
+            colno: start_col,
+            content: raw.to_string(),
+            level: self.nesting,
+        })
+    }
+
     fn parse_js_block(&mut self) -> CodeBlock {
         let start_line = self.line;
         // opening "<?"
@@ -100,9 +379,19 @@ impl<'a> Parser<'a> {
 
         // 1-based column index.
         let mut start_col = self.column_at(tag_pos) + 2;
+        if self.markers.before && self.lookahead("-") {
+            let _ = self.consume(); // -
+            start_col += 1;
+        }
 
         let mut buf = String::new();
+        let mut trim_after = self.file_trim.after;
         while self.pos < self.content.len() && !self.lookahead("?>") {
+            if self.markers.after && self.lookahead("-?>") {
+                let _ = self.consume(); // -
+                trim_after = true;
+                break;
+            }
             let c = self.consume();
             if c == '\n' {
                 self.line += 1;
@@ -113,6 +402,16 @@ impl<'a> Parser<'a> {
         if self.lookahead("?>") {
             let _ = self.consume(); // ?
             let _ = self.consume(); // >
+        } else {
+            self.diagnostics.push(Diagnostic {
+                lineno: start_line,
+                colno: self.column_at(tag_pos),
+                message: "unterminated code block: `<?` has no closing `?>`".to_string(),
+            });
+        }
+        let end_line = self.line;
+        if trim_after {
+            self.skip_trailing_whitespace();
         }
 
         let trimmed_start = buf.trim_start();
@@ -129,20 +428,30 @@ impl<'a> Parser<'a> {
 
         // expression block if it starts with '=' after leading whitespace
         if trimmed_start.starts_with('=') {
-            // find '=' in the original buffer to compute accurate expression column start.
+            // find '=' in the original buffer to compute accurate expression start position.
             let eq_byte_idx = buf.find('=');
-            let after_eq = trimmed_start[1..].trim();
+            let after_eq = trim_expression(trimmed_start[1..].trim());
+            let mut expr_line = start_line;
             if let Some(eq_idx) = eq_byte_idx {
-                // count chars from start of buf to '=' and whitespace after '=' to the first expr char
-                let chars_to_eq = buf[..eq_idx].chars().count();
-                let ws_after_eq = buf[eq_idx + '='.len_utf8()..]
+                // everything from the start of buf up to the first expression char
+                let ws_after_eq: usize = buf[eq_idx + '='.len_utf8()..]
                     .chars()
                     .take_while(|c| c.is_whitespace())
-                    .count();
-                start_col += chars_to_eq + 1 /* '=' */ + ws_after_eq;
+                    .map(char::len_utf8)
+                    .sum();
+                let lead = &buf[..eq_idx + '='.len_utf8() + ws_after_eq];
+                match lead.rfind('\n') {
+                    // expression starts on a later line than the opening tag
+                    Some(nl_idx) => {
+                        expr_line += lead.matches('\n').count();
+                        start_col = lead[nl_idx + 1..].chars().count() + 1;
+                    }
+                    None => start_col += lead.chars().count(),
+                }
             }
             CodeBlock::Expression(CodeBlockContent {
-                lineno: start_line,
+                lineno: expr_line,
+                end_lineno: end_line,
                 colno: start_col,
                 content: after_eq.to_string(),
                 level,
@@ -150,6 +459,7 @@ impl<'a> Parser<'a> {
         } else {
             CodeBlock::Javascript(CodeBlockContent {
                 lineno: start_line,
+                end_lineno: end_line,
                 colno: start_col,
                 content: buf,
                 level,
@@ -157,6 +467,42 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// When the next tag trims the whitespace before it and `block` is HTML, strip its
+    /// trailing spaces and tabs, then one newline. Returns whether anything of the block
+    /// is left to emit.
+    fn trim_before_tag(&self, block: &mut CodeBlock) -> bool {
+        let CodeBlock::Html(html) = block else {
+            return true;
+        };
+        let trims = !self.at_raw_open()
+            && self.lookahead("<?")
+            && (self.file_trim.before || (self.markers.before && self.lookahead("<?-")));
+        if !trims {
+            return true;
+        }
+        let content = html.content.trim_end_matches([' ', '\t']);
+        let content = content
+            .strip_suffix('\n')
+            .map(|c| c.strip_suffix('\r').unwrap_or(c))
+            .unwrap_or(content);
+        html.content.truncate(content.len());
+        !html.content.is_empty()
+    }
+
+    /// Skip spaces and tabs, then one newline, after a trimmed closing tag.
+    fn skip_trailing_whitespace(&mut self) {
+        while self.lookahead(" ") || self.lookahead("\t") {
+            self.pos += 1;
+        }
+        if self.lookahead("\r\n") {
+            self.pos += 2;
+            self.line += 1;
+        } else if self.lookahead("\n") {
+            self.pos += 1;
+            self.line += 1;
+        }
+    }
+
     fn lookahead(&self, pat: &str) -> bool {
         let bytes = self.content.as_bytes();
         let pat_bytes = pat.as_bytes();
@@ -176,6 +522,10 @@ impl<'a> Parser<'a> {
     }
 }
 
+/// Opening and closing markers of a raw block.
+const RAW_OPEN: &str = "<?raw";
+const RAW_CLOSE: &str = "raw?>";
+
 impl<'a> Parser<'a> {
     /// Compute the 1-based column number at the given byte position in `self.content`.
     /// Counts Unicode scalar values to avoid byte/char mismatches.
@@ -188,26 +538,418 @@ impl<'a> Parser<'a> {
     }
 }
 
+/// Parse a directive body such as `contentType("application/json")`. A bare name
+/// (`noEscape`) has no arguments.
+fn parse_directive(body: &str, lineno: usize) -> Directive {
+    let (name, args) = match body.find('(') {
+        Some(idx) => {
+            let inner = &body[idx + 1..];
+            let inner = inner.trim_end();
+            let inner = inner.strip_suffix(')').unwrap_or(inner);
+            (&body[..idx], split_directive_args(inner))
+        }
+        None => (body, Vec::new()),
+    };
+    Directive {
+        name: name.trim().to_string(),
+        args,
+        lineno,
+    }
+}
+
+/// Split comma-separated directive arguments, honouring single and double quotes
+/// (with backslash escapes) so commas inside strings don't split.
+fn split_directive_args(input: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut quote: Option<char> = None;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) if c == '\\' => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                quoted = true;
+            }
+            None if c == ',' => {
+                args.push(finish_arg(&mut current, quoted));
+                quoted = false;
+            }
+            None => current.push(c),
+        }
+    }
+    if quoted || !current.trim().is_empty() || !args.is_empty() {
+        args.push(finish_arg(&mut current, quoted));
+    }
+    args
+}
+
+fn finish_arg(current: &mut String, quoted: bool) -> String {
+    let arg = std::mem::take(current);
+    if quoted { arg } else { arg.trim().to_string() }
+}
+
 /// Convert parsed JHP blocks into executable JavaScript source.
 pub fn blocks_to_js<I>(blocks: I) -> String
 where
     I: IntoIterator<Item = Box<CodeBlock>>,
 {
-    let mut js_lines: Vec<String> = Vec::new();
+    blocks_to_js_with(blocks, ExpressionOutput::default())
+}
 
-    for block in blocks {
-        match *block {
-            CodeBlock::Javascript(block) => {
-                js_lines.push(block.content.trim().into());
-            }
-            CodeBlock::Html(block) => {
-                js_lines.push(format!("echo(`{}`);", block.content));
+/// Like [`blocks_to_js`], printing expression values according to `output`.
+pub fn blocks_to_js_with<I>(blocks: I, output: ExpressionOutput) -> String
+where
+    I: IntoIterator<Item = Box<CodeBlock>>,
+{
+    template_to_js(blocks, output).code
+}
+
+/// JavaScript generated for a whole template by [`template_to_js`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptJs {
+    pub code: String,
+    /// The template line each line of `code` came from, so positions V8 reports can be
+    /// mapped back; a wrapper line counts as a line of the code it wraps.
+    pub lines: Vec<usize>,
+    /// `code` is an async IIFE whose promise must be settled.
+    pub is_async: bool,
+}
+
+impl ScriptJs {
+    /// The template line of 1-based line `line` of the code, if it has one.
+    pub fn template_line(&self, line: usize) -> Option<usize> {
+        self.lines.get(line.checked_sub(1)?).copied()
+    }
+}
+
+/// The script running a template: its blocks in order, each starting on a line of its
+/// own, so a statement can span blocks (`<? for (…) { ?>…<? } ?>`) and a declaration
+/// stays visible to the blocks after it, whether the template is rendered or included.
+/// - HTML is echoed as a string literal.
+/// - Code is padded to its original column, so error columns stay exact; lines are
+///   mapped back through [`ScriptJs::lines`].
+/// - An expression goes on a line of its own between the echo wrapper of `output`. A
+///   comma-separated list of them (`<?= a, b ?>`) echoes each value in order.
+/// - If any block uses `await`, the whole script goes between [`ASYNC_PREFIX`] and
+///   [`ASYNC_SUFFIX`], on lines of their own. Its completion value is then a promise the
+///   caller must settle, and its declarations are local to the wrapper.
+pub fn template_to_js<I>(blocks: I, output: ExpressionOutput) -> ScriptJs
+where
+    I: IntoIterator<Item = Box<CodeBlock>>,
+{
+    let blocks: Vec<Box<CodeBlock>> = blocks.into_iter().collect();
+    let is_async = blocks.iter().any(|block| match block.as_ref() {
+        CodeBlock::Html(_) => false,
+        CodeBlock::Javascript(c) | CodeBlock::Expression(c) => uses_await(&c.content),
+    });
+    let mut lines: Vec<(String, usize)> = Vec::new();
+    for block in &blocks {
+        push_block_lines(&mut lines, block, output);
+    }
+    if is_async {
+        let first = lines.first().map_or(1, |(_, line)| *line);
+        let last = lines.last().map_or(1, |(_, line)| *line);
+        lines.insert(0, (ASYNC_PREFIX.to_string(), first));
+        lines.push((ASYNC_SUFFIX.to_string(), last));
+    }
+    let (code, lines): (Vec<String>, Vec<usize>) = lines.into_iter().unzip();
+    ScriptJs {
+        code: code.join("\n"),
+        lines,
+        is_async,
+    }
+}
+
+/// Append the lines of code running `block`, each with its template line.
+fn push_block_lines(lines: &mut Vec<(String, usize)>, block: &CodeBlock, output: ExpressionOutput) {
+    let (content, head, tail) = match block {
+        CodeBlock::Html(block) => {
+            let echo = format!("echo({});", js_string(&block.content));
+            lines.push((echo, block.lineno));
+            return;
+        }
+        CodeBlock::Javascript(block) => (block, None, None),
+        // `a, b` echoes each value in turn rather than just the last, as the comma
+        // operator would; the list becomes an array literal so its source isn't moved
+        CodeBlock::Expression(block) if has_top_level_comma(&block.content) => {
+            let (prefix, suffix) = output.wrapper();
+            (
+                block,
+                Some("for (const __jhp_value of [".to_string()),
+                Some(format!("]) {}__jhp_value{}", prefix, suffix)),
+            )
+        }
+        CodeBlock::Expression(block) => {
+            let (prefix, suffix) = output.wrapper();
+            (block, Some(prefix.to_string()), Some(suffix.to_string()))
+        }
+    };
+
+    if let Some(head) = head {
+        lines.push((head, content.lineno));
+    }
+    let mut line = content.lineno;
+    for (i, text) in content.content.split('\n').enumerate() {
+        line = content.lineno + i;
+        let pad = if i == 0 {
+            content.colno.saturating_sub(1)
+        } else {
+            0
+        };
+        lines.push((format!("{:pad$}{}", "", text, pad = pad), line));
+    }
+    if let Some(tail) = tail {
+        lines.push((tail, line));
+    }
+}
+
+/// `s` as a double-quoted JavaScript string literal, for generated code.
+pub fn js_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            // line terminators in JS, and control characters
+            '\u{2028}' | '\u{2029}' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// An expression's source without trailing comments and whitespace, and without one
+/// trailing semicolon, so `<?= x; // note ?>` echoes `x` instead of failing to compile.
+fn trim_expression(src: &str) -> &str {
+    let code = src[..code_end(src)].trim_end();
+    code.strip_suffix(';').map(str::trim_end).unwrap_or(code)
+}
+
+/// Byte offset just past the last token of `src` that isn't a comment. Strings, template
+/// literals and regular expressions count as code. When the source ends inside one of
+/// them (or inside a comment), where the code ends is unclear, so nothing is cut.
+fn code_end(src: &str) -> usize {
+    let mut end = 0;
+    for token in lexer::Lexer::new(src) {
+        if !token.terminated {
+            return src.len();
+        }
+        if !matches!(token.kind, Kind::LineComment | Kind::BlockComment) {
+            end = token.end;
+        }
+    }
+    end
+}
+
+/// Whether `src` has a comma outside of brackets, strings, template literals, regular
+/// expressions and comments, making it a list of expressions rather than one.
+fn has_top_level_comma(src: &str) -> bool {
+    // unclosed brackets and `${` substitutions
+    let mut depth = 0usize;
+    for token in code_tokens(src) {
+        match token.kind {
+            Kind::Punct(',') if depth == 0 => return true,
+            Kind::Punct('(' | '[' | '{') => depth += 1,
+            Kind::Punct(')' | ']' | '}') => depth = depth.saturating_sub(1),
+            Kind::Template {
+                after_substitution,
+                before_substitution,
+            } => {
+                depth = depth.saturating_sub(after_substitution as usize);
+                depth += before_substitution as usize;
             }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Code wrapped around a script that uses `await`, turning it into an async IIFE.
+/// Declarations inside become local to the wrapper.
+pub const ASYNC_PREFIX: &str = "(async () => {";
+pub const ASYNC_SUFFIX: &str = "})()";
+
+/// Whether `src` uses the `await` keyword outside of strings, template text, regular
+/// expressions and comments, meaning it can only run inside an async function. A
+/// property named `await` (`obj.await`, `{ await: 1 }`) isn't the keyword.
+pub fn uses_await(src: &str) -> bool {
+    let tokens: Vec<Token> = code_tokens(src).collect();
+    tokens.iter().enumerate().any(|(i, token)| {
+        token.kind == Kind::Word
+            && &src[token.start..token.end] == "await"
+            && !(i > 0 && tokens[i - 1].kind == Kind::Punct('.'))
+            && tokens.get(i + 1).map(|t| t.kind) != Some(Kind::Punct(':'))
+    })
+}
+
+/// Validate a template without running it: every `<?` needs its `?>`, and brackets must
+/// balance, across all code blocks together and within each `<?= ?>` expression.
+/// Strings, template literals, regular expressions and comments are skipped; an
+/// unterminated one is reported.
+/// Diagnostics come sorted by position; none means the template passed.
+pub fn check(source: &str) -> Vec<Diagnostic> {
+    let results = Parser::new(source).parse();
+    let mut diagnostics = results.diagnostics;
+    let mut open = Vec::new();
+    for block in &results.blocks {
+        match block.as_ref() {
+            CodeBlock::Html(_) => {}
+            CodeBlock::Javascript(block) => check_brackets(block, &mut open, &mut diagnostics),
             CodeBlock::Expression(block) => {
-                js_lines.push(format!("echo(String({}));", block.content.trim()));
+                let mut expression_open = Vec::new();
+                check_brackets(block, &mut expression_open, &mut diagnostics);
+                report_unclosed(expression_open, &mut diagnostics);
+            }
+        }
+    }
+    report_unclosed(open, &mut diagnostics);
+    diagnostics.sort_by_key(|d| (d.lineno, d.colno));
+    diagnostics
+}
+
+/// An opening bracket, or `$` for the `${` of a template substitution, with its line
+/// and column.
+type OpenBracket = (char, usize, usize);
+
+/// Scan a block's code, pushing its opening brackets onto `open` and popping them as
+/// they are closed.
+fn check_brackets(
+    block: &CodeBlockContent,
+    open: &mut Vec<OpenBracket>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut report = |lineno: usize, colno: usize, message: String| {
+        diagnostics.push(Diagnostic {
+            lineno,
+            colno,
+            message,
+        })
+    };
+    let mut positions = Positions::new(block);
+
+    for token in lexer::Lexer::new(&block.content) {
+        let (line, col) = positions.at(token.start);
+        match token.kind {
+            Kind::Punct(c @ ('(' | '[' | '{')) => open.push((c, line, col)),
+            Kind::Punct(c @ (')' | ']' | '}')) => {
+                if let Some(problem) = close_bracket(open, c) {
+                    report(line, col, problem);
+                }
+            }
+            Kind::Template {
+                after_substitution,
+                before_substitution,
+            } => {
+                if let Some(problem) = after_substitution
+                    .then(|| close_bracket(open, '}'))
+                    .flatten()
+                {
+                    report(line, col, problem);
+                }
+                if before_substitution {
+                    // the token ends with the `${`
+                    let (l, cl) = positions.at(token.end - 2);
+                    open.push(('$', l, cl));
+                }
+            }
+            _ => {}
+        }
+        if !token.terminated {
+            let unterminated = match token.kind {
+                Kind::Str => "unterminated string",
+                Kind::Template { .. } => "unterminated template literal",
+                Kind::Regex => "unterminated regular expression",
+                _ => "unterminated comment",
+            };
+            report(line, col, unterminated.to_string());
+        }
+    }
+}
+
+/// Pop the bracket closed by `c`; what is wrong when it isn't the one `c` closes.
+fn close_bracket(open: &mut Vec<OpenBracket>, c: char) -> Option<String> {
+    match open.pop() {
+        Some((o, ..)) if closing(o) == c => None,
+        Some((o, line, col)) => Some(format!(
+            "`{}` does not close `{}` opened at {}:{}",
+            c,
+            bracket_name(o),
+            line,
+            col
+        )),
+        None => Some(format!("unexpected `{}`", c)),
+    }
+}
+
+/// Lines and columns of byte offsets in a block's code, asked for in increasing order.
+struct Positions<'a> {
+    src: &'a str,
+    offset: usize,
+    line: usize,
+    col: usize,
+}
+
+impl<'a> Positions<'a> {
+    fn new(block: &'a CodeBlockContent) -> Self {
+        Self {
+            src: &block.content,
+            offset: 0,
+            line: block.lineno,
+            col: block.colno,
+        }
+    }
+
+    fn at(&mut self, offset: usize) -> (usize, usize) {
+        for c in self.src[self.offset..offset].chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.col = 1;
+            } else {
+                self.col += 1;
             }
         }
+        self.offset = offset;
+        (self.line, self.col)
     }
+}
 
-    js_lines.join("\n")
+fn closing(open: char) -> char {
+    match open {
+        '(' => ')',
+        '[' => ']',
+        _ => '}',
+    }
+}
+
+fn bracket_name(open: char) -> String {
+    if open == '$' {
+        "${".to_string()
+    } else {
+        open.to_string()
+    }
+}
+
+fn report_unclosed(open: Vec<OpenBracket>, diagnostics: &mut Vec<Diagnostic>) {
+    for (c, lineno, colno) in open {
+        diagnostics.push(Diagnostic {
+            lineno,
+            colno,
+            message: format!("unclosed `{}`", bracket_name(c)),
+        });
+    }
 }
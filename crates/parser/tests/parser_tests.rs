@@ -1,4 +1,7 @@
-use jhp_parser::{CodeBlock, Parser, blocks_to_js};
+use jhp_parser::{
+    CodeBlock, ExpressionOutput, Parser, STRINGIFIER_GLOBAL, Trim, blocks_to_js, blocks_to_js_with,
+    check, template_to_js, uses_await,
+};
 
 fn collect_summaries(blocks: Vec<Box<CodeBlock>>) -> Vec<(char, usize, String, usize)> {
     // (kind, line, content, level)
@@ -124,13 +127,14 @@ fn blocks_to_js_emits_expected_code() {
 
     let js = blocks_to_js(res.blocks);
 
-    // echo("Hello "), Expression -> echo(String(name)); echo("!\n"), js("log(name);")
+    // HTML is a string literal; code and expressions are padded to their original column
     let expected_lines = vec![
-        "echo(`Hello `);",
-        "echo(String(name));",
-        "echo(`!",
-        "`);",
-        "log(name);",
+        "echo(\"Hello \");",
+        "echo(String((",
+        "          name",
+        ") ?? ''));",
+        "echo(\"!\\n\");",
+        "   log(name); ",
     ];
 
     let actual_lines: Vec<&str> = js.lines().collect();
@@ -150,3 +154,494 @@ fn set_content_resets_state() {
     assert_eq!(s[0].0, 'H');
     assert_eq!(s[0].3, 0);
 }
+
+#[test]
+fn expression_position_points_at_first_expression_char() {
+    let input = "<p>héllo</p><?= name ?>\n<?=\n    other ?>";
+    let mut p = Parser::new(input);
+    let blocks = p.parse().blocks;
+
+    let positions: Vec<(usize, usize)> = blocks
+        .iter()
+        .filter_map(|b| match b.as_ref() {
+            CodeBlock::Expression(c) => Some((c.lineno, c.colno)),
+            _ => None,
+        })
+        .collect();
+    // `name` is the 17th character on line 1 (é counts once); `other` starts line 3, column 5.
+    assert_eq!(positions, vec![(1, 17), (3, 5)]);
+}
+
+#[test]
+fn blocks_to_js_string_output_keeps_plain_coercion() {
+    let mut p = Parser::new("<?= value ?>");
+    let js = blocks_to_js_with(p.parse().blocks, ExpressionOutput::String);
+    assert_eq!(js, "echo(String(\n    value\n));");
+}
+
+#[test]
+fn blocks_to_js_stringifier_output_calls_the_global() {
+    let mut p = Parser::new("<?= value ?>");
+    let js = blocks_to_js_with(p.parse().blocks, ExpressionOutput::Stringifier);
+    assert_eq!(js, format!("echo({}(\n    value\n));", STRINGIFIER_GLOBAL));
+}
+
+#[test]
+fn comma_separated_expressions_echo_each_value() {
+    let js = blocks_to_js(Parser::new("<?= a, b ?>").parse().blocks);
+    assert_eq!(
+        js,
+        "for (const __jhp_value of [\n    a, b\n]) echo(String((__jhp_value) ?? ''));"
+    );
+
+    // commas inside calls, literals, strings and templates don't make a list
+    for input in [
+        "<?= f(a, b) ?>",
+        "<?= [a, b].join() ?>",
+        "<?= { a, b }.a ?>",
+        "<?= 'a, b' ?>",
+        "<?= `${a, b}` ?>",
+        "<?= a /* , */ ?>",
+        "<?= /a,b/.test(s) ?>",
+        "<?= s.split(/[,;]/) ?>",
+    ] {
+        let js = blocks_to_js(Parser::new(input).parse().blocks);
+        assert!(js.starts_with("echo(String(("), "{input}: {js}");
+    }
+
+    // a slash after a value divides, so what follows is code again
+    let js = blocks_to_js(Parser::new("<?= a / 2, b / 2 ?>").parse().blocks);
+    assert!(js.starts_with("for (const __jhp_value of ["), "{js}");
+}
+
+#[test]
+fn leading_directives_are_extracted_not_emitted() {
+    let input = concat!(
+        "<?@ contentType(\"application/json\") ?>\n",
+        "<?jhp @cache('public, max-age=60', 3) ?>\n",
+        "<?@ noEscape @?>\n",
+        "<?= data ?>",
+    );
+    let mut p = Parser::new(input);
+    let res = p.parse();
+
+    let names: Vec<&str> = res.directives.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(names, ["contentType", "cache", "noEscape"]);
+    assert_eq!(
+        res.directive("contentType").unwrap().args,
+        ["application/json"]
+    );
+    let cache = res.directive("cache").unwrap();
+    assert_eq!(cache.args, ["public, max-age=60", "3"]);
+    assert_eq!(cache.lineno, 2);
+    assert!(res.directive("noEscape").unwrap().args.is_empty());
+
+    // Only the expression remains, on its original line.
+    let s = collect_summaries(res.blocks);
+    assert_eq!(s, vec![('E', 4, "data".to_string(), 0)]);
+}
+
+#[test]
+fn shebang_line_is_skipped() {
+    let input = "#!/usr/bin/env jhp\n<?@ contentType('text/plain') ?>\nhi";
+    let mut p = Parser::new(input);
+    let res = p.parse();
+    assert_eq!(res.directive("contentType").unwrap().lineno, 2);
+    let s = collect_summaries(res.blocks);
+    assert_eq!(s, vec![('H', 3, "hi".to_string(), 0)]);
+}
+
+#[test]
+fn directives_after_content_are_not_extracted() {
+    let input = "  <p>x</p>\n<?@ contentType('text/plain') ?>";
+    let mut p = Parser::new(input);
+    let res = p.parse();
+    assert!(res.directives.is_empty());
+    let s = collect_summaries(res.blocks);
+    assert_eq!(s[0], ('H', 1, "  <p>x</p>\n".to_string(), 0));
+}
+
+#[test]
+fn uses_await_ignores_strings_comments_and_identifiers() {
+    assert!(uses_await("await Promise.resolve(7)"));
+    assert!(uses_await("const x = f(await g());"));
+    assert!(uses_await("`a ${ { b: await c }.b } d`"));
+    assert!(uses_await("x = 1; await"));
+
+    assert!(!uses_await("'await' + \"await\""));
+    assert!(!uses_await("`please await ${name} here`"));
+    assert!(!uses_await("// await\n/* await */ awaited(); obj.awaiting"));
+    assert!(!uses_await("const s = 'it\\'s await';"));
+}
+
+#[test]
+fn uses_await_ignores_properties_and_regex_literals() {
+    assert!(!uses_await("obj.await; obj?.await()"));
+    assert!(!uses_await("const o = { await: 1 };"));
+    assert!(!uses_await("const r = /await/g;"));
+    assert!(!uses_await("s.replace(/ await /, '')"));
+    assert!(uses_await("const o = { v: await f() };"));
+    assert!(uses_await("x ? await f() : g()"));
+}
+
+#[test]
+fn blocks_to_js_wraps_scripts_using_await() {
+    let mut p = Parser::new("a<?= await v ?>");
+    let js = blocks_to_js(p.parse().blocks);
+    assert_eq!(
+        js,
+        "(async () => {\necho(\"a\");\necho(String((\n     await v\n) ?? ''));\n})()"
+    );
+
+    let mut p = Parser::new("a<?= v ?>");
+    assert!(!blocks_to_js(p.parse().blocks).starts_with("(async"));
+}
+
+#[test]
+fn html_is_echoed_as_a_string_literal() {
+    let input = "`${a}` \\n \"it's\"\r\n\u{2028}";
+    let js = blocks_to_js(Parser::new(input).parse().blocks);
+    assert_eq!(js, r#"echo("`${a}` \\n \"it's\"\r\n\u2028");"#);
+}
+
+#[test]
+fn async_declarations_stay_visible_to_later_blocks() {
+    let mut p = Parser::new("<? const a = await f(); ?>b<?= a ?>");
+    let js = blocks_to_js(p.parse().blocks);
+    // one wrapper for the whole script, so `a` is in scope for the expression
+    assert_eq!(
+        js,
+        "(async () => {\n   const a = await f(); \necho(\"b\");\necho(String((\n                               a\n) ?? ''));\n})()"
+    );
+}
+
+#[test]
+fn statements_can_span_blocks_and_lines_map_back_to_the_template() {
+    let input = "<ul>\n<? for (const i of [1, 2]) { ?>\n  <li><?= i ?></li>\n<? } ?>";
+    let js = template_to_js(
+        Parser::new(input).parse().blocks,
+        ExpressionOutput::default(),
+    );
+    assert_eq!(
+        js.code.lines().collect::<Vec<_>>(),
+        [
+            "echo(\"<ul>\\n\");",
+            "   for (const i of [1, 2]) { ",
+            "echo(\"\\n  <li>\");",
+            "echo(String((",
+            "          i",
+            ") ?? ''));",
+            "echo(\"</li>\\n\");",
+            "   } ",
+        ]
+    );
+    assert_eq!(js.lines, [1, 2, 2, 3, 3, 3, 3, 4]);
+    assert_eq!(js.template_line(5), Some(3));
+    assert_eq!(js.template_line(9), None);
+}
+
+/// The HTML a template echoes, with code and expression blocks left out.
+fn html_of(blocks: Vec<Box<CodeBlock>>) -> Vec<String> {
+    collect_summaries(blocks)
+        .into_iter()
+        .filter(|s| s.0 == 'H')
+        .map(|s| s.2)
+        .collect()
+}
+
+#[test]
+fn open_tag_trim_strips_preceding_whitespace_and_newline() {
+    let res = Parser::new("<ul>\n  <?- let a = 1; ?>\n</ul>").parse();
+    let s = collect_summaries(res.blocks);
+    assert_eq!(s[0], ('H', 1, "<ul>".to_string(), 0));
+    assert_eq!(s[1], ('J', 2, " let a = 1; ".to_string(), 0));
+    assert_eq!(s[2], ('H', 2, "\n</ul>".to_string(), 0));
+}
+
+#[test]
+fn close_tag_trim_strips_following_whitespace_and_newline() {
+    let res = Parser::new("<ul>\n<? let a = 1; -?>  \n</ul>\n<?= a ?>").parse();
+    let s = collect_summaries(res.blocks);
+    assert_eq!(s[1], ('J', 2, " let a = 1; ".to_string(), 0));
+    // the stripped newline still counts towards line numbers
+    assert_eq!(s[2], ('H', 3, "</ul>\n".to_string(), 0));
+    assert_eq!(s[3], ('E', 4, "a".to_string(), 0));
+}
+
+#[test]
+fn trimmed_tags_in_a_loop_leave_no_blank_lines() {
+    let input = concat!(
+        "<ul>\n",
+        "  <?- for (const i of items) { -?>\n",
+        "  <li><?= i ?></li>\n",
+        "  <?- } -?>\n",
+        "</ul>\n",
+    );
+    let res = Parser::new(input).parse();
+    assert_eq!(html_of(res.blocks), ["<ul>", "  <li>", "</li>", "</ul>\n"]);
+
+    let js = blocks_to_js(Parser::new(input).parse().blocks);
+    assert!(js.contains("for (const i of items) {"), "{js}");
+    assert!(!js.contains('-'), "{js}");
+}
+
+#[test]
+fn trim_directives_apply_to_every_tag() {
+    let body = "<p>\n  <? a(); ?>\n</p>\n";
+    let cases = [
+        ("trim", vec!["<p>", "</p>\n"]),
+        ("ltrim", vec!["<p>", "\n</p>\n"]),
+        ("rtrim", vec!["<p>\n  ", "</p>\n"]),
+    ];
+    for (directive, expected) in cases {
+        let input = format!("<?@ {directive} ?>\n{body}");
+        let res = Parser::new(&input).parse();
+        assert_eq!(html_of(res.blocks), expected, "{directive}");
+    }
+}
+
+#[test]
+fn trim_markers_and_default_trim_are_configurable() {
+    let input = "a\n<?- x -?>\nb";
+    let res = Parser::new(input).set_trim_markers(Trim::NONE).parse();
+    let s = collect_summaries(res.blocks);
+    assert_eq!(s[1], ('J', 2, "- x -".to_string(), 0));
+    assert_eq!(s[2].2, "\nb");
+
+    let res = Parser::new("a\n<? x ?>\nb").set_trim(Trim::BOTH).parse();
+    assert_eq!(html_of(res.blocks), ["a", "b"]);
+}
+
+#[test]
+fn raw_block_is_emitted_literally() {
+    let input = "<p>\n<?raw <? not code ?> and <?= x ?> raw?>\n<?= y ?>";
+    let res = Parser::new(input).parse();
+    let s = collect_summaries(res.blocks);
+    assert_eq!(s.len(), 4);
+    assert_eq!(s[0], ('H', 1, "<p>\n".to_string(), 0));
+    assert_eq!(
+        s[1],
+        ('H', 2, " <? not code ?> and <?= x ?> ".to_string(), 0)
+    );
+    assert_eq!(s[2], ('H', 2, "\n".to_string(), 0));
+    assert_eq!(s[3], ('E', 3, "y".to_string(), 0));
+}
+
+#[test]
+fn raw_block_tracks_lines_and_keeps_text_verbatim() {
+    let input = "<?raw\n<? if (a) { ?>\n  `it's`\nraw?>\n<? z ?>";
+    let res = Parser::new(input).parse();
+    let s = collect_summaries(res.blocks);
+    assert_eq!(
+        s[0],
+        ('H', 1, "\n<? if (a) { ?>\n  `it's`\n".to_string(), 0)
+    );
+    // the `{` inside the raw block doesn't open a level
+    assert_eq!(s[2], ('J', 5, " z ".to_string(), 0));
+
+    let js = blocks_to_js(Parser::new("<?raw <? x ?> raw?>").parse().blocks);
+    assert_eq!(js, "echo(\" <? x ?> \");");
+}
+
+#[test]
+fn raw_needs_whitespace_and_runs_to_the_end_when_unterminated() {
+    let res = Parser::new("<?rawValue ?>").parse();
+    let s = collect_summaries(res.blocks);
+    assert_eq!(s[0], ('J', 1, "rawValue ".to_string(), 0));
+
+    let res = Parser::new("a<?raw <? b ?>").parse();
+    let s = collect_summaries(res.blocks);
+    assert_eq!(s.len(), 2);
+    assert_eq!(s[1].2, " <? b ?>");
+}
+
+fn expression(src: &str) -> String {
+    match *Parser::new(src).parse().blocks.remove(0) {
+        CodeBlock::Expression(c) => c.content,
+        other => panic!("not an expression: {other:?}"),
+    }
+}
+
+#[test]
+fn expression_drops_a_trailing_semicolon_and_comments() {
+    assert_eq!(expression("<?= x; ?>"), "x");
+    assert_eq!(expression("<?= x /* c */ ?>"), "x");
+    assert_eq!(expression("<?= x; // note ?>"), "x");
+    assert_eq!(
+        expression("<?= a /* mid */ + b;\n// end\n?>"),
+        "a /* mid */ + b"
+    );
+    assert_eq!(expression("<?= x + 1 ?>"), "x + 1");
+
+    let js = blocks_to_js(Parser::new("<?= x; // note ?>").parse().blocks);
+    assert_eq!(js, "echo(String((\n    x\n) ?? ''));");
+}
+
+#[test]
+fn expression_keeps_semicolons_and_slashes_inside_strings() {
+    assert_eq!(expression("<?= 'a;' ?>"), "'a;'");
+    assert_eq!(expression("<?= \"http://x\" ?>"), "\"http://x\"");
+    assert_eq!(expression("<?= `${a}//` ?>"), "`${a}//`");
+    assert_eq!(expression("<?= `;${ {a: 1}.a };` ?>"), "`;${ {a: 1}.a };`");
+    // only one semicolon is dropped
+    assert_eq!(expression("<?= x;; ?>"), "x;");
+}
+
+#[test]
+fn expression_keeps_slashes_inside_regex_literals() {
+    assert_eq!(
+        expression("<?= s.replace(/\\//g, '-') ?>"),
+        "s.replace(/\\//g, '-')"
+    );
+    assert_eq!(expression("<?= /a;b/.test(s); ?>"), "/a;b/.test(s)");
+    assert_eq!(expression("<?= a / b // half ?>"), "a / b");
+    // a source ending inside a literal is left for the compiler to report
+    assert_eq!(expression("<?= 'x; // y ?>"), "'x; // y");
+}
+
+fn line_spans(src: &str) -> Vec<(char, usize, usize)> {
+    Parser::new(src)
+        .parse()
+        .blocks
+        .into_iter()
+        .map(|b| match *b {
+            CodeBlock::Html(c) => ('H', c.lineno, c.end_lineno),
+            CodeBlock::Javascript(c) => ('J', c.lineno, c.end_lineno),
+            CodeBlock::Expression(c) => ('E', c.lineno, c.end_lineno),
+        })
+        .collect()
+}
+
+#[test]
+fn blocks_record_start_and_end_lines() {
+    let src = "<ul>\n  <li>a</li>\n</ul>\n<?\n  const x = 1;\n  const y = 2;\n?>\n<p><?=\n  x + y\n?></p>";
+    assert_eq!(
+        line_spans(src),
+        vec![
+            ('H', 1, 3),
+            ('J', 4, 7),
+            ('H', 7, 8),
+            ('E', 9, 10),
+            ('H', 10, 10),
+        ]
+    );
+}
+
+#[test]
+fn end_line_of_a_raw_block_is_its_closing_marker() {
+    assert_eq!(
+        line_spans("<?raw\n<?= x ?>\nraw?>after"),
+        vec![('H', 1, 3), ('H', 3, 3)]
+    );
+}
+
+fn problems(source: &str) -> Vec<(usize, usize, String)> {
+    check(source)
+        .into_iter()
+        .map(|d| (d.lineno, d.colno, d.message))
+        .collect()
+}
+
+#[test]
+fn check_accepts_balanced_templates() {
+    let input = concat!(
+        "<ul>\n",
+        "<? for (const i of items) { ?>\n",
+        "  <li><?= fmt(i, { a: [1, 2] }) ?></li>\n",
+        "<? } ?>\n",
+        "<? const s = \"}\" + '(' + `${ { x: 1 }.x } ]`; // {\n /* ( */ ?>\n",
+        "</ul>{",
+    );
+    assert_eq!(problems(input), []);
+}
+
+#[test]
+fn check_skips_brackets_in_regex_literals() {
+    let input = concat!(
+        "<? if (/[(]/.test(s)) {} ?>\n",
+        "<?= s.replace(/\\)/g, '').split(/[}\\]]/) ?>\n",
+        "<?= total / count ?>",
+    );
+    assert_eq!(problems(input), []);
+    assert_eq!(
+        problems("<? const re = /(a; ?>"),
+        [(1, 15, "unterminated regular expression".to_string())]
+    );
+}
+
+#[test]
+fn check_reports_unbalanced_brackets_across_blocks() {
+    let input = "<? if (a) { ?>\nx\n<? if (b) { ?>y<? } ?>";
+    assert_eq!(problems(input), [(1, 11, "unclosed `{`".to_string())]);
+
+    assert_eq!(problems("<? } ?>"), [(1, 4, "unexpected `}`".to_string())]);
+    assert_eq!(
+        problems("<?= f(a] ?>"),
+        [(1, 8, "`]` does not close `(` opened at 1:6".to_string())]
+    );
+}
+
+#[test]
+fn check_reports_unterminated_blocks_and_literals() {
+    assert_eq!(
+        problems("<p>\n  <? x();"),
+        [(
+            2,
+            3,
+            "unterminated code block: `<?` has no closing `?>`".to_string()
+        )]
+    );
+    assert_eq!(
+        problems("<? a = 'x;\n b = `y; /* ?>"),
+        [
+            (1, 8, "unterminated string".to_string()),
+            (2, 6, "unterminated template literal".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn next_block_yields_the_same_blocks_as_parse() {
+    let inputs = [
+        "<h1>Hello</h1>\n<p>World</p>",
+        "#!/usr/bin/env jhp\n<?@ trim ?>\n<ul>\n  <? for (x of xs) { ?>\n  <li><?= x ?></li>\n  <? } ?>\n</ul>",
+        "a  \n<?- if (x) { -?>\nb\n<?raw <?= raw ?> raw?>  \n<?- } ?>\nc<? unterminated",
+        "",
+    ];
+    for input in inputs {
+        let batch = Parser::new(input).parse();
+
+        let mut p = Parser::new(input);
+        let mut blocks = Vec::new();
+        while let Some(block) = p.next_block() {
+            blocks.push(block);
+        }
+        assert_eq!(
+            format!("{:?}", blocks),
+            format!("{:?}", batch.blocks),
+            "{input}"
+        );
+        assert_eq!(p.directives(), batch.directives, "{input}");
+        assert_eq!(p.diagnostics(), batch.diagnostics, "{input}");
+        assert!(p.next_block().is_none());
+    }
+}
+
+#[test]
+fn position_follows_the_blocks() {
+    let mut p = Parser::new("<?@ noEscape ?>\n<p>é</p><?= x ?>\nend");
+    assert_eq!(p.position(), (1, 1, 0));
+
+    let block = p.next_block().unwrap();
+    assert!(matches!(*block, CodeBlock::Html(ref c) if c.content == "<p>é</p>"));
+    assert_eq!(p.directives()[0].name, "noEscape");
+    assert_eq!(p.position(), (2, 9, 25));
+
+    let block = p.next_block().unwrap();
+    assert!(matches!(*block, CodeBlock::Expression(ref c) if c.content == "x"));
+    assert_eq!(p.position(), (2, 17, 33));
+
+    p.next_block().unwrap();
+    assert_eq!(p.position(), (3, 4, 37));
+    assert!(p.next_block().is_none());
+}
@@ -1,6 +1,6 @@
 #![allow(non_snake_case)]
 
-use jhp_extensions::{JhpBuf, JhpCallResult, err_message, ok_json};
+use jhp_extensions::{JhpBuf, JhpCallResult, err_message, ok_json, parse_args};
 use std::sync::atomic::{AtomicU64, Ordering};
 
 static QUOTES: &[&str] = &[
@@ -10,19 +10,67 @@ static QUOTES: &[&str] = &[
     "Premature optimization is the root of all evil. - Donald Knuth",
 ];
 
+static SEED: AtomicU64 = AtomicU64::new(0x9e3779b97f4a7c15);
+
+// calls that reached `get_quote_at`, so a cached result can be told from a fresh one
+static AT_CALLS: AtomicU64 = AtomicU64::new(0);
+
+// picks up the engine's `rng_seed`, so the quotes come in the same order on every run
+extern "C" fn init(settings: JhpBuf) -> JhpCallResult {
+    let settings = unsafe { std::slice::from_raw_parts(settings.ptr, settings.len) };
+    let seed = serde_json::from_slice::<serde_json::Value>(settings)
+        .ok()
+        .and_then(|settings| settings.get("rng_seed")?.as_u64());
+    if let Some(seed) = seed {
+        SEED.store(seed, Ordering::Relaxed);
+    }
+    ok_json(&serde_json::Value::Null)
+}
+
 extern "C" fn get_quote_v1(_buf: JhpBuf) -> JhpCallResult {
-    static SEED: AtomicU64 = AtomicU64::new(0x9e3779b97f4a7c15);
     let x = SEED.fetch_add(0x9e3779b97f4a7c15, Ordering::Relaxed);
     let idx = (x % (QUOTES.len() as u64)) as usize;
     ok_json(&serde_json::json!({ "quote": QUOTES[idx] }))
 }
 
+// `prefix` followed by quote number `index`; its arguments are checked by the engine
+extern "C" fn get_quote_at(buf: JhpBuf) -> JhpCallResult {
+    AT_CALLS.fetch_add(1, Ordering::Relaxed);
+    let Ok(args) = parse_args(buf) else {
+        return err_message("invalid arguments", 2);
+    };
+    let prefix = args[0].as_str().unwrap_or_default();
+    let index = args[1].as_f64().unwrap_or_default() as usize;
+    ok_json(&format!("{}{}", prefix, QUOTES[index % QUOTES.len()]))
+}
+
+extern "C" fn get_quote_at_calls(_buf: JhpBuf) -> JhpCallResult {
+    ok_json(&AT_CALLS.load(Ordering::Relaxed))
+}
+
 // example of an error returning function
-#[allow(dead_code)]
 extern "C" fn get_quote_err(_buf: JhpBuf) -> JhpCallResult {
     err_message("not implemented", 1)
 }
 
 jhp_extensions::export_jhp_v1! {
     "get_quote" => get_quote_v1,
+    "get_quote_err" => get_quote_err,
+    "get_quote_at" => get_quote_at,
+    "get_quote_at_calls" => get_quote_at_calls,
+}
+
+jhp_extensions::export_jhp_signatures_v1! {
+    "get_quote_at" => [("prefix", "string"), ("index", "number")],
+}
+
+// the same prefix and index always give the same line
+jhp_extensions::export_jhp_cache_v1! {
+    "get_quote_at" => 60_000,
+}
+
+jhp_extensions::export_jhp_init_v1!(init);
+
+jhp_extensions::export_jhp_values_v1! {
+    "QUOTE_COUNT" => QUOTES.len(),
 }
@@ -21,10 +21,18 @@
     const _open = ensure(nativeSource, 'sqlite_open');
     const _close = ensure(nativeSource, 'sqlite_close');
     const _exec = ensure(nativeSource, 'sqlite_execute');
+    const _execMany = ensure(nativeSource, 'sqlite_execute_many');
     const _query = ensure(nativeSource, 'sqlite_query');
+    const _queryRow = ensure(nativeSource, 'sqlite_query_row');
+    const _queryValue = ensure(nativeSource, 'sqlite_query_value');
+    const _columnInfo = ensure(nativeSource, 'sqlite_column_info');
+    const _pragma = ensure(nativeSource, 'sqlite_pragma');
+    const _serialize = ensure(nativeSource, 'sqlite_serialize');
+    const _deserialize = ensure(nativeSource, 'sqlite_deserialize');
     const _version = ensure(nativeSource, 'sqlite_version');
     const _changes = ensure(nativeSource, 'sqlite_changes');
     const _lastid = ensure(nativeSource, 'sqlite_last_insert_rowid');
+    const _interrupt = ensure(nativeSource, 'sqlite_interrupt');
 
     function unwrap(res) {
         // Native returns JSON objects; on errors we standardize to { error, code }
@@ -139,12 +147,42 @@
         exec(sql, params) {
             return unwrap(_exec(this.handle, String(sql), params));
         }
+        // Run sql once per entry of paramSets; all of them are applied or, if one fails,
+        // none. Returns the total rowsAffected.
+        execMany(sql, paramSets) {
+            return unwrap(_execMany(this.handle, String(sql), paramSets));
+        }
+        // opts: { limit, offset, format: 'objects' | 'arrays' }. Without a limit every
+        // row is returned; limit: 0 returns none.
         query(sql, params, opts) {
             return unwrap(_query(this.handle, String(sql), params, opts));
         }
+        // The first row as an object, or null when the query returns none.
+        queryRow(sql, params) {
+            return unwrap(_queryRow(this.handle, String(sql), params)).row;
+        }
+        // The first column of the first row, or null when the query returns no row.
+        queryValue(sql, params) {
+            return unwrap(_queryValue(this.handle, String(sql), params)).value;
+        }
+        // [{ name, declType, tableName }] for each column sql returns, without running it.
+        columnInfo(sql) {
+            return unwrap(_columnInfo(this.handle, String(sql))).columns;
+        }
+        // Cancel the statement running on this handle (e.g. from another request); it
+        // throws an error with code 4.
+        interrupt() {
+            return unwrap(_interrupt(this.handle));
+        }
+        // Read (without value) or set a pragma: { columns, rows, value }, as query() gives
+        // them plus value, the first column of the first row. name is an identifier, optionally 'schema.name'.
         pragma(name, value) {
-            const sql = value === undefined ? `PRAGMA ${name}` : `PRAGMA ${name}=${value}`;
-            return this.query(sql);
+            return unwrap(_pragma(this.handle, String(name), value));
+        }
+        // The database as the bytes of a database file (Uint8Array), e.g. to snapshot it
+        // and restore it with Sqlite3.deserialize.
+        serialize() {
+            return Sqlite3.toBytes(unwrap(_serialize(this.handle)).data);
         }
         transaction(fn) {
             unwrap(_exec(this.handle, 'BEGIN'));
@@ -164,10 +202,19 @@
         }
     }
 
+    // opts: { readers } opens that many read-only connections (in WAL mode) that serve
+    // read-only queries while the main connection writes; needs a database file.
     Sqlite3.open = function (path, opts) {
         const res = unwrap(_open(String(path), opts));
         return new Database(res.db);
     };
+    // A new in-memory database holding a copy of bytes (Uint8Array or ArrayBuffer, as
+    // serialize() returns), or of a blob object.
+    Sqlite3.deserialize = function (bytes) {
+        const data = (bytes && typeof bytes.blob === 'string') ? bytes : Sqlite3.blob(bytes);
+        const res = unwrap(_deserialize(data));
+        return new Database(res.db);
+    };
     Sqlite3.version = function () { return unwrap(_version()).version; };
     Sqlite3.Database = Database;
 
@@ -1,29 +1,76 @@
 use base64::{Engine as _, engine::general_purpose};
 use jhp_extensions::{JhpBuf, JhpCallResult, ok_json, parse_args};
 use rusqlite::{
-    Connection, Row, Statement, ToSql, params_from_iter,
+    Connection, ErrorCode, InterruptHandle, OpenFlags, Row, Statement, ToSql, params_from_iter,
     types::{Value, ValueRef},
 };
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{LazyLock, Mutex};
 
 thread_local! {
-    static CONNS: RefCell<HashMap<u32, Connection>> = RefCell::new(HashMap::new());
-    static NEXT_ID: Cell<u32> = Cell::new(1);
+    static CONNS: RefCell<HashMap<u32, Database>> = RefCell::new(HashMap::new());
 }
 
+/// An open handle: the connection every write goes through, plus optional read-only
+/// connections (`readers`) that take read-only queries off it.
+struct Database {
+    writer: Connection,
+    readers: Vec<Connection>,
+    next_reader: Cell<usize>,
+}
+
+impl Database {
+    /// The connection to run `sql` on. Read-only statements go to the readers in turn,
+    /// unless the writer is inside a transaction whose uncommitted changes they wouldn't
+    /// see; everything else, including statements that fail to prepare, goes to the writer.
+    /// Statements are classified by preparing them on the reader they would run on, so
+    /// the writer is left to the writes.
+    fn conn_for(&self, sql: &str) -> &Connection {
+        if self.readers.is_empty() || !self.writer.is_autocommit() {
+            return &self.writer;
+        }
+        let i = self.next_reader.get();
+        self.next_reader.set(i.wrapping_add(1));
+        let reader = &self.readers[i % self.readers.len()];
+        if reader.prepare(sql).is_ok_and(|stmt| stmt.readonly()) {
+            reader
+        } else {
+            &self.writer
+        }
+    }
+
+    fn interrupt_handles(&self) -> Vec<InterruptHandle> {
+        std::iter::once(&self.writer)
+            .chain(&self.readers)
+            .map(Connection::get_interrupt_handle)
+            .collect()
+    }
+}
+
+/// Handle ids are unique across threads, so a handle can be interrupted from any of them.
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Interrupt handles of open connections, reachable from every thread.
+static INTERRUPTS: LazyLock<Mutex<HashMap<u32, Vec<InterruptHandle>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Error code for a statement cancelled through `sqlite_interrupt`.
+const INTERRUPTED: i32 = 4;
+
 fn alloc_id() -> u32 {
-    NEXT_ID.with(|c| {
-        let id = c.get();
-        c.set(id.saturating_add(1));
-        id
-    })
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
 }
 
-fn insert_conn(conn: Connection) -> u32 {
+fn insert_conn(db: Database) -> u32 {
     let id = alloc_id();
+    INTERRUPTS
+        .lock()
+        .unwrap()
+        .insert(id, db.interrupt_handles());
     CONNS.with(|m| {
-        m.borrow_mut().insert(id, conn);
+        m.borrow_mut().insert(id, db);
     });
     id
 }
@@ -32,6 +79,15 @@ fn json_err<E: std::fmt::Display>(msg: &str, e: E) -> JhpCallResult {
     ok_json(&serde_json::json!({"error": format!("{}: {}", msg, e), "code": 1}))
 }
 
+/// Like [`json_err`], but a statement stopped by `sqlite_interrupt` gets its own error.
+fn sqlite_err(msg: &str, e: rusqlite::Error) -> JhpCallResult {
+    if e.sqlite_error_code() == Some(ErrorCode::OperationInterrupted) {
+        err_obj("interrupted", INTERRUPTED)
+    } else {
+        json_err(msg, e)
+    }
+}
+
 fn err_obj<S: ToString>(msg: S, code: i32) -> JhpCallResult {
     ok_json(&serde_json::json!({"error": msg.to_string(), "code": code}))
 }
@@ -47,6 +103,10 @@ fn decode_blob(obj: &serde_json::Map<String, serde_json::Value>) -> Option<Vec<u
     }
 }
 
+/// The SQLite value a JSON parameter binds as. Integers SQLite can hold (up to `i64::MAX`)
+/// bind as integers; larger ones up to `u64::MAX` bind as their decimal text, so they read
+/// back digit for digit instead of rounded to a float (in a column without `INTEGER`,
+/// `NUMERIC` or `REAL` affinity, which would convert them). Other numbers bind as reals.
 fn value_from_json(v: &serde_json::Value) -> Option<Value> {
     match v {
         serde_json::Value::Null => Some(Value::Null),
@@ -54,6 +114,8 @@ fn value_from_json(v: &serde_json::Value) -> Option<Value> {
         serde_json::Value::Number(n) => {
             if let Some(i) = n.as_i64() {
                 Some(Value::Integer(i))
+            } else if let Some(u) = n.as_u64() {
+                Some(Value::Text(u.to_string()))
             } else if let Some(f) = n.as_f64() {
                 Some(Value::Real(f))
             } else {
@@ -109,34 +171,132 @@ fn bind_params<'a>(
     }
 }
 
+fn cell_to_json(row: &Row, i: usize) -> serde_json::Value {
+    match row.get_ref_unwrap(i) {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => serde_json::json!(i),
+        ValueRef::Real(f) => serde_json::json!(f),
+        ValueRef::Text(t) => serde_json::Value::String(String::from_utf8_lossy(t).to_string()),
+        ValueRef::Blob(b) => {
+            let b64 = general_purpose::STANDARD.encode(b);
+            let mut m = serde_json::Map::new();
+            m.insert("blob".to_string(), serde_json::Value::String(b64));
+            m.insert(
+                "length".to_string(),
+                serde_json::Value::Number((b.len() as u64).into()),
+            );
+            serde_json::Value::Object(m)
+        }
+    }
+}
+
 fn row_to_json(row: &Row) -> serde_json::Value {
     let mut obj = serde_json::Map::new();
     for (i, col) in row.as_ref().column_names().iter().enumerate() {
-        let val = match row.get_ref_unwrap(i) {
-            ValueRef::Null => serde_json::Value::Null,
-            ValueRef::Integer(i) => serde_json::json!(i),
-            ValueRef::Real(f) => serde_json::json!(f),
-            ValueRef::Text(t) => serde_json::Value::String(String::from_utf8_lossy(t).to_string()),
-            ValueRef::Blob(b) => {
-                let b64 = general_purpose::STANDARD.encode(b);
-                let mut m = serde_json::Map::new();
-                m.insert("blob".to_string(), serde_json::Value::String(b64));
-                m.insert(
-                    "length".to_string(),
-                    serde_json::Value::Number((b.len() as u64).into()),
-                );
-                serde_json::Value::Object(m)
+        obj.insert((*col).to_string(), cell_to_json(row, i));
+    }
+    serde_json::Value::Object(obj)
+}
+
+fn row_to_array(row: &Row) -> serde_json::Value {
+    let len = row.as_ref().column_count();
+    serde_json::Value::Array((0..len).map(|i| cell_to_json(row, i)).collect())
+}
+
+/// Shape of each row in a query result.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum RowFormat {
+    /// `{column: value}` objects.
+    #[default]
+    Objects,
+    /// Arrays of values in `columns` order.
+    Arrays,
+}
+
+/// The `{limit, offset, format}` options object of `sqlite_query`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct QueryOptions {
+    /// Maximum rows to return; `None` returns all of them and `Some(0)` none.
+    limit: Option<usize>,
+    /// Rows to skip before the first returned one.
+    offset: usize,
+    format: RowFormat,
+}
+
+impl QueryOptions {
+    /// Parse the options argument; absent or `null` fields keep their defaults.
+    fn parse(opts: Option<&serde_json::Value>) -> Result<Self, String> {
+        let mut out = QueryOptions::default();
+        let map = match opts {
+            None | Some(serde_json::Value::Null) => return Ok(out),
+            Some(serde_json::Value::Object(map)) => map,
+            Some(_) => return Err("query options must be an object".to_string()),
+        };
+        let count = |key: &str| -> Result<Option<usize>, String> {
+            match map.get(key) {
+                None | Some(serde_json::Value::Null) => Ok(None),
+                Some(v) => v
+                    .as_u64()
+                    .map(|n| Some(n as usize))
+                    .ok_or_else(|| format!("{key} must be a non-negative integer")),
             }
         };
-        obj.insert((*col).to_string(), val);
+        out.limit = count("limit")?;
+        out.offset = count("offset")?.unwrap_or(0);
+        out.format = match map.get("format") {
+            None | Some(serde_json::Value::Null) => RowFormat::Objects,
+            Some(v) => match v.as_str() {
+                Some("objects") => RowFormat::Objects,
+                Some("arrays") => RowFormat::Arrays,
+                _ => return Err("format must be 'objects' or 'arrays'".to_string()),
+            },
+        };
+        Ok(out)
     }
-    serde_json::Value::Object(obj)
 }
 
 extern "C" fn sqlite_test(_buf: JhpBuf) -> JhpCallResult {
     ok_json(&serde_json::json!({"message": "It works!"}))
 }
 
+/// Flags for opening `path`. Paths starting with `file:` are SQLite URIs, so options such
+/// as `mode=ro` or `cache=shared` apply; e.g. every handle opening
+/// `file:name?mode=memory&cache=shared` shares one in-memory database, whereas each
+/// plain `:memory:` open gets its own.
+fn open_flags(path: &str) -> OpenFlags {
+    let flags = OpenFlags::SQLITE_OPEN_READ_WRITE
+        | OpenFlags::SQLITE_OPEN_CREATE
+        | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+    if path.starts_with("file:") {
+        flags | OpenFlags::SQLITE_OPEN_URI
+    } else {
+        flags
+    }
+}
+
+/// Open `path` with `readers` extra read-only connections. With readers the database is
+/// switched to WAL mode, so they keep reading while the writer writes.
+fn open_database(path: &str, readers: usize) -> rusqlite::Result<Database> {
+    let writer = Connection::open_with_flags(path, open_flags(path))?;
+    if readers > 0 {
+        writer.query_row("PRAGMA journal_mode=WAL", [], |_| Ok(()))?;
+    }
+    // read-only connections, so a statement misjudged as a read can't write through them
+    let reader_flags =
+        (open_flags(path) - OpenFlags::SQLITE_OPEN_READ_WRITE - OpenFlags::SQLITE_OPEN_CREATE)
+            | OpenFlags::SQLITE_OPEN_READ_ONLY;
+    let readers = (0..readers)
+        .map(|_| Connection::open_with_flags(path, reader_flags))
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(Database {
+        writer,
+        readers,
+        next_reader: Cell::new(0),
+    })
+}
+
+/// `open(path, {readers})`: `readers` (default 0) read-only connections take read-only
+/// queries. They need a database file, since each `:memory:` open is a separate database.
 extern "C" fn sqlite_open(buf: JhpBuf) -> JhpCallResult {
     let args = match parse_args(buf) {
         Ok(a) => a,
@@ -146,9 +306,19 @@ extern "C" fn sqlite_open(buf: JhpBuf) -> JhpCallResult {
         Some(s) => s,
         None => return err_obj("open(path) requires path", 2),
     };
-    match Connection::open(path) {
-        Ok(conn) => {
-            let id = insert_conn(conn);
+    let readers = match args.get(1).and_then(|o| o.get("readers")) {
+        None | Some(serde_json::Value::Null) => 0,
+        Some(v) => match v.as_u64() {
+            Some(n) => n as usize,
+            None => return err_obj("readers must be a non-negative integer", 2),
+        },
+    };
+    if readers > 0 && (path.is_empty() || path == ":memory:") {
+        return err_obj("readers need a database file", 2);
+    }
+    match open_database(path, readers) {
+        Ok(db) => {
+            let id = insert_conn(db);
             ok_json(&serde_json::json!({"db": id}))
         }
         Err(e) => json_err("open failed", e),
@@ -164,9 +334,10 @@ extern "C" fn sqlite_close(buf: JhpBuf) -> JhpCallResult {
         Some(n) => n as u32,
         None => return err_obj("close(db) requires handle", 2),
     };
+    INTERRUPTS.lock().unwrap().remove(&id);
     let removed = CONNS.with(|m| m.borrow_mut().remove(&id));
-    if let Some(conn) = removed {
-        drop(conn);
+    if let Some(db) = removed {
+        drop(db);
         ok_json(&serde_json::json!({"ok": true}))
     } else {
         ok_json(&serde_json::json!({"ok": true}))
@@ -189,8 +360,8 @@ extern "C" fn sqlite_execute(buf: JhpBuf) -> JhpCallResult {
     let params = args.get(2);
     let mut out: Option<JhpCallResult> = None;
     CONNS.with(|m| {
-        let mut map = m.borrow_mut();
-        let Some(conn) = map.get_mut(&id) else {
+        let map = m.borrow();
+        let Some(conn) = map.get(&id).map(|db| &db.writer) else {
             out = Some(err_obj("invalid db handle", 3));
             return;
         };
@@ -203,7 +374,7 @@ extern "C" fn sqlite_execute(buf: JhpBuf) -> JhpCallResult {
                     ));
                 }
                 Err(e) => {
-                    out = Some(json_err("execute failed", e));
+                    out = Some(sqlite_err("execute failed", e));
                 }
             },
             Err(e) => {
@@ -214,6 +385,76 @@ extern "C" fn sqlite_execute(buf: JhpBuf) -> JhpCallResult {
     out.unwrap_or_else(|| err_obj("unknown error", 500))
 }
 
+/// Savepoint wrapping a `sqlite_execute_many` batch.
+const BATCH_SAVEPOINT: &str = "jhp_execute_many";
+
+/// `sqlite_execute_many(db, sql, paramSets)`: prepare `sql` once and run it for each
+/// parameter set (an array or object, as for `sqlite_execute`). The batch runs inside a
+/// savepoint, so any failure rolls all of it back, also inside an open transaction.
+/// Returns the total `rowsAffected` and the last `lastInsertRowId`.
+extern "C" fn sqlite_execute_many(buf: JhpBuf) -> JhpCallResult {
+    let args = match parse_args(buf) {
+        Ok(a) => a,
+        Err(_) => return err_obj("invalid args", 1),
+    };
+    let id = match args.first().and_then(|v| v.as_u64()) {
+        Some(n) => n as u32,
+        None => return err_obj("execute_many(db, sql, paramSets) missing db", 2),
+    };
+    let sql = match args.get(1).and_then(|v| v.as_str()) {
+        Some(s) => s,
+        None => return err_obj("execute_many(db, sql, paramSets) missing sql", 2),
+    };
+    let Some(sets) = args.get(2).and_then(|v| v.as_array()) else {
+        return err_obj(
+            "execute_many(db, sql, paramSets) paramSets must be an array",
+            2,
+        );
+    };
+    CONNS.with(|m| {
+        let map = m.borrow();
+        let Some(conn) = map.get(&id).map(|db| &db.writer) else {
+            return err_obj("invalid db handle", 3);
+        };
+        if let Err(e) = conn.execute_batch(&format!("SAVEPOINT {}", BATCH_SAVEPOINT)) {
+            return sqlite_err("execute_many failed", e);
+        }
+        let result = run_batch(conn, sql, sets).and_then(|changes| {
+            conn.execute_batch(&format!("RELEASE {}", BATCH_SAVEPOINT))
+                .map(|_| changes)
+                .map_err(|e| sqlite_err("execute_many failed", e))
+        });
+        match result {
+            Ok(changes) => ok_json(&serde_json::json!({
+                "rowsAffected": changes,
+                "lastInsertRowId": conn.last_insert_rowid(),
+            })),
+            Err(e) => {
+                let _ =
+                    conn.execute_batch(&format!("ROLLBACK TO {0}; RELEASE {0}", BATCH_SAVEPOINT));
+                e
+            }
+        }
+    })
+}
+
+/// Run the prepared `sql` once per parameter set, returning the total changes.
+fn run_batch(
+    conn: &Connection,
+    sql: &str,
+    sets: &[serde_json::Value],
+) -> Result<usize, JhpCallResult> {
+    let mut stmt = conn
+        .prepare(sql)
+        .map_err(|e| json_err("prepare failed", e))?;
+    let mut changes = 0;
+    for (i, params) in sets.iter().enumerate() {
+        changes += bind_params(&mut stmt, Some(params))
+            .map_err(|e| sqlite_err(&format!("execute_many failed at parameter set {}", i), e))?;
+    }
+    Ok(changes)
+}
+
 extern "C" fn sqlite_query(buf: JhpBuf) -> JhpCallResult {
     let args = match parse_args(buf) {
         Ok(a) => a,
@@ -228,16 +469,93 @@ extern "C" fn sqlite_query(buf: JhpBuf) -> JhpCallResult {
         None => return err_obj("query(db, sql) missing sql", 2),
     };
     let params = args.get(2);
-    let limit = args
-        .get(3)
-        .and_then(|v| v.get("limit"))
-        .and_then(|v| v.as_u64())
-        .unwrap_or(u64::MAX) as usize;
-    let mut out: Option<JhpCallResult> = None;
+    let opts = match QueryOptions::parse(args.get(3)) {
+        Ok(o) => o,
+        Err(e) => return err_obj(e, 2),
+    };
+    match run_query(id, sql, params, opts) {
+        Ok((cols, rows)) => ok_json(&serde_json::json!({"columns": cols, "rows": rows})),
+        Err(e) => e,
+    }
+}
+
+/// `sqlite_query_row(db, sql, params?)`: `{"row": ...}` holding the first row as an
+/// object, or `null` when there is none.
+extern "C" fn sqlite_query_row(buf: JhpBuf) -> JhpCallResult {
+    let args = match parse_args(buf) {
+        Ok(a) => a,
+        Err(_) => return err_obj("invalid args", 1),
+    };
+    let id = match args.first().and_then(|v| v.as_u64()) {
+        Some(n) => n as u32,
+        None => return err_obj("queryRow(db, sql) missing db", 2),
+    };
+    let sql = match args.get(1).and_then(|v| v.as_str()) {
+        Some(s) => s,
+        None => return err_obj("queryRow(db, sql) missing sql", 2),
+    };
+    let opts = QueryOptions {
+        limit: Some(1),
+        ..QueryOptions::default()
+    };
+    match run_query(id, sql, args.get(2), opts) {
+        Ok((_, rows)) => {
+            let row = rows.into_iter().next().unwrap_or_default();
+            ok_json(&serde_json::json!({ "row": row }))
+        }
+        Err(e) => e,
+    }
+}
+
+/// `sqlite_query_value(db, sql, params?)`: `{"value": ...}` holding the first column of
+/// the first row, or `null` when there is no row.
+extern "C" fn sqlite_query_value(buf: JhpBuf) -> JhpCallResult {
+    let args = match parse_args(buf) {
+        Ok(a) => a,
+        Err(_) => return err_obj("invalid args", 1),
+    };
+    let id = match args.first().and_then(|v| v.as_u64()) {
+        Some(n) => n as u32,
+        None => return err_obj("queryValue(db, sql) missing db", 2),
+    };
+    let sql = match args.get(1).and_then(|v| v.as_str()) {
+        Some(s) => s,
+        None => return err_obj("queryValue(db, sql) missing sql", 2),
+    };
+    let opts = QueryOptions {
+        limit: Some(1),
+        format: RowFormat::Arrays,
+        ..QueryOptions::default()
+    };
+    match run_query(id, sql, args.get(2), opts) {
+        Ok((_, rows)) => {
+            let value = rows
+                .into_iter()
+                .next()
+                .and_then(|row| row.get(0).cloned())
+                .unwrap_or_default();
+            ok_json(&serde_json::json!({ "value": value }))
+        }
+        Err(e) => e,
+    }
+}
+
+/// Column names and rows of a query result.
+type QueryRows = (Vec<String>, Vec<serde_json::Value>);
+
+/// Run a query on handle `id`, shaping and paging the rows as `opts` says. Shared by
+/// `sqlite_query` and its single-row and single-value variants.
+fn run_query(
+    id: u32,
+    sql: &str,
+    params: Option<&serde_json::Value>,
+    opts: QueryOptions,
+) -> Result<QueryRows, JhpCallResult> {
+    let mut out: Option<Result<QueryRows, JhpCallResult>> = None;
     CONNS.with(|m| {
-        let mut map = m.borrow_mut();
-        let Some(conn) = map.get_mut(&id) else {
-            out = Some(err_obj("invalid db handle", 3));
+        let map = m.borrow();
+        let Some(conn) = map.get(&id).map(|db| db.conn_for(sql)) else {
+            out = Some(Err(err_obj("invalid db handle", 3)));
             return;
         };
         match conn.prepare(sql) {
@@ -281,29 +599,165 @@ extern "C" fn sqlite_query(buf: JhpBuf) -> JhpCallResult {
                 match rows_res {
                     Ok(mut rows) => {
                         let mut out_rows: Vec<serde_json::Value> = Vec::new();
-                        let mut count = 0usize;
-                        while count < limit {
+                        let mut skipped = 0usize;
+                        while opts.limit.is_none_or(|limit| out_rows.len() < limit) {
                             match rows.next() {
-                                Ok(Some(row)) => {
-                                    out_rows.push(row_to_json(&row));
-                                    count += 1;
-                                }
+                                Ok(Some(_)) if skipped < opts.offset => skipped += 1,
+                                Ok(Some(row)) => out_rows.push(match opts.format {
+                                    RowFormat::Objects => row_to_json(row),
+                                    RowFormat::Arrays => row_to_array(row),
+                                }),
                                 Ok(None) => break,
                                 Err(e) => {
-                                    out = Some(json_err("row fetch failed", e));
+                                    out = Some(Err(sqlite_err("row fetch failed", e)));
                                     return;
                                 }
                             }
                         }
-                        out = Some(ok_json(
-                            &serde_json::json!({"columns": cols, "rows": out_rows}),
-                        ));
+                        out = Some(Ok((cols, out_rows)));
                     }
                     Err(e) => {
-                        out = Some(json_err("query failed", e));
+                        out = Some(Err(sqlite_err("query failed", e)));
                     }
                 }
             }
+            Err(e) => {
+                out = Some(Err(json_err("prepare failed", e)));
+            }
+        }
+    });
+    out.unwrap_or_else(|| Err(err_obj("unknown error", 500)))
+}
+
+/// Whether `name` can be put in a `PRAGMA` statement as is: an identifier of letters,
+/// digits and underscores, optionally prefixed by a schema name and a dot.
+fn is_pragma_name(name: &str) -> bool {
+    let is_ident = |s: &str| {
+        s.chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    match name.split_once('.') {
+        Some((schema, pragma)) => is_ident(schema) && is_ident(pragma),
+        None => is_ident(name),
+    }
+}
+
+/// A JSON pragma value as SQL: numbers as they are, booleans as `1`/`0` and strings as
+/// quoted literals (which SQLite takes for keywords such as `WAL` too).
+fn pragma_value(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        serde_json::Value::Bool(b) => Some(if *b { "1" } else { "0" }.to_string()),
+        serde_json::Value::String(s) => Some(format!("'{}'", s.replace('\'', "''"))),
+        _ => None,
+    }
+}
+
+/// `sqlite_pragma(db, name, value?)`: run `PRAGMA name`, or `PRAGMA name = value` with a
+/// value, and return `{"columns": [...], "rows": [...], "value": ...}`: as
+/// `sqlite_query` gives them, plus the first column of the first row (`null` without
+/// rows, as for most writes). `name` must be an identifier, optionally `schema.name`;
+/// `value` a number, boolean or string.
+extern "C" fn sqlite_pragma(buf: JhpBuf) -> JhpCallResult {
+    let args = match parse_args(buf) {
+        Ok(a) => a,
+        Err(_) => return err_obj("invalid args", 1),
+    };
+    let id = match args.first().and_then(|v| v.as_u64()) {
+        Some(n) => n as u32,
+        None => return err_obj("pragma(db, name) missing db", 2),
+    };
+    let name = match args.get(1).and_then(|v| v.as_str()) {
+        Some(s) if is_pragma_name(s) => s,
+        Some(s) => return err_obj(format!("pragma(db, name): invalid pragma name {:?}", s), 2),
+        None => return err_obj("pragma(db, name) missing name", 2),
+    };
+    let sql = match args.get(2) {
+        None | Some(serde_json::Value::Null) => format!("PRAGMA {}", name),
+        Some(value) => match pragma_value(value) {
+            Some(value) => format!("PRAGMA {} = {}", name, value),
+            None => {
+                return err_obj(
+                    "pragma(db, name, value): value must be a number, boolean or string",
+                    2,
+                );
+            }
+        },
+    };
+    let mut out: Option<JhpCallResult> = None;
+    CONNS.with(|m| {
+        let map = m.borrow();
+        let Some(conn) = map.get(&id).map(|db| &db.writer) else {
+            out = Some(err_obj("invalid db handle", 3));
+            return;
+        };
+        let rows = conn.prepare(&sql).and_then(|mut stmt| {
+            let cols: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+            let mut rows = stmt.query([])?;
+            let mut out_rows: Vec<serde_json::Value> = Vec::new();
+            let mut value = serde_json::Value::Null;
+            while let Some(row) = rows.next()? {
+                if out_rows.is_empty() && row.as_ref().column_count() > 0 {
+                    value = cell_to_json(row, 0);
+                }
+                out_rows.push(row_to_json(row));
+            }
+            Ok((cols, out_rows, value))
+        });
+        out = Some(match rows {
+            Ok((cols, rows, value)) => ok_json(&serde_json::json!({
+                "columns": cols,
+                "rows": rows,
+                "value": value,
+            })),
+            Err(e) => sqlite_err("pragma failed", e),
+        });
+    });
+    out.unwrap_or_else(|| err_obj("unknown error", 500))
+}
+
+/// `sqlite_column_info(db, sql)`: `{"columns": [{name, declType, tableName}]}` describing
+/// the result of `sql`, which is prepared but never run, so its parameters need no values.
+/// `declType` is the type the column was declared with and `tableName` the table it comes
+/// from; both are `null` for an expression.
+extern "C" fn sqlite_column_info(buf: JhpBuf) -> JhpCallResult {
+    let args = match parse_args(buf) {
+        Ok(a) => a,
+        Err(_) => return err_obj("invalid args", 1),
+    };
+    let id = match args.first().and_then(|v| v.as_u64()) {
+        Some(n) => n as u32,
+        None => return err_obj("columnInfo(db, sql) missing db", 2),
+    };
+    let sql = match args.get(1).and_then(|v| v.as_str()) {
+        Some(s) => s,
+        None => return err_obj("columnInfo(db, sql) missing sql", 2),
+    };
+    let mut out: Option<JhpCallResult> = None;
+    CONNS.with(|m| {
+        let map = m.borrow();
+        let Some(conn) = map.get(&id).map(|db| &db.writer) else {
+            out = Some(err_obj("invalid db handle", 3));
+            return;
+        };
+        match conn.prepare(sql) {
+            Ok(stmt) => {
+                let columns: Vec<serde_json::Value> = stmt
+                    .columns()
+                    .iter()
+                    .zip(stmt.columns_with_metadata())
+                    .map(|(col, meta)| {
+                        serde_json::json!({
+                            "name": col.name(),
+                            "declType": col.decl_type(),
+                            "tableName": meta.table_name(),
+                        })
+                    })
+                    .collect();
+                out = Some(ok_json(&serde_json::json!({ "columns": columns })));
+            }
             Err(e) => {
                 out = Some(json_err("prepare failed", e));
             }
@@ -312,6 +766,124 @@ extern "C" fn sqlite_query(buf: JhpBuf) -> JhpCallResult {
     out.unwrap_or_else(|| err_obj("unknown error", 500))
 }
 
+/// Largest database `sqlite_serialize` returns and `sqlite_deserialize` takes, in bytes;
+/// the image travels base64-encoded through JSON, so it has to fit in memory a few times.
+const MAX_SERIALIZED_BYTES: u64 = 64 * 1024 * 1024;
+
+/// `sqlite_serialize(db)`: `{"data": {"blob", "length"}}`, the main database of a handle
+/// as the bytes of a database file, in the blob format of query results. Databases over
+/// [`MAX_SERIALIZED_BYTES`] are refused.
+extern "C" fn sqlite_serialize(buf: JhpBuf) -> JhpCallResult {
+    let args = match parse_args(buf) {
+        Ok(a) => a,
+        Err(_) => return err_obj("invalid args", 1),
+    };
+    let id = match args.first().and_then(|v| v.as_u64()) {
+        Some(n) => n as u32,
+        None => return err_obj("serialize(db) missing db", 2),
+    };
+    let mut out: Option<JhpCallResult> = None;
+    CONNS.with(|m| {
+        let map = m.borrow();
+        let Some(conn) = map.get(&id).map(|db| &db.writer) else {
+            out = Some(err_obj("invalid db handle", 3));
+            return;
+        };
+        let size = conn.query_row(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+            [],
+            |row| row.get::<_, u64>(0),
+        );
+        out = Some(match size {
+            Ok(size) if size > MAX_SERIALIZED_BYTES => err_obj(
+                format!(
+                    "database is too large to serialize ({} bytes, limit {})",
+                    size, MAX_SERIALIZED_BYTES
+                ),
+                1,
+            ),
+            Ok(_) => match conn.serialize("main") {
+                Ok(data) => ok_json(&serde_json::json!({"data": {
+                    "blob": general_purpose::STANDARD.encode(&*data),
+                    "length": data.len(),
+                }})),
+                Err(e) => sqlite_err("serialize failed", e),
+            },
+            Err(e) => sqlite_err("serialize failed", e),
+        });
+    });
+    out.unwrap_or_else(|| err_obj("unknown error", 500))
+}
+
+/// `sqlite_deserialize(data)`: open a new in-memory handle holding a copy of the database
+/// in `data`, a blob object (as `sqlite_serialize` returns) or a base64 string, and return
+/// `{"db": handle}`. Data that isn't a database, or is over [`MAX_SERIALIZED_BYTES`], is
+/// refused.
+extern "C" fn sqlite_deserialize(buf: JhpBuf) -> JhpCallResult {
+    let args = match parse_args(buf) {
+        Ok(a) => a,
+        Err(_) => return err_obj("invalid args", 1),
+    };
+    let bytes = match args.first() {
+        Some(serde_json::Value::Object(obj)) => decode_blob(obj),
+        Some(serde_json::Value::String(b64)) => general_purpose::STANDARD.decode(b64).ok(),
+        _ => return err_obj("deserialize(data) missing data", 2),
+    };
+    let Some(bytes) = bytes else {
+        return err_obj("deserialize(data): data is not valid base64", 2);
+    };
+    if bytes.len() as u64 > MAX_SERIALIZED_BYTES {
+        return err_obj(
+            format!(
+                "deserialize(data): database is too large ({} bytes, limit {})",
+                bytes.len(),
+                MAX_SERIALIZED_BYTES
+            ),
+            2,
+        );
+    }
+    let opened = Connection::open_in_memory().and_then(|mut conn| {
+        // an empty image is an empty database, which is what a new connection holds
+        if !bytes.is_empty() {
+            conn.deserialize_read_exact("main", bytes.as_slice(), bytes.len(), false)?;
+        }
+        // the image is only read when first used; reject one that isn't a database now
+        conn.query_row("SELECT count(*) FROM sqlite_schema", [], |_| Ok(()))?;
+        Ok(conn)
+    });
+    match opened {
+        Ok(writer) => {
+            let id = insert_conn(Database {
+                writer,
+                readers: Vec::new(),
+                next_reader: Cell::new(0),
+            });
+            ok_json(&serde_json::json!({"db": id}))
+        }
+        Err(e) => json_err("deserialize failed", e),
+    }
+}
+
+/// Cancel the statement currently running on a handle, from any thread. The statement
+/// fails with an `interrupted` error (code 4); with nothing running this is a no-op.
+extern "C" fn sqlite_interrupt(buf: JhpBuf) -> JhpCallResult {
+    let args = match parse_args(buf) {
+        Ok(a) => a,
+        Err(_) => return err_obj("invalid args", 1),
+    };
+    let id = match args.first().and_then(|v| v.as_u64()) {
+        Some(n) => n as u32,
+        None => return err_obj("interrupt(db) missing db", 2),
+    };
+    match INTERRUPTS.lock().unwrap().get(&id) {
+        Some(handles) => {
+            handles.iter().for_each(InterruptHandle::interrupt);
+            ok_json(&serde_json::json!({"ok": true}))
+        }
+        None => err_obj("invalid db handle", 3),
+    }
+}
+
 extern "C" fn sqlite_version(_buf: JhpBuf) -> JhpCallResult {
     ok_json(&serde_json::json!({"version": rusqlite::version() }))
 }
@@ -328,8 +900,10 @@ extern "C" fn sqlite_changes(buf: JhpBuf) -> JhpCallResult {
     let mut out: Option<JhpCallResult> = None;
     CONNS.with(|m| {
         let mut map = m.borrow_mut();
-        if let Some(conn) = map.get_mut(&id) {
-            out = Some(ok_json(&serde_json::json!({"changes": conn.changes() })));
+        if let Some(db) = map.get_mut(&id) {
+            out = Some(ok_json(
+                &serde_json::json!({"changes": db.writer.changes() }),
+            ));
         } else {
             out = Some(err_obj("invalid db handle", 3));
         }
@@ -349,9 +923,9 @@ extern "C" fn sqlite_last_insert_rowid(buf: JhpBuf) -> JhpCallResult {
     let mut out: Option<JhpCallResult> = None;
     CONNS.with(|m| {
         let mut map = m.borrow_mut();
-        if let Some(conn) = map.get_mut(&id) {
+        if let Some(db) = map.get_mut(&id) {
             out = Some(ok_json(
-                &serde_json::json!({"id": conn.last_insert_rowid() }),
+                &serde_json::json!({"id": db.writer.last_insert_rowid() }),
             ));
         } else {
             out = Some(err_obj("invalid db handle", 3));
@@ -365,8 +939,562 @@ jhp_extensions::export_jhp_v1! {
     "sqlite_open" => sqlite_open,
     "sqlite_close" => sqlite_close,
     "sqlite_execute" => sqlite_execute,
+    "sqlite_execute_many" => sqlite_execute_many,
     "sqlite_query" => sqlite_query,
+    "sqlite_query_row" => sqlite_query_row,
+    "sqlite_query_value" => sqlite_query_value,
+    "sqlite_column_info" => sqlite_column_info,
+    "sqlite_pragma" => sqlite_pragma,
+    "sqlite_serialize" => sqlite_serialize,
+    "sqlite_deserialize" => sqlite_deserialize,
     "sqlite_version" => sqlite_version,
     "sqlite_changes" => sqlite_changes,
     "sqlite_last_insert_rowid" => sqlite_last_insert_rowid,
+    "sqlite_interrupt" => sqlite_interrupt,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Call an extension function with JSON `args` and return its JSON result.
+    fn call(
+        f: extern "C" fn(JhpBuf) -> JhpCallResult,
+        args: serde_json::Value,
+    ) -> serde_json::Value {
+        let input = args.to_string();
+        let res = f(JhpBuf {
+            ptr: input.as_ptr(),
+            len: input.len(),
+        });
+        let bytes = unsafe { std::slice::from_raw_parts(res.data.ptr, res.data.len) };
+        let value = serde_json::from_slice(bytes).unwrap();
+        jhp_extensions::free_v1(res.data.ptr, res.data.len);
+        value
+    }
+
+    fn open(path: &str) -> u64 {
+        let res = call(sqlite_open, serde_json::json!([path]));
+        res["db"]
+            .as_u64()
+            .unwrap_or_else(|| panic!("open failed: {res}"))
+    }
+
+    #[test]
+    fn shared_cache_memory_uri_is_shared_between_handles() {
+        let uri = "file:shared_mem_test?mode=memory&cache=shared";
+        let (a, b) = (open(uri), open(uri));
+        call(sqlite_execute, serde_json::json!([a, "CREATE TABLE t (x)"]));
+        call(
+            sqlite_execute,
+            serde_json::json!([a, "INSERT INTO t VALUES (42)"]),
+        );
+
+        let res = call(sqlite_query, serde_json::json!([b, "SELECT x FROM t"]));
+        assert_eq!(res["rows"], serde_json::json!([{"x": 42}]));
+    }
+
+    #[test]
+    fn plain_memory_databases_are_separate() {
+        let (a, b) = (open(":memory:"), open(":memory:"));
+        call(sqlite_execute, serde_json::json!([a, "CREATE TABLE t (x)"]));
+
+        let res = call(sqlite_query, serde_json::json!([b, "SELECT x FROM t"]));
+        assert!(
+            res["error"].as_str().unwrap().contains("no such table"),
+            "{res}"
+        );
+    }
+
+    #[test]
+    fn interrupt_from_another_thread_cancels_a_slow_query() {
+        let db = open(":memory:");
+        let interrupter = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            call(sqlite_interrupt, serde_json::json!([db]))
+        });
+
+        let started = std::time::Instant::now();
+        let res = call(
+            sqlite_query,
+            serde_json::json!([
+                db,
+                "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c WHERE x < 1e12) \
+                 SELECT count(*) FROM c"
+            ]),
+        );
+        assert_eq!(res, serde_json::json!({"error": "interrupted", "code": 4}));
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+        assert_eq!(interrupter.join().unwrap(), serde_json::json!({"ok": true}));
+
+        // the handle stays usable afterwards
+        let res = call(sqlite_query, serde_json::json!([db, "SELECT 1 AS one"]));
+        assert_eq!(res["rows"], serde_json::json!([{"one": 1}]));
+    }
+
+    #[test]
+    fn integers_above_i64_max_read_back_without_precision_loss() {
+        let db = open(":memory:");
+        call(
+            sqlite_execute,
+            serde_json::json!([db, "CREATE TABLE t (x, y TEXT)"]),
+        );
+        let above = i64::MAX as u64 + 1;
+        for x in [i64::MAX as u64, above, u64::MAX] {
+            call(
+                sqlite_execute,
+                serde_json::json!([db, "INSERT INTO t (x, y) VALUES (?, ?)", [x, x]]),
+            );
+        }
+
+        let res = call(
+            sqlite_query,
+            serde_json::json!([db, "SELECT x, y, typeof(x) AS t FROM t ORDER BY rowid"]),
+        );
+        assert_eq!(
+            res["rows"],
+            serde_json::json!([
+                {"x": i64::MAX, "y": i64::MAX.to_string(), "t": "integer"},
+                {"x": above.to_string(), "y": above.to_string(), "t": "text"},
+                {"x": u64::MAX.to_string(), "y": u64::MAX.to_string(), "t": "text"},
+            ])
+        );
+        // bound the same way in a lookup, so the stored value is found again
+        let res = call(
+            sqlite_query_value,
+            serde_json::json!([db, "SELECT count(*) FROM t WHERE x = ?", [u64::MAX]]),
+        );
+        assert_eq!(res, serde_json::json!({"value": 1}));
+    }
+
+    fn numbers(n: u32) -> u64 {
+        let db = open(":memory:");
+        call(
+            sqlite_execute,
+            serde_json::json!([db, "CREATE TABLE t (x INTEGER)"]),
+        );
+        for x in 1..=n {
+            call(
+                sqlite_execute,
+                serde_json::json!([db, "INSERT INTO t (x) VALUES (?)", [x]]),
+            );
+        }
+        db
+    }
+
+    fn xs(res: &serde_json::Value) -> Vec<i64> {
+        res["rows"]
+            .as_array()
+            .unwrap_or_else(|| panic!("{res}"))
+            .iter()
+            .map(|r| r["x"].as_i64().unwrap())
+            .collect()
+    }
+
+    const SELECT: &str = "SELECT x FROM t ORDER BY x";
+
+    #[test]
+    fn query_row_returns_the_first_row() {
+        let db = numbers(3);
+        let res = call(
+            sqlite_query_row,
+            serde_json::json!([
+                db,
+                "SELECT x, x * 10 AS y FROM t WHERE x >= ? ORDER BY x",
+                [2]
+            ]),
+        );
+        assert_eq!(res, serde_json::json!({"row": {"x": 2, "y": 20}}));
+    }
+
+    #[test]
+    fn query_row_without_a_match_is_null() {
+        let db = numbers(3);
+        let res = call(
+            sqlite_query_row,
+            serde_json::json!([db, "SELECT x FROM t WHERE x > :min", {"min": 10}]),
+        );
+        assert_eq!(res, serde_json::json!({"row": null}));
+    }
+
+    #[test]
+    fn query_value_returns_a_scalar() {
+        let db = numbers(4);
+        let res = call(
+            sqlite_query_value,
+            serde_json::json!([db, "SELECT sum(x), count(*) FROM t"]),
+        );
+        assert_eq!(res, serde_json::json!({"value": 10}));
+        let res = call(
+            sqlite_query_value,
+            serde_json::json!([db, "SELECT x FROM t WHERE x < 0"]),
+        );
+        assert_eq!(res, serde_json::json!({"value": null}));
+        let res = call(
+            sqlite_query_value,
+            serde_json::json!([db, "SELECT nope FROM t"]),
+        );
+        assert!(res["error"].is_string(), "{res}");
+    }
+
+    #[test]
+    fn pragma_reads_and_sets_user_version() {
+        let db = open(":memory:");
+        let read = || call(sqlite_pragma, serde_json::json!([db, "user_version"]));
+        assert_eq!(read()["value"], 0);
+
+        let res = call(sqlite_pragma, serde_json::json!([db, "user_version", 7]));
+        assert_eq!(res["rows"], serde_json::json!([]));
+        assert_eq!(res["value"], serde_json::Value::Null);
+        assert_eq!(
+            read(),
+            serde_json::json!({"columns": ["user_version"], "rows": [{"user_version": 7}], "value": 7})
+        );
+        let res = call(sqlite_pragma, serde_json::json!([db, "main.user_version"]));
+        assert_eq!(res["value"], 7);
+    }
+
+    #[test]
+    fn pragma_rejects_names_that_are_not_identifiers() {
+        let db = open(":memory:");
+        for name in ["user_version; DROP TABLE t", "", "1abc", "a.b.c", "x y"] {
+            let res = call(sqlite_pragma, serde_json::json!([db, name]));
+            assert!(
+                res["error"]
+                    .as_str()
+                    .unwrap()
+                    .contains("invalid pragma name"),
+                "{res}"
+            );
+        }
+        let res = call(sqlite_pragma, serde_json::json!([db, "user_version", [1]]));
+        assert!(res["error"].is_string(), "{res}");
+    }
+
+    #[test]
+    fn column_info_describes_the_result_without_running_it() {
+        let db = open(":memory:");
+        call(
+            sqlite_execute,
+            serde_json::json!([
+                db,
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, score REAL)"
+            ]),
+        );
+        let res = call(
+            sqlite_column_info,
+            serde_json::json!([
+                db,
+                "SELECT id, name AS label, score * 2 AS doubled FROM users WHERE id > ?"
+            ]),
+        );
+        assert_eq!(
+            res,
+            serde_json::json!({"columns": [
+                {"name": "id", "declType": "INTEGER", "tableName": "users"},
+                {"name": "label", "declType": "TEXT", "tableName": "users"},
+                {"name": "doubled", "declType": null, "tableName": null},
+            ]})
+        );
+
+        let res = call(
+            sqlite_column_info,
+            serde_json::json!([db, "SELECT * FROM missing"]),
+        );
+        assert!(res["error"].is_string(), "{res}");
+    }
+
+    #[test]
+    fn serialized_databases_deserialize_into_a_new_handle() {
+        let db = open(":memory:");
+        call(
+            sqlite_execute,
+            serde_json::json!([db, "CREATE TABLE t (x INTEGER, s TEXT)"]),
+        );
+        call(
+            sqlite_execute_many,
+            serde_json::json!([db, "INSERT INTO t VALUES (?, ?)", [[1, "a"], [2, "b"]]]),
+        );
+        let res = call(sqlite_serialize, serde_json::json!([db]));
+        assert!(res["data"]["length"].as_u64().unwrap() > 0, "{res}");
+
+        let copy = call(sqlite_deserialize, serde_json::json!([res["data"]]))["db"]
+            .as_u64()
+            .unwrap();
+        assert_ne!(copy, db);
+        let select = "SELECT x, s FROM t ORDER BY x";
+        let rows = |db| call(sqlite_query, serde_json::json!([db, select, null]));
+        assert_eq!(rows(copy), rows(db));
+        assert_eq!(rows(copy)["rows"][1], serde_json::json!({"x": 2, "s": "b"}));
+
+        // the copy is a database of its own
+        call(sqlite_execute, serde_json::json!([copy, "DELETE FROM t"]));
+        assert_eq!(rows(db)["rows"].as_array().unwrap().len(), 2);
+
+        // a base64 string works too
+        let b64 = res["data"]["blob"].clone();
+        let res = call(sqlite_deserialize, serde_json::json!([b64]));
+        assert!(res["db"].is_u64(), "{res}");
+    }
+
+    #[test]
+    fn deserialize_rejects_data_that_is_not_a_database() {
+        for data in [
+            serde_json::json!("not base64!"),
+            serde_json::json!({"blob": "aGVsbG8gd29ybGQ="}),
+            serde_json::json!(42),
+        ] {
+            let res = call(sqlite_deserialize, serde_json::json!([data]));
+            assert!(res["error"].is_string(), "{res}");
+        }
+        let res = call(sqlite_serialize, serde_json::json!([0]));
+        assert_eq!(res["code"], 3);
+    }
+
+    #[test]
+    fn query_limit_caps_rows_and_zero_returns_none() {
+        let db = numbers(5);
+        let all = call(sqlite_query, serde_json::json!([db, SELECT, null]));
+        assert_eq!(xs(&all), [1, 2, 3, 4, 5]);
+        let res = call(
+            sqlite_query,
+            serde_json::json!([db, SELECT, null, {"limit": 2}]),
+        );
+        assert_eq!(xs(&res), [1, 2]);
+        let res = call(
+            sqlite_query,
+            serde_json::json!([db, SELECT, null, {"limit": 0}]),
+        );
+        assert_eq!(xs(&res), Vec::<i64>::new());
+        assert_eq!(res["columns"], serde_json::json!(["x"]));
+    }
+
+    #[test]
+    fn query_offset_skips_rows() {
+        let db = numbers(5);
+        let res = call(
+            sqlite_query,
+            serde_json::json!([db, SELECT, null, {"offset": 3}]),
+        );
+        assert_eq!(xs(&res), [4, 5]);
+        let res = call(
+            sqlite_query,
+            serde_json::json!([db, SELECT, null, {"offset": 10}]),
+        );
+        assert_eq!(xs(&res), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn query_offset_and_limit_combine() {
+        let db = numbers(5);
+        let res = call(
+            sqlite_query,
+            serde_json::json!([db, SELECT, null, {"offset": 1, "limit": 2}]),
+        );
+        assert_eq!(xs(&res), [2, 3]);
+    }
+
+    #[test]
+    fn query_arrays_format_returns_rows_in_column_order() {
+        let db = numbers(2);
+        let res = call(
+            sqlite_query,
+            serde_json::json!([db, "SELECT x, x * 10 AS y FROM t ORDER BY x", null, {"format": "arrays"}]),
+        );
+        assert_eq!(res["columns"], serde_json::json!(["x", "y"]));
+        assert_eq!(res["rows"], serde_json::json!([[1, 10], [2, 20]]));
+    }
+
+    #[test]
+    fn query_rejects_invalid_options() {
+        let db = numbers(1);
+        for opts in [
+            serde_json::json!({"limit": -1}),
+            serde_json::json!({"offset": "2"}),
+            serde_json::json!({"format": "csv"}),
+            serde_json::json!(5),
+        ] {
+            let res = call(sqlite_query, serde_json::json!([db, SELECT, null, opts]));
+            assert_eq!(res["code"], 2, "{opts}: {res}");
+        }
+    }
+
+    fn open_with_readers(path: &std::path::Path, readers: u32) -> u64 {
+        let res = call(
+            sqlite_open,
+            serde_json::json!([path.to_str().unwrap(), {"readers": readers}]),
+        );
+        res["db"]
+            .as_u64()
+            .unwrap_or_else(|| panic!("open failed: {res}"))
+    }
+
+    fn count(db: u64) -> serde_json::Value {
+        call(
+            sqlite_query,
+            serde_json::json!([db, "SELECT count(*) AS n FROM t"]),
+        )["rows"][0]["n"]
+            .clone()
+    }
+
+    #[test]
+    fn reads_proceed_while_another_writer_holds_a_write_transaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pool.db");
+        let db = open_with_readers(&path, 2);
+        call(
+            sqlite_execute,
+            serde_json::json!([db, "CREATE TABLE t (x)"]),
+        );
+        call(
+            sqlite_execute,
+            serde_json::json!([db, "INSERT INTO t VALUES (1)"]),
+        );
+
+        // another worker's handle is in the middle of a write
+        let other = open(path.to_str().unwrap());
+        for sql in ["BEGIN IMMEDIATE", "INSERT INTO t VALUES (2)"] {
+            let res = call(sqlite_execute, serde_json::json!([other, sql]));
+            assert!(res.get("error").is_none(), "{sql}: {res}");
+        }
+
+        let started = std::time::Instant::now();
+        for _ in 0..4 {
+            assert_eq!(count(db), 1);
+        }
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+
+        call(sqlite_execute, serde_json::json!([other, "COMMIT"]));
+        assert_eq!(count(db), 2);
+    }
+
+    #[test]
+    fn reads_inside_a_transaction_see_its_own_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_with_readers(&dir.path().join("tx.db"), 1);
+        call(
+            sqlite_execute,
+            serde_json::json!([db, "CREATE TABLE t (x)"]),
+        );
+        call(sqlite_execute, serde_json::json!([db, "BEGIN"]));
+        call(
+            sqlite_execute,
+            serde_json::json!([db, "INSERT INTO t VALUES (1)"]),
+        );
+        assert_eq!(count(db), 1);
+        call(sqlite_execute, serde_json::json!([db, "ROLLBACK"]));
+        assert_eq!(count(db), 0);
+    }
+
+    #[test]
+    fn writing_queries_go_to_the_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_with_readers(&dir.path().join("rw.db"), 1);
+        call(
+            sqlite_execute,
+            serde_json::json!([db, "CREATE TABLE t (x)"]),
+        );
+        let res = call(
+            sqlite_query,
+            serde_json::json!([db, "INSERT INTO t VALUES (7) RETURNING x"]),
+        );
+        assert_eq!(res["rows"], serde_json::json!([{"x": 7}]), "{res}");
+        assert_eq!(count(db), 1);
+    }
+
+    #[test]
+    fn readers_are_read_only_and_need_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_database(dir.path().join("ro.db").to_str().unwrap(), 1).unwrap();
+        db.writer.execute_batch("CREATE TABLE t (x)").unwrap();
+        let err = db.readers[0]
+            .execute("INSERT INTO t VALUES (1)", [])
+            .unwrap_err();
+        assert_eq!(err.sqlite_error_code(), Some(ErrorCode::ReadOnly));
+
+        let res = call(sqlite_open, serde_json::json!([":memory:", {"readers": 1}]));
+        assert_eq!(res["code"], 2, "{res}");
+    }
+
+    #[test]
+    fn interrupt_rejects_unknown_handles() {
+        let res = call(sqlite_interrupt, serde_json::json!([u32::MAX]));
+        assert_eq!(res["code"], 3);
+    }
+
+    #[test]
+    fn execute_many_inserts_every_parameter_set() {
+        let db = open(":memory:");
+        call(
+            sqlite_execute,
+            serde_json::json!([db, "CREATE TABLE t (x INTEGER, label TEXT)"]),
+        );
+        let sets: Vec<serde_json::Value> = (1..=500)
+            .map(|x| serde_json::json!({"x": x, "label": format!("row {x}")}))
+            .collect();
+
+        let res = call(
+            sqlite_execute_many,
+            serde_json::json!([db, "INSERT INTO t (x, label) VALUES (:x, :label)", sets]),
+        );
+        assert_eq!(res["rowsAffected"], 500, "{res}");
+        assert_eq!(res["lastInsertRowId"], 500);
+        let res = call(
+            sqlite_query_value,
+            serde_json::json!([db, "SELECT count(*) || ':' || sum(x) FROM t"]),
+        );
+        assert_eq!(res["value"], "500:125250");
+    }
+
+    #[test]
+    fn execute_many_rolls_back_the_whole_batch_on_failure() {
+        let db = open(":memory:");
+        call(
+            sqlite_execute,
+            serde_json::json!([db, "CREATE TABLE t (x INTEGER UNIQUE)"]),
+        );
+        call(
+            sqlite_execute,
+            serde_json::json!([db, "INSERT INTO t (x) VALUES (0)"]),
+        );
+
+        let res = call(
+            sqlite_execute_many,
+            serde_json::json!([db, "INSERT INTO t (x) VALUES (?)", [[1], [2], [2], [3]]]),
+        );
+        let error = res["error"].as_str().unwrap_or_else(|| panic!("{res}"));
+        assert!(error.contains("parameter set 2"), "{error}");
+        assert!(error.contains("UNIQUE"), "{error}");
+        assert_eq!(
+            xs(&call(sqlite_query, serde_json::json!([db, SELECT]))),
+            [0]
+        );
+
+        // inside a transaction only the batch is undone
+        call(sqlite_execute, serde_json::json!([db, "BEGIN"]));
+        call(
+            sqlite_execute,
+            serde_json::json!([db, "INSERT INTO t (x) VALUES (10)"]),
+        );
+        let res = call(
+            sqlite_execute_many,
+            serde_json::json!([db, "INSERT INTO t (x) VALUES (?)", [[11], [10]]]),
+        );
+        assert!(res["error"].is_string(), "{res}");
+        call(sqlite_execute, serde_json::json!([db, "COMMIT"]));
+        assert_eq!(
+            xs(&call(sqlite_query, serde_json::json!([db, SELECT]))),
+            [0, 10]
+        );
+    }
+
+    #[test]
+    fn execute_many_needs_an_array_of_parameter_sets() {
+        let db = numbers(1);
+        let res = call(
+            sqlite_execute_many,
+            serde_json::json!([db, "INSERT INTO t (x) VALUES (?)", {"x": 1}]),
+        );
+        assert_eq!(res["code"], 2, "{res}");
+    }
 }